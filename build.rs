@@ -12,7 +12,7 @@ fn main() {
 
     // Start the embedded scripts module
     writeln!(file, "// Auto-generated embedded scripts").unwrap();
-    writeln!(file, "").unwrap();
+    writeln!(file).unwrap();
     writeln!(
         file,
         "pub fn get_embedded_scripts() -> std::collections::HashMap<&'static str, &'static [u8]> {{"
@@ -43,34 +43,32 @@ fn main() {
 
 fn embed_scripts_recursively(base_dir: &Path, current_dir: &Path, file: &mut fs::File) {
     if let Ok(entries) = fs::read_dir(current_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_dir() {
-                    embed_scripts_recursively(base_dir, &path, file);
-                } else if path.is_file() {
-                    // Get relative path from base scripts directory
-                    let relative_path = path.strip_prefix(base_dir).unwrap();
-                    let relative_str = relative_path.to_string_lossy().replace('\\', "/");
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                embed_scripts_recursively(base_dir, &path, file);
+            } else if path.is_file() {
+                // Get relative path from base scripts directory
+                let relative_path = path.strip_prefix(base_dir).unwrap();
+                let relative_str = relative_path.to_string_lossy().replace('\\', "/");
 
-                    // Convert Windows path to Unix-style path for the include_bytes! macro
-                    let include_path = if cfg!(target_os = "windows") {
-                        path.to_string_lossy().replace('\\', "/")
-                    } else {
-                        path.to_string_lossy().to_string()
-                    };
+                // Convert Windows path to Unix-style path for the include_bytes! macro
+                let include_path = if cfg!(target_os = "windows") {
+                    path.to_string_lossy().replace('\\', "/")
+                } else {
+                    path.to_string_lossy().to_string()
+                };
 
-                    // Embed the file content using raw string to avoid escape issues
-                    // Cast to &[u8] to ensure consistent type
-                    writeln!(
-                        file,
-                        "    scripts.insert(\"{}\", include_bytes!(r\"{}\") as &'static [u8]);",
-                        relative_str, include_path
-                    )
-                    .unwrap();
+                // Embed the file content using raw string to avoid escape issues
+                // Cast to &[u8] to ensure consistent type
+                writeln!(
+                    file,
+                    "    scripts.insert(\"{}\", include_bytes!(r\"{}\") as &'static [u8]);",
+                    relative_str, include_path
+                )
+                .unwrap();
 
-                    println!("cargo:rerun-if-changed={}", path.display());
-                }
+                println!("cargo:rerun-if-changed={}", path.display());
             }
         }
     }
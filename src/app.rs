@@ -1,5 +1,7 @@
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -9,10 +11,17 @@ use ratatui::{
     Frame, Terminal,
 };
 use std::io;
+use std::time::Instant;
 use tokio::time::Duration;
 
-use crate::commands::{Command, CommandCategory};
-use crate::config::Config;
+use crate::keymap::{self, Keymap};
+use crate::viewer::OutputViewer;
+use linux_toolkit::cli::favorites::{self, UsageStore};
+use linux_toolkit::cli::firewall::{Action, Detected, Policies, Rule};
+use linux_toolkit::cli::history::{self, History};
+use linux_toolkit::Config;
+use linux_toolkit::ExecutionRecord;
+use linux_toolkit::{Command, CommandCategory};
 
 pub struct App {
     pub config: Config,
@@ -25,12 +34,216 @@ pub struct App {
     pub focused_panel: FocusedPanel,
     pub show_help: bool,
     pub show_command_details: bool,
-    pub executing_command: bool,
+    /// Built-in command running in the background
+    pub native_run: Option<NativeRun>,
     pub command_output: Vec<String>,
     pub input_mode: bool,
     pub input_buffer: String,
     pub execute_in_terminal: bool,
     pub pending_command: Option<Command>,
+    pub output_source: Option<Command>,
+    pub firewall_editor: Option<FirewallEditor>,
+    pub output_viewer: Option<OutputViewer>,
+    /// Stored runs shown in the output viewer as a diff
+    pub run_diff: Option<RunDiff>,
+    pub usage: UsageStore,
+    pub keymap: Keymap,
+    /// Whether the input line is a command search rather than a firewall rule
+    pub searching: bool,
+    /// Visible rows in the commands list, used for paging
+    pub page_size: usize,
+    /// First visible line of the output pane
+    pub output_scroll: u16,
+    pub categories_collapsed: bool,
+    /// Show only the focused panel
+    pub zoomed: bool,
+    pub host: HostInfo,
+    /// Where each panel was drawn last frame, for mapping mouse clicks
+    pub panel_areas: PanelAreas,
+    /// Panel, row and time of the last left click, to detect double clicks
+    last_click: Option<(FocusedPanel, usize, Instant)>,
+}
+
+/// A built-in command running off the event loop, so a long check such as
+/// the integrity scan does not freeze the interface
+pub struct NativeRun {
+    command: Command,
+    started_at: chrono::DateTime<chrono::Utc>,
+    task: tokio::task::JoinHandle<Result<String>>,
+}
+
+impl NativeRun {
+    fn start(command: Command, config: Config) -> Self {
+        let task_command = command.clone();
+        let runtime = tokio::runtime::Handle::current();
+        Self {
+            command,
+            started_at: chrono::Utc::now(),
+            // Built-ins may block while scanning, so they get a thread of their own
+            task: tokio::task::spawn_blocking(move || {
+                runtime.block_on(linux_toolkit::cli::commands::execute_native_command(
+                    &task_command,
+                    &config,
+                ))
+            }),
+        }
+    }
+
+    /// Spinner and elapsed time for the details panel
+    fn progress(&self) -> String {
+        const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+        let elapsed = (chrono::Utc::now() - self.started_at)
+            .num_milliseconds()
+            .max(0);
+        format!(
+            "{} Running {}... {}s",
+            SPINNER[(elapsed / 250) as usize % SPINNER.len()],
+            self.command.name,
+            elapsed / 1000
+        )
+    }
+}
+
+/// Facts about this machine and session shown in the header and status bar
+#[derive(Debug, Clone)]
+pub struct HostInfo {
+    pub hostname: String,
+    pub user: String,
+    pub summary: String,
+    /// Privilege summary, refreshed after each terminal run
+    pub privilege: &'static str,
+}
+
+impl HostInfo {
+    pub fn probe() -> Self {
+        Self {
+            hostname: linux_toolkit::cli::utils::get_hostname(),
+            user: linux_toolkit::cli::utils::get_current_user(),
+            summary: linux_toolkit::cli::environment::current().summary(),
+            privilege: linux_toolkit::cli::utils::privilege_state(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PanelAreas {
+    pub categories: Rect,
+    pub commands: Rect,
+    pub details: Rect,
+}
+
+/// Below this many columns the panels are stacked vertically
+const NARROW_WIDTH: u16 = 100;
+/// Below this many rows the System Info header is dropped
+const SHORT_HEIGHT: u16 = 24;
+
+/// Longest gap between two clicks on the same row that counts as a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Rules and default policies shown in the firewall editor popup
+pub struct FirewallEditor {
+    pub detected: Option<Detected>,
+    pub rules: Vec<Rule>,
+    pub policies: Option<Policies>,
+    pub list_state: ListState,
+    pub error: Option<String>,
+}
+
+impl FirewallEditor {
+    fn load() -> Self {
+        let detected = linux_toolkit::cli::firewall::detect();
+        let mut editor = Self {
+            detected,
+            rules: Vec::new(),
+            policies: None,
+            list_state: ListState::default(),
+            error: None,
+        };
+
+        match detected {
+            Some(detected) => match linux_toolkit::cli::firewall::list_rules(detected.backend) {
+                Ok(rules) => {
+                    editor.rules = rules;
+                    editor.policies = linux_toolkit::cli::firewall::policies(detected.backend).ok();
+                }
+                Err(e) => editor.error = Some(format!("Cannot list rules (root needed?): {}", e)),
+            },
+            None => editor.error = Some("No supported firewall found".to_string()),
+        }
+        if !editor.rules.is_empty() {
+            editor.list_state.select(Some(0));
+        }
+        editor
+    }
+
+    fn selected_rule(&self) -> Option<&Rule> {
+        self.rules.get(self.list_state.selected()?)
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let Some(current) = self.list_state.selected() else {
+            return;
+        };
+        let next = if down {
+            (current + 1).min(self.rules.len().saturating_sub(1))
+        } else {
+            current.saturating_sub(1)
+        };
+        self.list_state.select(Some(next));
+    }
+}
+
+/// Stored runs of a command, the latest compared with an earlier one
+pub struct RunDiff {
+    pub name: String,
+    pub runs: Vec<ExecutionRecord>,
+    /// Index of the run the latest one is compared with
+    pub baseline: usize,
+}
+
+impl RunDiff {
+    /// Header and changes, coloured with ANSI escapes for the viewer
+    fn lines(&self) -> Vec<String> {
+        let format = |run: &ExecutionRecord| {
+            run.started_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        };
+        let (Some(latest), Some(previous)) = (self.runs.last(), self.runs.get(self.baseline))
+        else {
+            return vec![format!(
+                "{} stored run(s), at least two are needed to compare. Runs from the TUI, \
+                 -x, the scheduler and the web UI are stored; runbooks are not. Runs with \
+                 extra arguments are compared with `linux-toolkit diff --arg`.",
+                self.runs.len()
+            )];
+        };
+
+        let mut lines = vec![
+            format!(
+                "Run of {} compared with {} ({} of {} stored runs)",
+                format(latest),
+                format(previous),
+                self.baseline + 1,
+                self.runs.len()
+            ),
+            String::new(),
+        ];
+        let changes = history::diff(&previous.output, &latest.output).lines();
+        if changes.is_empty() {
+            lines.push("No changes".to_string());
+        }
+        lines.extend(changes.into_iter().map(|line| {
+            let color = match line.chars().next() {
+                Some('+') => 32,
+                Some('-') => 31,
+                _ => 33,
+            };
+            format!("\x1b[{}m{}\x1b[0m", color, line)
+        }));
+        lines
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -41,8 +254,26 @@ pub enum FocusedPanel {
 }
 
 impl App {
-    pub fn new(config: Config) -> Self {
-        let categories = crate::commands::load_categories();
+    pub fn new(config: Config) -> Result<Self> {
+        Self::with_categories(config, linux_toolkit::load_categories(), HostInfo::probe())
+    }
+
+    pub fn with_categories(
+        config: Config,
+        mut categories: Vec<CommandCategory>,
+        host: HostInfo,
+    ) -> Result<Self> {
+        let keymap = Keymap::from_config(&config.keybindings)?;
+        if config.behavior.hide_unsupported_commands {
+            for category in &mut categories {
+                category
+                    .commands
+                    .retain(|command| command.unavailable.is_none() && command.missing.is_empty());
+            }
+            categories.retain(|category| !category.commands.is_empty());
+        }
+        let usage = UsageStore::load(&config.directories.data_dir);
+        categories.splice(0..0, usage.virtual_categories(&categories));
         let mut category_list_state = ListState::default();
         let mut command_list_state = ListState::default();
 
@@ -54,7 +285,7 @@ impl App {
             }
         }
 
-        Self {
+        Ok(Self {
             config,
             should_quit: false,
             categories,
@@ -65,15 +296,89 @@ impl App {
             focused_panel: FocusedPanel::Categories,
             show_help: false,
             show_command_details: false,
-            executing_command: false,
+            native_run: None,
             command_output: Vec::new(),
             input_mode: false,
             input_buffer: String::new(),
             execute_in_terminal: false,
             pending_command: None,
+            output_source: None,
+            firewall_editor: None,
+            output_viewer: None,
+            run_diff: None,
+            usage,
+            keymap,
+            searching: false,
+            page_size: 10,
+            output_scroll: 0,
+            categories_collapsed: false,
+            zoomed: false,
+            host,
+            panel_areas: PanelAreas::default(),
+            last_click: None,
+        })
+    }
+
+    /// Rebuild the Favorites and Recent categories, keeping the selection where possible
+    fn refresh_virtual_categories(&mut self) {
+        let selected_category = self
+            .categories
+            .get(self.current_category)
+            .map(|category| category.name.clone());
+
+        self.categories
+            .retain(|category| !favorites::is_virtual(category));
+        let virtual_categories = self.usage.virtual_categories(&self.categories);
+        self.categories.splice(0..0, virtual_categories);
+
+        let index =
+            selected_category.and_then(|name| self.categories.iter().position(|c| c.name == name));
+        match index {
+            Some(index) => self.current_category = index,
+            None => {
+                self.current_category = 0;
+                self.update_commands_for_category();
+            }
+        }
+        self.category_list_state.select(Some(self.current_category));
+
+        let len = self
+            .categories
+            .get(self.current_category)
+            .map_or(0, |category| category.commands.len());
+        if self.current_command >= len {
+            self.current_command = len.saturating_sub(1);
+            self.command_list_state.select(Some(self.current_command));
         }
     }
 
+    fn save_usage(&mut self) {
+        if let Err(e) = self.usage.save(&self.config.directories.data_dir) {
+            self.command_output = vec![format!("❌ Failed to save usage data: {}", e)];
+        }
+    }
+
+    /// Pin or unpin the selected command
+    fn toggle_favorite(&mut self) {
+        let Some(command) = self
+            .categories
+            .get(self.current_category)
+            .and_then(|category| category.commands.get(self.current_command))
+        else {
+            return;
+        };
+
+        let name = command.name.clone();
+        let pinned = self.usage.toggle_favorite(&command.id());
+        self.command_output = vec![if pinned {
+            format!("★ Pinned {}", name)
+        } else {
+            format!("☆ Unpinned {}", name)
+        }];
+        self.save_usage();
+        self.refresh_virtual_categories();
+    }
+
     pub async fn run(
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
@@ -86,8 +391,11 @@ impl App {
                     crate::ui::restore_terminal(terminal)?;
 
                     // Execute command in terminal
-                    let result =
-                        crate::commands::execute_command_in_terminal(command, &self.config).await;
+                    let result = linux_toolkit::cli::terminal::execute_command_in_terminal(
+                        command,
+                        &self.config,
+                    )
+                    .await;
 
                     // Re-setup terminal for TUI
                     *terminal = crate::ui::setup_terminal()?;
@@ -105,17 +413,45 @@ impl App {
 
                     // Reset flags
                     self.execute_in_terminal = false;
+                    self.host.privilege = linux_toolkit::cli::utils::privilege_state();
                     self.pending_command = None;
+
+                    // The run may have installed programs other commands need
+                    for command in self
+                        .categories
+                        .iter_mut()
+                        .flat_map(|category| category.commands.iter_mut())
+                    {
+                        if !command.missing.is_empty() {
+                            command.missing = command.missing_binaries();
+                        }
+                    }
+
+                    // Show the rules as they are after the change
+                    if let Some(editor) = &mut self.firewall_editor {
+                        let selected = editor.list_state.selected();
+                        *editor = FirewallEditor::load();
+                        if let Some(index) = selected {
+                            if !editor.rules.is_empty() {
+                                editor
+                                    .list_state
+                                    .select(Some(index.min(editor.rules.len() - 1)));
+                            }
+                        }
+                    }
                 }
             }
 
+            self.collect_native_run().await;
             terminal.draw(|f| self.ui(f))?;
 
             if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    if key.kind == KeyEventKind::Press {
-                        self.handle_key_event(key.code).await?;
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        self.handle_key_event(key).await?;
                     }
+                    Event::Mouse(mouse) => self.handle_mouse_event(mouse).await?,
+                    _ => {}
                 }
             }
 
@@ -126,64 +462,111 @@ impl App {
         Ok(())
     }
 
-    async fn handle_key_event(&mut self, key: KeyCode) -> Result<()> {
+    async fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
         if self.input_mode {
-            match key {
+            match key.code {
                 KeyCode::Enter => {
                     // Process input
+                    if self.firewall_editor.is_some() {
+                        self.submit_firewall_rule();
+                    }
                     self.input_mode = false;
+                    self.searching = false;
                     self.input_buffer.clear();
                 }
                 KeyCode::Esc => {
+                    if let Some(viewer) = &mut self.output_viewer {
+                        viewer.set_search("");
+                    }
                     self.input_mode = false;
+                    self.searching = false;
                     self.input_buffer.clear();
                 }
                 KeyCode::Char(c) => {
                     self.input_buffer.push(c);
+                    if self.searching {
+                        self.jump_to_match();
+                    }
                 }
                 KeyCode::Backspace => {
                     self.input_buffer.pop();
+                    if self.searching {
+                        self.jump_to_match();
+                    }
                 }
                 _ => {}
             }
             return Ok(());
         }
 
-        match key {
-            KeyCode::Char('q') | KeyCode::Esc => {
+        if self.firewall_editor.is_some() {
+            self.handle_firewall_key(key);
+            return Ok(());
+        }
+
+        if self.output_viewer.is_some() {
+            self.handle_viewer_key(key);
+            return Ok(());
+        }
+
+        let Some(action) = self.keymap.action_for(&key) else {
+            return Ok(());
+        };
+        match action {
+            keymap::Action::Quit => {
                 self.should_quit = true;
             }
-            KeyCode::Char('h') | KeyCode::F(1) => {
+            keymap::Action::Help => {
                 self.show_help = !self.show_help;
             }
-            KeyCode::Tab => {
+            keymap::Action::NextPanel => {
                 self.cycle_focus();
             }
-            KeyCode::Enter => {
-                if self.focused_panel == FocusedPanel::Commands {
-                    self.execute_selected_command().await?;
-                }
+            keymap::Action::Export => {
+                self.export_last_output();
             }
-            KeyCode::Char(' ') => {
-                if self.focused_panel == FocusedPanel::Commands {
-                    self.show_command_details = !self.show_command_details;
-                }
+            keymap::Action::Search => {
+                self.searching = true;
+                self.input_mode = true;
+                self.input_buffer.clear();
             }
-            KeyCode::Up => {
-                self.move_up();
+            keymap::Action::Favorite if self.focused_panel == FocusedPanel::Commands => {
+                self.toggle_favorite();
             }
-            KeyCode::Down => {
-                self.move_down();
+            keymap::Action::Execute if self.focused_panel == FocusedPanel::Commands => {
+                self.execute_selected_command().await?;
             }
-            KeyCode::Left => {
-                if self.focused_panel == FocusedPanel::Commands {
-                    self.focused_panel = FocusedPanel::Categories;
-                }
+            keymap::Action::Execute if self.focused_panel == FocusedPanel::Details => {
+                self.open_output_viewer();
             }
-            KeyCode::Right => {
-                if self.focused_panel == FocusedPanel::Categories {
-                    self.focused_panel = FocusedPanel::Commands;
-                }
+            keymap::Action::ViewOutput => {
+                self.open_output_viewer();
+            }
+            keymap::Action::Diff => {
+                self.open_diff_viewer();
+            }
+            keymap::Action::ToggleDetails if self.focused_panel == FocusedPanel::Commands => {
+                self.show_command_details = !self.show_command_details;
+            }
+            keymap::Action::Up => self.move_by(-1),
+            keymap::Action::Down => self.move_by(1),
+            keymap::Action::PageUp => self.move_by(-(self.page_size as isize)),
+            keymap::Action::PageDown => self.move_by(self.page_size as isize),
+            keymap::Action::Top => self.move_by(isize::MIN),
+            keymap::Action::Bottom => self.move_by(isize::MAX),
+            keymap::Action::ToggleCategories => {
+                self.toggle_categories();
+            }
+            keymap::Action::Zoom => {
+                self.zoomed = !self.zoomed;
+            }
+            keymap::Action::Left
+                if self.focused_panel == FocusedPanel::Commands && !self.categories_collapsed =>
+            {
+                self.focused_panel = FocusedPanel::Categories;
+            }
+            keymap::Action::Right if self.focused_panel == FocusedPanel::Categories => {
+                self.focused_panel = FocusedPanel::Commands;
             }
             _ => {}
         }
@@ -191,52 +574,414 @@ impl App {
         Ok(())
     }
 
+    fn open_output_viewer(&mut self) {
+        if self.command_output.is_empty() {
+            return;
+        }
+        let title = self
+            .output_source
+            .as_ref()
+            .map_or("Output", |command| command.name.as_str());
+        self.output_viewer = Some(OutputViewer::new(
+            title,
+            &self.command_output,
+            self.config.behavior.max_output_lines,
+        ));
+    }
+
+    /// Compare the stored runs of the selected command, latest against the one before
+    fn open_diff_viewer(&mut self) {
+        let Some(command) = self
+            .categories
+            .get(self.current_category)
+            .and_then(|category| category.commands.get(self.current_command))
+        else {
+            return;
+        };
+        let runs = History::new(&self.config)
+            .runs(&command.id())
+            .unwrap_or_default();
+        let baseline = history::baseline(&runs, None).unwrap_or(0);
+        self.run_diff = Some(RunDiff {
+            name: command.name.clone(),
+            runs,
+            baseline,
+        });
+        self.show_run_diff();
+    }
+
+    fn show_run_diff(&mut self) {
+        let Some(diff) = &self.run_diff else {
+            return;
+        };
+        let mut viewer = OutputViewer::new(
+            &format!("Changes in {}", diff.name),
+            &diff.lines(),
+            self.config.behavior.max_output_lines,
+        );
+        if diff.runs.len() > 2 {
+            viewer.status = Some("[ / ] compare with an older/newer run".to_string());
+        }
+        self.output_viewer = Some(viewer);
+    }
+
+    /// Move the diff baseline, never onto the latest run itself
+    fn move_diff_baseline(&mut self, older: bool) {
+        let Some(diff) = &mut self.run_diff else {
+            return;
+        };
+        let last = diff.runs.len().saturating_sub(2);
+        diff.baseline = if older {
+            diff.baseline.saturating_sub(1)
+        } else {
+            (diff.baseline + 1).min(last)
+        };
+        self.show_run_diff();
+    }
+
+    fn close_viewer(&mut self) {
+        self.output_viewer = None;
+        self.run_diff = None;
+    }
+
+    fn handle_viewer_key(&mut self, key: KeyEvent) {
+        let Some(viewer) = &mut self.output_viewer else {
+            return;
+        };
+        viewer.status = None;
+
+        // Movement follows the active keymap, the viewer's own keys are fixed
+        match self.keymap.action_for(&key) {
+            Some(keymap::Action::Up) => return viewer.move_cursor(-1),
+            Some(keymap::Action::Down) => return viewer.move_cursor(1),
+            Some(keymap::Action::PageUp) => return viewer.move_cursor(-viewer.page_size()),
+            Some(keymap::Action::PageDown) => return viewer.move_cursor(viewer.page_size()),
+            Some(keymap::Action::Top) => return viewer.move_cursor(isize::MIN),
+            Some(keymap::Action::Bottom) => return viewer.move_cursor(isize::MAX),
+            Some(keymap::Action::Left) => return viewer.scroll_horizontal(-8),
+            Some(keymap::Action::Right) => return viewer.scroll_horizontal(8),
+            Some(keymap::Action::Help) => {
+                self.show_help = !self.show_help;
+                return;
+            }
+            Some(keymap::Action::Search) => {
+                self.searching = true;
+                self.input_mode = true;
+                self.input_buffer.clear();
+                return;
+            }
+            Some(keymap::Action::Quit) => {
+                self.close_viewer();
+                return;
+            }
+            _ => {}
+        }
+
+        match key.code {
+            KeyCode::Esc => self.close_viewer(),
+            KeyCode::Char('[') => self.move_diff_baseline(true),
+            KeyCode::Char(']') => self.move_diff_baseline(false),
+            KeyCode::Char('n') => viewer.next_match(true),
+            KeyCode::Char('N') => viewer.next_match(false),
+            KeyCode::Char('w') => viewer.toggle_wrap(),
+            KeyCode::Char('v') => viewer.toggle_selection(),
+            KeyCode::Char('y') => viewer.copy_selection(),
+            KeyCode::Char('s') => viewer.save(&self.config.directories.data_dir),
+            _ => {}
+        }
+    }
+
+    /// Select the first command whose name, tags or description contain the search text
+    fn jump_to_match(&mut self) {
+        if let Some(viewer) = &mut self.output_viewer {
+            viewer.set_search(self.input_buffer.trim());
+            return;
+        }
+
+        let query = self.input_buffer.trim().to_lowercase();
+        if query.is_empty() {
+            return;
+        }
+
+        let matches = |command: &Command| {
+            command.name.to_lowercase().contains(&query)
+                || command.tags.iter().any(|tag| tag.to_lowercase() == query)
+        };
+        let loosely = |command: &Command| command.description.to_lowercase().contains(&query);
+
+        // Prefer name and tag hits anywhere over description hits
+        let found = [&matches as &dyn Fn(&Command) -> bool, &loosely]
+            .into_iter()
+            .find_map(|predicate| {
+                self.categories
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, category)| !favorites::is_virtual(category))
+                    .find_map(|(category_index, category)| {
+                        let command_index = category.commands.iter().position(predicate)?;
+                        Some((category_index, command_index))
+                    })
+            });
+
+        if let Some((category_index, command_index)) = found {
+            self.current_category = category_index;
+            self.category_list_state.select(Some(category_index));
+            self.current_command = command_index;
+            self.command_list_state.select(Some(command_index));
+            self.focused_panel = FocusedPanel::Commands;
+        }
+    }
+
+    fn handle_firewall_key(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.firewall_editor else {
+            return;
+        };
+
+        // List movement follows the active keymap, the editor's own keys are fixed
+        match self.keymap.action_for(&key) {
+            Some(keymap::Action::Up) => return editor.move_selection(false),
+            Some(keymap::Action::Down) => return editor.move_selection(true),
+            _ => {}
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.firewall_editor = None,
+            KeyCode::Char('r') => self.firewall_editor = Some(FirewallEditor::load()),
+            KeyCode::Char('a') => {
+                editor.error = None;
+                self.input_mode = true;
+                self.input_buffer.clear();
+            }
+            KeyCode::Char('d') => {
+                if let Some(rule) = editor.selected_rule() {
+                    let rule = rule.to_string();
+                    self.queue_firewall_change(&["remove", &rule]);
+                }
+            }
+            KeyCode::Char('i') | KeyCode::Char('o') => {
+                let (direction, current) = match key.code {
+                    KeyCode::Char('i') => ("incoming", editor.policies.and_then(|p| p.incoming)),
+                    _ => ("outgoing", editor.policies.and_then(|p| p.outgoing)),
+                };
+                let next = if current == Some(Action::Allow) {
+                    "deny"
+                } else {
+                    "allow"
+                };
+                self.queue_firewall_change(&["default", direction, next]);
+            }
+            _ => {}
+        }
+    }
+
+    /// Validate the rule typed in the editor and queue it for adding
+    fn submit_firewall_rule(&mut self) {
+        let input = self.input_buffer.trim().to_string();
+        if input.is_empty() {
+            return;
+        }
+        match input.parse::<Rule>() {
+            Ok(rule) => self.queue_firewall_change(&["add", &rule.to_string()]),
+            Err(e) => {
+                if let Some(editor) = &mut self.firewall_editor {
+                    editor.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Changes run in the terminal with sudo so the safety timer can ask
+    /// for confirmation and the run is audited
+    fn queue_firewall_change(&mut self, args: &[&str]) {
+        let mut argv = vec!["firewall"];
+        argv.extend_from_slice(args);
+        self.pending_command = Some(linux_toolkit::cli::commands::toolkit_command(
+            "Firewall Change",
+            "Apply a firewall change with automatic revert",
+            &argv,
+            true,
+            "network",
+            &["network", "firewall"],
+        ));
+        self.execute_in_terminal = true;
+    }
+
     fn cycle_focus(&mut self) {
         self.focused_panel = match self.focused_panel {
             FocusedPanel::Categories => FocusedPanel::Commands,
             FocusedPanel::Commands => FocusedPanel::Details,
+            FocusedPanel::Details if self.categories_collapsed => FocusedPanel::Commands,
             FocusedPanel::Details => FocusedPanel::Categories,
         };
     }
 
-    fn move_up(&mut self) {
-        match self.focused_panel {
+    fn toggle_categories(&mut self) {
+        self.categories_collapsed = !self.categories_collapsed;
+        if self.categories_collapsed && self.focused_panel == FocusedPanel::Categories {
+            self.focused_panel = FocusedPanel::Commands;
+        }
+    }
+
+    /// Move the selection in the focused list by `delta`, clamped to its bounds
+    fn move_by(&mut self, delta: isize) {
+        self.move_in(self.focused_panel.clone(), delta);
+    }
+
+    /// Move the selection in `panel`, or scroll the output when it is the details panel
+    fn move_in(&mut self, panel: FocusedPanel, delta: isize) {
+        let clamp = |current: usize, len: usize| {
+            (current as isize)
+                .saturating_add(delta)
+                .clamp(0, len.saturating_sub(1) as isize) as usize
+        };
+
+        match panel {
             FocusedPanel::Categories => {
-                if self.current_category > 0 {
-                    self.current_category -= 1;
-                    self.category_list_state.select(Some(self.current_category));
+                let index = clamp(self.current_category, self.categories.len());
+                if index != self.current_category {
+                    self.current_category = index;
+                    self.category_list_state.select(Some(index));
                     self.update_commands_for_category();
                 }
             }
             FocusedPanel::Commands => {
-                if self.current_command > 0 {
-                    self.current_command -= 1;
+                if let Some(category) = self.categories.get(self.current_category) {
+                    self.current_command = clamp(self.current_command, category.commands.len());
                     self.command_list_state.select(Some(self.current_command));
                 }
             }
-            _ => {}
+            FocusedPanel::Details => {
+                self.output_scroll = clamp(self.output_scroll as usize, self.command_output.len())
+                    .min(u16::MAX as usize) as u16;
+            }
         }
     }
 
-    fn move_down(&mut self) {
-        match self.focused_panel {
-            FocusedPanel::Categories => {
-                if self.current_category < self.categories.len().saturating_sub(1) {
-                    self.current_category += 1;
-                    self.category_list_state.select(Some(self.current_category));
-                    self.update_commands_for_category();
-                }
+    async fn handle_mouse_event(&mut self, mouse: MouseEvent) -> Result<()> {
+        if let Some(viewer) = &mut self.output_viewer {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => viewer.move_cursor(-3),
+                MouseEventKind::ScrollDown => viewer.move_cursor(3),
+                _ => {}
             }
-            FocusedPanel::Commands => {
-                if let Some(category) = self.categories.get(self.current_category) {
-                    if self.current_command < category.commands.len().saturating_sub(1) {
-                        self.current_command += 1;
-                        self.command_list_state.select(Some(self.current_command));
+            return Ok(());
+        }
+
+        // Popups and the input line own the screen until they are closed
+        if self.input_mode || self.firewall_editor.is_some() {
+            return Ok(());
+        }
+        if self.show_help {
+            if let MouseEventKind::Down(MouseButton::Left) = mouse.kind {
+                self.show_help = false;
+            }
+            return Ok(());
+        }
+
+        let Some((panel, area)) = self.panel_at(mouse.column, mouse.row) else {
+            return Ok(());
+        };
+
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.move_in(panel, -1),
+            MouseEventKind::ScrollDown => self.move_in(panel, 1),
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.focused_panel = panel.clone();
+
+                // Clicks on the border only change focus
+                let inside = mouse.row > area.y
+                    && mouse.row < area.bottom().saturating_sub(1)
+                    && mouse.column > area.x
+                    && mouse.column < area.right().saturating_sub(1);
+                if !inside || panel == FocusedPanel::Details {
+                    self.last_click = None;
+                    return Ok(());
+                }
+
+                let row = (mouse.row - area.y - 1) as usize;
+                let index = match panel {
+                    FocusedPanel::Categories => self.category_list_state.offset() + row,
+                    _ => self.command_list_state.offset() + row,
+                };
+                let double_click = matches!(
+                    &self.last_click,
+                    Some((last_panel, last_index, at))
+                        if *last_panel == panel && *last_index == index && at.elapsed() <= DOUBLE_CLICK
+                );
+                self.last_click = Some((panel.clone(), index, Instant::now()));
+
+                match panel {
+                    FocusedPanel::Categories
+                        if index < self.categories.len() && index != self.current_category =>
+                    {
+                        self.current_category = index;
+                        self.category_list_state.select(Some(index));
+                        self.update_commands_for_category();
                     }
+                    FocusedPanel::Commands => {
+                        let len = self
+                            .categories
+                            .get(self.current_category)
+                            .map_or(0, |category| category.commands.len());
+                        if index < len {
+                            self.current_command = index;
+                            self.command_list_state.select(Some(index));
+                            if double_click {
+                                self.last_click = None;
+                                self.execute_selected_command().await?;
+                            }
+                        }
+                    }
+                    _ => {}
                 }
             }
             _ => {}
         }
+        Ok(())
+    }
+
+    fn panel_at(&self, column: u16, row: u16) -> Option<(FocusedPanel, Rect)> {
+        let position = ratatui::layout::Position { x: column, y: row };
+        [
+            (FocusedPanel::Categories, self.panel_areas.categories),
+            (FocusedPanel::Commands, self.panel_areas.commands),
+            (FocusedPanel::Details, self.panel_areas.details),
+        ]
+        .into_iter()
+        .find(|(_, area)| area.contains(position))
+    }
+
+    /// Show the output of a finished built-in command and store it for comparison
+    async fn collect_native_run(&mut self) {
+        if !self
+            .native_run
+            .as_ref()
+            .is_some_and(|run| run.task.is_finished())
+        {
+            return;
+        }
+        let Some(run) = self.native_run.take() else {
+            return;
+        };
+        let output = match run.task.await {
+            Ok(Ok(output)) => {
+                let record = ExecutionRecord {
+                    command_id: run.command.id(),
+                    command_name: run.command.name.clone(),
+                    started_at: run.started_at,
+                    success: Some(true),
+                    output: output.clone(),
+                };
+                // Failing to store only costs the comparison later
+                let _ = history::remember(&self.config, &run.command.id(), &record);
+                output
+            }
+            Ok(Err(e)) => format!("❌ Execution failed: {}", e),
+            Err(e) => format!("❌ Execution failed: {}", e),
+        };
+        self.command_output = output.lines().map(String::from).collect();
+        self.output_scroll = 0;
+        self.output_source = Some(run.command);
     }
 
     fn update_commands_for_category(&mut self) {
@@ -245,8 +990,51 @@ impl App {
     }
 
     async fn execute_selected_command(&mut self) -> Result<()> {
+        self.output_scroll = 0;
         if let Some(category) = self.categories.get(self.current_category) {
             if let Some(command) = category.commands.get(self.current_command) {
+                if let Some(reason) = &command.unavailable {
+                    self.command_output = vec![format!(
+                        "❌ {} is not available on this system: {}",
+                        command.name, reason
+                    )];
+                    self.output_source = None;
+                    return Ok(());
+                }
+
+                let command = command.clone();
+                self.usage.record_use(&command.id());
+                self.save_usage();
+                self.refresh_virtual_categories();
+                if self.categories[self.current_category].name == favorites::RECENT_CATEGORY {
+                    // The command just moved to the top of Recent
+                    self.current_command = 0;
+                    self.command_list_state.select(Some(0));
+                }
+                let command = &command;
+
+                if command.command == "native"
+                    && command.args.first().map(String::as_str) == Some("firewall-rules")
+                {
+                    self.firewall_editor = Some(FirewallEditor::load());
+                    return Ok(());
+                }
+
+                // Built-in commands render their output in the details panel
+                if command.command == "native" {
+                    if let Some(run) = &self.native_run {
+                        self.command_output = vec![format!(
+                            "⏳ {} is still running, wait for it to finish",
+                            run.command.name
+                        )];
+                        return Ok(());
+                    }
+                    self.command_output.clear();
+                    self.output_source = None;
+                    self.native_run = Some(NativeRun::start(command.clone(), self.config.clone()));
+                    return Ok(());
+                }
+
                 // Set the command to execute in terminal
                 self.pending_command = Some(command.clone());
                 self.execute_in_terminal = true;
@@ -259,23 +1047,337 @@ impl App {
         Ok(())
     }
 
+    /// Write the output shown in the details panel to a Markdown report
+    fn export_last_output(&mut self) {
+        let Some(command) = &self.output_source else {
+            return;
+        };
+        if self.command_output.is_empty() {
+            return;
+        }
+
+        let path = linux_toolkit::cli::report::default_export_path(
+            &self.config.directories.data_dir,
+            &command.name,
+        );
+        let report = linux_toolkit::Report::new(&command.name).with_execution(
+            linux_toolkit::ExecutionRecord {
+                command_id: command.id(),
+                command_name: command.name.clone(),
+                started_at: chrono::Utc::now(),
+                success: None,
+                output: self.command_output.join("\n"),
+            },
+        );
+
+        let message = match report.write_to(&path) {
+            Ok(()) => format!("📄 Exported to {}", path.display()),
+            Err(e) => format!("❌ Export failed: {}", e),
+        };
+        self.command_output.push(String::new());
+        self.command_output.push(message);
+    }
+
     fn ui(&mut self, f: &mut Frame) {
+        let size = f.size();
+        let header_height = if size.height < SHORT_HEIGHT && !self.searching {
+            0
+        } else {
+            3
+        };
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(header_height),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(size);
+        if header_height > 0 {
+            self.render_system_info(f, rows[0]);
+        }
+        self.render_status_bar(f, rows[2]);
+
+        self.panel_areas = self.panel_layout(rows[1]);
+        let areas = self.panel_areas;
+        if !areas.categories.is_empty() {
+            self.render_categories(f, areas.categories);
+        }
+        if !areas.commands.is_empty() {
+            self.render_commands(f, areas.commands);
+        }
+        if !areas.details.is_empty() {
+            self.render_details(f, areas.details);
+        }
+
+        if self.firewall_editor.is_some() {
+            self.render_firewall_editor(f);
+        }
+
+        if let Some(viewer) = &mut self.output_viewer {
+            let footer = self.searching.then(|| {
+                Line::from(vec![
+                    Span::styled("Search: ", Style::default().fg(Color::Green)),
+                    Span::styled(
+                        format!("{}_", self.input_buffer),
+                        Style::default().fg(Color::White),
+                    ),
+                ])
+            });
+            viewer.render(f, f.size(), footer);
+        }
+
+        if self.show_help {
+            self.render_help_popup(f);
+        }
+    }
+
+    fn render_firewall_editor(&mut self, f: &mut Frame) {
+        let popup_area = self.centered_rect(80, 80, f.size());
+        let input_mode = self.input_mode;
+        let input = self.input_buffer.clone();
+        let Some(editor) = &mut self.firewall_editor else {
+            return;
+        };
+
+        f.render_widget(Clear, popup_area);
         let chunks = Layout::default()
-            .direction(Direction::Horizontal)
+            .direction(Direction::Vertical)
             .constraints([
+                Constraint::Length(4),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ])
+            .split(popup_area);
+
+        let policy = |action: Option<Action>| match action {
+            Some(Action::Allow) => "allow",
+            Some(Action::Deny) => "deny",
+            Some(Action::Reject) => "reject",
+            Some(Action::Limit) => "limit",
+            None => "unknown",
+        };
+        let header = match editor.detected {
+            Some(detected) => vec![
+                Line::from(vec![
+                    Span::styled("Backend: ", Style::default().fg(Color::Green)),
+                    Span::styled(
+                        format!(
+                            "{} ({})",
+                            detected.backend.name(),
+                            if detected.active {
+                                "active"
+                            } else {
+                                "inactive"
+                            }
+                        ),
+                        Style::default().fg(if detected.active {
+                            Color::White
+                        } else {
+                            Color::Yellow
+                        }),
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled("Default: ", Style::default().fg(Color::Green)),
+                    Span::styled(
+                        format!(
+                            "incoming {}, outgoing {}",
+                            policy(editor.policies.and_then(|p| p.incoming)),
+                            policy(editor.policies.and_then(|p| p.outgoing))
+                        ),
+                        Style::default().fg(Color::White),
+                    ),
+                ]),
+            ],
+            None => vec![Line::from(Span::styled(
+                "No supported firewall found",
+                Style::default().fg(Color::Red),
+            ))],
+        };
+        f.render_widget(
+            Paragraph::new(header).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Firewall")
+                    .border_style(Style::default().fg(Color::Green)),
+            ),
+            chunks[0],
+        );
+
+        let items: Vec<ListItem> = editor
+            .rules
+            .iter()
+            .map(|rule| {
+                let color = match rule.action {
+                    Action::Allow => Color::Green,
+                    Action::Limit => Color::Yellow,
+                    Action::Deny | Action::Reject => Color::Red,
+                };
+                ListItem::new(Line::from(Span::styled(
+                    rule.to_string(),
+                    Style::default().fg(color),
+                )))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Rules")
+                    .border_style(Style::default().fg(Color::Green)),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            );
+        f.render_stateful_widget(list, chunks[1], &mut editor.list_state);
+
+        let footer = if input_mode {
+            Line::from(vec![
+                Span::styled("New rule: ", Style::default().fg(Color::Green)),
+                Span::styled(format!("{}_", input), Style::default().fg(Color::White)),
+            ])
+        } else if let Some(error) = &editor.error {
+            Line::from(Span::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ))
+        } else {
+            Line::from(Span::styled(
+                "a add  d delete  i/o toggle default in/out  r refresh  Esc close",
+                Style::default().fg(Color::Gray),
+            ))
+        };
+        let title = if input_mode {
+            "allow|deny|reject [in|out] [tcp|udp] [PORT] [from ADDRESS]"
+        } else {
+            "Keys"
+        };
+        f.render_widget(
+            Paragraph::new(footer).block(Block::default().borders(Borders::ALL).title(title)),
+            chunks[2],
+        );
+    }
+
+    /// Place the panels: side by side, stacked on narrow screens, or only the
+    /// focused one when zoomed. Hidden panels get an empty area.
+    fn panel_layout(&self, area: Rect) -> PanelAreas {
+        if self.zoomed {
+            let mut areas = PanelAreas::default();
+            match self.focused_panel {
+                FocusedPanel::Categories => areas.categories = area,
+                FocusedPanel::Commands => areas.commands = area,
+                FocusedPanel::Details => areas.details = area,
+            }
+            return areas;
+        }
+
+        let narrow = area.width < NARROW_WIDTH;
+        let constraints = if self.categories_collapsed {
+            [
+                Constraint::Length(0),
+                Constraint::Percentage(45),
+                Constraint::Percentage(55),
+            ]
+        } else {
+            [
                 Constraint::Percentage(25),
                 Constraint::Percentage(35),
                 Constraint::Percentage(40),
-            ])
-            .split(f.size());
+            ]
+        };
+        let chunks = Layout::default()
+            .direction(if narrow {
+                Direction::Vertical
+            } else {
+                Direction::Horizontal
+            })
+            .constraints(constraints)
+            .split(area);
 
-        self.render_categories(f, chunks[0]);
-        self.render_commands(f, chunks[1]);
-        self.render_details(f, chunks[2]);
+        PanelAreas {
+            categories: chunks[0],
+            commands: chunks[1],
+            details: chunks[2],
+        }
+    }
 
-        if self.show_help {
-            self.render_help_popup(f);
+    fn render_status_bar(&self, f: &mut Frame, area: Rect) {
+        let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+        let privilege_color = match self.host.privilege {
+            "root" => Color::Red,
+            "sudo ready" => Color::Yellow,
+            _ => Color::Gray,
+        };
+
+        let mut hints = vec![
+            (keymap::Action::Help, "help"),
+            (keymap::Action::Search, "search"),
+            (keymap::Action::Zoom, "zoom"),
+            (keymap::Action::ToggleCategories, "categories"),
+            (keymap::Action::Quit, "quit"),
+        ]
+        .into_iter()
+        .map(|(action, label)| format!("{} {}", self.keymap.describe(action), label))
+        .collect::<Vec<_>>()
+        .join("  ");
+        if self.zoomed {
+            hints.insert_str(0, "[zoomed]  ");
+        }
+
+        let line = Line::from(vec![
+            Span::styled(
+                format!(" {}", self.host.hostname),
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            separator.clone(),
+            Span::styled(self.host.user.as_str(), Style::default().fg(Color::White)),
+            separator.clone(),
+            Span::styled(self.host.privilege, Style::default().fg(privilege_color)),
+            separator,
+            Span::styled(hints, Style::default().fg(Color::Gray)),
+        ]);
+        f.render_widget(
+            Paragraph::new(line).style(Style::default().bg(Color::Black)),
+            area,
+        );
+    }
+
+    fn render_system_info(&self, f: &mut Frame, area: Rect) {
+        if self.searching {
+            let line = Line::from(vec![
+                Span::styled("Search: ", Style::default().fg(Color::Green)),
+                Span::styled(
+                    format!("{}_", self.input_buffer),
+                    Style::default().fg(Color::White),
+                ),
+            ]);
+            let paragraph = Paragraph::new(line).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Enter to keep, Esc to close")
+                    .border_style(Style::default().fg(Color::Green)),
+            );
+            f.render_widget(paragraph, area);
+            return;
         }
+
+        let line = Line::from(Span::styled(
+            self.host.summary.as_str(),
+            Style::default().fg(Color::White),
+        ));
+        let paragraph = Paragraph::new(line).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("System Info")
+                .border_style(Style::default().fg(Color::Gray)),
+        );
+        f.render_widget(paragraph, area);
     }
 
     fn render_categories(&mut self, f: &mut Frame, area: Rect) {
@@ -314,28 +1416,44 @@ impl App {
     }
 
     fn render_commands(&mut self, f: &mut Frame, area: Rect) {
-        let items: Vec<ListItem> =
-            if let Some(category) = self.categories.get(self.current_category) {
-                category
-                    .commands
-                    .iter()
-                    .map(|command| {
-                        let mut spans = vec![Span::styled(
-                            &command.name,
-                            Style::default().fg(Color::White),
-                        )];
+        self.page_size = area.height.saturating_sub(2).max(1) as usize;
+        let items: Vec<ListItem> = if let Some(category) =
+            self.categories.get(self.current_category)
+        {
+            category
+                .commands
+                .iter()
+                .map(|command| {
+                    // Commands this host cannot run are greyed out
+                    let color = if command.unavailable.is_some() || !command.missing.is_empty() {
+                        Color::DarkGray
+                    } else {
+                        Color::White
+                    };
+                    let mut spans = vec![Span::styled(&command.name, Style::default().fg(color))];
 
-                        // Add sudo indicator if command requires elevation
-                        if command.requires_sudo {
-                            spans.push(Span::styled(" 🔐", Style::default().fg(Color::Yellow)));
-                        }
+                    // Add sudo indicator if command requires elevation
+                    if command.requires_sudo {
+                        spans.push(Span::styled(" 🔐", Style::default().fg(Color::Yellow)));
+                    }
 
-                        ListItem::new(Line::from(spans))
-                    })
-                    .collect()
-            } else {
-                vec![]
-            };
+                    if self.usage.is_favorite(&command.id()) {
+                        spans.push(Span::styled(" ★", Style::default().fg(Color::Yellow)));
+                    }
+
+                    if !command.missing.is_empty() {
+                        spans.push(Span::styled(
+                            format!(" missing: {}", command.missing.join(", ")),
+                            Style::default().fg(Color::Red),
+                        ));
+                    }
+
+                    ListItem::new(Line::from(spans))
+                })
+                .collect()
+        } else {
+            vec![]
+        };
 
         let border_style = if self.focused_panel == FocusedPanel::Commands {
             Style::default().fg(Color::Green)
@@ -367,8 +1485,8 @@ impl App {
             Style::default().fg(Color::Gray)
         };
 
-        if self.executing_command {
-            let paragraph = Paragraph::new("Executing command...")
+        if let Some(run) = &self.native_run {
+            let paragraph = Paragraph::new(run.progress())
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
@@ -381,7 +1499,11 @@ impl App {
         }
 
         if !self.command_output.is_empty() {
-            let output_text = self.command_output.join("\n");
+            let output_text: Vec<Line> = self
+                .command_output
+                .iter()
+                .map(|line| crate::viewer::styled_line(line, None))
+                .collect();
             let paragraph = Paragraph::new(output_text)
                 .block(
                     Block::default()
@@ -389,7 +1511,8 @@ impl App {
                         .title("Output")
                         .border_style(border_style),
                 )
-                .wrap(Wrap { trim: true })
+                .wrap(Wrap { trim: false })
+                .scroll((self.output_scroll, 0))
                 .style(Style::default().fg(Color::White));
             f.render_widget(paragraph, area);
             return;
@@ -418,6 +1541,32 @@ impl App {
                     text.push(Line::from(""));
                 }
 
+                if favorites::is_virtual(category) {
+                    text.push(Line::from(vec![
+                        Span::styled("Category: ", Style::default().fg(Color::Green)),
+                        Span::styled(&command.category, Style::default().fg(Color::White)),
+                    ]));
+                    text.push(Line::from(""));
+                }
+
+                if let Some(usage) = self.usage.usage.get(&command.id()) {
+                    text.push(Line::from(vec![
+                        Span::styled("Last run: ", Style::default().fg(Color::Green)),
+                        Span::styled(
+                            format!(
+                                "{} ({} run(s) in total)",
+                                usage
+                                    .last_used
+                                    .with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M"),
+                                usage.count
+                            ),
+                            Style::default().fg(Color::White),
+                        ),
+                    ]));
+                    text.push(Line::from(""));
+                }
+
                 // Show permission requirements
                 text.push(Line::from(vec![
                     Span::styled("Permissions: ", Style::default().fg(Color::Green)),
@@ -432,6 +1581,30 @@ impl App {
                 ]));
                 text.push(Line::from(""));
 
+                if let Some(reason) = &command.unavailable {
+                    text.push(Line::from(vec![
+                        Span::styled("Availability: ", Style::default().fg(Color::Green)),
+                        Span::styled(
+                            format!("✗ Not available, {}", reason),
+                            Style::default().fg(Color::Red),
+                        ),
+                    ]));
+                    text.push(Line::from(""));
+                } else if !command.missing.is_empty() {
+                    text.push(Line::from(vec![
+                        Span::styled("Availability: ", Style::default().fg(Color::Green)),
+                        Span::styled(
+                            format!(
+                                "✗ Missing {}, press {} to install",
+                                command.missing.join(", "),
+                                self.keymap.describe(keymap::Action::Execute)
+                            ),
+                            Style::default().fg(Color::Red),
+                        ),
+                    ]));
+                    text.push(Line::from(""));
+                }
+
                 if !command.tags.is_empty() {
                     text.push(Line::from(vec![
                         Span::styled("Tags: ", Style::default().fg(Color::Green)),
@@ -443,12 +1616,15 @@ impl App {
                 text.push(Line::from(""));
                 text.push(Line::from(vec![
                     Span::styled("Press ", Style::default().fg(Color::Gray)),
-                    Span::styled("Enter", Style::default().fg(Color::Green)),
+                    Span::styled(
+                        self.keymap.describe(keymap::Action::Execute),
+                        Style::default().fg(Color::Green),
+                    ),
                     Span::styled(" to execute", Style::default().fg(Color::Gray)),
                 ]));
 
                 // Add permission hint
-                if command.requires_sudo && !crate::utils::is_root() {
+                if command.requires_sudo && self.host.privilege != "root" {
                     text.push(Line::from(vec![
                         Span::styled("Note: ", Style::default().fg(Color::Yellow)),
                         Span::styled(
@@ -476,7 +1652,21 @@ impl App {
 
         f.render_widget(Clear, popup_area);
 
-        let help_text = vec![
+        // Key sections come from the active keymap so they match the config
+        let section = |navigation: bool| {
+            keymap::Action::ALL
+                .into_iter()
+                .filter(move |action| action.is_navigation() == navigation)
+                .map(|action| {
+                    Line::from(format!(
+                        "  {:<14} - {}",
+                        self.keymap.describe(action),
+                        action.description()
+                    ))
+                })
+        };
+
+        let mut help_text = vec![
             Line::from(vec![Span::styled(
                 "Linux Toolkit - Help",
                 Style::default()
@@ -488,18 +1678,33 @@ impl App {
                 "Navigation:",
                 Style::default().fg(Color::Yellow),
             )]),
-            Line::from("  ↑/↓        - Navigate lists"),
-            Line::from("  ←/→        - Switch between panels"),
-            Line::from("  Tab        - Cycle through panels"),
-            Line::from("  Enter      - Execute selected command"),
-            Line::from("  Space      - Toggle command details"),
+        ];
+        help_text.extend(section(true));
+        help_text.push(Line::from(""));
+        help_text.push(Line::from(vec![Span::styled(
+            "General:",
+            Style::default().fg(Color::Yellow),
+        )]));
+        help_text.extend(section(false));
+        help_text.extend([
+            Line::from(""),
+            Line::from(vec![Span::styled(
+                "Firewall editor:",
+                Style::default().fg(Color::Yellow),
+            )]),
+            Line::from("  a/d        - Add or delete a rule"),
+            Line::from("  i/o        - Toggle default incoming/outgoing policy"),
+            Line::from("  • Changes revert unless connectivity is confirmed"),
             Line::from(""),
             Line::from(vec![Span::styled(
-                "General:",
+                "Output viewer:",
                 Style::default().fg(Color::Yellow),
             )]),
-            Line::from("  h/F1       - Toggle this help"),
-            Line::from("  q/Esc      - Quit application"),
+            Line::from("  n/N        - Next/previous search match"),
+            Line::from("  w          - Toggle line wrapping"),
+            Line::from("  v/y        - Select lines, copy them to the clipboard"),
+            Line::from("  s          - Save output to a file"),
+            Line::from("  [/]        - Compare with an older/newer run"),
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Permissions:",
@@ -516,10 +1721,14 @@ impl App {
             Line::from("  • Commands are organized by category"),
             Line::from("  • Green highlights indicate focus"),
             Line::from("  • Output appears in the details panel"),
+            Line::from("  • Click to select, double-click to run, wheel to scroll"),
             Line::from("  • Permission denied? Tool will auto-elevate"),
             Line::from(""),
-            Line::from("Press h or F1 to close this help"),
-        ];
+            Line::from(format!(
+                "Press {} to close this help",
+                self.keymap.describe(keymap::Action::Help)
+            )),
+        ]);
 
         let paragraph = Paragraph::new(help_text)
             .block(
@@ -553,3 +1762,187 @@ impl App {
             .split(popup_layout[1])[1]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use linux_toolkit::testing::{self, TempDir};
+    use ratatui::backend::TestBackend;
+    use std::path::PathBuf;
+
+    fn command(name: &str, category: &str, program: &str, requires_sudo: bool) -> Command {
+        testing::command(name, program)
+            .description(&format!("{} description", name))
+            .tags(&["test"])
+            .category(category)
+            .sudo(requires_sudo)
+            .build()
+    }
+
+    /// The app and the data directory it writes to, removed when dropped
+    fn test_app(name: &str) -> (App, TempDir) {
+        let dir = TempDir::new(name);
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        config.directories.data_dir = dir.path().to_path_buf();
+
+        let categories = vec![
+            CommandCategory {
+                name: "Files".to_string(),
+                description: "File tools".to_string(),
+                commands: vec![
+                    command("Disk Usage", "Files", "du", false),
+                    command("Open Files", "Files", "lsof", true),
+                ],
+            },
+            CommandCategory {
+                name: "System".to_string(),
+                description: "System tools".to_string(),
+                commands: vec![command("Uptime", "System", "uptime", false)],
+            },
+        ];
+        let host = HostInfo {
+            hostname: "testhost".to_string(),
+            user: "tester".to_string(),
+            summary: "Test Linux · x86_64 · systemd".to_string(),
+            privilege: "sudo needs password",
+        };
+        (App::with_categories(config, categories, host).unwrap(), dir)
+    }
+
+    async fn press(app: &mut App, code: KeyCode) {
+        app.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE))
+            .await
+            .unwrap();
+    }
+
+    fn render(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| app.ui(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Compare against `src/snapshots/<name>.txt`; set UPDATE_SNAPSHOTS=1 to rewrite it
+    fn assert_snapshot(name: &str, lines: &[String]) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(format!("{}.txt", name));
+        let actual = lines.join("\n") + "\n";
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("missing snapshot {}", path.display()));
+        assert_eq!(actual, expected, "snapshot {} differs", name);
+    }
+
+    #[test]
+    fn renders_initial_layout() {
+        let (mut app, _dir) = test_app("initial");
+        assert_snapshot("initial_layout", &render(&mut app, 100, 24));
+    }
+
+    #[tokio::test]
+    async fn navigation_updates_details() {
+        let (mut app, _dir) = test_app("navigation");
+        press(&mut app, KeyCode::Right).await;
+        press(&mut app, KeyCode::Down).await;
+
+        assert_eq!(app.focused_panel, FocusedPanel::Commands);
+        assert_eq!(app.current_command, 1);
+        assert_snapshot("navigation", &render(&mut app, 100, 24));
+    }
+
+    #[tokio::test]
+    async fn help_popup_lists_active_keys() {
+        let (mut app, _dir) = test_app("help");
+        press(&mut app, KeyCode::Char('h')).await;
+
+        assert!(app.show_help);
+        assert_snapshot("help_popup", &render(&mut app, 100, 40));
+    }
+
+    #[test]
+    fn help_follows_configured_preset() {
+        let (mut app, _dir) = test_app("vim-help");
+        app.keymap = Keymap::from_config(&linux_toolkit::config::Keybindings {
+            preset: "vim".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        app.show_help = true;
+
+        let screen = render(&mut app, 100, 40).join("\n");
+        assert!(screen.contains("k/↑"), "{}", screen);
+        assert!(screen.contains("?/F1"), "{}", screen);
+    }
+
+    #[test]
+    fn output_replaces_details() {
+        let (mut app, _dir) = test_app("output");
+        app.command_output = vec![
+            "✅ Passed check".to_string(),
+            "⚠ Needs attention".to_string(),
+            "❌ Failed check".to_string(),
+        ];
+        assert_snapshot("output", &render(&mut app, 100, 24));
+    }
+
+    #[tokio::test]
+    async fn diff_view_compares_stored_runs() {
+        let (mut app, _dir) = test_app("diff");
+        let history = History::new(&app.config);
+        for (hour, output) in [(1, "sda 10G\n"), (2, "sda 10G\nsdb 2G\n"), (3, "sdb 4G\n")] {
+            let record = ExecutionRecord {
+                command_id: "files/disk-usage".to_string(),
+                command_name: "Disk Usage".to_string(),
+                started_at: chrono::TimeZone::with_ymd_and_hms(
+                    &chrono::Utc,
+                    2024,
+                    5,
+                    1,
+                    hour,
+                    0,
+                    0,
+                )
+                .unwrap(),
+                success: Some(true),
+                output: output.to_string(),
+            };
+            history.record("files/disk-usage", &record).unwrap();
+        }
+
+        press(&mut app, KeyCode::Char('d')).await;
+        let screen = render(&mut app, 100, 24).join("\n");
+        assert!(screen.contains("Changes in Disk Usage"), "{}", screen);
+        assert!(screen.contains("(2 of 3 stored runs)"), "{}", screen);
+        assert!(screen.contains("- sdb 2G"), "{}", screen);
+
+        press(&mut app, KeyCode::Char('[')).await;
+        let screen = render(&mut app, 100, 24).join("\n");
+        assert!(screen.contains("(1 of 3 stored runs)"), "{}", screen);
+        assert!(screen.contains("- sda 10G"), "{}", screen);
+
+        press(&mut app, KeyCode::Esc).await;
+        assert!(app.output_viewer.is_none() && app.run_diff.is_none());
+    }
+
+    #[test]
+    fn narrow_terminal_stacks_panels() {
+        let (mut app, _dir) = test_app("narrow");
+        assert_snapshot("narrow_layout", &render(&mut app, 60, 30));
+    }
+}
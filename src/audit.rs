@@ -0,0 +1,655 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Hash used as `prev_hash` for the first entry of a fresh log
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// SHA-256 block size, used to pad the HMAC key
+const HMAC_BLOCK: usize = 64;
+
+/// The hashed part of an audit entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub user: String,
+    pub uid: Option<u32>,
+    pub euid: Option<u32>,
+    pub command_id: String,
+    pub argv: Vec<String>,
+    pub script_sha256: Option<String>,
+    pub backend: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub exit_status: Option<i32>,
+    pub prev_hash: String,
+}
+
+/// One line of the audit log: the record plus its chained HMAC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    pub hash: String,
+}
+
+/// Last sequence number and hash, stored next to the log to detect truncation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AuditHead {
+    seq: u64,
+    hash: String,
+    /// HMAC of the two fields above, so the head cannot be rewritten to
+    /// match a truncated log
+    mac: String,
+}
+
+impl AuditHead {
+    fn new(seq: u64, hash: String, key: &[u8]) -> Self {
+        let mac = Self::mac_for(seq, &hash, key);
+        Self { seq, hash, mac }
+    }
+
+    fn mac_for(seq: u64, hash: &str, key: &[u8]) -> String {
+        hex::encode(hmac_sha256(key, format!("{}:{}", seq, hash).as_bytes()))
+    }
+
+    fn is_authentic(&self, key: &[u8]) -> bool {
+        Self::mac_for(self.seq, &self.hash, key) == self.mac
+    }
+}
+
+/// A privileged execution that has started but not yet been recorded
+pub struct PrivilegedRun {
+    command_id: String,
+    argv: Vec<String>,
+    script_sha256: Option<String>,
+    backend: String,
+    started_at: DateTime<Utc>,
+}
+
+impl PrivilegedRun {
+    /// Start tracking an execution if it runs with elevated privileges
+    ///
+    /// Returns `None` for unprivileged runs, which are not audited.
+    pub fn begin(
+        command_id: &str,
+        argv: &[String],
+        elevated_with: Option<&str>,
+        script: Option<&Path>,
+    ) -> Option<Self> {
+        let euid = crate::utils::user_ids().map(|(_, euid)| euid);
+        let backend = match elevated_with {
+            Some(backend) => backend.to_string(),
+            None if euid == Some(0) => "root".to_string(),
+            None => return None,
+        };
+
+        Some(Self {
+            command_id: command_id.to_string(),
+            argv: argv.to_vec(),
+            script_sha256: script.and_then(|path| hash_file(path).ok()),
+            backend,
+            started_at: Utc::now(),
+        })
+    }
+
+    /// Append the finished execution to the audit log
+    ///
+    /// Failures are reported on stderr rather than aborting the command that
+    /// was audited.
+    pub fn finish(self, config: &Config, exit_status: Option<i32>) {
+        if !config.audit.enabled {
+            return;
+        }
+
+        let ids = crate::utils::user_ids();
+        let log = AuditLog::new(config.audit_log_path(), config.audit_key_path());
+        let result = log.append(|seq, prev_hash| AuditRecord {
+            seq,
+            user: crate::utils::get_current_user(),
+            uid: ids.map(|(uid, _)| uid),
+            euid: ids.map(|(_, euid)| euid),
+            command_id: self.command_id.clone(),
+            argv: self.argv.clone(),
+            script_sha256: self.script_sha256.clone(),
+            backend: self.backend.clone(),
+            started_at: self.started_at,
+            finished_at: Utc::now(),
+            exit_status,
+            prev_hash,
+        });
+
+        match result {
+            Ok(entry) => {
+                if config.audit.syslog {
+                    send_to_syslog(&entry);
+                }
+            }
+            Err(e) => eprintln!("Warning: Failed to write audit log: {}", e),
+        }
+    }
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, key_path: PathBuf) -> Self {
+        Self { path, key_path }
+    }
+
+    fn head_path(&self) -> PathBuf {
+        let mut head = self.path.clone().into_os_string();
+        head.push(".head");
+        PathBuf::from(head)
+    }
+
+    /// Append a record built from the next sequence number and previous hash
+    pub fn append<F>(&self, build: F) -> Result<AuditEntry>
+    where
+        F: FnOnce(u64, String) -> AuditRecord,
+    {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true).append(true).read(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&self.path)
+            .with_context(|| format!("opening {}", self.path.display()))?;
+        let _lock = FileLock::acquire(&file)?;
+        let key = self.load_or_create_key()?;
+
+        let (seq, prev_hash) = match self.last_entry()? {
+            Some(entry) => (entry.record.seq + 1, entry.hash),
+            None => (1, GENESIS_HASH.to_string()),
+        };
+
+        let record = build(seq, prev_hash);
+        let entry = AuditEntry {
+            hash: hash_record(&record, &key)?,
+            record,
+        };
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        file.sync_all()?;
+
+        let head = AuditHead::new(entry.record.seq, entry.hash.clone(), &key);
+        write_private(&self.head_path(), serde_json::to_string(&head)?.as_bytes())?;
+
+        Ok(entry)
+    }
+
+    /// Read the HMAC key, `None` when it does not exist yet
+    fn load_key(&self) -> Result<Option<Vec<u8>>> {
+        match fs::read_to_string(&self.key_path) {
+            Ok(content) => Ok(Some(hex::decode(content.trim()).with_context(|| {
+                format!("audit key {} is not valid hex", self.key_path.display())
+            })?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading {}", self.key_path.display())),
+        }
+    }
+
+    fn load_or_create_key(&self) -> Result<Vec<u8>> {
+        if let Some(key) = self.load_key()? {
+            return Ok(key);
+        }
+        let mut key = vec![0u8; 32];
+        File::open("/dev/urandom")
+            .and_then(|mut random| std::io::Read::read_exact(&mut random, &mut key))
+            .context("generating the audit key")?;
+        if let Some(parent) = self.key_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_private(&self.key_path, hex::encode(&key).as_bytes())?;
+        // `verify` treats a key without a log as a deleted log, so a key
+        // created for `sign` comes with an empty one
+        if !self.path.exists() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_private(&self.path, b"")?;
+        }
+        Ok(key)
+    }
+
+    /// HMAC of `message` with the audit key, for records kept outside the log
+    /// that must not be forged by whoever can write them
+    pub(crate) fn sign(&self, message: &[u8]) -> Result<String> {
+        let key = self.load_or_create_key()?;
+        Ok(hex::encode(hmac_sha256(&key, message)))
+    }
+
+    /// Whether `mac` is the HMAC of `message`, an error when there is no key
+    pub(crate) fn is_signed(&self, message: &[u8], mac: &str) -> Result<bool> {
+        let key = self.load_key()?.ok_or_else(|| {
+            anyhow!(
+                "audit key {} is missing, records cannot be authenticated",
+                self.key_path.display()
+            )
+        })?;
+        Ok(hex::encode(hmac_sha256(&key, message)) == mac)
+    }
+
+    /// Most recent successful run of `command_id` in the log, if any
+    pub(crate) fn last_success(&self, command_id: &str) -> Result<Option<AuditRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", self.path.display())),
+        };
+        let mut last = None;
+        for line in BufReader::new(file).lines() {
+            let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) else {
+                continue;
+            };
+            if entry.record.command_id == command_id && entry.record.exit_status == Some(0) {
+                last = Some(entry.record);
+            }
+        }
+        Ok(last)
+    }
+
+    fn last_entry(&self) -> Result<Option<AuditEntry>> {
+        let file = File::open(&self.path)?;
+        let mut last = None;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                last = Some(line);
+            }
+        }
+
+        match last {
+            Some(line) => Ok(Some(serde_json::from_str(&line).map_err(|e| {
+                anyhow!("audit log is corrupt, run `audit verify`: {}", e)
+            })?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Walk the whole chain and report every inconsistency found
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+
+        let key = self.load_key()?;
+        if !self.path.exists() {
+            if self.head_path().exists() {
+                report
+                    .problems
+                    .push("audit log is missing but its head file exists".to_string());
+            } else if key.is_some() {
+                // The key is created with the first entry
+                report
+                    .problems
+                    .push("audit log is missing but its key exists".to_string());
+            }
+            return Ok(report);
+        }
+
+        if key.is_none() {
+            report.problems.push(format!(
+                "audit key {} is missing, entries cannot be authenticated",
+                self.key_path.display()
+            ));
+        }
+
+        let file = File::open(&self.path)?;
+        let mut expected_seq = 1;
+        let mut prev_hash = GENESIS_HASH.to_string();
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let line_no = index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: AuditEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report
+                        .problems
+                        .push(format!("line {}: unreadable entry ({})", line_no, e));
+                    continue;
+                }
+            };
+
+            if entry.record.seq != expected_seq {
+                report.problems.push(format!(
+                    "line {}: expected sequence {} but found {}",
+                    line_no, expected_seq, entry.record.seq
+                ));
+            }
+            if entry.record.prev_hash != prev_hash {
+                report.problems.push(format!(
+                    "line {}: previous hash does not match entry {}",
+                    line_no,
+                    entry.record.seq.saturating_sub(1)
+                ));
+            }
+            let modified = match &key {
+                Some(key) => hash_record(&entry.record, key)? != entry.hash,
+                None => false,
+            };
+            if modified {
+                report.problems.push(format!(
+                    "line {}: entry {} has been modified",
+                    line_no, entry.record.seq
+                ));
+            }
+
+            report.entries += 1;
+            expected_seq = entry.record.seq + 1;
+            prev_hash = entry.hash;
+        }
+
+        match fs::read_to_string(self.head_path()) {
+            Ok(content) => match serde_json::from_str::<AuditHead>(&content) {
+                Ok(head) => {
+                    if key.as_ref().is_some_and(|key| !head.is_authentic(key)) {
+                        report
+                            .problems
+                            .push("head file has been modified".to_string());
+                    } else if head.seq + 1 != expected_seq || head.hash != prev_hash {
+                        report.problems.push(format!(
+                            "log ends at entry {} but the head records entry {}, the log was truncated or rewritten",
+                            expected_seq - 1,
+                            head.seq
+                        ));
+                    }
+                }
+                Err(e) => report
+                    .problems
+                    .push(format!("head file is unreadable ({})", e)),
+            },
+            Err(_) if report.entries > 0 => {
+                report.problems.push("head file is missing".to_string())
+            }
+            Err(_) => {}
+        }
+
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub entries: usize,
+    pub problems: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Handler for `linux-toolkit audit verify`
+pub fn verify_command(config: &Config) -> Result<()> {
+    let path = config.audit_log_path();
+    let report = AuditLog::new(path.clone(), config.audit_key_path()).verify()?;
+
+    println!("Audit log: {}", path.display());
+    println!("Entries checked: {}", report.entries);
+
+    if report.is_intact() {
+        println!("✅ Hash chain intact");
+        return Ok(());
+    }
+
+    for problem in &report.problems {
+        println!("❌ {}", problem);
+    }
+    Err(anyhow!(
+        "audit log verification failed with {} problem(s)",
+        report.problems.len()
+    ))
+}
+
+fn hash_record(record: &AuditRecord, key: &[u8]) -> Result<String> {
+    let bytes = serde_json::to_vec(record)?;
+    Ok(hex::encode(hmac_sha256(key, &bytes)))
+}
+
+/// HMAC-SHA256 as defined in RFC 2104
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; HMAC_BLOCK];
+    if key.len() > HMAC_BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+/// Replace `path` atomically with a file only its owner can read
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let mut temporary = path.to_path_buf().into_os_string();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temporary)
+        .with_context(|| format!("opening {}", temporary.display()))?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+pub fn hash_file(path: &Path) -> Result<String> {
+    let content = fs::read(path)?;
+    Ok(hex::encode(Sha256::digest(&content)))
+}
+
+#[cfg(unix)]
+fn send_to_syslog(entry: &AuditEntry) {
+    use std::ffi::CString;
+
+    let message = format!(
+        "seq={} user={} uid={} euid={} command={} backend={} exit={} hash={}",
+        entry.record.seq,
+        entry.record.user,
+        entry.record.uid.unwrap_or_default(),
+        entry.record.euid.unwrap_or_default(),
+        entry.record.command_id,
+        entry.record.backend,
+        entry
+            .record
+            .exit_status
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "none".to_string()),
+        entry.hash
+    );
+
+    if let (Ok(ident), Ok(format), Ok(message)) = (
+        CString::new("linux-toolkit"),
+        CString::new("%s"),
+        CString::new(message),
+    ) {
+        // SAFETY: all pointers come from live CStrings and the format string
+        // consumes exactly one string argument
+        unsafe {
+            libc::openlog(ident.as_ptr(), libc::LOG_PID, libc::LOG_AUTHPRIV);
+            libc::syslog(libc::LOG_NOTICE, format.as_ptr(), message.as_ptr());
+            libc::closelog();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn send_to_syslog(_entry: &AuditEntry) {}
+
+/// Exclusive advisory lock held while appending, so concurrent instances
+/// cannot interleave entries with the same sequence number
+struct FileLock {
+    #[cfg(unix)]
+    fd: std::os::unix::io::RawFd,
+}
+
+impl FileLock {
+    #[cfg(unix)]
+    fn acquire(file: &File) -> Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = file.as_raw_fd();
+        // SAFETY: the descriptor belongs to an open file owned by the caller
+        if unsafe { libc::flock(fd, libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self { fd })
+    }
+
+    #[cfg(not(unix))]
+    fn acquire(_file: &File) -> Result<Self> {
+        Ok(Self {})
+    }
+}
+
+#[cfg(unix)]
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // SAFETY: the lock is dropped before the file it was taken on
+        unsafe {
+            libc::flock(self.fd, libc::LOCK_UN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn record(seq: u64, prev_hash: String) -> AuditRecord {
+        AuditRecord {
+            seq,
+            user: "alice".to_string(),
+            uid: Some(1000),
+            euid: Some(0),
+            command_id: "security/list-users".to_string(),
+            argv: vec!["sudo".to_string(), "cat".to_string()],
+            script_sha256: None,
+            backend: "sudo".to_string(),
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            exit_status: Some(0),
+            prev_hash,
+        }
+    }
+
+    /// A log with three entries in a fresh directory
+    fn log_with_entries(dir: &Path) -> AuditLog {
+        fs::create_dir_all(dir).unwrap();
+        let log = AuditLog::new(dir.join("audit.log"), dir.join("keys/audit.key"));
+        for _ in 0..3 {
+            log.append(record).unwrap();
+        }
+        log
+    }
+
+    fn rewrite_lines(path: &Path, edit: impl FnOnce(&mut Vec<String>)) {
+        let content = fs::read_to_string(path).unwrap();
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
+        edit(&mut lines);
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn verify_detects_modified_and_truncated_entries() {
+        let dir = TempDir::new("audit");
+
+        let log = log_with_entries(&dir.join("intact"));
+        let report = log.verify().unwrap();
+        assert_eq!(report.entries, 3);
+        assert!(report.is_intact(), "{:?}", report.problems);
+
+        let log = log_with_entries(&dir.join("modified"));
+        rewrite_lines(&log.path, |lines| {
+            lines[1] = lines[1].replace("\"exit_status\":0", "\"exit_status\":1")
+        });
+        let problems = log.verify().unwrap().problems;
+        assert_eq!(
+            problems,
+            vec!["line 2: entry 2 has been modified".to_string()]
+        );
+
+        let log = log_with_entries(&dir.join("truncated"));
+        rewrite_lines(&log.path, |lines| {
+            lines.pop();
+        });
+        let problems = log.verify().unwrap().problems;
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("truncated"), "{:?}", problems);
+
+        // A head rewritten to match the truncated log is caught by its MAC
+        let head_path = log.head_path();
+        let mut head: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&head_path).unwrap()).unwrap();
+        let last: AuditEntry = serde_json::from_str(
+            fs::read_to_string(&log.path)
+                .unwrap()
+                .lines()
+                .last()
+                .unwrap(),
+        )
+        .unwrap();
+        head["seq"] = last.record.seq.into();
+        head["hash"] = last.hash.into();
+        fs::write(&head_path, head.to_string()).unwrap();
+        assert_eq!(
+            log.verify().unwrap().problems,
+            vec!["head file has been modified".to_string()]
+        );
+
+        // Deleting the log and its head does not pass for a fresh install
+        fs::remove_file(&log.path).unwrap();
+        fs::remove_file(&head_path).unwrap();
+        assert!(!log.verify().unwrap().is_intact());
+
+        // Without the key a rewritten chain cannot be passed off as intact
+        let log = log_with_entries(&dir.join("rekeyed"));
+        fs::remove_file(&log.key_path).unwrap();
+        assert!(!log.verify().unwrap().is_intact());
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let mac = hmac_sha256(&[0x0b; 20], b"Hi There");
+        assert_eq!(
+            hex::encode(mac),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+    }
+}
@@ -0,0 +1,671 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::report::Report;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✅ [PASS]",
+            CheckStatus::Warn => "⚠️  [WARN]",
+            CheckStatus::Fail => "❌ [FAIL]",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub id: String,
+    pub title: String,
+    pub status: CheckStatus,
+    pub evidence: Vec<String>,
+    pub remediation: String,
+    /// Name of the toolkit command that fixes this finding, if there is one
+    pub remediation_command: Option<String>,
+}
+
+impl CheckResult {
+    fn new(id: &str, title: &str, remediation: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            status: CheckStatus::Pass,
+            evidence: Vec::new(),
+            remediation: remediation.to_string(),
+            remediation_command: None,
+        }
+    }
+
+    fn fixed_by(mut self, command: &str) -> Self {
+        self.remediation_command = Some(command.to_string());
+        self
+    }
+
+    fn status(mut self, status: CheckStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    fn evidence(mut self, evidence: impl Into<String>) -> Self {
+        self.evidence.push(evidence.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineReport {
+    pub hostname: String,
+    pub generated_at: DateTime<Utc>,
+    pub score: u32,
+    pub results: Vec<CheckResult>,
+}
+
+impl BaselineReport {
+    pub fn count(&self, status: CheckStatus) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+
+    /// Render the scored summary shown in the TUI and on the CLI
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("Security Baseline for {}", self.hostname),
+            format!(
+                "Score: {}/100 ({} checks: {} pass, {} warn, {} fail)",
+                self.score,
+                self.results.len(),
+                self.count(CheckStatus::Pass),
+                self.count(CheckStatus::Warn),
+                self.count(CheckStatus::Fail)
+            ),
+            String::new(),
+        ];
+
+        // Failures first so the important findings are visible without scrolling
+        let mut results: Vec<&CheckResult> = self.results.iter().collect();
+        results.sort_by_key(|r| match r.status {
+            CheckStatus::Fail => 0,
+            CheckStatus::Warn => 1,
+            CheckStatus::Pass => 2,
+        });
+
+        for result in results {
+            lines.push(format!("{} {}", result.status.label(), result.title));
+            for evidence in &result.evidence {
+                lines.push(format!("    Evidence: {}", evidence));
+            }
+            if result.status != CheckStatus::Pass {
+                lines.push(format!("    Fix: {}", result.remediation));
+                if let Some(command) = &result.remediation_command {
+                    lines.push(format!("    Toolkit: run \"{}\"", command));
+                }
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Run every check and compute the score
+///
+/// Passing checks count fully, warnings count half and failures not at all.
+pub fn run_baseline() -> BaselineReport {
+    let mut results = vec![
+        check_ssh_root_login(),
+        check_ssh_password_auth(),
+        check_firewall_active(),
+        check_unattended_upgrades(),
+        check_world_writable_path(),
+        check_suid_outliers(),
+        check_password_policy(),
+        check_tmp_mount(),
+    ];
+    results.extend(check_sysctls());
+
+    BaselineReport {
+        hostname: crate::utils::get_hostname(),
+        generated_at: Utc::now(),
+        score: score(&results),
+        results,
+    }
+}
+
+/// Percentage of the points available across `results`
+fn score(results: &[CheckResult]) -> u32 {
+    let earned: u32 = results
+        .iter()
+        .map(|r| match r.status {
+            CheckStatus::Pass => 2,
+            CheckStatus::Warn => 1,
+            CheckStatus::Fail => 0,
+        })
+        .sum();
+    let possible = (results.len() as u32 * 2).max(1);
+    earned * 100 / possible
+}
+
+/// Run the baseline for the TUI and RPC clients
+///
+/// Nothing is written to disk; `linux-toolkit baseline -o` or `--report`
+/// saves a copy when one is wanted.
+pub fn run_native(_config: &Config) -> Result<String> {
+    Ok(run_baseline().to_text())
+}
+
+/// Handler for `linux-toolkit baseline`
+pub fn baseline_command(output: Option<&Path>, report_path: Option<&Path>) -> Result<()> {
+    let report = run_baseline();
+    println!("{}", report.to_text());
+
+    if let Some(path) = output {
+        fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("\nReport written to {}", path.display());
+    }
+    if let Some(path) = report_path {
+        Report::from_baseline(&report).write_to(path)?;
+        println!("\nReport written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// First value of a whitespace separated `Keyword value` setting, matched
+/// case-insensitively as login.defs does
+fn config_value(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let line = line.trim();
+        if line.starts_with('#') {
+            return None;
+        }
+        let mut parts = line.splitn(2, |c: char| c.is_whitespace() || c == '=');
+        let name = parts.next()?;
+        if !name.eq_ignore_ascii_case(key) {
+            return None;
+        }
+        Some(
+            parts
+                .next()?
+                .trim_start_matches([' ', '\t', '='])
+                .trim()
+                .to_string(),
+        )
+    })
+}
+
+/// Effective sshd value of `keyword` and where it was set, following
+/// Include directives; `Err` carries evidence when sshd is not configured
+fn sshd_value(keyword: &str) -> Result<Option<(String, String)>, String> {
+    let path = Path::new(crate::sshd::SSHD_CONFIG);
+    if !path.exists() {
+        return Err(format!(
+            "{} not present, sshd not installed",
+            path.display()
+        ));
+    }
+    let parsed = crate::sshd::SshdConfig::load(path).map_err(|e| e.to_string())?;
+    Ok(parsed
+        .effective(keyword)
+        .map(|d| (d.value.clone(), format!("{}:{}", d.file.display(), d.line))))
+}
+
+fn check_ssh_root_login() -> CheckResult {
+    let result = CheckResult::new(
+        "ssh.permit_root_login",
+        "SSH root login disabled",
+        "Set `PermitRootLogin no` in /etc/ssh/sshd_config and reload sshd",
+    )
+    .fixed_by("Secure SSH");
+
+    match sshd_value("PermitRootLogin") {
+        Err(evidence) => result.evidence(evidence),
+        Ok(Some((value, source))) if value.eq_ignore_ascii_case("no") => {
+            result.evidence(format!("PermitRootLogin {} ({})", value, source))
+        }
+        Ok(Some((value, source))) if value.eq_ignore_ascii_case("yes") => result
+            .status(CheckStatus::Fail)
+            .evidence(format!("PermitRootLogin {} ({})", value, source)),
+        Ok(Some((value, source))) => result
+            .status(CheckStatus::Warn)
+            .evidence(format!("PermitRootLogin {} ({})", value, source)),
+        Ok(None) => result
+            .status(CheckStatus::Warn)
+            .evidence("PermitRootLogin not set, default is prohibit-password"),
+    }
+}
+
+fn check_ssh_password_auth() -> CheckResult {
+    let result = CheckResult::new(
+        "ssh.password_authentication",
+        "SSH password authentication disabled",
+        "Set `PasswordAuthentication no` once key-based login works",
+    )
+    .fixed_by("Secure SSH");
+
+    match sshd_value("PasswordAuthentication") {
+        Err(evidence) => result.evidence(evidence),
+        Ok(Some((value, source))) if value.eq_ignore_ascii_case("no") => {
+            result.evidence(format!("PasswordAuthentication {} ({})", value, source))
+        }
+        Ok(Some((value, source))) => result
+            .status(CheckStatus::Warn)
+            .evidence(format!("PasswordAuthentication {} ({})", value, source)),
+        Ok(None) => result
+            .status(CheckStatus::Warn)
+            .evidence("PasswordAuthentication not set, default is yes"),
+    }
+}
+
+fn check_firewall_active() -> CheckResult {
+    let result = CheckResult::new(
+        "firewall.active",
+        "Host firewall active",
+        "Enable a host firewall with a default-deny inbound policy",
+    )
+    .fixed_by("Enable Firewall");
+
+    match crate::firewall::detect() {
+        Some(detected) if detected.active => {
+            result.evidence(format!("{} is active", detected.backend.name()))
+        }
+        Some(detected) => result.status(CheckStatus::Fail).evidence(format!(
+            "{} is installed but not active",
+            detected.backend.name()
+        )),
+        None => result
+            .status(CheckStatus::Fail)
+            .evidence("no ufw, firewalld, nftables or iptables found"),
+    }
+}
+
+fn check_unattended_upgrades() -> CheckResult {
+    let result = CheckResult::new(
+        "updates.automatic",
+        "Automatic security updates enabled",
+        "Install and enable unattended-upgrades (Debian/Ubuntu) or dnf-automatic (Fedora/RHEL)",
+    );
+
+    if let Ok(content) = fs::read_to_string("/etc/apt/apt.conf.d/20auto-upgrades") {
+        if content.contains("Unattended-Upgrade \"1\"") {
+            return result.evidence("APT::Periodic::Unattended-Upgrade \"1\"");
+        }
+    }
+
+    let timers = Path::new("/etc/systemd/system/timers.target.wants");
+    if let Ok(entries) = fs::read_dir(timers) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with("dnf-automatic") || name.starts_with("yum-cron") {
+                return result.evidence(format!("{} is enabled", name));
+            }
+        }
+    }
+
+    result
+        .status(CheckStatus::Warn)
+        .evidence("no unattended-upgrades or dnf-automatic configuration found")
+}
+
+fn check_world_writable_path() -> CheckResult {
+    let mut result = CheckResult::new(
+        "path.world_writable",
+        "No world-writable directories or files in PATH",
+        "Remove the world-writable bit with `chmod o-w <path>`",
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::var("PATH").unwrap_or_default();
+        for dir in path.split(':').filter(|d| !d.is_empty()) {
+            let dir = Path::new(dir);
+            if let Ok(metadata) = fs::metadata(dir) {
+                // Sticky directories such as /tmp are writable by design
+                let mode = metadata.permissions().mode();
+                if mode & 0o002 != 0 && mode & 0o1000 == 0 {
+                    result = result.status(CheckStatus::Fail).evidence(format!(
+                        "{} (directory, mode {:o})",
+                        dir.display(),
+                        mode & 0o7777
+                    ));
+                }
+            }
+
+            if let Ok(entries) = fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    if let Ok(metadata) = entry.metadata() {
+                        let mode = metadata.permissions().mode();
+                        if metadata.is_file() && mode & 0o002 != 0 {
+                            result = result.status(CheckStatus::Fail).evidence(format!(
+                                "{} (mode {:o})",
+                                entry.path().display(),
+                                mode & 0o7777
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// SUID/SGID binaries shipped by mainstream distributions
+const KNOWN_SUID: &[&str] = &[
+    "chage",
+    "chfn",
+    "chsh",
+    "crontab",
+    "expiry",
+    "fusermount",
+    "fusermount3",
+    "gpasswd",
+    "mount",
+    "newgrp",
+    "newgidmap",
+    "newuidmap",
+    "passwd",
+    "pkexec",
+    "ping",
+    "sg",
+    "ssh-agent",
+    "ssh-keysign",
+    "su",
+    "sudo",
+    "sudoedit",
+    "umount",
+    "unix_chkpwd",
+    "wall",
+    "write",
+    "dbus-daemon-launch-helper",
+    "polkit-agent-helper-1",
+    "Xorg.wrap",
+];
+
+fn check_suid_outliers() -> CheckResult {
+    let mut result = CheckResult::new(
+        "files.suid_outliers",
+        "No unexpected SUID/SGID binaries",
+        "Review each binary and remove the bit with `chmod u-s,g-s <path>` if it is not needed",
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dirs = [
+            "/bin",
+            "/sbin",
+            "/usr/bin",
+            "/usr/sbin",
+            "/usr/local/bin",
+            "/usr/local/sbin",
+            "/usr/lib/openssh",
+            "/usr/libexec",
+        ];
+        let mut seen = std::collections::HashSet::new();
+
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(metadata) = fs::metadata(&path) else {
+                    continue;
+                };
+                let mode = metadata.permissions().mode();
+                if !metadata.is_file() || mode & 0o6000 == 0 {
+                    continue;
+                }
+                let canonical = fs::canonicalize(&path).unwrap_or(path.clone());
+                if !seen.insert(canonical) {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !KNOWN_SUID.contains(&name.as_str()) {
+                    result = result.status(CheckStatus::Warn).evidence(format!(
+                        "{} (mode {:o})",
+                        path.display(),
+                        mode & 0o7777
+                    ));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+fn check_password_policy() -> CheckResult {
+    let mut result = CheckResult::new(
+        "accounts.password_policy",
+        "Password ageing policy configured",
+        "Set PASS_MAX_DAYS 365 (or less) and PASS_MIN_DAYS 1 in /etc/login.defs",
+    );
+
+    let Ok(content) = fs::read_to_string("/etc/login.defs") else {
+        return result
+            .status(CheckStatus::Warn)
+            .evidence("/etc/login.defs not found");
+    };
+
+    let number = |key: &str| config_value(&content, key).and_then(|v| v.parse::<i64>().ok());
+
+    match number("PASS_MAX_DAYS") {
+        Some(days) if (1..=365).contains(&days) => {
+            result = result.evidence(format!("PASS_MAX_DAYS {}", days));
+        }
+        Some(days) => {
+            result = result
+                .status(CheckStatus::Warn)
+                .evidence(format!("PASS_MAX_DAYS {}", days));
+        }
+        None => {
+            result = result
+                .status(CheckStatus::Warn)
+                .evidence("PASS_MAX_DAYS not set");
+        }
+    }
+
+    match number("PASS_MIN_DAYS") {
+        Some(days) if days >= 1 => {
+            result = result.evidence(format!("PASS_MIN_DAYS {}", days));
+        }
+        other => {
+            result = result.status(CheckStatus::Warn).evidence(format!(
+                "PASS_MIN_DAYS {}",
+                other
+                    .map(|d| d.to_string())
+                    .unwrap_or("not set".to_string())
+            ));
+        }
+    }
+
+    result
+}
+
+fn check_tmp_mount() -> CheckResult {
+    let result = CheckResult::new(
+        "mounts.tmp_options",
+        "/tmp mounted with nodev,nosuid,noexec",
+        "Mount /tmp as a separate filesystem (or tmpfs) with nodev,nosuid,noexec",
+    );
+
+    let Ok(mounts) = fs::read_to_string("/proc/mounts") else {
+        return result
+            .status(CheckStatus::Warn)
+            .evidence("/proc/mounts is not readable");
+    };
+
+    let Some(options) = mounts.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        (fields.get(1) == Some(&"/tmp")).then(|| fields.get(3).unwrap_or(&"").to_string())
+    }) else {
+        return result
+            .status(CheckStatus::Warn)
+            .evidence("/tmp is not a separate mount");
+    };
+
+    let missing: Vec<&str> = ["nodev", "nosuid", "noexec"]
+        .into_iter()
+        .filter(|option| !options.split(',').any(|o| o == *option))
+        .collect();
+
+    if missing.is_empty() {
+        result.evidence(format!("/tmp options: {}", options))
+    } else {
+        result
+            .status(CheckStatus::Warn)
+            .evidence(format!("/tmp is missing {}", missing.join(",")))
+    }
+}
+
+/// Kernel parameters checked by the baseline, their expected values and
+/// whether a higher value is stricter and therefore also acceptable
+const SYSCTL_CHECKS: &[(&str, &str, &str, bool)] = &[
+    (
+        "kernel.randomize_va_space",
+        "2",
+        "Full address space layout randomisation",
+        false,
+    ),
+    ("kernel.kptr_restrict", "1", "Kernel pointers hidden", true),
+    (
+        "kernel.dmesg_restrict",
+        "1",
+        "dmesg restricted to root",
+        false,
+    ),
+    (
+        "fs.protected_symlinks",
+        "1",
+        "Symlink protection enabled",
+        false,
+    ),
+    (
+        "fs.protected_hardlinks",
+        "1",
+        "Hardlink protection enabled",
+        false,
+    ),
+    (
+        "net.ipv4.tcp_syncookies",
+        "1",
+        "TCP SYN cookies enabled",
+        false,
+    ),
+    (
+        "net.ipv4.conf.all.accept_redirects",
+        "0",
+        "ICMP redirects ignored",
+        false,
+    ),
+    (
+        "net.ipv4.conf.all.rp_filter",
+        "1",
+        "Reverse path filtering enabled",
+        false,
+    ),
+];
+
+/// Whether `value` satisfies `expected`, allowing a numerically higher value
+/// for settings where higher is stricter
+fn sysctl_ok(value: &str, expected: &str, stricter_ok: bool) -> bool {
+    if value == expected {
+        return true;
+    }
+    match (value.parse::<i64>(), expected.parse::<i64>()) {
+        (Ok(value), Ok(expected)) => stricter_ok && value >= expected,
+        _ => false,
+    }
+}
+
+fn check_sysctls() -> Vec<CheckResult> {
+    SYSCTL_CHECKS
+        .iter()
+        .map(|(key, expected, title, stricter_ok)| {
+            let result = CheckResult::new(
+                &format!("sysctl.{}", key),
+                &format!("{} ({})", title, key),
+                &format!(
+                    "Set `{} = {}` in /etc/sysctl.d/ and run `sysctl --system`",
+                    key, expected
+                ),
+            )
+            .fixed_by("Apply Sysctl Hardening");
+            match crate::sysctl::read_effective(key) {
+                Some(value) if sysctl_ok(&value, expected, *stricter_ok) => {
+                    result.evidence(format!("{} = {}", key, value))
+                }
+                Some(value) => result
+                    .status(CheckStatus::Fail)
+                    .evidence(format!("{} = {} (expected {})", key, value, expected)),
+                None => result
+                    .status(CheckStatus::Warn)
+                    .evidence(format!("{} is not available", key)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: CheckStatus) -> CheckResult {
+        CheckResult::new("test", "Test", "").status(status)
+    }
+
+    #[test]
+    fn reads_config_values() {
+        let content = "# PASS_MAX_DAYS 1\n\
+                       PASS_MAX_DAYS\t90\n\
+                       pass_min_days 7\n\
+                       minlen = 14\n\
+                       PASS_WARN_AGE\n";
+        assert_eq!(
+            config_value(content, "PASS_MAX_DAYS").as_deref(),
+            Some("90")
+        );
+        assert_eq!(config_value(content, "PASS_MIN_DAYS").as_deref(), Some("7"));
+        assert_eq!(config_value(content, "minlen").as_deref(), Some("14"));
+        assert_eq!(config_value(content, "PASS_WARN_AGE"), None);
+        assert_eq!(config_value(content, "ENCRYPT_METHOD"), None);
+    }
+
+    #[test]
+    fn scores_warnings_at_half() {
+        let results = [
+            result(CheckStatus::Pass),
+            result(CheckStatus::Warn),
+            result(CheckStatus::Fail),
+            result(CheckStatus::Pass),
+        ];
+        assert_eq!(score(&results), 62);
+        assert_eq!(score(&[result(CheckStatus::Pass)]), 100);
+        assert_eq!(score(&[result(CheckStatus::Fail)]), 0);
+        assert_eq!(score(&[]), 0);
+    }
+
+    #[test]
+    fn accepts_stricter_sysctl_values_only_when_allowed() {
+        assert!(sysctl_ok("1", "1", false));
+        assert!(sysctl_ok("2", "1", true));
+        assert!(!sysctl_ok("2", "1", false));
+        assert!(!sysctl_ok("0", "1", true));
+        assert!(!sysctl_ok("on", "1", true));
+        assert!(!sysctl_ok("a", "b", true));
+    }
+}
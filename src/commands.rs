@@ -1,9 +1,12 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::process::Stdio;
-use tokio::process::Command as TokioCommand;
 
+use crate::audit::PrivilegedRun;
 use crate::config::Config;
+use crate::environment::Environment;
+use crate::executor::{self, Invocation};
+use crate::report::ExecutionRecord;
 use crate::scripts::ScriptManager;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +19,103 @@ pub struct Command {
     pub tags: Vec<String>,
     pub requires_sudo: bool,
     pub category: String,
+    /// Distribution IDs (or ID_LIKE families) the command works on, empty for all
+    #[serde(default)]
+    pub supported_distros: Vec<String>,
+    /// Programs that must be on PATH for the command to work
+    #[serde(default)]
+    pub requires_tools: Vec<String>,
+    /// Why this host cannot run the command, filled in at load time
+    #[serde(skip)]
+    pub unavailable: Option<String>,
+    /// Required programs not found on PATH, filled in at load time
+    #[serde(skip)]
+    pub missing: Vec<String>,
+}
+
+impl Command {
+    /// Reason the command cannot run in `environment`, if any
+    pub fn unsupported_reason(&self, environment: &Environment) -> Option<String> {
+        if !self.supported_distros.is_empty()
+            && !self
+                .supported_distros
+                .iter()
+                .any(|distro| environment.matches_distro(distro))
+        {
+            return Some(format!(
+                "only supported on {}",
+                self.supported_distros.join(", ")
+            ));
+        }
+        None
+    }
+
+    /// Programs that must be installed: declared tools plus the executable itself
+    pub fn required_binaries(&self) -> Vec<String> {
+        let mut binaries = self.requires_tools.clone();
+        let is_marker = matches!(self.command.as_str(), "script" | "native" | "runbook");
+        if !is_marker && !binaries.contains(&self.command) {
+            binaries.insert(0, self.command.clone());
+        }
+        binaries
+    }
+
+    pub fn missing_binaries(&self) -> Vec<String> {
+        self.required_binaries()
+            .into_iter()
+            .filter(|binary| which::which(binary).is_err())
+            .collect()
+    }
+
+    /// Stable identifier of the form `category/command-name`
+    pub fn id(&self) -> String {
+        format!(
+            "{}/{}",
+            crate::utils::slugify(&self.category),
+            crate::utils::slugify(&self.name)
+        )
+    }
+}
+
+/// Outcome of running a command with its output captured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandRun {
+    /// Text to show the user, an explanation when the command failed
+    pub output: String,
+    /// The command ran and exited successfully
+    pub success: bool,
+    /// The output comes from a retry with sudo
+    pub elevated: bool,
+}
+
+impl CommandRun {
+    fn failed(output: String) -> Self {
+        Self {
+            output,
+            success: false,
+            elevated: false,
+        }
+    }
+
+    /// Output as shown in the details panel, noting a sudo retry
+    pub fn display(&self) -> String {
+        if self.elevated {
+            format!("Command elevated with sudo:\n{}", self.output)
+        } else {
+            self.output.clone()
+        }
+    }
+
+    /// Record for reports, timed from `started_at`
+    pub fn record(&self, command: &Command, started_at: DateTime<Utc>) -> ExecutionRecord {
+        ExecutionRecord {
+            command_id: command.id(),
+            command_name: command.name.clone(),
+            started_at,
+            success: Some(self.success),
+            output: self.display(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,18 +125,14 @@ pub struct CommandCategory {
     pub commands: Vec<Command>,
 }
 
+/// Built-in commands, plus scripts and runbooks when the scripts folder is available
 pub fn load_categories() -> Vec<CommandCategory> {
     let mut categories = load_builtin_categories();
 
     // Try to load and merge script-based commands
-    match ScriptManager::new_from_exe() {
-        Ok(script_manager) => {
-            merge_script_commands(&mut categories, &script_manager);
-        }
-        Err(e) => {
-            eprintln!("Warning: Failed to initialize script manager: {}", e);
-            eprintln!("Scripts folder may not be available");
-        }
+    if let Ok(script_manager) = ScriptManager::new_from_exe() {
+        merge_script_commands(&mut categories, &script_manager);
+        categories.extend(crate::runbooks::runbook_category(&script_manager));
     }
 
     // Sort categories alphabetically by name
@@ -47,369 +143,426 @@ pub fn load_categories() -> Vec<CommandCategory> {
         category.commands.sort_by(|a, b| a.name.cmp(&b.name));
     }
 
+    // Flag commands this host cannot run or that need something installed
+    let environment = crate::environment::current();
+    for command in categories.iter_mut().flat_map(|c| c.commands.iter_mut()) {
+        command.unavailable = command.unsupported_reason(environment);
+        command.missing = command.missing_binaries();
+    }
+
     categories
 }
 
-fn load_builtin_categories() -> Vec<CommandCategory> {
-    vec![CommandCategory {
-        name: "Security".to_string(),
-        description: "Security analysis and forensics tools".to_string(),
-        commands: vec![
-            Command {
-                name: "Check Failed Logins".to_string(),
-                description: "Display failed login attempts".to_string(),
-                command: "grep".to_string(),
-                args: vec!["Failed".to_string(), "/var/log/auth.log".to_string()],
-                usage: "grep Failed /var/log/auth.log".to_string(),
-                tags: vec![
-                    "security".to_string(),
-                    "logs".to_string(),
-                    "authentication".to_string(),
-                ],
-                requires_sudo: true,
-                category: "security".to_string(),
-            },
-            Command {
-                name: "List Users".to_string(),
-                description: "Display all system users".to_string(),
-                command: "cat".to_string(),
-                args: vec!["/etc/passwd".to_string()],
-                usage: "cat /etc/passwd".to_string(),
-                tags: vec![
-                    "users".to_string(),
-                    "accounts".to_string(),
-                    "system".to_string(),
-                ],
-                requires_sudo: false,
-                category: "security".to_string(),
-            },
-            Command {
-                name: "Check SUID Files".to_string(),
-                description: "Find SUID/SGID files (potential privilege escalation)".to_string(),
-                command: "find".to_string(),
-                args: vec![
-                    "/".to_string(),
-                    "-perm".to_string(),
-                    "-4000".to_string(),
-                    "-o".to_string(),
-                    "-perm".to_string(),
-                    "-2000".to_string(),
-                    "2>/dev/null".to_string(),
-                ],
-                usage: "find / -perm -4000 -o -perm -2000 2>/dev/null".to_string(),
-                tags: vec![
-                    "suid".to_string(),
-                    "privilege".to_string(),
-                    "escalation".to_string(),
-                ],
-                requires_sudo: true,
-                category: "security".to_string(),
-            },
-            Command {
-                name: "Open Files".to_string(),
-                description: "List open files and network connections".to_string(),
-                command: "lsof".to_string(),
-                args: vec!["-i".to_string()],
-                usage: "lsof -i".to_string(),
-                tags: vec![
-                    "files".to_string(),
-                    "network".to_string(),
-                    "monitoring".to_string(),
-                ],
-                requires_sudo: true,
-                category: "security".to_string(),
-            },
-        ],
-    }]
+/// Look a command up by id, then by name, then by the first name containing `query`
+pub fn find_command<'a>(categories: &'a [CommandCategory], query: &str) -> Option<&'a Command> {
+    let commands = || categories.iter().flat_map(|category| &category.commands);
+    let lowered = query.to_lowercase();
+    commands()
+        .find(|command| command.id() == query)
+        .or_else(|| commands().find(|command| command.name.eq_ignore_ascii_case(query)))
+        .or_else(|| commands().find(|command| command.name.to_lowercase().contains(&lowered)))
 }
 
-pub async fn execute_command_in_terminal(command: &Command, config: &Config) -> Result<()> {
-    use std::process::Command as StdCommand;
-
-    // Handle script commands specially
-    if command.command == "script" {
-        return execute_script_command_in_terminal(command).await;
+/// A command that runs one of the toolkit's own subcommands
+pub fn toolkit_command(
+    name: &str,
+    description: &str,
+    args: &[&str],
+    requires_sudo: bool,
+    category: &str,
+    tags: &[&str],
+) -> Command {
+    Command {
+        name: name.to_string(),
+        description: description.to_string(),
+        command: crate::utils::toolkit_exe(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        usage: format!("linux-toolkit {}", args.join(" ")),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        requires_sudo,
+        category: category.to_string(),
+        supported_distros: Vec::new(),
+        requires_tools: Vec::new(),
+        unavailable: None,
+        missing: Vec::new(),
     }
+}
 
-    // Clear the terminal screen before executing the command
-    if cfg!(target_os = "windows") {
-        let _ = StdCommand::new("cls").status();
-    } else {
-        let _ = StdCommand::new("clear").status();
+/// A command implemented in Rust whose output is shown in the details panel
+fn native_command(
+    name: &str,
+    description: &str,
+    action: &str,
+    usage: &str,
+    category: &str,
+    tags: &[&str],
+) -> Command {
+    Command {
+        name: name.to_string(),
+        description: description.to_string(),
+        command: "native".to_string(), // Special marker for built-in Rust commands
+        args: vec![action.to_string()],
+        usage: usage.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        requires_sudo: false,
+        category: category.to_string(),
+        supported_distros: Vec::new(),
+        requires_tools: Vec::new(),
+        unavailable: None,
+        missing: Vec::new(),
     }
+}
 
-    // Build the command with proper shell handling
-    let has_shell_operators = command.args.iter().any(|arg| {
-        arg.contains("&&") || arg.contains("|") || arg.contains(">") || arg.contains("<")
-    });
-
-    let should_use_sudo = command.requires_sudo;
-
-    let (final_command, final_args) = if has_shell_operators {
-        let full_command = format!("{} {}", command.command, command.args.join(" "));
-        if should_use_sudo && !crate::utils::is_root() {
-            (
-                "sudo".to_string(),
-                vec!["sh".to_string(), "-c".to_string(), full_command],
-            )
-        } else {
-            ("sh".to_string(), vec!["-c".to_string(), full_command])
-        }
-    } else {
-        if should_use_sudo && !crate::utils::is_root() {
-            let mut sudo_args = vec![command.command.clone()];
-            sudo_args.extend(command.args.clone());
-            ("sudo".to_string(), sudo_args)
-        } else {
-            (command.command.clone(), command.args.clone())
-        }
-    };
-
-    // Show command info before execution
-    println!("\n{}", "=".repeat(60));
-    println!("Executing: {}", command.name);
-    println!("Description: {}", command.description);
-    if should_use_sudo {
-        println!("⚠️  This command requires elevated privileges");
-    }
-    println!("Command: {}", command.usage);
-    println!("{}", "=".repeat(60));
-    println!();
-
-    // Execute the command and capture the exit status
-    let mut cmd = StdCommand::new(&final_command);
-    cmd.args(&final_args);
-
-    match cmd.status() {
-        Ok(status) => {
-            println!();
-            if status.success() {
-                println!("✅ Command completed successfully");
-            } else {
-                let exit_code = status.code().unwrap_or(-1);
-                println!("❌ Command failed with exit code: {}", exit_code);
-
-                // Check if it might be a permission issue and suggest retry
-                if exit_code == 1 || exit_code == 126 || exit_code == 127 {
-                    if !should_use_sudo && should_retry_with_sudo(command, config) {
-                        println!("💡 This might be a permission issue. Retrying with sudo...");
-                        println!();
-
-                        // Retry with sudo
-                        return execute_command_with_sudo_retry(command).await;
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            println!("❌ Failed to execute command: {}", e);
-
-            // Check if command not found and suggest sudo retry
-            if !should_use_sudo && should_retry_with_sudo(command, config) {
-                println!("💡 Retrying with elevated privileges...");
-                println!();
-
-                return execute_command_with_sudo_retry(command).await;
-            }
-        }
-    }
+fn load_builtin_categories() -> Vec<CommandCategory> {
+    vec![
+        CommandCategory {
+            name: "Kernel Hardening".to_string(),
+            description: "Inspect and harden kernel parameters".to_string(),
+            commands: vec![
+                native_command(
+                    "Sysctl Drift",
+                    "Compare running kernel parameters against the hardening profile",
+                    "sysctl-drift",
+                    "linux-toolkit sysctl drift",
+                    "kernel",
+                    &["kernel", "sysctl", "hardening"],
+                ),
+                toolkit_command(
+                    "Apply Sysctl Hardening",
+                    "Write /etc/sysctl.d/99-linux-toolkit.conf from the profile and apply it",
+                    &["sysctl", "apply"],
+                    true,
+                    "kernel",
+                    &["kernel", "sysctl", "hardening"],
+                ),
+                toolkit_command(
+                    "Rollback Sysctl Hardening",
+                    "Restore the kernel parameters saved by the last apply",
+                    &["sysctl", "rollback"],
+                    true,
+                    "kernel",
+                    &["kernel", "sysctl", "rollback"],
+                ),
+            ],
+        },
+        CommandCategory {
+            name: "System Utilities".to_string(),
+            description: "General system utilities and maintenance scripts".to_string(),
+            commands: vec![
+                native_command(
+                    "Pending Updates",
+                    "List available package updates with security fixes highlighted",
+                    "pending-updates",
+                    "linux-toolkit packages list-upgradable",
+                    "system",
+                    &["system", "updates", "security"],
+                ),
+                toolkit_command(
+                    "Update and Upgrade System",
+                    "Review pending updates, then upgrade with the detected package manager",
+                    &["packages", "upgrade"],
+                    true,
+                    "system",
+                    &["system", "maintenance", "updates"],
+                ),
+                toolkit_command(
+                    "Refresh Package Index",
+                    "Download the latest package lists without upgrading",
+                    &["packages", "refresh"],
+                    true,
+                    "system",
+                    &["system", "maintenance", "updates"],
+                ),
+            ],
+        },
+        CommandCategory {
+            name: "Network Security".to_string(),
+            description: "Network analysis and security tools".to_string(),
+            commands: vec![
+                native_command(
+                    "SSH Hardening Status",
+                    "Show effective sshd settings, where they are set and what to change",
+                    "sshd-status",
+                    "linux-toolkit ssh status",
+                    "network",
+                    &["network", "ssh", "hardening"],
+                ),
+                native_command(
+                    "SSH Key Inventory",
+                    "List authorized keys and known hosts, flagging weak, duplicate and unlabelled keys",
+                    "ssh-keys",
+                    "linux-toolkit ssh-keys",
+                    "network",
+                    &["network", "ssh", "keys"],
+                ),
+                toolkit_command(
+                    "Secure SSH",
+                    "Harden sshd_config after showing a diff and validating it with sshd -t",
+                    &["ssh", "harden"],
+                    true,
+                    "network",
+                    &["network", "security", "ssh", "hardening"],
+                ),
+                native_command(
+                    "Firewall Rules",
+                    "Review and edit firewall rules on whichever backend is active",
+                    "firewall-rules",
+                    "linux-toolkit firewall status",
+                    "network",
+                    &["network", "firewall", "rules"],
+                ),
+                toolkit_command(
+                    "Enable Firewall",
+                    "Deny incoming traffic except SSH using ufw, firewalld, nftables or iptables",
+                    &["firewall", "enable"],
+                    true,
+                    "network",
+                    &["network", "security", "firewall"],
+                ),
+                toolkit_command(
+                    "Rollback SSH Config",
+                    "Restore the sshd_config backup taken by the last hardening run",
+                    &["ssh", "rollback"],
+                    true,
+                    "network",
+                    &["network", "ssh", "rollback"],
+                ),
+            ],
+        },
+        CommandCategory {
+            name: "Security".to_string(),
+            description: "Security analysis and forensics tools".to_string(),
+            commands: vec![
+                Command {
+                    name: "Check Failed Logins".to_string(),
+                    description: "Display failed login attempts".to_string(),
+                    command: "grep".to_string(),
+                    args: vec!["Failed".to_string(), "/var/log/auth.log".to_string()],
+                    usage: "grep Failed /var/log/auth.log".to_string(),
+                    tags: vec![
+                        "security".to_string(),
+                        "logs".to_string(),
+                        "authentication".to_string(),
+                    ],
+                    requires_sudo: true,
+                    category: "security".to_string(),
+                    // Other families log authentication to /var/log/secure
+                    supported_distros: vec!["debian".to_string()],
+                    requires_tools: Vec::new(),
+                    unavailable: None,
+                    missing: Vec::new(),
+                },
+                Command {
+                    name: "List Users".to_string(),
+                    description: "Display all system users".to_string(),
+                    command: "cat".to_string(),
+                    args: vec!["/etc/passwd".to_string()],
+                    usage: "cat /etc/passwd".to_string(),
+                    tags: vec![
+                        "users".to_string(),
+                        "accounts".to_string(),
+                        "system".to_string(),
+                    ],
+                    requires_sudo: false,
+                    category: "security".to_string(),
+                    supported_distros: Vec::new(),
+                    requires_tools: Vec::new(),
+                    unavailable: None,
+                    missing: Vec::new(),
+                },
+                Command {
+                    name: "Check SUID Files".to_string(),
+                    description: "Find SUID/SGID files (potential privilege escalation)"
+                        .to_string(),
+                    command: "find".to_string(),
+                    args: vec![
+                        "/".to_string(),
+                        "-perm".to_string(),
+                        "-4000".to_string(),
+                        "-o".to_string(),
+                        "-perm".to_string(),
+                        "-2000".to_string(),
+                        "2>/dev/null".to_string(),
+                    ],
+                    usage: "find / -perm -4000 -o -perm -2000 2>/dev/null".to_string(),
+                    tags: vec![
+                        "suid".to_string(),
+                        "privilege".to_string(),
+                        "escalation".to_string(),
+                    ],
+                    requires_sudo: true,
+                    category: "security".to_string(),
+                    supported_distros: Vec::new(),
+                    requires_tools: Vec::new(),
+                    unavailable: None,
+                    missing: Vec::new(),
+                },
+                Command {
+                    name: "Open Files".to_string(),
+                    description: "List open files and network connections".to_string(),
+                    command: "lsof".to_string(),
+                    args: vec!["-i".to_string()],
+                    usage: "lsof -i".to_string(),
+                    tags: vec![
+                        "files".to_string(),
+                        "network".to_string(),
+                        "monitoring".to_string(),
+                    ],
+                    requires_sudo: true,
+                    category: "security".to_string(),
+                    supported_distros: Vec::new(),
+                    requires_tools: Vec::new(),
+                    unavailable: None,
+                    missing: Vec::new(),
+                },
+                native_command(
+                    "Security Baseline Audit",
+                    "Score how well this host is hardened against a baseline of checks",
+                    "baseline",
+                    "linux-toolkit baseline",
+                    "security",
+                    &["security", "audit", "hardening"],
+                ),
+                native_command(
+                    "File Integrity Check",
+                    "Show files added, removed or modified since the integrity baseline",
+                    "integrity-check",
+                    "linux-toolkit integrity check",
+                    "security",
+                    &["security", "integrity", "files"],
+                ),
+                native_command(
+                    "Persistence Hunter",
+                    "List cron jobs, units, startup files, keys and modules that survive a reboot",
+                    "persistence",
+                    "linux-toolkit persistence",
+                    "security",
+                    &["security", "forensics", "incident-response"],
+                ),
+                toolkit_command(
+                    "Update Integrity Baseline",
+                    "Record the current state of the monitored paths as the new baseline",
+                    &["integrity", "update"],
+                    true,
+                    "security",
+                    &["security", "integrity", "files"],
+                ),
+            ],
+        },
+    ]
+}
 
-    println!("\nPress Enter to continue...");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).ok();
+pub async fn execute_command(command: &Command, config: &Config) -> Result<String> {
+    run_command(command, config).await.map(|run| run.display())
+}
 
-    Ok(())
+/// Run a command with its output captured, retrying with sudo on permission errors
+pub async fn run_command(command: &Command, config: &Config) -> Result<CommandRun> {
+    run(command, config, true).await
 }
 
-async fn execute_script_command_in_terminal(command: &Command) -> Result<()> {
-    // Get the script manager
-    let script_manager = ScriptManager::new_from_exe()?;
+/// Like [`run_command`], but sudo fails instead of prompting, for runs nobody watches
+pub async fn run_unattended(command: &Command, config: &Config) -> Result<CommandRun> {
+    run(command, config, false).await
+}
 
-    // The script name should be in the first argument
-    if command.args.is_empty() {
-        println!("❌ Error: No script specified");
-        println!("\nPress Enter to continue...");
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input).ok();
-        return Ok(());
+async fn run(command: &Command, config: &Config, interactive: bool) -> Result<CommandRun> {
+    // Built-in commands run in-process and never need a sudo retry
+    if command.command == "native" {
+        return Ok(CommandRun {
+            output: execute_native_command(command, config).await?,
+            success: true,
+            elevated: false,
+        });
     }
 
-    let script_name = &command.args[0];
-
-    // Find the script path by looking in all categories
-    let mut script_path = None;
-    for (_, category) in &script_manager.config.scripts {
-        let potential_path = script_manager
-            .scripts_dir
-            .join(&category.directory)
-            .join(script_name);
-
-        if potential_path.exists() {
-            script_path = Some(potential_path);
-            break;
-        }
+    let missing = command.missing_binaries();
+    if !missing.is_empty() {
+        return Ok(CommandRun::failed(format!(
+            "❌ Missing required program(s): {}",
+            missing.join(", ")
+        )));
     }
 
-    let script_path = match script_path {
-        Some(path) => path,
-        None => {
-            println!("❌ Error: Script '{}' not found", script_name);
-            println!("\nPress Enter to continue...");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).ok();
-            return Ok(());
-        }
-    };
-
-    // Execute the script with any additional arguments
-    let script_args: Vec<String> = command.args[1..].to_vec();
+    // First, try to execute the command normally
+    let result = execute_command_internal(command, false, interactive, config).await;
 
-    match script_manager
-        .execute_script_in_terminal(&script_path, &script_args, command.requires_sudo)
-        .await
-    {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            println!("❌ Script execution error: {}", e);
-            println!("\nPress Enter to continue...");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).ok();
-            Ok(())
+    let retry = match &result {
+        // Check if the output contains permission denied errors
+        Ok(run) => is_permission_denied_error(&run.output),
+        // This might be a permission issue as well
+        Err(_) => true,
+    };
+    if retry && should_retry_with_sudo(command, config) {
+        if let Ok(run) = execute_command_internal(command, true, interactive, config).await {
+            return Ok(run);
         }
     }
+    // Return the original result if sudo also fails
+    result
 }
 
-async fn execute_command_with_sudo_retry(command: &Command) -> Result<()> {
-    use std::process::Command as StdCommand;
-
-    // Clear the terminal screen before retrying with sudo
-    if cfg!(target_os = "windows") {
-        let _ = StdCommand::new("cls").status();
-    } else {
-        let _ = StdCommand::new("clear").status();
+/// Process to spawn for a plain command, through `sh -c` when it uses shell operators
+pub fn invocation(command: &Command, elevate: bool) -> Invocation {
+    let mut argv = Vec::new();
+    if elevate {
+        argv.push("sudo".to_string());
     }
-
-    let has_shell_operators = command.args.iter().any(|arg| {
-        arg.contains("&&") || arg.contains("|") || arg.contains(">") || arg.contains("<")
-    });
-
-    let (final_command, final_args) = if has_shell_operators {
+    if uses_shell(command) {
         let full_command = format!("{} {}", command.command, command.args.join(" "));
-        (
-            "sudo".to_string(),
-            vec!["sh".to_string(), "-c".to_string(), full_command],
-        )
+        argv.extend(["sh".to_string(), "-c".to_string(), full_command]);
     } else {
-        let mut sudo_args = vec![command.command.clone()];
-        sudo_args.extend(command.args.clone());
-        ("sudo".to_string(), sudo_args)
-    };
-
-    println!("🔓 Executing with elevated privileges...");
-    println!();
-
-    let mut cmd = StdCommand::new(&final_command);
-    cmd.args(&final_args);
-
-    match cmd.status() {
-        Ok(status) => {
-            println!();
-            if status.success() {
-                println!("✅ Command completed successfully (elevated)");
-            } else {
-                println!(
-                    "❌ Command failed even with elevated privileges (exit code: {})",
-                    status.code().unwrap_or(-1)
-                );
-            }
-        }
-        Err(e) => {
-            println!("❌ Failed to execute command with sudo: {}", e);
-        }
+        argv.push(command.command.clone());
+        argv.extend(command.args.iter().cloned());
     }
-
-    println!("\nPress Enter to continue...");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).ok();
-
-    Ok(())
+    Invocation::from_argv(&argv)
 }
 
-pub async fn execute_command(command: &Command, config: &Config) -> Result<String> {
-    // First, try to execute the command normally
-    let result = execute_command_internal(command, false).await;
-
-    match &result {
-        Ok(output) => {
-            // Check if the output contains permission denied errors
-            if is_permission_denied_error(output) && should_retry_with_sudo(command, config) {
-                // Retry with sudo
-                match execute_command_internal(command, true).await {
-                    Ok(sudo_output) => Ok(format!("Command elevated with sudo:\n{}", sudo_output)),
-                    Err(_) => result, // Return original result if sudo also fails
-                }
-            } else {
-                result
-            }
-        }
-        Err(_) => {
-            // Check if this might be a permission issue and retry with sudo
-            if should_retry_with_sudo(command, config) {
-                match execute_command_internal(command, true).await {
-                    Ok(sudo_output) => Ok(format!("Command elevated with sudo:\n{}", sudo_output)),
-                    Err(_) => result, // Return original error if sudo also fails
-                }
-            } else {
-                result
-            }
-        }
+/// Make sudo fail instead of asking for a password nobody can answer
+pub(crate) fn non_interactive(mut invocation: Invocation) -> Invocation {
+    if invocation.program == "sudo" {
+        invocation.args.insert(0, "-n".to_string());
     }
+    invocation
+}
+
+/// Whether the arguments need a shell to interpret them
+pub fn uses_shell(command: &Command) -> bool {
+    command.args.iter().any(|arg| {
+        arg.contains("&&") || arg.contains("|") || arg.contains(">") || arg.contains("<")
+    })
 }
 
-async fn execute_command_internal(command: &Command, use_sudo: bool) -> Result<String> {
+async fn execute_command_internal(
+    command: &Command,
+    use_sudo: bool,
+    interactive: bool,
+    config: &Config,
+) -> Result<CommandRun> {
     // Handle script commands specially
     if command.command == "script" {
-        return execute_script_command(command, use_sudo).await;
+        return execute_script_command(command, use_sudo, interactive, config).await;
     }
 
-    let mut cmd = if use_sudo && !crate::utils::is_root() {
-        let mut sudo_cmd = TokioCommand::new("sudo");
-        sudo_cmd.arg(&command.command);
-        sudo_cmd
-    } else {
-        TokioCommand::new(&command.command)
-    };
-
-    // Add arguments
-    let has_shell_operators = command.args.iter().any(|arg| {
-        arg.contains("&&") || arg.contains("|") || arg.contains(">") || arg.contains("<")
-    });
-
-    if has_shell_operators {
-        if use_sudo && !crate::utils::is_root() {
-            cmd = TokioCommand::new("sudo");
-            cmd.arg("sh");
-            cmd.arg("-c");
-            let full_command = format!("{} {}", command.command, command.args.join(" "));
-            cmd.arg(full_command);
-        } else {
-            cmd = TokioCommand::new("sh");
-            cmd.arg("-c");
-            let full_command = format!("{} {}", command.command, command.args.join(" "));
-            cmd.arg(full_command);
-        }
-    } else {
-        for arg in &command.args {
-            cmd.arg(arg);
-        }
+    let elevate = use_sudo && !crate::utils::is_root();
+    let mut invocation = invocation(command, elevate);
+    if !interactive {
+        invocation = non_interactive(invocation);
     }
 
-    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
-
-    let output = cmd.output().await?;
+    let elevated_with = elevate.then_some("sudo");
+    let audit = PrivilegedRun::begin(&command.id(), &invocation.argv(), elevated_with, None);
+    let output = executor::capture(invocation).await;
+    if let Some(audit) = audit {
+        audit.finish(
+            config,
+            output.as_ref().ok().and_then(|output| output.status.code),
+        );
+    }
+    let output = output?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = output.stdout;
+    let stderr = output.stderr;
 
     // Check for permission errors in stderr
     if !output.status.success() {
@@ -421,67 +574,85 @@ async fn execute_command_internal(command: &Command, use_sudo: bool) -> Result<S
 
         // If it's a permission error, include that information
         if is_permission_denied_error(&stderr) {
-            return Ok(format!(
+            return Ok(CommandRun::failed(format!(
                 "Permission denied. Try running with elevated privileges.\n{}",
                 error_msg
-            ));
+            )));
         }
 
-        return Ok(error_msg);
+        return Ok(CommandRun::failed(error_msg));
     }
 
-    if stdout.is_empty() && !stderr.is_empty() {
-        Ok(stderr.to_string())
-    } else {
-        Ok(stdout.to_string())
-    }
+    Ok(CommandRun {
+        output: if stdout.is_empty() && !stderr.is_empty() {
+            stderr
+        } else {
+            stdout
+        },
+        success: true,
+        elevated: use_sudo,
+    })
 }
 
-async fn execute_script_command(command: &Command, use_sudo: bool) -> Result<String> {
+async fn execute_script_command(
+    command: &Command,
+    use_sudo: bool,
+    interactive: bool,
+    config: &Config,
+) -> Result<CommandRun> {
     // Get the script manager
     let script_manager = ScriptManager::new_from_exe()?;
 
     // The script name should be in the first argument
     if command.args.is_empty() {
-        return Ok("Error: No script specified".to_string());
+        return Ok(CommandRun::failed("Error: No script specified".to_string()));
     }
 
     let script_name = &command.args[0];
-
-    // Find the script path by looking in all categories
-    let mut script_path = None;
-    for (_, category) in &script_manager.config.scripts {
-        let potential_path = script_manager
-            .scripts_dir
-            .join(&category.directory)
-            .join(script_name);
-
-        if potential_path.exists() {
-            script_path = Some(potential_path);
-            break;
-        }
-    }
-
-    let script_path = match script_path {
+    let script_path = match script_manager.find_script(script_name) {
         Some(path) => path,
         None => {
-            return Ok(format!("Error: Script '{}' not found", script_name));
+            return Ok(CommandRun::failed(format!(
+                "Error: Script '{}' not found",
+                script_name
+            )));
         }
     };
 
     // Execute the script with any additional arguments
     let script_args: Vec<String> = command.args[1..].to_vec();
 
-    match script_manager
-        .execute_script(&script_path, &script_args, use_sudo)
-        .await
-    {
-        Ok(output) => Ok(output),
-        Err(e) => Ok(format!("Script execution error: {}", e)),
+    let mut invocation = ScriptManager::invocation(&script_path, &script_args, use_sudo);
+    if !interactive {
+        invocation = non_interactive(invocation);
+    }
+    match ScriptManager::capture(invocation, &script_path, &command.id(), config).await {
+        Ok((output, success)) => Ok(CommandRun {
+            output,
+            success,
+            elevated: use_sudo && success,
+        }),
+        Err(e) => Ok(CommandRun::failed(format!("Script execution error: {}", e))),
+    }
+}
+
+/// Run a command implemented in Rust rather than by an external program
+pub async fn execute_native_command(command: &Command, config: &Config) -> Result<String> {
+    match command.args.first().map(String::as_str) {
+        Some("baseline") => crate::baseline::run_native(config),
+        Some("sysctl-drift") => crate::sysctl::run_native(config),
+        Some("integrity-check") => crate::integrity::run_native(config).await,
+        Some("persistence") => crate::persistence::run_native(config),
+        Some("sshd-status") => crate::sshd::run_native(),
+        Some("ssh-keys") => crate::sshkeys::run_native(),
+        Some("firewall-rules") => crate::firewall::run_native(),
+        Some("pending-updates") => crate::packages::run_native(),
+        Some(other) => Err(anyhow::anyhow!("Unknown built-in command '{}'", other)),
+        None => Err(anyhow::anyhow!("No built-in command specified")),
     }
 }
 
-fn should_retry_with_sudo(command: &Command, config: &Config) -> bool {
+pub(crate) fn should_retry_with_sudo(command: &Command, config: &Config) -> bool {
     // Always retry with sudo if the command is marked as requiring sudo
     if command.requires_sudo {
         return true;
@@ -515,7 +686,7 @@ fn should_retry_with_sudo(command: &Command, config: &Config) -> bool {
     )
 }
 
-fn is_permission_denied_error(stderr: &str) -> bool {
+pub(crate) fn is_permission_denied_error(stderr: &str) -> bool {
     let permission_indicators = [
         "permission denied",
         "operation not permitted",
@@ -533,28 +704,6 @@ fn is_permission_denied_error(stderr: &str) -> bool {
         .any(|&indicator| stderr_lower.contains(indicator))
 }
 
-pub async fn execute_direct_command(command_name: &str, config: &Config) -> Result<()> {
-    let categories = load_categories();
-
-    for category in &categories {
-        for cmd in &category.commands {
-            if cmd
-                .name
-                .to_lowercase()
-                .contains(&command_name.to_lowercase())
-            {
-                println!("Executing: {}", cmd.name);
-                let output = execute_command(cmd, config).await?;
-                println!("{}", output);
-                return Ok(());
-            }
-        }
-    }
-
-    println!("Command not found: {}", command_name);
-    Ok(())
-}
-
 fn merge_script_commands(categories: &mut Vec<CommandCategory>, script_manager: &ScriptManager) {
     let script_categories = script_manager.list_available_scripts();
 
@@ -570,8 +719,13 @@ fn merge_script_commands(categories: &mut Vec<CommandCategory>, script_manager:
         if let Some(category) = existing_category {
             // Add script commands to existing category
             for script_cmd in script_commands {
+                // Built-in commands replace scripts of the same name that an
+                // older install may still have extracted
+                if category.commands.iter().any(|c| c.name == script_cmd.name) {
+                    continue;
+                }
                 let command = Command {
-                    name: format!("{}", script_cmd.name),
+                    name: script_cmd.name.to_string(),
                     description: script_cmd.description,
                     command: "script".to_string(), // Special marker for script commands
                     args: vec![script_cmd.script],
@@ -579,6 +733,10 @@ fn merge_script_commands(categories: &mut Vec<CommandCategory>, script_manager:
                     tags: script_cmd.tags,
                     requires_sudo: script_cmd.requires_sudo,
                     category: script_category_name.clone(),
+                    supported_distros: script_cmd.supported_distros,
+                    requires_tools: script_cmd.requires_tools,
+                    unavailable: None,
+                    missing: Vec::new(),
                 };
                 category.commands.push(command);
             }
@@ -587,7 +745,7 @@ fn merge_script_commands(categories: &mut Vec<CommandCategory>, script_manager:
             let mut commands = Vec::new();
             for script_cmd in script_commands {
                 let command = Command {
-                    name: format!("{}", script_cmd.name),
+                    name: script_cmd.name.to_string(),
                     description: script_cmd.description,
                     command: "script".to_string(), // Special marker for script commands
                     args: vec![script_cmd.script],
@@ -595,6 +753,10 @@ fn merge_script_commands(categories: &mut Vec<CommandCategory>, script_manager:
                     tags: script_cmd.tags,
                     requires_sudo: script_cmd.requires_sudo,
                     category: script_category_name.clone(),
+                    supported_distros: script_cmd.supported_distros,
+                    requires_tools: script_cmd.requires_tools,
+                    unavailable: None,
+                    missing: Vec::new(),
                 };
                 commands.push(command);
             }
@@ -607,3 +769,147 @@ fn merge_script_commands(categories: &mut Vec<CommandCategory>, script_manager:
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{override_current, RecordingExecutor};
+    use crate::testing;
+    use std::sync::Arc;
+
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        config
+    }
+
+    fn ls(args: &[&str], requires_sudo: bool) -> Command {
+        testing::command("List", "ls")
+            .args(args)
+            .sudo(requires_sudo)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn captures_output_of_plain_command() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(0, "file.txt\n", "");
+
+        let output = execute_command(&ls(&["-la"], false), &test_config())
+            .await
+            .unwrap();
+
+        assert_eq!(output, "file.txt\n");
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].argv(), ["ls", "-la"]);
+    }
+
+    #[tokio::test]
+    async fn shell_operators_run_through_sh() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+
+        execute_command(&ls(&["|", "wc", "-l"], false), &test_config())
+            .await
+            .unwrap();
+
+        assert_eq!(fake.calls()[0].argv(), ["sh", "-c", "ls | wc -l"]);
+    }
+
+    #[tokio::test]
+    async fn permission_denied_is_retried_elevated() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(2, "", "ls: cannot open directory: Permission denied");
+        fake.respond(0, "secret\n", "");
+
+        let output = execute_command(&ls(&["/root"], true), &test_config())
+            .await
+            .unwrap();
+
+        assert_eq!(output, "Command elevated with sudo:\nsecret\n");
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 2);
+        let expected: &[&str] = if crate::utils::is_root() {
+            &["ls", "/root"]
+        } else {
+            &["sudo", "ls", "/root"]
+        };
+        assert_eq!(calls[1].argv(), expected);
+    }
+
+    #[tokio::test]
+    async fn unattended_retry_never_prompts() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(2, "", "ls: cannot open directory: Permission denied");
+        fake.respond(0, "secret\n", "");
+
+        let run = run_unattended(&ls(&["/root"], true), &test_config())
+            .await
+            .unwrap();
+
+        assert!(run.success);
+        let expected: &[&str] = if crate::utils::is_root() {
+            &["ls", "/root"]
+        } else {
+            &["sudo", "-n", "ls", "/root"]
+        };
+        assert_eq!(fake.calls()[1].argv(), expected);
+    }
+
+    #[tokio::test]
+    async fn missing_program_is_reported_without_running() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        let mut command = ls(&[], false);
+        command.command = "definitely-not-installed-ltk".to_string();
+
+        let output = execute_command(&command, &test_config()).await.unwrap();
+
+        assert!(
+            output.contains("definitely-not-installed-ltk"),
+            "{}",
+            output
+        );
+        assert!(fake.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_run_is_reported_unsuccessful() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(1, "", "ls: no such file");
+
+        let run = run_command(&ls(&["/nope"], false), &test_config())
+            .await
+            .unwrap();
+
+        assert!(!run.success);
+        assert!(!run.elevated);
+        assert_eq!(run.output, "Error: ls: no such file");
+    }
+
+    #[test]
+    fn find_command_prefers_id_then_exact_name() {
+        let mut listing = ls(&[], false);
+        listing.name = "List files".to_string();
+        let mut long = ls(&["-la"], false);
+        long.name = "List files long".to_string();
+        let categories = vec![CommandCategory {
+            name: "Test".to_string(),
+            description: String::new(),
+            commands: vec![long.clone(), listing.clone()],
+        }];
+
+        let by_id = find_command(&categories, &listing.id()).unwrap();
+        assert_eq!(by_id.name, "List files");
+        let by_name = find_command(&categories, "list FILES").unwrap();
+        assert_eq!(by_name.name, "List files");
+        let by_substring = find_command(&categories, "long").unwrap();
+        assert_eq!(by_substring.name, "List files long");
+        assert!(find_command(&categories, "missing").is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -8,6 +9,25 @@ pub struct Config {
     pub theme: Theme,
     pub behavior: Behavior,
     pub directories: Directories,
+    #[serde(default)]
+    pub audit: Audit,
+    #[serde(default)]
+    pub sysctl: Sysctl,
+    #[serde(default)]
+    pub firewall: Firewall,
+    #[serde(default)]
+    pub keybindings: Keybindings,
+    #[serde(default)]
+    pub serve: Serve,
+    #[serde(default)]
+    pub web: Web,
+    #[serde(default)]
+    pub integrity: Integrity,
+    #[serde(default)]
+    pub persistence: Persistence,
+    /// Commands and runbooks run by `scheduler`, written as `[[schedules]]` tables
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<Schedule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +44,9 @@ pub struct Behavior {
     pub confirm_destructive_commands: bool,
     pub save_command_history: bool,
     pub max_output_lines: usize,
+    /// Hide commands this host cannot run instead of greying them out
+    #[serde(default)]
+    pub hide_unsupported_commands: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +56,197 @@ pub struct Directories {
     pub data_dir: PathBuf,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Audit {
+    /// Record every privileged execution in the hash-chained audit log
+    pub enabled: bool,
+    /// Location of the audit log, defaults to `<data_dir>/audit.log`, or to
+    /// `/var/lib/linux-toolkit/audit.log` when running as root
+    pub path: Option<PathBuf>,
+    /// Also send a one-line summary of each entry to syslog
+    pub syslog: bool,
+    /// HMAC key for the entry hashes, defaults to `<config_dir>/audit.key`,
+    /// or to `/var/lib/linux-toolkit/audit.key` when running as root
+    ///
+    /// Keep it out of `data_dir` so that whoever can edit the log cannot
+    /// recompute the chain. A key in a user's own directory only makes the
+    /// log tamper-evident against other users, not against that user.
+    pub key_path: Option<PathBuf>,
+}
+
+impl Default for Audit {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+            syslog: false,
+            key_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sysctl {
+    /// Drop-in written by `sysctl apply`
+    pub managed_file: PathBuf,
+    /// Per-key changes to the hardening profile, an empty value drops the key
+    pub overrides: BTreeMap<String, String>,
+    /// Also enforce `net.ipv4.ip_forward = 0`, which breaks routers and
+    /// Docker or Kubernetes hosts
+    pub disable_ip_forward: bool,
+}
+
+impl Default for Sysctl {
+    fn default() -> Self {
+        Self {
+            managed_file: PathBuf::from("/etc/sysctl.d/99-linux-toolkit.conf"),
+            overrides: BTreeMap::new(),
+            disable_ip_forward: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Firewall {
+    /// Seconds to confirm connectivity before a firewall change is reverted
+    pub confirm_timeout_secs: u64,
+}
+
+impl Default for Firewall {
+    fn default() -> Self {
+        Self {
+            confirm_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    /// Starting keymap: `default`, `vim` or `emacs`
+    pub preset: String,
+    /// Per-action key lists replacing the preset's, e.g. `execute = ["enter", "ctrl+m"]`
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            preset: "default".to_string(),
+            bindings: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Serve {
+    /// Socket used by `serve socket`, defaults to `$XDG_RUNTIME_DIR/linux-toolkit.sock`
+    pub socket_path: Option<PathBuf>,
+    /// Permission bits of the socket file
+    pub socket_mode: u32,
+    /// Users besides root and the server's own user allowed to connect
+    pub allowed_uids: Vec<u32>,
+}
+
+impl Default for Serve {
+    fn default() -> Self {
+        Self {
+            socket_path: None,
+            socket_mode: 0o600,
+            allowed_uids: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Web {
+    /// Port of `web` on 127.0.0.1
+    pub port: u16,
+    /// Fixed access token, a random one is generated per run when unset
+    pub token: Option<String>,
+}
+
+impl Default for Web {
+    fn default() -> Self {
+        Self {
+            port: 8765,
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Integrity {
+    /// Trees recorded in the file integrity baseline
+    pub paths: Vec<PathBuf>,
+    /// Glob patterns of paths left out, e.g. files rewritten on every boot
+    pub exclude: Vec<String>,
+}
+
+impl Default for Integrity {
+    fn default() -> Self {
+        Self {
+            paths: ["/etc", "/usr/bin", "/usr/sbin"]
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            exclude: [
+                "/etc/mtab",
+                "/etc/adjtime",
+                "/etc/ld.so.cache",
+                "/etc/resolv.conf",
+            ]
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Persistence {
+    /// Entries modified within this many days are flagged by the persistence hunter
+    pub recent_days: u32,
+}
+
+impl Default for Persistence {
+    fn default() -> Self {
+        Self { recent_days: 7 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Shown in the scheduler log, defaults to the command or runbook
+    pub name: Option<String>,
+    /// ID or name of the command to run
+    pub command: Option<String>,
+    /// File name or title of a runbook to run instead of a command
+    pub runbook: Option<String>,
+    /// Extra arguments appended to the command's own
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Five-field cron expression, or `@hourly`, `@daily`, `@weekly`, `@monthly`
+    pub cron: String,
+}
+
+impl Schedule {
+    pub fn label(&self) -> &str {
+        self.name
+            .as_deref()
+            .or(self.command.as_deref())
+            .or(self.runbook.as_deref())
+            .unwrap_or("unnamed schedule")
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
@@ -52,16 +266,34 @@ impl Default for Config {
                 confirm_destructive_commands: true,
                 save_command_history: true,
                 max_output_lines: 1000,
+                hide_unsupported_commands: false,
             },
             directories: Directories {
                 config_dir,
                 cache_dir,
                 data_dir,
             },
+            audit: Audit::default(),
+            sysctl: Sysctl::default(),
+            firewall: Firewall::default(),
+            keybindings: Keybindings::default(),
+            serve: Serve::default(),
+            web: Web::default(),
+            integrity: Integrity::default(),
+            persistence: Persistence::default(),
+            schedules: Vec::new(),
         }
     }
 }
 
+/// Where root keeps its audit log and key, out of reach of the user who ran sudo
+const SYSTEM_AUDIT_DIR: &str = "/var/lib/linux-toolkit";
+
+fn system_audit_dir() -> Option<PathBuf> {
+    let euid = crate::utils::user_ids().map(|(_, euid)| euid);
+    (euid == Some(0)).then(|| PathBuf::from(SYSTEM_AUDIT_DIR))
+}
+
 impl Config {
     pub fn load(config_path: Option<&String>) -> Result<Self> {
         let config = if let Some(path) = config_path {
@@ -89,6 +321,33 @@ impl Config {
         Ok(config)
     }
 
+    /// Resolve the audit log location, falling back to the data directory
+    pub fn audit_log_path(&self) -> PathBuf {
+        self.audit.path.clone().unwrap_or_else(|| {
+            system_audit_dir()
+                .unwrap_or_else(|| self.directories.data_dir.clone())
+                .join("audit.log")
+        })
+    }
+
+    /// Resolve the audit HMAC key location, kept apart from the log itself
+    pub fn audit_key_path(&self) -> PathBuf {
+        self.audit.key_path.clone().unwrap_or_else(|| {
+            system_audit_dir()
+                .unwrap_or_else(|| self.directories.config_dir.clone())
+                .join("audit.key")
+        })
+    }
+
+    /// Resolve the `serve` socket location
+    pub fn socket_path(&self) -> PathBuf {
+        self.serve.socket_path.clone().unwrap_or_else(|| {
+            dirs::runtime_dir()
+                .unwrap_or_else(|| self.directories.data_dir.clone())
+                .join("linux-toolkit.sock")
+        })
+    }
+
     pub fn save(&self, config_path: Option<&String>) -> Result<()> {
         let path = if let Some(path) = config_path {
             PathBuf::from(path)
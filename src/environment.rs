@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Tools commands commonly depend on, reported in `LTK_TOOLS`
+const KNOWN_TOOLS: &[&str] = &[
+    "sudo",
+    "systemctl",
+    "journalctl",
+    "ss",
+    "netstat",
+    "lsof",
+    "ip",
+    "curl",
+    "wget",
+    "git",
+    "ufw",
+    "firewall-cmd",
+    "nft",
+    "iptables",
+    "sshd",
+    "docker",
+    "podman",
+];
+
+/// Facts about the host, probed once and shared by the TUI and scripts
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// `ID` from /etc/os-release, e.g. `ubuntu`
+    pub os_id: String,
+    /// `ID_LIKE` from /etc/os-release, e.g. `debian`
+    pub os_id_like: Vec<String>,
+    pub os_name: String,
+    pub os_version: String,
+    pub init: String,
+    pub container: Option<String>,
+    pub virtualization: Option<String>,
+    pub wsl: bool,
+    pub arch: String,
+    pub package_manager: Option<String>,
+    pub tools: Vec<String>,
+}
+
+static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+
+/// The probed environment of the running host
+pub fn current() -> &'static Environment {
+    ENVIRONMENT.get_or_init(Environment::probe)
+}
+
+impl Environment {
+    pub fn probe() -> Self {
+        let os_release = fs::read_to_string("/etc/os-release")
+            .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+            .map(|content| parse_os_release(&content))
+            .unwrap_or_default();
+        let field = |key: &str| os_release.get(key).cloned().unwrap_or_default();
+
+        Self {
+            os_id: field("ID"),
+            os_id_like: field("ID_LIKE")
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            os_name: os_release
+                .get("PRETTY_NAME")
+                .or_else(|| os_release.get("NAME"))
+                .cloned()
+                .unwrap_or_else(|| std::env::consts::OS.to_string()),
+            os_version: field("VERSION_ID"),
+            init: detect_init(),
+            container: detect_container(),
+            virtualization: detect_virtualization(),
+            wsl: detect_wsl(),
+            arch: std::env::consts::ARCH.to_string(),
+            package_manager: crate::packages::detect().map(|m| m.name().to_string()),
+            tools: KNOWN_TOOLS
+                .iter()
+                .filter(|tool| which::which(tool).is_ok())
+                .map(|tool| tool.to_string())
+                .collect(),
+        }
+    }
+
+    /// Whether `distro` names this system or a distribution it derives from
+    pub fn matches_distro(&self, distro: &str) -> bool {
+        self.os_id.eq_ignore_ascii_case(distro)
+            || self
+                .os_id_like
+                .iter()
+                .any(|like| like.eq_ignore_ascii_case(distro))
+    }
+
+    /// One-line description for the System Info header
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.os_name.clone(), self.arch.clone(), self.init.clone()];
+        if let Some(container) = &self.container {
+            parts.push(format!("container: {}", container));
+        }
+        if let Some(virtualization) = &self.virtualization {
+            parts.push(format!("vm: {}", virtualization));
+        }
+        if self.wsl {
+            parts.push("WSL".to_string());
+        }
+        if let Some(manager) = &self.package_manager {
+            parts.push(format!("pkg: {}", manager));
+        }
+        parts.join(" · ")
+    }
+
+    /// `LTK_*` variables exported to every script
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        let vars = [
+            ("LTK_OS_ID", self.os_id.clone()),
+            ("LTK_OS_ID_LIKE", self.os_id_like.join(" ")),
+            ("LTK_OS_NAME", self.os_name.clone()),
+            ("LTK_OS_VERSION", self.os_version.clone()),
+            ("LTK_INIT", self.init.clone()),
+            ("LTK_CONTAINER", self.container.clone().unwrap_or_default()),
+            ("LTK_VIRT", self.virtualization.clone().unwrap_or_default()),
+            ("LTK_WSL", if self.wsl { "1" } else { "0" }.to_string()),
+            ("LTK_ARCH", self.arch.clone()),
+            (
+                "LTK_PKG_MANAGER",
+                self.package_manager.clone().unwrap_or_default(),
+            ),
+            ("LTK_TOOLS", self.tools.join(" ")),
+        ];
+        vars.into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+}
+
+/// `KEY=value` pairs, with optional double or single quotes around the value
+fn parse_os_release(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn detect_init() -> String {
+    if Path::new("/run/systemd/system").exists() {
+        return "systemd".to_string();
+    }
+    let comm = fs::read_to_string("/proc/1/comm").unwrap_or_default();
+    match comm.trim() {
+        "" => "unknown".to_string(),
+        "init" if Path::new("/sbin/openrc").exists() => "openrc".to_string(),
+        "init" => "sysvinit".to_string(),
+        "runit" | "runit-init" => "runit".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn detect_container() -> Option<String> {
+    if let Ok(container) = std::env::var("container") {
+        if !container.is_empty() {
+            return Some(container);
+        }
+    }
+    if Path::new("/.dockerenv").exists() {
+        return Some("docker".to_string());
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some("podman".to_string());
+    }
+
+    let cgroup = fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+    [
+        ("kubepods", "kubernetes"),
+        ("docker", "docker"),
+        ("libpod", "podman"),
+        ("lxc", "lxc"),
+        ("containerd", "containerd"),
+    ]
+    .iter()
+    .find(|(marker, _)| cgroup.contains(marker))
+    .map(|(_, name)| name.to_string())
+}
+
+fn detect_virtualization() -> Option<String> {
+    let dmi = |name: &str| {
+        fs::read_to_string(Path::new("/sys/class/dmi/id").join(name))
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    let identity = format!(
+        "{} {} {}",
+        dmi("sys_vendor"),
+        dmi("product_name"),
+        dmi("board_vendor")
+    );
+
+    let known = [
+        ("kvm", "kvm"),
+        ("qemu", "qemu"),
+        ("vmware", "vmware"),
+        ("virtualbox", "virtualbox"),
+        ("innotek", "virtualbox"),
+        ("microsoft corporation", "hyper-v"),
+        ("xen", "xen"),
+        ("amazon ec2", "amazon"),
+        ("google compute engine", "google"),
+        ("parallels", "parallels"),
+        ("bochs", "bochs"),
+        ("firecracker", "firecracker"),
+    ];
+    if let Some((_, name)) = known.iter().find(|(marker, _)| identity.contains(marker)) {
+        return Some(name.to_string());
+    }
+
+    // No DMI (e.g. microVMs): the CPU still reports running under a hypervisor
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    cpuinfo
+        .lines()
+        .any(|line| line.starts_with("flags") && line.split_whitespace().any(|f| f == "hypervisor"))
+        .then(|| "unknown".to_string())
+}
+
+fn detect_wsl() -> bool {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|release| {
+            let release = release.to_lowercase();
+            release.contains("microsoft") || release.contains("wsl")
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::executor::{override_current, RecordingExecutor};
+    use crate::scripts::ScriptManager;
+    use crate::testing;
+    use std::sync::Arc;
+
+    fn ubuntu() -> Environment {
+        Environment {
+            os_id: "ubuntu".to_string(),
+            os_id_like: vec!["debian".to_string()],
+            os_name: "Ubuntu 24.04 LTS".to_string(),
+            os_version: "24.04".to_string(),
+            init: "systemd".to_string(),
+            container: Some("docker".to_string()),
+            arch: "x86_64".to_string(),
+            package_manager: Some("apt".to_string()),
+            tools: vec!["ss".to_string(), "ip".to_string()],
+            ..Environment::default()
+        }
+    }
+
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        config
+    }
+
+    #[test]
+    fn parses_os_release() {
+        let content = "# comment\nNAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE='debian'\n\
+                       PRETTY_NAME=\"Ubuntu 24.04 LTS\"\nVERSION_ID=\"24.04\"\n";
+        let fields = parse_os_release(content);
+        assert_eq!(fields["ID"], "ubuntu");
+        assert_eq!(fields["ID_LIKE"], "debian");
+        assert_eq!(fields["PRETTY_NAME"], "Ubuntu 24.04 LTS");
+        assert_eq!(fields["VERSION_ID"], "24.04");
+        assert!(!fields.contains_key("# comment"));
+    }
+
+    #[test]
+    fn probe_describes_the_running_host() {
+        let environment = Environment::probe();
+        assert_eq!(environment.arch, std::env::consts::ARCH);
+        assert!(!environment.os_name.is_empty());
+        assert!(!environment.init.is_empty());
+        assert!(environment
+            .tools
+            .iter()
+            .all(|tool| KNOWN_TOOLS.contains(&tool.as_str())));
+    }
+
+    #[test]
+    fn exports_every_fact_as_ltk_variables() {
+        let vars: BTreeMap<String, String> = ubuntu().env_vars().into_iter().collect();
+        assert_eq!(vars["LTK_OS_ID"], "ubuntu");
+        assert_eq!(vars["LTK_OS_ID_LIKE"], "debian");
+        assert_eq!(vars["LTK_CONTAINER"], "docker");
+        assert_eq!(vars["LTK_VIRT"], "");
+        assert_eq!(vars["LTK_WSL"], "0");
+        assert_eq!(vars["LTK_PKG_MANAGER"], "apt");
+        assert_eq!(vars["LTK_TOOLS"], "ss ip");
+        assert!(vars.keys().all(|key| key.starts_with("LTK_")));
+    }
+
+    #[tokio::test]
+    async fn scripts_run_with_the_ltk_variables() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        let script = Path::new("/tmp/check.sh");
+
+        ScriptManager::capture(
+            ScriptManager::invocation(script, &[], false),
+            script,
+            "test/check",
+            &test_config(),
+        )
+        .await
+        .unwrap();
+
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 1);
+        let exported: Vec<&str> = calls[0].env.iter().map(|(key, _)| key.as_str()).collect();
+        assert!(exported.contains(&"LTK_OS_ID"));
+        assert!(exported.contains(&"LTK_TOOLS"));
+        assert_eq!(calls[0].env, current().env_vars());
+    }
+
+    #[test]
+    fn distro_requirements_follow_id_like() {
+        let mut command = testing::command("Apt Upgrade", "apt").build();
+        assert_eq!(command.unsupported_reason(&ubuntu()), None);
+
+        command.supported_distros = vec!["Debian".to_string()];
+        assert_eq!(command.unsupported_reason(&ubuntu()), None);
+
+        command.supported_distros = vec!["fedora".to_string(), "rhel".to_string()];
+        assert_eq!(
+            command.unsupported_reason(&ubuntu()).as_deref(),
+            Some("only supported on fedora, rhel")
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_tools_stop_a_command_before_it_runs() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+
+        let mut script = testing::command("Scan", "script").build();
+        script.requires_tools = vec!["ltk-no-such-tool".to_string()];
+        assert_eq!(script.required_binaries(), ["ltk-no-such-tool"]);
+
+        let mut command = testing::command("Scan", "ltk-no-such-program").build();
+        command.requires_tools = vec!["sh".to_string()];
+        assert_eq!(command.required_binaries(), ["ltk-no-such-program", "sh"]);
+        assert_eq!(command.missing_binaries(), ["ltk-no-such-program"]);
+
+        let run = crate::run_command(&command, &test_config()).await.unwrap();
+        assert!(!run.success);
+        assert_eq!(
+            run.output,
+            "❌ Missing required program(s): ltk-no-such-program"
+        );
+        assert!(fake.calls().is_empty());
+    }
+}
@@ -0,0 +1,413 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A program to run, with extra environment variables on top of ours
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Fed to the process when captured, otherwise stdin is empty
+    pub stdin: Option<String>,
+}
+
+impl Invocation {
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            env: Vec::new(),
+            stdin: None,
+        }
+    }
+
+    /// Program and arguments from a single argv list
+    pub fn from_argv(argv: &[String]) -> Self {
+        Self::new(argv[0].clone(), argv[1..].to_vec())
+    }
+
+    pub fn with_env(mut self, env: Vec<(String, String)>) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn with_stdin(mut self, input: impl Into<String>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    /// Program followed by its arguments, as recorded in the audit log
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv = vec![self.program.clone()];
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+}
+
+/// Exit code of a finished process, `None` when it was killed by a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub code: Option<i32>,
+}
+
+impl Status {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}", code),
+            None => write!(f, "killed by signal"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub status: Status,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Which output stream a line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// How long a cancelled process group has to exit after SIGTERM before SIGKILL
+const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+/// Stops a streaming run together with everything it started
+#[derive(Clone, Default)]
+pub struct Cancel(Arc<Mutex<CancelState>>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: bool,
+    /// Process group of the run while it is alive
+    group: Option<u32>,
+}
+
+impl Cancel {
+    /// Signal the run's process group, now or as soon as it starts
+    ///
+    /// SIGTERM comes first since sudo relays it to the elevated command, which
+    /// a SIGKILL to sudo itself would leave running.
+    pub fn cancel(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.cancelled = true;
+        if let Some(group) = state.group {
+            signal_group(group, false);
+            let cancel = self.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(CANCEL_GRACE);
+                if cancel.0.lock().unwrap().group == Some(group) {
+                    signal_group(group, true);
+                }
+            });
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.lock().unwrap().cancelled
+    }
+
+    fn started(&self, group: u32) {
+        self.0.lock().unwrap().group = Some(group);
+        if self.is_cancelled() {
+            self.cancel();
+        }
+    }
+
+    fn exited(&self) {
+        self.0.lock().unwrap().group = None;
+    }
+}
+
+#[cfg(unix)]
+fn signal_group(group: u32, kill: bool) {
+    let signal = if kill { libc::SIGKILL } else { libc::SIGTERM };
+    // SAFETY: killpg only sends a signal
+    unsafe { libc::killpg(group as libc::pid_t, signal) };
+}
+
+#[cfg(not(unix))]
+fn signal_group(_group: u32, _kill: bool) {}
+
+/// Runs commands and scripts on behalf of the toolkit, so tests can swap in a fake
+pub trait Executor: Send + Sync {
+    /// Run to completion with stdout and stderr captured
+    fn capture(&self, invocation: &Invocation) -> io::Result<Output>;
+
+    /// Run attached to the terminal
+    fn attach(&self, invocation: &Invocation) -> io::Result<Status>;
+
+    /// Run with stdin on the terminal, copying its output there while capturing it
+    fn tee(&self, invocation: &Invocation) -> io::Result<Output>;
+
+    /// Run in its own process group, passing output lines to `on_line` as they arrive
+    fn stream(
+        &self,
+        invocation: &Invocation,
+        on_line: &(dyn Fn(Stream, &str) + Sync),
+        cancel: &Cancel,
+    ) -> io::Result<Status>;
+}
+
+/// Spawns real processes
+pub struct SystemExecutor;
+
+impl SystemExecutor {
+    fn command(invocation: &Invocation) -> std::process::Command {
+        let mut cmd = std::process::Command::new(&invocation.program);
+        cmd.args(&invocation.args)
+            .envs(invocation.env.iter().cloned());
+        cmd
+    }
+}
+
+impl Executor for SystemExecutor {
+    fn capture(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut child = Self::command(invocation)
+            .stdin(match invocation.stdin {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let (Some(input), Some(mut stdin)) = (&invocation.stdin, child.stdin.take()) {
+            stdin.write_all(input.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        Ok(Output {
+            status: Status {
+                code: output.status.code(),
+            },
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    fn attach(&self, invocation: &Invocation) -> io::Result<Status> {
+        let status = Self::command(invocation).status()?;
+        Ok(Status {
+            code: status.code(),
+        })
+    }
+
+    fn tee(&self, invocation: &Invocation) -> io::Result<Output> {
+        let mut child = Self::command(invocation)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let (status, stdout, stderr) = std::thread::scope(|scope| {
+            let stdout = scope.spawn(|| copy_through(stdout, io::stdout()));
+            let stderr = scope.spawn(|| copy_through(stderr, io::stderr()));
+            let status = child.wait();
+            (
+                status,
+                stdout.join().unwrap_or_default(),
+                stderr.join().unwrap_or_default(),
+            )
+        });
+        Ok(Output {
+            status: Status {
+                code: status?.code(),
+            },
+            stdout: String::from_utf8_lossy(&stdout).to_string(),
+            stderr: String::from_utf8_lossy(&stderr).to_string(),
+        })
+    }
+
+    fn stream(
+        &self,
+        invocation: &Invocation,
+        on_line: &(dyn Fn(Stream, &str) + Sync),
+        cancel: &Cancel,
+    ) -> io::Result<Status> {
+        let mut command = Self::command(invocation);
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn()?;
+        cancel.started(child.id());
+
+        let forward = |stream: Stream, reader: Option<Box<dyn Read + Send>>| {
+            let Some(reader) = reader else {
+                return;
+            };
+            for line in BufReader::new(reader).lines() {
+                match line {
+                    Ok(line) => on_line(stream, &line),
+                    Err(_) => break,
+                }
+            }
+        };
+        let stdout = child
+            .stdout
+            .take()
+            .map(|r| Box::new(r) as Box<dyn Read + Send>);
+        let stderr = child
+            .stderr
+            .take()
+            .map(|r| Box::new(r) as Box<dyn Read + Send>);
+        let status = std::thread::scope(|scope| {
+            scope.spawn(|| forward(Stream::Stdout, stdout));
+            scope.spawn(|| forward(Stream::Stderr, stderr));
+            child.wait()
+        });
+        cancel.exited();
+        Ok(Status {
+            code: status?.code(),
+        })
+    }
+}
+
+/// Copy everything `reader` produces to `writer` as it arrives, returning a copy
+fn copy_through(reader: Option<impl Read>, mut writer: impl Write) -> Vec<u8> {
+    let mut seen = Vec::new();
+    let Some(mut reader) = reader else {
+        return seen;
+    };
+    let mut buffer = [0u8; 8192];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => {
+                let _ = writer.write_all(&buffer[..read]);
+                let _ = writer.flush();
+                seen.extend_from_slice(&buffer[..read]);
+            }
+        }
+    }
+    seen
+}
+
+thread_local! {
+    static OVERRIDE: RefCell<Option<Arc<dyn Executor>>> = const { RefCell::new(None) };
+}
+
+/// The executor for this thread: the real one unless a test replaced it
+pub fn current() -> Arc<dyn Executor> {
+    OVERRIDE
+        .with(|executor| executor.borrow().clone())
+        .unwrap_or_else(|| Arc::new(SystemExecutor))
+}
+
+/// Capture output without blocking the async runtime
+pub async fn capture(invocation: Invocation) -> io::Result<Output> {
+    let executor = current();
+    tokio::task::spawn_blocking(move || executor.capture(&invocation))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Use `executor` on this thread until the guard is dropped
+#[cfg(test)]
+pub fn override_current(executor: Arc<dyn Executor>) -> OverrideGuard {
+    OVERRIDE.with(|current| *current.borrow_mut() = Some(executor));
+    OverrideGuard
+}
+
+#[cfg(test)]
+pub struct OverrideGuard;
+
+#[cfg(test)]
+impl Drop for OverrideGuard {
+    fn drop(&mut self) {
+        OVERRIDE.with(|current| *current.borrow_mut() = None);
+    }
+}
+
+/// Records every invocation and answers with canned output
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingExecutor {
+    pub calls: std::sync::Mutex<Vec<Invocation>>,
+    responses: std::sync::Mutex<std::collections::VecDeque<Output>>,
+}
+
+#[cfg(test)]
+impl RecordingExecutor {
+    /// Queue the output for the next call; calls beyond the queue succeed silently
+    pub fn respond(&self, code: i32, stdout: &str, stderr: &str) {
+        self.responses.lock().unwrap().push_back(Output {
+            status: Status { code: Some(code) },
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        });
+    }
+
+    pub fn calls(&self) -> Vec<Invocation> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn next(&self, invocation: &Invocation) -> Output {
+        self.calls.lock().unwrap().push(invocation.clone());
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(Output {
+                status: Status { code: Some(0) },
+                stdout: String::new(),
+                stderr: String::new(),
+            })
+    }
+}
+
+#[cfg(test)]
+impl Executor for RecordingExecutor {
+    fn capture(&self, invocation: &Invocation) -> io::Result<Output> {
+        Ok(self.next(invocation))
+    }
+
+    fn attach(&self, invocation: &Invocation) -> io::Result<Status> {
+        Ok(self.next(invocation).status)
+    }
+
+    fn tee(&self, invocation: &Invocation) -> io::Result<Output> {
+        Ok(self.next(invocation))
+    }
+
+    fn stream(
+        &self,
+        invocation: &Invocation,
+        on_line: &(dyn Fn(Stream, &str) + Sync),
+        _cancel: &Cancel,
+    ) -> io::Result<Status> {
+        let output = self.next(invocation);
+        output
+            .stdout
+            .lines()
+            .for_each(|line| on_line(Stream::Stdout, line));
+        output
+            .stderr
+            .lines()
+            .for_each(|line| on_line(Stream::Stderr, line));
+        Ok(output.status)
+    }
+}
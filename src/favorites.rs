@@ -0,0 +1,221 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::{Command, CommandCategory};
+
+pub const FAVORITES_CATEGORY: &str = "★ Favorites";
+pub const RECENT_CATEGORY: &str = "Recent";
+
+/// How many commands the Recent category shows
+const RECENT_LIMIT: usize = 10;
+
+/// Pinned commands and how often each command has been run, keyed by `Command::id`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStore {
+    pub favorites: Vec<String>,
+    pub usage: BTreeMap<String, Usage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Usage {
+    pub count: u64,
+    pub last_used: DateTime<Utc>,
+}
+
+fn store_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("usage.json")
+}
+
+impl UsageStore {
+    /// Load the store, starting empty if it is missing or unreadable
+    pub fn load(data_dir: &Path) -> Self {
+        fs::read_to_string(store_path(data_dir))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        fs::create_dir_all(data_dir)?;
+        crate::utils::write_atomic(&store_path(data_dir), &serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn is_favorite(&self, id: &str) -> bool {
+        self.favorites.iter().any(|favorite| favorite == id)
+    }
+
+    /// Pin or unpin a command, returning whether it is now pinned
+    pub fn toggle_favorite(&mut self, id: &str) -> bool {
+        if self.is_favorite(id) {
+            self.favorites.retain(|favorite| favorite != id);
+            false
+        } else {
+            self.favorites.push(id.to_string());
+            true
+        }
+    }
+
+    pub fn record_use(&mut self, id: &str) {
+        let entry = self.usage.entry(id.to_string()).or_insert(Usage {
+            count: 0,
+            last_used: Utc::now(),
+        });
+        entry.count += 1;
+        entry.last_used = Utc::now();
+    }
+
+    /// Command IDs by most recent use, most frequently used first on ties
+    pub fn recent(&self) -> Vec<&str> {
+        let mut entries: Vec<(&String, &Usage)> = self.usage.iter().collect();
+        entries.sort_by(|a, b| {
+            b.1.last_used
+                .cmp(&a.1.last_used)
+                .then(b.1.count.cmp(&a.1.count))
+        });
+        entries
+            .into_iter()
+            .take(RECENT_LIMIT)
+            .map(|(id, _)| id.as_str())
+            .collect()
+    }
+
+    /// Favorites and Recent categories built from the real ones, omitted when empty
+    pub fn virtual_categories(&self, categories: &[CommandCategory]) -> Vec<CommandCategory> {
+        let find = |id: &str| -> Option<Command> {
+            categories
+                .iter()
+                .flat_map(|category| &category.commands)
+                .find(|command| command.id() == id)
+                .cloned()
+        };
+
+        let favorites: Vec<Command> = self.favorites.iter().filter_map(|id| find(id)).collect();
+        let recent: Vec<Command> = self.recent().into_iter().filter_map(find).collect();
+
+        let mut virtual_categories = Vec::new();
+        if !favorites.is_empty() {
+            virtual_categories.push(CommandCategory {
+                name: FAVORITES_CATEGORY.to_string(),
+                description: "Pinned commands".to_string(),
+                commands: favorites,
+            });
+        }
+        if !recent.is_empty() {
+            virtual_categories.push(CommandCategory {
+                name: RECENT_CATEGORY.to_string(),
+                description: "Recently used commands".to_string(),
+                commands: recent,
+            });
+        }
+        virtual_categories
+    }
+}
+
+/// Whether `category` is one of the generated Favorites/Recent categories
+pub fn is_virtual(category: &CommandCategory) -> bool {
+    category.name == FAVORITES_CATEGORY || category.name == RECENT_CATEGORY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+    use chrono::Duration;
+
+    fn catalog(names: &[&str]) -> Vec<CommandCategory> {
+        vec![CommandCategory {
+            name: "Test".to_string(),
+            description: String::new(),
+            commands: names
+                .iter()
+                .map(|name| testing::command(name, "ls").build())
+                .collect(),
+        }]
+    }
+
+    fn used(store: &mut UsageStore, id: &str, count: u64, minutes_ago: i64) {
+        store.usage.insert(
+            id.to_string(),
+            Usage {
+                count,
+                last_used: Utc::now() - Duration::minutes(minutes_ago),
+            },
+        );
+    }
+
+    #[test]
+    fn favorites_keep_pin_order_and_drop_unknown_commands() {
+        let mut store = UsageStore::default();
+        assert!(store.toggle_favorite("test/b"));
+        assert!(store.toggle_favorite("test/gone"));
+        assert!(store.toggle_favorite("test/a"));
+        assert!(!store.toggle_favorite("test/gone"));
+        assert!(store.toggle_favorite("test/gone"));
+
+        let categories = store.virtual_categories(&catalog(&["a", "b"]));
+        assert_eq!(categories.len(), 1);
+        assert_eq!(categories[0].name, FAVORITES_CATEGORY);
+        let names: Vec<&str> = categories[0]
+            .commands
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, ["b", "a"]);
+    }
+
+    #[test]
+    fn recent_orders_by_last_use_then_count() {
+        let mut store = UsageStore::default();
+        used(&mut store, "test/old", 50, 60);
+        used(&mut store, "test/rare", 1, 5);
+        used(&mut store, "test/often", 9, 5);
+        used(&mut store, "test/new", 1, 0);
+        assert_eq!(
+            store.recent(),
+            ["test/new", "test/often", "test/rare", "test/old"]
+        );
+
+        store.record_use("test/old");
+        assert_eq!(store.usage["test/old"].count, 51);
+        assert_eq!(store.recent()[0], "test/old");
+    }
+
+    #[test]
+    fn recent_is_capped_and_skips_removed_commands() {
+        let mut store = UsageStore::default();
+        let names: Vec<String> = (0..15).map(|i| format!("c{:02}", i)).collect();
+        for (i, name) in names.iter().enumerate() {
+            used(&mut store, &format!("test/{}", name), 1, i as i64);
+        }
+        assert_eq!(store.recent().len(), RECENT_LIMIT);
+        assert_eq!(store.recent()[RECENT_LIMIT - 1], "test/c09");
+
+        // c00 was removed from the catalog, so only nine of the ten show
+        let present: Vec<&str> = names[1..].iter().map(String::as_str).collect();
+        let categories = store.virtual_categories(&catalog(&present));
+        assert_eq!(categories.len(), 1);
+        assert_eq!(categories[0].name, RECENT_CATEGORY);
+        assert_eq!(categories[0].commands.len(), RECENT_LIMIT - 1);
+        assert_eq!(categories[0].commands[0].name, "c01");
+    }
+
+    #[test]
+    fn saves_and_loads_without_leaving_temporary_files() {
+        let dir = TempDir::new("favorites-save");
+        let mut store = UsageStore::default();
+        store.toggle_favorite("test/a");
+        store.record_use("test/b");
+        store.save(dir.path()).unwrap();
+        store.save(dir.path()).unwrap();
+
+        let loaded = UsageStore::load(dir.path());
+        assert_eq!(loaded.favorites, ["test/a"]);
+        assert_eq!(loaded.usage["test/b"].count, 1);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
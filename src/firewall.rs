@@ -0,0 +1,1530 @@
+use anyhow::{anyhow, Context, Result};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::executor::{self, Invocation};
+
+/// Table linux-toolkit adds its own nftables chains to
+const NFT_TABLE: &str = "linux_toolkit";
+
+/// firewalld's own settings, read while the daemon is stopped
+const FIREWALLD_CONF: &str = "/etc/firewalld/firewalld.conf";
+/// Zone definitions, admin overrides first
+const FIREWALLD_ZONE_DIRS: &[&str] = &["/etc/firewalld/zones", "/usr/lib/firewalld/zones"];
+
+/// Files holding ufw's rules and enabled state
+const UFW_STATE_FILES: &[&str] = &[
+    "/etc/ufw/user.rules",
+    "/etc/ufw/user6.rules",
+    "/etc/ufw/ufw.conf",
+    "/etc/default/ufw",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Ufw,
+    Firewalld,
+    Nftables,
+    Iptables,
+}
+
+impl Backend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Ufw => "ufw",
+            Backend::Firewalld => "firewalld",
+            Backend::Nftables => "nftables",
+            Backend::Iptables => "iptables",
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Backend::Ufw => "ufw",
+            Backend::Firewalld => "firewall-cmd",
+            Backend::Nftables => "nft",
+            Backend::Iptables => "iptables",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detected {
+    pub backend: Backend,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Allow,
+    Deny,
+    Reject,
+    /// Allow with rate limiting, as ufw's `limit`
+    Limit,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+        }
+    }
+}
+
+impl Protocol {
+    fn as_str(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Any => "any",
+        }
+    }
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+            Action::Reject => "reject",
+            Action::Limit => "limit",
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "in" | "incoming" | "input" => Ok(Direction::In),
+            "out" | "outgoing" | "output" => Ok(Direction::Out),
+            other => Err(anyhow!("Unknown direction '{}'", other)),
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            "any" | "all" => Ok(Protocol::Any),
+            other => Err(anyhow!("Unknown protocol '{}'", other)),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "allow" | "accept" => Ok(Action::Allow),
+            "deny" | "drop" => Ok(Action::Deny),
+            "reject" => Ok(Action::Reject),
+            "limit" => Ok(Action::Limit),
+            other => Err(anyhow!("Unknown action '{}'", other)),
+        }
+    }
+}
+
+/// A firewall rule in the form every backend can express
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub action: Action,
+    pub direction: Direction,
+    pub protocol: Protocol,
+    /// Port, `low-high` range or, for ufw and firewalld, a service name
+    pub port: Option<String>,
+    /// Remote address: the source of incoming and destination of outgoing traffic
+    pub source: Option<String>,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.action.as_str(),
+            self.direction.as_str(),
+            self.protocol.as_str()
+        )?;
+        if let Some(port) = &self.port {
+            write!(f, " {}", port)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, " from {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse `ACTION [in|out] [tcp|udp|any] [PORT] [from ADDRESS]`
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tokens = s.split_whitespace();
+        let action = tokens
+            .next()
+            .ok_or_else(|| anyhow!("Empty rule, expected e.g. 'allow in tcp 22'"))?
+            .parse()?;
+        let mut rule = Rule {
+            action,
+            direction: Direction::In,
+            protocol: Protocol::Any,
+            port: None,
+            source: None,
+        };
+
+        while let Some(token) = tokens.next() {
+            if token == "from" {
+                let source = tokens
+                    .next()
+                    .ok_or_else(|| anyhow!("'from' needs an address"))?;
+                rule.source = normalize_address(source);
+            } else if let Ok(direction) = token.parse() {
+                rule.direction = direction;
+            } else if let Ok(protocol) = token.parse() {
+                rule.protocol = protocol;
+            } else if rule.port.is_none() {
+                rule.port = Some(token.replace(':', "-"));
+            } else {
+                return Err(anyhow!("Unexpected '{}' in rule '{}'", token, s));
+            }
+        }
+        Ok(rule)
+    }
+}
+
+fn normalize_address(address: &str) -> Option<String> {
+    let address = address.trim_end_matches(" (v6)");
+    match address.to_lowercase().as_str() {
+        "any" | "anywhere" | "0.0.0.0/0" | "::/0" => None,
+        _ => Some(address.to_string()),
+    }
+}
+
+fn is_numeric_port(port: &str) -> bool {
+    port.chars()
+        .all(|c| c.is_ascii_digit() || c == '-' || c == ',')
+}
+
+/// Default actions for traffic no rule matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policies {
+    pub incoming: Option<Action>,
+    pub outgoing: Option<Action>,
+}
+
+/// State captured before a change so it can be put back
+#[derive(Debug, Clone)]
+enum Snapshot {
+    Ufw {
+        files: Vec<(PathBuf, Option<Vec<u8>>)>,
+        active: bool,
+    },
+    /// Changes are made to the runtime config only, so a reload reverts them
+    Firewalld {
+        zone: String,
+        target: String,
+        running: bool,
+    },
+    Nftables(String),
+    Iptables(String),
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    run_invocation(Invocation::new(program, args))
+}
+
+fn run_owned(argv: &[String]) -> Result<String> {
+    let args: Vec<&str> = argv[1..].iter().map(String::as_str).collect();
+    run(&argv[0], &args)
+}
+
+fn run_with_stdin(program: &str, args: &[&str], input: &str) -> Result<()> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    run_invocation(Invocation::new(program, args).with_stdin(input)).map(|_| ())
+}
+
+fn run_invocation(invocation: Invocation) -> Result<String> {
+    let output = executor::current().capture(&invocation)?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(anyhow!(
+            "{} failed: {}",
+            invocation.argv().join(" "),
+            output.stderr.trim()
+        ))
+    }
+}
+
+/// Find the firewall in use, preferring one that is already active
+pub fn detect() -> Option<Detected> {
+    let installed: Vec<Backend> = [
+        Backend::Ufw,
+        Backend::Firewalld,
+        Backend::Nftables,
+        Backend::Iptables,
+    ]
+    .into_iter()
+    .filter(|b| which::which(b.program()).is_ok())
+    .collect();
+
+    let active = installed.iter().copied().find(|backend| match backend {
+        Backend::Ufw => run("ufw", &["status"])
+            .map(|o| o.contains("Status: active"))
+            .unwrap_or(false),
+        Backend::Firewalld => run("firewall-cmd", &["--state"])
+            .map(|o| o.trim() == "running")
+            .unwrap_or(false),
+        // Docker and iptables-nft add chains of their own, only ours count
+        Backend::Nftables => run("nft", &["list", "ruleset"])
+            .map(|o| owns_nft_input(&o))
+            .unwrap_or(false),
+        Backend::Iptables => run("iptables", &["-S", "INPUT"])
+            .map(|o| o.lines().any(|l| l.starts_with("-A INPUT")) || o.contains("-P INPUT DROP"))
+            .unwrap_or(false),
+    });
+
+    match active {
+        Some(backend) => Some(Detected {
+            backend,
+            active: true,
+        }),
+        None => installed.first().map(|&backend| Detected {
+            backend,
+            active: false,
+        }),
+    }
+}
+
+/// Whether the ruleset has our table with an input filter chain
+fn owns_nft_input(ruleset: &str) -> bool {
+    let mut in_our_table = false;
+    for line in ruleset.lines().map(str::trim) {
+        if let Some(table) = line.strip_prefix("table ") {
+            in_our_table = table.trim_end_matches(" {").trim() == format!("inet {}", NFT_TABLE);
+        } else if in_our_table && line.starts_with("type filter hook input") {
+            return true;
+        }
+    }
+    false
+}
+
+fn require_backend() -> Result<Detected> {
+    detect().ok_or_else(|| {
+        anyhow!("No supported firewall found, install ufw, firewalld, nftables or iptables")
+    })
+}
+
+pub fn list_rules(backend: Backend) -> Result<Vec<Rule>> {
+    match backend {
+        Backend::Ufw => Ok(parse_ufw_status(&run("ufw", &["status"])?)),
+        Backend::Firewalld => firewalld_rules(),
+        Backend::Nftables => Ok(nft_rules()?.into_iter().map(|(rule, _)| rule).collect()),
+        Backend::Iptables => Ok(parse_iptables(&run("iptables", &["-S"])?)),
+    }
+}
+
+fn parse_ufw_status(output: &str) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for line in output.lines() {
+        // Columns are separated by runs of spaces: To, Action, From
+        let columns: Vec<&str> = line
+            .split("  ")
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .collect();
+        if columns.len() != 3 || columns[0].ends_with("(v6)") {
+            continue;
+        }
+        let mut action_words = columns[1].split_whitespace();
+        let Some(Ok(action)) = action_words.next().map(str::parse::<Action>) else {
+            continue;
+        };
+        let direction = action_words
+            .next()
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(Direction::In);
+
+        let (to_address, port, protocol) = ufw_endpoint(columns[0]);
+        let (from_address, _, _) = ufw_endpoint(columns[2]);
+        rules.push(Rule {
+            action,
+            direction,
+            protocol,
+            port,
+            // For outgoing rules the remote side is the "To" column
+            source: match direction {
+                Direction::In => from_address,
+                Direction::Out => to_address,
+            },
+        });
+    }
+    rules
+}
+
+/// Split a ufw status column such as `10.0.0.1 22/tcp` or `Anywhere`
+fn ufw_endpoint(column: &str) -> (Option<String>, Option<String>, Protocol) {
+    let port = |spec: &str| match spec.split_once('/') {
+        Some((port, proto)) => (
+            Some(port.replace(':', "-")),
+            proto.parse().unwrap_or(Protocol::Any),
+        ),
+        None => (Some(spec.replace(':', "-")), Protocol::Any),
+    };
+    let is_address = |token: &str| token.eq_ignore_ascii_case("anywhere") || token.contains('.');
+
+    match column.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [address, spec, ..] => {
+            let (port, protocol) = port(spec);
+            (normalize_address(address), port, protocol)
+        }
+        [token] if is_address(token) => (normalize_address(token), None, Protocol::Any),
+        [spec] => {
+            let (port, protocol) = port(spec);
+            (None, port, protocol)
+        }
+        [] => (None, None, Protocol::Any),
+    }
+}
+
+fn firewalld_rules() -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for port in run("firewall-cmd", &["--list-ports"])?.split_whitespace() {
+        let (port, protocol) = port.split_once('/').unwrap_or((port, "any"));
+        rules.push(Rule {
+            action: Action::Allow,
+            direction: Direction::In,
+            protocol: protocol.parse().unwrap_or(Protocol::Any),
+            port: Some(port.to_string()),
+            source: None,
+        });
+    }
+    for service in run("firewall-cmd", &["--list-services"])?.split_whitespace() {
+        rules.push(Rule {
+            action: Action::Allow,
+            direction: Direction::In,
+            protocol: Protocol::Any,
+            port: Some(service.to_string()),
+            source: None,
+        });
+    }
+    for line in run("firewall-cmd", &["--list-rich-rules"])?.lines() {
+        if let Some(rule) = parse_rich_rule(line) {
+            rules.push(rule);
+        }
+    }
+    Ok(rules)
+}
+
+/// Read the subset of firewalld rich rules that fits the rule model
+fn parse_rich_rule(line: &str) -> Option<Rule> {
+    let value = |key: &str| {
+        let start = line.find(&format!("{}=\"", key))? + key.len() + 2;
+        let end = line[start..].find('"')? + start;
+        Some(line[start..end].to_string())
+    };
+    let action = line.split_whitespace().last()?.parse().ok()?;
+    Some(Rule {
+        action,
+        direction: Direction::In,
+        protocol: value("protocol")
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(Protocol::Any),
+        port: value("port port").or_else(|| value("service name")),
+        source: value("source address"),
+    })
+}
+
+fn rich_rule(rule: &Rule) -> String {
+    let mut parts = vec!["rule".to_string()];
+    if let Some(source) = &rule.source {
+        let family = if source.contains(':') { "ipv6" } else { "ipv4" };
+        parts.push(format!("family=\"{}\"", family));
+        parts.push(format!("source address=\"{}\"", source));
+    }
+    match &rule.port {
+        Some(port) if is_numeric_port(port) => parts.push(format!(
+            "port port=\"{}\" protocol=\"{}\"",
+            port,
+            rule.protocol.as_str()
+        )),
+        Some(service) => parts.push(format!("service name=\"{}\"", service)),
+        None => {}
+    }
+    parts.push(
+        match rule.action {
+            Action::Allow | Action::Limit => "accept",
+            Action::Deny => "drop",
+            Action::Reject => "reject",
+        }
+        .to_string(),
+    );
+    parts.join(" ")
+}
+
+/// Rules from `nft -a list ruleset` with the handle needed to delete them
+fn nft_rules() -> Result<Vec<(Rule, String)>> {
+    Ok(parse_nft_ruleset(&run("nft", &["-a", "list", "ruleset"])?))
+}
+
+fn parse_nft_ruleset(output: &str) -> Vec<(Rule, String)> {
+    let mut rules = Vec::new();
+    let mut table = String::new();
+    let mut chain = String::new();
+    let mut direction = None;
+
+    for line in output.lines().map(str::trim) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["table", family, name, ..] => {
+                table = format!("{} {}", family, name);
+                direction = None;
+            }
+            ["chain", name, ..] => {
+                chain = name.to_string();
+                direction = None;
+            }
+            _ if line.starts_with("type filter hook input") => direction = Some(Direction::In),
+            _ if line.starts_with("type filter hook output") => direction = Some(Direction::Out),
+            _ => {
+                let Some(direction) = direction else {
+                    continue;
+                };
+                if let Some((rule, handle)) = parse_nft_rule(&tokens, direction) {
+                    rules.push((rule, format!("{} {} handle {}", table, chain, handle)));
+                }
+            }
+        }
+    }
+    rules
+}
+
+fn parse_nft_rule(tokens: &[&str], direction: Direction) -> Option<(Rule, String)> {
+    // Connection tracking and interface rules are plumbing, not port rules
+    if tokens.iter().any(|t| {
+        matches!(
+            *t,
+            "ct" | "iif" | "oif" | "iifname" | "oifname" | "jump" | "goto"
+        )
+    }) {
+        return None;
+    }
+    let handle = tokens
+        .iter()
+        .position(|t| *t == "handle")
+        .and_then(|i| tokens.get(i + 1))?;
+    // Only verdicts, `limit rate ...` is a match expression in nftables
+    let action = tokens
+        .iter()
+        .filter(|t| matches!(**t, "accept" | "drop" | "reject"))
+        .find_map(|t| t.parse::<Action>().ok())?;
+
+    let after = |key: &str| {
+        tokens
+            .iter()
+            .position(|t| *t == key)
+            .and_then(|i| tokens.get(i + 1))
+            .map(|v| v.to_string())
+    };
+    let protocol = if tokens.contains(&"tcp") {
+        Protocol::Tcp
+    } else if tokens.contains(&"udp") {
+        Protocol::Udp
+    } else {
+        Protocol::Any
+    };
+    let source = match direction {
+        Direction::In => after("saddr"),
+        Direction::Out => after("daddr"),
+    };
+
+    Some((
+        Rule {
+            action,
+            direction,
+            protocol,
+            port: after("dport"),
+            source,
+        },
+        handle.to_string(),
+    ))
+}
+
+fn parse_iptables(output: &str) -> Vec<Rule> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let direction = match tokens.get(..2)? {
+                ["-A", "INPUT"] => Direction::In,
+                ["-A", "OUTPUT"] => Direction::Out,
+                _ => return None,
+            };
+            if tokens
+                .iter()
+                .any(|t| matches!(*t, "-i" | "-o" | "--ctstate" | "--state"))
+            {
+                return None;
+            }
+            let after = |key: &str| {
+                tokens
+                    .iter()
+                    .position(|t| *t == key)
+                    .and_then(|i| tokens.get(i + 1))
+                    .map(|v| v.to_string())
+            };
+            let action = after("-j")?.parse().ok()?;
+            let remote = match direction {
+                Direction::In => after("-s"),
+                Direction::Out => after("-d"),
+            };
+            Some(Rule {
+                action,
+                direction,
+                protocol: after("-p")
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(Protocol::Any),
+                port: after("--dport").map(|p| p.replace(':', "-")),
+                source: remote.as_deref().and_then(normalize_address),
+            })
+        })
+        .collect()
+}
+
+pub fn policies(backend: Backend) -> Result<Policies> {
+    Ok(match backend {
+        Backend::Ufw => {
+            let output = run("ufw", &["status", "verbose"])?;
+            let policy = |direction: &str| {
+                let line = output.lines().find(|l| l.starts_with("Default:"))?;
+                line.split(',')
+                    .find(|part| part.contains(direction))?
+                    .split_whitespace()
+                    .find_map(|word| word.parse().ok())
+            };
+            Policies {
+                incoming: policy("(incoming)"),
+                outgoing: policy("(outgoing)"),
+            }
+        }
+        Backend::Firewalld => {
+            let (_, target) = firewalld_target()?;
+            Policies {
+                incoming: match target.as_str() {
+                    "ACCEPT" => Some(Action::Allow),
+                    "DROP" => Some(Action::Deny),
+                    // The "default" target rejects anything not allowed
+                    _ => Some(Action::Reject),
+                },
+                outgoing: Some(Action::Allow),
+            }
+        }
+        Backend::Nftables => {
+            let output = run("nft", &["list", "table", "inet", NFT_TABLE]).unwrap_or_default();
+            let policy = |hook: &str| {
+                let line = output
+                    .lines()
+                    .find(|l| l.contains(&format!("hook {}", hook)))?;
+                let policy = line.split("policy").nth(1)?;
+                policy.trim().trim_end_matches(';').parse().ok()
+            };
+            Policies {
+                incoming: policy("input"),
+                outgoing: policy("output"),
+            }
+        }
+        Backend::Iptables => {
+            let output = run("iptables", &["-S"])?;
+            let policy = |chain: &str| {
+                output
+                    .lines()
+                    .find_map(|l| l.strip_prefix(&format!("-P {} ", chain)))
+                    .and_then(|p| p.trim().parse().ok())
+            };
+            Policies {
+                incoming: policy("INPUT"),
+                outgoing: policy("OUTPUT"),
+            }
+        }
+    })
+}
+
+fn firewalld_running() -> bool {
+    run("firewall-cmd", &["--state"])
+        .map(|o| o.trim() == "running")
+        .unwrap_or(false)
+}
+
+/// Default zone and its target, read from disk while firewalld is stopped
+fn firewalld_target() -> Result<(String, String)> {
+    if firewalld_running() {
+        let zone = run("firewall-cmd", &["--get-default-zone"])?
+            .trim()
+            .to_string();
+        let target = run(
+            "firewall-cmd",
+            &["--permanent", &format!("--zone={}", zone), "--get-target"],
+        )?
+        .trim()
+        .to_string();
+        return Ok((zone, target));
+    }
+
+    let zone = fs::read_to_string(FIREWALLD_CONF)
+        .ok()
+        .and_then(|content| parse_default_zone(&content))
+        .unwrap_or_else(|| "public".to_string());
+    let target = FIREWALLD_ZONE_DIRS
+        .iter()
+        .find_map(|dir| fs::read_to_string(format!("{}/{}.xml", dir, zone)).ok())
+        .map(|xml| parse_zone_target(&xml))
+        .unwrap_or_else(|| "default".to_string());
+    Ok((zone, target))
+}
+
+/// `DefaultZone=` from firewalld.conf
+fn parse_default_zone(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("DefaultZone=")?.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// `target` attribute of a zone file's `<zone>` element, `default` when unset
+fn parse_zone_target(xml: &str) -> String {
+    xml.find("<zone")
+        .map(|start| &xml[start..])
+        .and_then(|zone| zone.split('>').next())
+        .and_then(|element| {
+            let start = element.find("target=\"")? + "target=\"".len();
+            let end = element[start..].find('"')? + start;
+            Some(element[start..end].to_string())
+        })
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Backend commands that add (or with `remove`, delete) a rule
+fn rule_commands(backend: Backend, rule: &Rule, remove: bool) -> Result<Vec<Vec<String>>> {
+    let owned = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    if rule.action == Action::Limit && backend != Backend::Ufw {
+        return Err(anyhow!("Rate-limited rules are only supported with ufw"));
+    }
+
+    Ok(match backend {
+        Backend::Ufw => {
+            let mut argv = owned(&["ufw"]);
+            if remove {
+                argv.push("delete".to_string());
+            }
+            argv.extend(owned(&[rule.action.as_str(), rule.direction.as_str()]));
+            if rule.protocol != Protocol::Any {
+                argv.extend(owned(&["proto", rule.protocol.as_str()]));
+            }
+            let remote = rule.source.as_deref().unwrap_or("any");
+            let (from, to) = match rule.direction {
+                Direction::In => (remote, "any"),
+                Direction::Out => ("any", remote),
+            };
+            argv.extend(owned(&["from", from, "to", to]));
+            match &rule.port {
+                Some(port) if is_numeric_port(port) => {
+                    argv.extend(owned(&["port", &port.replace('-', ":")]))
+                }
+                Some(app) => argv.extend(owned(&["app", app])),
+                None => {}
+            }
+            vec![argv]
+        }
+        Backend::Firewalld => {
+            if rule.direction == Direction::Out {
+                return Err(anyhow!("firewalld zones only filter incoming traffic"));
+            }
+            let verb = if remove { "remove" } else { "add" };
+            let plain = rule.action == Action::Allow && rule.source.is_none();
+            let option = match &rule.port {
+                Some(port) if plain && is_numeric_port(port) => {
+                    if rule.protocol == Protocol::Any {
+                        return Err(anyhow!("firewalld ports need a protocol, tcp or udp"));
+                    }
+                    format!("--{}-port={}/{}", verb, port, rule.protocol.as_str())
+                }
+                Some(service) if plain => format!("--{}-service={}", verb, service),
+                _ => format!("--{}-rich-rule={}", verb, rich_rule(rule)),
+            };
+            vec![owned(&["firewall-cmd", &option])]
+        }
+        Backend::Nftables => {
+            if remove {
+                let (_, location) = nft_rules()?
+                    .into_iter()
+                    .find(|(listed, _)| listed == rule)
+                    .ok_or_else(|| anyhow!("No nftables rule matches '{}'", rule))?;
+                let mut argv = owned(&["nft", "delete", "rule"]);
+                argv.extend(location.split_whitespace().map(String::from));
+                return Ok(vec![argv]);
+            }
+            let mut commands = nft_setup_commands();
+            let chain = match rule.direction {
+                Direction::In => "input",
+                Direction::Out => "output",
+            };
+            let mut argv = owned(&["nft", "add", "rule", "inet", NFT_TABLE, chain]);
+            if let Some(source) = &rule.source {
+                let family = if source.contains(':') { "ip6" } else { "ip" };
+                let field = match rule.direction {
+                    Direction::In => "saddr",
+                    Direction::Out => "daddr",
+                };
+                argv.extend(owned(&[family, field, source]));
+            }
+            match (&rule.port, rule.protocol) {
+                (Some(_), Protocol::Any) => {
+                    return Err(anyhow!("nftables port rules need a protocol, tcp or udp"))
+                }
+                (Some(port), protocol) => argv.extend(owned(&[protocol.as_str(), "dport", port])),
+                (None, Protocol::Any) => {}
+                (None, protocol) => argv.extend(owned(&["meta", "l4proto", protocol.as_str()])),
+            }
+            argv.push(
+                match rule.action {
+                    Action::Allow | Action::Limit => "accept",
+                    Action::Deny => "drop",
+                    Action::Reject => "reject",
+                }
+                .to_string(),
+            );
+            commands.push(argv);
+            commands
+        }
+        Backend::Iptables => {
+            let chain = match rule.direction {
+                Direction::In => "INPUT",
+                Direction::Out => "OUTPUT",
+            };
+            if rule.source.as_deref().is_some_and(|s| s.contains(':')) {
+                return Err(anyhow!(
+                    "The iptables backend only manages IPv4, use ip6tables for IPv6 sources"
+                ));
+            }
+            let mut argv = owned(&["iptables", if remove { "-D" } else { "-A" }, chain]);
+            if let Some(source) = &rule.source {
+                let flag = match rule.direction {
+                    Direction::In => "-s",
+                    Direction::Out => "-d",
+                };
+                argv.extend(owned(&[flag, source]));
+            }
+            if rule.protocol != Protocol::Any {
+                argv.extend(owned(&["-p", rule.protocol.as_str()]));
+            }
+            if let Some(port) = &rule.port {
+                if rule.protocol == Protocol::Any {
+                    return Err(anyhow!("iptables port rules need a protocol, tcp or udp"));
+                }
+                argv.extend(owned(&["--dport", &port.replace('-', ":")]));
+            }
+            let target = match rule.action {
+                Action::Allow | Action::Limit => "ACCEPT",
+                Action::Deny => "DROP",
+                Action::Reject => "REJECT",
+            };
+            argv.extend(owned(&["-j", target]));
+            vec![argv]
+        }
+    })
+}
+
+/// Create the linux-toolkit table and base chains if they are missing
+fn nft_setup_commands() -> Vec<Vec<String>> {
+    let chain = |name: &str| {
+        vec![
+            "nft".to_string(),
+            "add".to_string(),
+            "chain".to_string(),
+            "inet".to_string(),
+            NFT_TABLE.to_string(),
+            name.to_string(),
+            format!("{{ type filter hook {} priority 0; }}", name),
+        ]
+    };
+    vec![
+        vec![
+            "nft".to_string(),
+            "add".to_string(),
+            "table".to_string(),
+            "inet".to_string(),
+            NFT_TABLE.to_string(),
+        ],
+        chain("input"),
+        chain("output"),
+    ]
+}
+
+fn policy_commands(
+    backend: Backend,
+    direction: Direction,
+    action: Action,
+) -> Result<Vec<Vec<String>>> {
+    let owned = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    if action == Action::Limit {
+        return Err(anyhow!(
+            "limit is not a default policy, use allow, deny or reject"
+        ));
+    }
+
+    Ok(match backend {
+        Backend::Ufw => {
+            let direction = match direction {
+                Direction::In => "incoming",
+                Direction::Out => "outgoing",
+            };
+            vec![owned(&["ufw", "default", action.as_str(), direction])]
+        }
+        Backend::Firewalld => {
+            if direction == Direction::Out {
+                return Err(anyhow!("firewalld zones only filter incoming traffic"));
+            }
+            let zone = firewalld_target()?.0;
+            let target = match action {
+                Action::Allow | Action::Limit => "ACCEPT",
+                Action::Deny => "DROP",
+                Action::Reject => "%%REJECT%%",
+            };
+            vec![
+                owned(&[
+                    "firewall-cmd",
+                    "--permanent",
+                    &format!("--zone={}", zone),
+                    &format!("--set-target={}", target),
+                ]),
+                owned(&["firewall-cmd", "--reload"]),
+            ]
+        }
+        Backend::Nftables => {
+            let (chain, policy) = (
+                match direction {
+                    Direction::In => "input",
+                    Direction::Out => "output",
+                },
+                match action {
+                    Action::Allow | Action::Limit => "accept",
+                    Action::Deny | Action::Reject => "drop",
+                },
+            );
+            let mut commands = nft_setup_commands();
+            commands.push(owned(&[
+                "nft",
+                "chain",
+                "inet",
+                NFT_TABLE,
+                chain,
+                &format!("{{ policy {}; }}", policy),
+            ]));
+            commands
+        }
+        Backend::Iptables => {
+            let chain = match direction {
+                Direction::In => "INPUT",
+                Direction::Out => "OUTPUT",
+            };
+            let policy = match action {
+                Action::Allow | Action::Limit => "ACCEPT",
+                Action::Deny | Action::Reject => "DROP",
+            };
+            vec![owned(&["iptables", "-P", chain, policy])]
+        }
+    })
+}
+
+fn snapshot(backend: Backend) -> Result<Snapshot> {
+    Ok(match backend {
+        Backend::Ufw => Snapshot::Ufw {
+            files: UFW_STATE_FILES
+                .iter()
+                .map(|path| (PathBuf::from(path), fs::read(path).ok()))
+                .collect(),
+            active: run("ufw", &["status"])?.contains("Status: active"),
+        },
+        Backend::Firewalld => {
+            let (zone, target) = firewalld_target()?;
+            Snapshot::Firewalld {
+                zone,
+                target,
+                running: firewalld_running(),
+            }
+        }
+        Backend::Nftables => Snapshot::Nftables(run("nft", &["list", "ruleset"])?),
+        Backend::Iptables => Snapshot::Iptables(run("iptables-save", &[])?),
+    })
+}
+
+fn restore(snapshot: &Snapshot) -> Result<()> {
+    match snapshot {
+        Snapshot::Ufw { files, active } => {
+            for (path, content) in files {
+                match content {
+                    Some(content) => fs::write(path, content)?,
+                    None if path.exists() => fs::remove_file(path)?,
+                    None => {}
+                }
+            }
+            if *active {
+                run("ufw", &["reload"])?;
+            } else {
+                run("ufw", &["disable"])?;
+            }
+        }
+        Snapshot::Firewalld { running: false, .. } => {
+            // Runtime changes go away with the daemon
+            run("systemctl", &["disable", "--now", "firewalld"])?;
+        }
+        Snapshot::Firewalld { zone, target, .. } => {
+            run(
+                "firewall-cmd",
+                &[
+                    "--permanent",
+                    &format!("--zone={}", zone),
+                    &format!("--set-target={}", target),
+                ],
+            )?;
+            run("firewall-cmd", &["--reload"])?;
+        }
+        Snapshot::Nftables(ruleset) => {
+            run("nft", &["flush", "ruleset"])?;
+            run_with_stdin("nft", &["-f", "-"], ruleset)?;
+        }
+        Snapshot::Iptables(rules) => run_with_stdin("iptables-restore", &[], rules)?,
+    }
+    Ok(())
+}
+
+/// Make runtime-only changes survive a reload or reboot where we can
+fn persist(backend: Backend) -> Result<()> {
+    match backend {
+        Backend::Ufw => {}
+        Backend::Firewalld => {
+            run("firewall-cmd", &["--runtime-to-permanent"])?;
+        }
+        Backend::Nftables | Backend::Iptables => println!(
+            "⚠️  {} rules are not persistent, save them with your distribution's {} service",
+            backend.name(),
+            backend.name()
+        ),
+    }
+    Ok(())
+}
+
+/// Wait for the user to type "yes", giving up after `timeout`
+fn confirm_connectivity(timeout: Duration) -> bool {
+    println!();
+    println!(
+        "⏳ Check you can still reach this host (open a new SSH session), then type 'yes' within {}s to keep the changes.",
+        timeout.as_secs()
+    );
+    print!("Keep changes? ");
+    let _ = std::io::stdout().flush();
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input).is_ok() {
+            let _ = tx.send(input);
+        }
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(input) => matches!(input.trim().to_lowercase().as_str(), "y" | "yes"),
+        Err(_) => {
+            println!();
+            false
+        }
+    }
+}
+
+/// Apply backend commands, reverting them unless connectivity is confirmed
+fn apply_with_safety(
+    config: &Config,
+    backend: Backend,
+    commands: Vec<Vec<String>>,
+    assume_yes: bool,
+    timeout: Option<u64>,
+) -> Result<()> {
+    let snapshot = snapshot(backend)?;
+
+    for argv in &commands {
+        println!("▶ {}", argv.join(" "));
+        if let Err(e) = run_owned(argv) {
+            println!("❌ {}", e);
+            println!("↩️  Reverting to the previous firewall state");
+            restore(&snapshot)?;
+            return Err(e);
+        }
+    }
+
+    let timeout = Duration::from_secs(timeout.unwrap_or(config.firewall.confirm_timeout_secs));
+    if assume_yes || confirm_connectivity(timeout) {
+        persist(backend)?;
+        println!("✅ Firewall changes kept");
+        Ok(())
+    } else {
+        restore(&snapshot)?;
+        Err(anyhow!(
+            "Changes not confirmed, firewall reverted to its previous state"
+        ))
+    }
+}
+
+/// Whether /proc/net/if_inet6 lists an address outside the loopback device
+fn ipv6_active_in(if_inet6: &str) -> bool {
+    if_inet6
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .any(|device| device != "lo")
+}
+
+fn ipv6_active() -> bool {
+    fs::read_to_string("/proc/net/if_inet6")
+        .map(|content| ipv6_active_in(&content))
+        .unwrap_or(false)
+}
+
+/// `iptables -C` form of an `iptables -A` command, to test for the rule first
+fn iptables_check(argv: &[String]) -> Option<Vec<String>> {
+    if argv.first().map(String::as_str) != Some("iptables") || argv.get(1)? != "-A" {
+        return None;
+    }
+    let mut check = argv.to_vec();
+    check[1] = "-C".to_string();
+    Some(check)
+}
+
+/// Drop `iptables -A` commands whose rule is already in place
+fn skip_existing_iptables(commands: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    commands
+        .into_iter()
+        .filter(|argv| match iptables_check(argv) {
+            Some(check) => run_owned(&check).is_err(),
+            None => true,
+        })
+        .collect()
+}
+
+/// Default-deny inbound with SSH still reachable, on whichever backend is present
+fn enable_commands(backend: Backend) -> Result<Vec<Vec<String>>> {
+    let owned = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    let ssh = Rule {
+        action: Action::Allow,
+        direction: Direction::In,
+        protocol: Protocol::Tcp,
+        port: Some("22".to_string()),
+        source: None,
+    };
+
+    let mut commands = Vec::new();
+    match backend {
+        Backend::Ufw => {
+            commands.extend(rule_commands(backend, &ssh, false)?);
+            commands.extend(policy_commands(backend, Direction::In, Action::Deny)?);
+            commands.extend(policy_commands(backend, Direction::Out, Action::Allow)?);
+            commands.push(owned(&["ufw", "--force", "enable"]));
+        }
+        Backend::Firewalld => {
+            commands.push(owned(&["systemctl", "enable", "--now", "firewalld"]));
+            commands.push(owned(&["firewall-cmd", "--add-service=ssh"]));
+        }
+        Backend::Nftables => {
+            commands.extend(nft_setup_commands());
+            for rule in [
+                &["iif", "lo", "accept"][..],
+                &["ct", "state", "established,related", "accept"],
+            ] {
+                let mut argv = owned(&["nft", "add", "rule", "inet", NFT_TABLE, "input"]);
+                argv.extend(owned(rule));
+                commands.push(argv);
+            }
+            commands.extend(rule_commands(backend, &ssh, false)?);
+            commands.extend(policy_commands(backend, Direction::In, Action::Deny)?);
+        }
+        Backend::Iptables => {
+            commands.push(owned(&[
+                "iptables", "-A", "INPUT", "-i", "lo", "-j", "ACCEPT",
+            ]));
+            commands.push(owned(&[
+                "iptables",
+                "-A",
+                "INPUT",
+                "-m",
+                "conntrack",
+                "--ctstate",
+                "ESTABLISHED,RELATED",
+                "-j",
+                "ACCEPT",
+            ]));
+            commands.extend(rule_commands(backend, &ssh, false)?);
+            commands.extend(policy_commands(backend, Direction::In, Action::Deny)?);
+        }
+    }
+    Ok(commands)
+}
+
+fn action_label(action: Option<Action>) -> &'static str {
+    action.map(Action::as_str).unwrap_or("unknown")
+}
+
+pub fn status_text(detected: Detected) -> Result<String> {
+    let rules = list_rules(detected.backend)?;
+    let policies = policies(detected.backend).unwrap_or(Policies {
+        incoming: None,
+        outgoing: None,
+    });
+
+    let mut lines = vec![
+        format!(
+            "{} Backend: {} ({})",
+            if detected.active { "✅" } else { "⚠️ " },
+            detected.backend.name(),
+            if detected.active {
+                "active"
+            } else {
+                "inactive"
+            }
+        ),
+        format!(
+            "Default policy: incoming {}, outgoing {}",
+            action_label(policies.incoming),
+            action_label(policies.outgoing)
+        ),
+        String::new(),
+        format!(
+            "{:>3}  {:<7} {:<4} {:<5} {:<16} {}",
+            "#", "ACTION", "DIR", "PROTO", "PORT", "SOURCE"
+        ),
+    ];
+    for (index, rule) in rules.iter().enumerate() {
+        lines.push(format!(
+            "{:>3}  {:<7} {:<4} {:<5} {:<16} {}",
+            index + 1,
+            rule.action.as_str(),
+            rule.direction.as_str(),
+            rule.protocol.as_str(),
+            rule.port.as_deref().unwrap_or("any"),
+            rule.source.as_deref().unwrap_or("any")
+        ));
+    }
+    if rules.is_empty() {
+        lines.push("     (no rules)".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Firewall status for the TUI details panel
+pub fn run_native() -> Result<String> {
+    match detect() {
+        Some(detected) => status_text(detected),
+        None => {
+            Ok("❌ No supported firewall found (ufw, firewalld, nftables, iptables)".to_string())
+        }
+    }
+}
+
+/// Install the firewall the distribution expects when none is present
+fn install_backend(assume_yes: bool) -> Result<Detected> {
+    use crate::packages::PackageManager;
+
+    let manager = crate::packages::require_manager()?;
+    let package = match manager {
+        PackageManager::Dnf | PackageManager::Yum => "firewalld",
+        PackageManager::Nix => {
+            return Err(anyhow!(
+                "Enable the firewall in configuration.nix with networking.firewall.enable"
+            ))
+        }
+        _ => "ufw",
+    };
+
+    println!(
+        "No firewall found, {} can be installed with {}",
+        package,
+        manager.name()
+    );
+    if !assume_yes {
+        crate::utils::require_confirmation(&format!("Install {}?", package))
+            .context("No firewall installed")?;
+    }
+    crate::packages::run_attached(&manager.install_command(&[package.to_string()]))?;
+    require_backend()
+}
+
+/// Handler for `linux-toolkit firewall <action>`
+pub fn firewall_command(
+    action: &str,
+    args: &[String],
+    config: &Config,
+    assume_yes: bool,
+    timeout: Option<u64>,
+) -> Result<()> {
+    let detected = match detect() {
+        Some(detected) => detected,
+        None if action == "enable" => install_backend(assume_yes)?,
+        None => require_backend()?,
+    };
+    let backend = detected.backend;
+
+    let commands = match action {
+        "status" => {
+            println!("{}", status_text(detected)?);
+            return Ok(());
+        }
+        "enable" => skip_existing_iptables(enable_commands(backend)?),
+        "add" => skip_existing_iptables(rule_commands(backend, &args.join(" ").parse()?, false)?),
+        "remove" => rule_commands(backend, &args.join(" ").parse()?, true)?,
+        "default" => {
+            let [direction, policy] = args else {
+                return Err(anyhow!(
+                    "Usage: firewall default <incoming|outgoing> <allow|deny|reject>"
+                ));
+            };
+            policy_commands(backend, direction.parse()?, policy.parse()?)?
+        }
+        other => return Err(anyhow!("Unknown firewall action '{}'", other)),
+    };
+
+    println!("Using {}", backend.name());
+    if backend == Backend::Iptables && ipv6_active() {
+        eprintln!(
+            "⚠️  IPv6 is active but the iptables backend only manages IPv4, \
+             IPv6 traffic is not affected by these changes"
+        );
+    }
+    apply_with_safety(config, backend, commands, assume_yes, timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{override_current, RecordingExecutor};
+    use std::sync::Arc;
+
+    fn rule(s: &str) -> Rule {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_ufw_status() {
+        let output = "Status: active\n\n\
+                      To                         Action      From\n\
+                      --                         ------      ----\n\
+                      22/tcp                     LIMIT IN    Anywhere\n\
+                      80                         ALLOW IN    10.0.0.0/8\n\
+                      Anywhere                   DENY OUT    192.0.2.1 53/udp\n\
+                      22/tcp (v6)                LIMIT IN    Anywhere (v6)\n";
+        assert_eq!(
+            parse_ufw_status(output),
+            vec![
+                rule("limit in tcp 22"),
+                rule("allow in any 80 from 10.0.0.0/8"),
+                Rule {
+                    action: Action::Deny,
+                    direction: Direction::Out,
+                    protocol: Protocol::Any,
+                    port: None,
+                    source: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_rich_rules() {
+        assert_eq!(
+            parse_rich_rule(
+                r#"rule family="ipv4" source address="10.0.0.5" port port="8080" protocol="tcp" reject"#
+            ),
+            Some(rule("reject in tcp 8080 from 10.0.0.5"))
+        );
+        assert_eq!(
+            parse_rich_rule(r#"rule service name="http" accept"#),
+            Some(rule("allow in http"))
+        );
+        assert_eq!(parse_rich_rule(r#"rule service name="ssh" log"#), None);
+    }
+
+    #[test]
+    fn parses_nft_ruleset() {
+        let output = "table inet linux_toolkit { # handle 7\n\
+                      \tchain input { # handle 1\n\
+                      \t\ttype filter hook input priority filter; policy drop;\n\
+                      \t\tct state established,related accept # handle 2\n\
+                      \t\ttcp dport 22 accept # handle 3\n\
+                      \t\tip saddr 10.0.0.0/8 udp dport 53 drop # handle 4\n\
+                      \t\ttcp dport 80 limit rate 10/second accept # handle 5\n\
+                      \t}\n\
+                      \tchain forward { # handle 8\n\
+                      \t\ttcp dport 25 drop # handle 9\n\
+                      \t}\n\
+                      }\n";
+        let rules = parse_nft_ruleset(output);
+        assert_eq!(
+            rules,
+            vec![
+                (
+                    rule("allow in tcp 22"),
+                    "inet linux_toolkit input handle 3".into()
+                ),
+                (
+                    rule("deny in udp 53 from 10.0.0.0/8"),
+                    "inet linux_toolkit input handle 4".into()
+                ),
+                (
+                    rule("allow in tcp 80"),
+                    "inet linux_toolkit input handle 5".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn nft_is_active_only_with_our_input_chain() {
+        let docker = "table ip nat {\n\tchain DOCKER {\n\t}\n}\n\
+                      table ip filter {\n\tchain INPUT {\n\
+                      \t\ttype filter hook input priority filter; policy accept;\n\t}\n}\n";
+        assert!(!owns_nft_input(docker));
+        let ours = format!(
+            "{}table inet linux_toolkit {{\n\tchain input {{\n\
+             \t\ttype filter hook input priority filter; policy drop;\n\t}}\n}}\n",
+            docker
+        );
+        assert!(owns_nft_input(&ours));
+        assert!(!owns_nft_input(
+            "table inet linux_toolkit {\n\tchain output {\n\
+             \t\ttype filter hook output priority filter;\n\t}\n}\n"
+        ));
+    }
+
+    #[test]
+    fn parses_iptables_rules() {
+        let output = "-P INPUT DROP\n\
+                      -A INPUT -i lo -j ACCEPT\n\
+                      -A INPUT -m conntrack --ctstate RELATED,ESTABLISHED -j ACCEPT\n\
+                      -A INPUT -p tcp -m tcp --dport 22 -j ACCEPT\n\
+                      -A INPUT -s 192.0.2.0/24 -p udp -m udp --dport 161 -j REJECT\n\
+                      -A DOCKER -p tcp --dport 80 -j ACCEPT\n";
+        assert_eq!(
+            parse_iptables(output),
+            vec![
+                rule("allow in tcp 22"),
+                rule("reject in udp 161 from 192.0.2.0/24"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_firewalld_zone_from_disk() {
+        let conf = "# comment\nDefaultZone=drop\nLogDenied=off\n";
+        assert_eq!(parse_default_zone(conf), Some("drop".to_string()));
+        assert_eq!(parse_default_zone("DefaultZone=\n"), None);
+
+        let zone = r#"<?xml version="1.0" encoding="utf-8"?>
+<zone target="DROP">
+  <short>Drop</short>
+</zone>"#;
+        assert_eq!(parse_zone_target(zone), "DROP");
+        assert_eq!(
+            parse_zone_target(
+                "<?xml version=\"1.0\"?>\n<zone>\n  <service name=\"ssh\"/>\n</zone>"
+            ),
+            "default"
+        );
+    }
+
+    #[test]
+    fn iptables_appends_are_checked_first() {
+        let argv: Vec<String> = ["iptables", "-A", "INPUT", "-i", "lo", "-j", "ACCEPT"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(
+            iptables_check(&argv).unwrap(),
+            ["iptables", "-C", "INPUT", "-i", "lo", "-j", "ACCEPT"]
+        );
+        let policy: Vec<String> = ["iptables", "-P", "INPUT", "DROP"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(iptables_check(&policy), None);
+    }
+
+    #[test]
+    fn existing_iptables_rules_are_not_appended_again() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        // The loopback and conntrack rules are in place, the ssh rule is not
+        fake.respond(0, "", "");
+        fake.respond(0, "", "");
+        fake.respond(1, "", "iptables: Bad rule");
+
+        let commands = skip_existing_iptables(enable_commands(Backend::Iptables).unwrap());
+        assert!(!commands.iter().any(|argv| argv.contains(&"lo".to_string())));
+        assert!(commands.iter().any(|argv| argv.contains(&"22".to_string())));
+        assert!(fake.calls().iter().all(|call| call.args[0] == "-C"));
+    }
+
+    #[test]
+    fn limit_is_its_own_action() {
+        assert_eq!("limit".parse::<Action>().unwrap(), Action::Limit);
+        assert!(rule_commands(Backend::Ufw, &rule("limit in tcp 22"), false).is_ok());
+        assert!(rule_commands(Backend::Iptables, &rule("limit in tcp 22"), false).is_err());
+        assert!(policy_commands(Backend::Ufw, Direction::In, Action::Limit).is_err());
+    }
+
+    #[test]
+    fn iptables_leaves_ipv6_to_ip6tables() {
+        assert!(rule_commands(
+            Backend::Iptables,
+            &rule("allow in tcp 22 from 10.0.0.1"),
+            false
+        )
+        .is_ok());
+        assert!(rule_commands(
+            Backend::Iptables,
+            &rule("allow in tcp 22 from 2001:db8::1"),
+            false
+        )
+        .is_err());
+
+        let loopback_only = "00000000000000000000000000000001 01 80 10 80       lo\n";
+        let global = "20010db8000000000000000000000001 02 40 00 80     eth0\n";
+        assert!(!ipv6_active_in(""));
+        assert!(!ipv6_active_in(loopback_only));
+        assert!(ipv6_active_in(&format!("{}{}", loopback_only, global)));
+    }
+}
@@ -0,0 +1,494 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use colored::{ColoredString, Colorize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::report::ExecutionRecord;
+
+/// Runs kept per command, older ones are pruned
+const RUNS_KEPT: usize = 50;
+
+/// Past outputs of each command, one JSON line per run in `<data_dir>/history/<command>.jsonl`
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            dir: config.directories.data_dir.join("history"),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.jsonl", crate::utils::slugify(key)))
+    }
+
+    /// Runs stored under `key`, oldest first; unreadable lines are skipped
+    pub fn runs(&self, key: &str) -> Result<Vec<ExecutionRecord>> {
+        let path = self.path(key);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn latest(&self, key: &str) -> Result<Option<ExecutionRecord>> {
+        Ok(self.runs(key)?.pop())
+    }
+
+    pub fn record(&self, key: &str, record: &ExecutionRecord) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let mut runs = self.runs(key)?;
+        if runs.len() < RUNS_KEPT {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?;
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+            return Ok(());
+        }
+
+        runs.push(record.clone());
+        let keep = &runs[runs.len() - RUNS_KEPT..];
+        let mut content = String::new();
+        for run in keep {
+            content.push_str(&serde_json::to_string(run)?);
+            content.push('\n');
+        }
+        crate::utils::write_atomic(&path, &content)
+    }
+
+    /// Stored history names whose slug starts with `prefix`, sorted
+    pub fn stored(&self, prefix: &str) -> Vec<String> {
+        let prefix = crate::utils::slugify(prefix);
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        name.strip_suffix(".jsonl").map(String::from)
+                    })
+                    .filter(|name| name.starts_with(&prefix))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+}
+
+/// Store a run if `behavior.save_command_history` is on
+pub fn remember(config: &Config, key: &str, record: &ExecutionRecord) -> Result<()> {
+    if !config.behavior.save_command_history {
+        return Ok(());
+    }
+    History::new(config).record(key, record)
+}
+
+/// History key of a command run with extra arguments, so differently invoked runs are kept apart
+pub fn key(command_id: &str, extra_args: &[String]) -> String {
+    if extra_args.is_empty() {
+        command_id.to_string()
+    } else {
+        format!("{} {}", command_id, extra_args.join(" "))
+    }
+}
+
+/// Line diff of two outputs as `+`/`-` prefixed lines, empty when they are equal
+pub fn line_diff(previous: &str, current: &str) -> Vec<String> {
+    similar::TextDiff::from_lines(previous, current)
+        .iter_all_changes()
+        .filter_map(|change| {
+            let sign = match change.tag() {
+                similar::ChangeTag::Delete => '-',
+                similar::ChangeTag::Insert => '+',
+                similar::ChangeTag::Equal => return None,
+            };
+            Some(format!(
+                "{} {}",
+                sign,
+                change.value().trim_end_matches('\n')
+            ))
+        })
+        .collect()
+}
+
+/// Fields that identify an element of a JSON array better than its position
+const NATURAL_KEYS: [&str; 6] = ["id", "name", "path", "pid", "unit", "key"];
+
+/// Changes between two outputs of the same command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputDiff {
+    /// `+`/`-` prefixed lines, see [`line_diff`]
+    Lines(Vec<String>),
+    /// Values that changed in JSON output, by their path in the document
+    Keyed(Vec<KeyChange>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChange {
+    pub key: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl OutputDiff {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            OutputDiff::Lines(lines) => lines.is_empty(),
+            OutputDiff::Keyed(changes) => changes.is_empty(),
+        }
+    }
+
+    /// One line per change, `+` added, `-` removed and `~` changed
+    pub fn lines(&self) -> Vec<String> {
+        match self {
+            OutputDiff::Lines(lines) => lines.clone(),
+            OutputDiff::Keyed(changes) => changes
+                .iter()
+                .map(|change| match (&change.before, &change.after) {
+                    (None, Some(after)) => format!("+ {} = {}", change.key, after),
+                    (Some(before), None) => format!("- {} = {}", change.key, before),
+                    (Some(before), Some(after)) => {
+                        format!("~ {}: {} → {}", change.key, before, after)
+                    }
+                    (None, None) => format!("~ {}", change.key),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Compare two outputs, by key when both are JSON documents and by line otherwise
+pub fn diff(previous: &str, current: &str) -> OutputDiff {
+    match (parse_document(previous), parse_document(current)) {
+        (Some(before), Some(after)) => {
+            let (mut before_values, mut after_values) = (BTreeMap::new(), BTreeMap::new());
+            flatten("", &before, &mut before_values);
+            flatten("", &after, &mut after_values);
+
+            let mut keys: Vec<&String> = before_values.keys().chain(after_values.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            OutputDiff::Keyed(
+                keys.into_iter()
+                    .filter(|key| before_values.get(*key) != after_values.get(*key))
+                    .map(|key| KeyChange {
+                        key: key.clone(),
+                        before: before_values.get(key).cloned(),
+                        after: after_values.get(key).cloned(),
+                    })
+                    .collect(),
+            )
+        }
+        _ => OutputDiff::Lines(line_diff(previous, current)),
+    }
+}
+
+/// A JSON object or array, other output is compared as text
+fn parse_document(output: &str) -> Option<Value> {
+    let trimmed = output.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    serde_json::from_str(trimmed).ok()
+}
+
+/// Collect the scalar values of `value` by their path, e.g. `disks[name=sda].size`
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&path, value, out);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (index, item) in items.iter().enumerate() {
+                let natural = NATURAL_KEYS.iter().find_map(|field| {
+                    let value = item.get(field)?;
+                    (!value.is_object() && !value.is_array())
+                        .then(|| format!("{}={}", field, scalar(value)))
+                });
+                let label = natural.unwrap_or_else(|| index.to_string());
+                flatten(&format!("{}[{}]", prefix, label), item, out);
+            }
+        }
+        other => {
+            out.insert(prefix.to_string(), scalar(other));
+        }
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Colour a diff line by its `+`, `-` or `~` marker
+pub fn paint(line: &str) -> ColoredString {
+    match line.chars().next() {
+        Some('+') => line.green(),
+        Some('-') => line.red(),
+        Some('~') => line.yellow(),
+        _ => line.normal(),
+    }
+}
+
+/// Parse `--since`: an age such as `30m`, `12h`, `7d` or `2w`, a date or a date and time
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(unit) = value.chars().last().filter(char::is_ascii_alphabetic) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<i64>() {
+            let age = match unit {
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                'w' => Duration::weeks(amount),
+                _ => return Err(anyhow!("Unknown unit '{}' in '{}'", unit, value)),
+            };
+            return Ok(Utc::now() - age);
+        }
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .map_err(|_| {
+            anyhow!(
+                "Cannot read '{}' as a time, use e.g. 2d, 2024-05-01 or \"2024-05-01 14:00\"",
+                value
+            )
+        })?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("'{}' does not exist in the local time zone", value))
+}
+
+/// Index of the run to compare the latest one with
+///
+/// Without `since` this is the run before the latest. With it, the last run
+/// recorded at or before that time, or the oldest one when all are newer.
+pub fn baseline(runs: &[ExecutionRecord], since: Option<DateTime<Utc>>) -> Option<usize> {
+    let earlier = runs.len().checked_sub(1).filter(|&count| count > 0)?;
+    match since {
+        None => Some(earlier - 1),
+        Some(since) => Some(
+            runs[..earlier]
+                .iter()
+                .rposition(|run| run.started_at <= since)
+                .unwrap_or(0),
+        ),
+    }
+}
+
+/// Handler for `linux-toolkit diff`
+///
+/// Runs with extra arguments are stored apart, `args` picks them like `-x --arg`.
+pub fn diff_command(
+    target: &str,
+    args: &[String],
+    since: Option<&str>,
+    config: &Config,
+) -> Result<()> {
+    let since = since.map(parse_since).transpose()?;
+    let categories = crate::commands::load_categories();
+    let id = crate::commands::find_command(&categories, target)
+        .map(|command| command.id())
+        .unwrap_or_else(|| target.to_string());
+    let key = key(&id, args);
+
+    let history = History::new(config);
+    let runs = history.runs(&key)?;
+    let (Some(index), Some(latest)) = (baseline(&runs, since), runs.last()) else {
+        println!(
+            "{} stored run(s) of {}, at least two are needed to compare",
+            runs.len(),
+            key
+        );
+        let stored: Vec<String> = history
+            .stored(&id)
+            .into_iter()
+            .filter(|name| *name != crate::utils::slugify(&key))
+            .collect();
+        if !stored.is_empty() {
+            println!("Other stored runs, pass one as the command to compare it:");
+            for name in stored {
+                println!("  {}", name);
+            }
+        }
+        return Ok(());
+    };
+    let previous = &runs[index];
+
+    let format = |run: &ExecutionRecord| {
+        run.started_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    println!(
+        "{}: run of {} compared with {}",
+        latest.command_name,
+        format(latest),
+        format(previous)
+    );
+    let changes = diff(&previous.output, &latest.output);
+    if changes.is_empty() {
+        println!("No changes");
+    }
+    for line in changes.lines() {
+        println!("{}", paint(&line));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn run(output: &str) -> ExecutionRecord {
+        ExecutionRecord {
+            command_id: "security/check-suid-files".to_string(),
+            command_name: "Check SUID Files".to_string(),
+            started_at: chrono::Utc::now(),
+            success: Some(true),
+            output: output.to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_latest_runs() {
+        let mut config = Config::default();
+        let dir = TempDir::new("history");
+        config.directories.data_dir = dir.path().to_path_buf();
+        let history = History::new(&config);
+
+        for index in 0..RUNS_KEPT + 3 {
+            history
+                .record("security/check-suid-files", &run(&index.to_string()))
+                .unwrap();
+        }
+
+        let runs = history.runs("security/check-suid-files").unwrap();
+        assert_eq!(runs.len(), RUNS_KEPT);
+        assert_eq!(runs[0].output, "3");
+        let latest = history
+            .latest("security/check-suid-files")
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.output, (RUNS_KEPT + 2).to_string());
+        // Pruning replaced the file without leaving a temporary one behind
+        assert_eq!(
+            history.stored("security/check-suid-files"),
+            ["security-check-suid-files"]
+        );
+        assert_eq!(fs::read_dir(dir.join("history")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn runs_with_arguments_are_stored_apart() {
+        let mut config = Config::default();
+        let dir = TempDir::new("history-keys");
+        config.directories.data_dir = dir.path().to_path_buf();
+        let history = History::new(&config);
+        let id = "network/port-scanner";
+        let args = ["-p".to_string(), "22".to_string()];
+
+        history.record(id, &run("plain")).unwrap();
+        history.record(&key(id, &args), &run("port 22")).unwrap();
+        history.record("other/command", &run("other")).unwrap();
+
+        assert_eq!(key(id, &[]), id);
+        assert_eq!(
+            history.latest(&key(id, &args)).unwrap().unwrap().output,
+            "port 22"
+        );
+        assert_eq!(
+            history.stored(id),
+            ["network-port-scanner", "network-port-scanner-p-22"]
+        );
+        // A listed name finds the same runs when given back as the key
+        assert_eq!(
+            history
+                .latest("network-port-scanner-p-22")
+                .unwrap()
+                .unwrap()
+                .output,
+            "port 22"
+        );
+    }
+
+    #[test]
+    fn diff_lists_changed_lines() {
+        let diff = line_diff(
+            "/usr/bin/sudo\n/usr/bin/passwd\n",
+            "/usr/bin/sudo\n/tmp/sh\n",
+        );
+        assert_eq!(diff, ["- /usr/bin/passwd", "+ /tmp/sh"]);
+        assert!(line_diff("same\n", "same\n").is_empty());
+    }
+
+    #[test]
+    fn json_output_is_compared_by_key() {
+        let before = r#"{"disks": [{"name": "sda", "size": 10}, {"name": "sdb", "size": 20}]}"#;
+        let after = r#"{"disks": [{"name": "sdb", "size": 25}, {"name": "sdc", "size": 5}]}"#;
+
+        let changes = diff(before, after);
+        assert_eq!(
+            changes.lines(),
+            [
+                "- disks[name=sda].name = sda",
+                "- disks[name=sda].size = 10",
+                "~ disks[name=sdb].size: 20 → 25",
+                "+ disks[name=sdc].name = sdc",
+                "+ disks[name=sdc].size = 5",
+            ]
+        );
+        assert!(matches!(diff("not json", "{}"), OutputDiff::Lines(_)));
+    }
+
+    #[test]
+    fn baseline_honours_since() {
+        let at = |hour: u32| ExecutionRecord {
+            started_at: Utc.with_ymd_and_hms(2024, 5, 1, hour, 0, 0).unwrap(),
+            ..run("")
+        };
+        let runs = [at(1), at(2), at(3), at(4)];
+
+        assert_eq!(baseline(&runs, None), Some(2));
+        let since = |hour: u32| Some(Utc.with_ymd_and_hms(2024, 5, 1, hour, 30, 0).unwrap());
+        assert_eq!(baseline(&runs, since(1)), Some(0));
+        assert_eq!(baseline(&runs, since(2)), Some(1));
+        assert_eq!(baseline(&runs, since(0)), Some(0));
+        assert_eq!(baseline(&runs[..1], None), None);
+        assert!(parse_since("3d").unwrap() < Utc::now() - Duration::days(2));
+        assert!(parse_since("yesterday").is_err());
+    }
+}
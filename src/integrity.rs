@@ -0,0 +1,973 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::audit::{AuditLog, PrivilegedRun};
+use crate::baseline::{CheckResult, CheckStatus};
+use crate::config::Config;
+use crate::report::Report;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+/// What the baseline records about one path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub kind: FileKind,
+    /// Content hash of regular files, `None` when the file could not be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Where a symlink points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Permission bits including setuid, setgid and sticky
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub mtime: i64,
+    pub inode: u64,
+    /// Extended attributes, values hex encoded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+}
+
+/// The stored baseline, `<data_dir>/integrity/baseline.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Database {
+    pub created_at: DateTime<Utc>,
+    pub hostname: String,
+    pub paths: Vec<PathBuf>,
+    pub files: BTreeMap<String, FileEntry>,
+}
+
+/// One baseline update, appended to `<data_dir>/integrity/updates.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateRecord {
+    pub updated_at: DateTime<Utc>,
+    pub user: String,
+    pub uid: Option<u32>,
+    pub files: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    /// Hash of the database as written
+    pub sha256: String,
+    /// HMAC of the record with the audit key, so a matching record cannot be
+    /// appended without it
+    #[serde(default)]
+    pub mac: String,
+}
+
+impl UpdateRecord {
+    /// The bytes `mac` covers: the record with `mac` left empty
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        let mut unsigned = self.clone();
+        unsigned.mac.clear();
+        Ok(serde_json::to_vec(&unsigned)?)
+    }
+}
+
+/// Files found by a scan, plus how many could not be read
+pub struct Scan {
+    pub files: BTreeMap<String, FileEntry>,
+    pub unreadable: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl ChangeKind {
+    fn marker(self) -> char {
+        match self {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Modified => '~',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// What differs for modified paths, e.g. `mode 0644 → 0666`
+    pub details: Vec<String>,
+}
+
+fn integrity_dir(config: &Config) -> PathBuf {
+    config.directories.data_dir.join("integrity")
+}
+
+fn database_path(config: &Config) -> PathBuf {
+    integrity_dir(config).join("baseline.json")
+}
+
+fn updates_path(config: &Config) -> PathBuf {
+    integrity_dir(config).join("updates.jsonl")
+}
+
+fn audit_log(config: &Config) -> AuditLog {
+    AuditLog::new(config.audit_log_path(), config.audit_key_path())
+}
+
+/// The stored baseline, `None` if none has been created yet
+///
+/// The baseline must match the last signed update record. A baseline that
+/// disappeared is reported rather than treated as never created, also when
+/// its update log went with it but the audit log recorded the update.
+pub fn load_database(config: &Config) -> Result<Option<Database>> {
+    let path = database_path(config);
+    let recorded = last_update(config)?;
+    if !path.exists() {
+        if let Some(record) = recorded {
+            return Err(anyhow!(
+                "{} is missing but {} records an update on {}, it was removed outside \
+                 `linux-toolkit integrity update`",
+                path.display(),
+                updates_path(config).display(),
+                record
+                    .updated_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+            ));
+        }
+        if let Some(run) = audit_log(config).last_success("integrity/update")? {
+            return Err(anyhow!(
+                "{} and its update log are missing but the audit log records an update on {}",
+                path.display(),
+                run.finished_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+            ));
+        }
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let recorded = recorded.ok_or_else(|| anyhow!("{} has no recorded update", path.display()))?;
+    if !audit_log(config).is_signed(&recorded.signed_bytes()?, &recorded.mac)? {
+        return Err(anyhow!(
+            "the last record in {} is not signed with the audit key, it was added outside \
+             `linux-toolkit integrity update`",
+            updates_path(config).display()
+        ));
+    }
+    if hex::encode(Sha256::digest(content.as_bytes())) != recorded.sha256 {
+        return Err(anyhow!(
+            "{} does not match the hash recorded in {}, it was changed outside \
+             `linux-toolkit integrity update`",
+            path.display(),
+            updates_path(config).display()
+        ));
+    }
+    let database =
+        serde_json::from_str(&content).with_context(|| format!("{} is corrupt", path.display()))?;
+    Ok(Some(database))
+}
+
+/// The most recent entry of the update log, if any
+fn last_update(config: &Config) -> Result<Option<UpdateRecord>> {
+    let path = updates_path(config);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let Some(line) = content.lines().rev().find(|line| !line.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let record =
+        serde_json::from_str(line).with_context(|| format!("{} is corrupt", path.display()))?;
+    Ok(Some(record))
+}
+
+/// Record every path under the configured roots, staying on each root's filesystem
+pub fn scan(config: &Config) -> Result<Scan> {
+    let exclude = config
+        .integrity
+        .exclude
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .map_err(|e| anyhow!("Invalid integrity exclude '{}': {}", pattern, e))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut scan = Scan {
+        files: BTreeMap::new(),
+        unreadable: 0,
+    };
+    for root in &config.integrity.paths {
+        let Ok(metadata) = fs::symlink_metadata(root) else {
+            continue;
+        };
+        let root_device = device(&metadata);
+        let mut pending = vec![root.clone()];
+        while let Some(path) = pending.pop() {
+            if exclude.iter().any(|pattern| pattern.matches_path(&path)) {
+                continue;
+            }
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            let entry = file_entry(&path, &metadata);
+            if entry.kind == FileKind::File && entry.sha256.is_none() {
+                scan.unreadable += 1;
+            }
+            if entry.kind == FileKind::Directory && device(&metadata) == root_device {
+                match fs::read_dir(&path) {
+                    Ok(children) => {
+                        pending.extend(children.filter_map(|child| Some(child.ok()?.path())))
+                    }
+                    Err(_) => scan.unreadable += 1,
+                }
+            }
+            scan.files
+                .insert(path.to_string_lossy().into_owned(), entry);
+        }
+    }
+    Ok(scan)
+}
+
+fn file_entry(path: &Path, metadata: &fs::Metadata) -> FileEntry {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_file() {
+        FileKind::File
+    } else if file_type.is_dir() {
+        FileKind::Directory
+    } else if file_type.is_symlink() {
+        FileKind::Symlink
+    } else {
+        FileKind::Other
+    };
+
+    let entry = FileEntry {
+        kind,
+        sha256: (kind == FileKind::File)
+            .then(|| hash_contents(path).ok())
+            .flatten(),
+        target: (kind == FileKind::Symlink)
+            .then(|| fs::read_link(path).ok())
+            .flatten()
+            .map(|target| target.to_string_lossy().into_owned()),
+        mode: 0,
+        uid: 0,
+        gid: 0,
+        size: metadata.len(),
+        mtime: 0,
+        inode: 0,
+        xattrs: xattrs(path),
+    };
+    #[cfg(unix)]
+    let entry = FileEntry {
+        mode: metadata.mode() & 0o7777,
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        mtime: metadata.mtime(),
+        inode: metadata.ino(),
+        ..entry
+    };
+    entry
+}
+
+/// Filesystem a path lives on, so scans do not cross mount points
+#[cfg(unix)]
+fn device(metadata: &fs::Metadata) -> u64 {
+    metadata.dev()
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+/// SHA-256 of a file, streamed so large binaries are not read into memory
+fn hash_contents(path: &Path) -> Result<String> {
+    use std::io::Read;
+
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Extended attributes of `path` itself, not of a symlink's target
+#[cfg(target_os = "linux")]
+fn xattrs(path: &Path) -> BTreeMap<String, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut attributes = BTreeMap::new();
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return attributes;
+    };
+    // SAFETY: a null buffer with size 0 asks only for the size of the list
+    let size = unsafe { libc::llistxattr(c_path.as_ptr(), std::ptr::null_mut(), 0) };
+    if size <= 0 {
+        return attributes;
+    }
+    let mut names = vec![0u8; size as usize];
+    // SAFETY: the buffer is valid for `names.len()` bytes
+    let size = unsafe {
+        libc::llistxattr(
+            c_path.as_ptr(),
+            names.as_mut_ptr() as *mut libc::c_char,
+            names.len(),
+        )
+    };
+    if size < 0 {
+        return attributes;
+    }
+    names.truncate(size as usize);
+
+    for name in names
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+    {
+        let Ok(c_name) = CString::new(name) else {
+            continue;
+        };
+        // SAFETY: as above, first the size and then into a buffer of that size
+        let size =
+            unsafe { libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            continue;
+        }
+        let mut value = vec![0u8; size as usize];
+        let size = unsafe {
+            libc::lgetxattr(
+                c_path.as_ptr(),
+                c_name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+            )
+        };
+        if size < 0 {
+            continue;
+        }
+        value.truncate(size as usize);
+        attributes.insert(
+            String::from_utf8_lossy(name).into_owned(),
+            hex::encode(value),
+        );
+    }
+    attributes
+}
+
+#[cfg(not(target_os = "linux"))]
+fn xattrs(_path: &Path) -> BTreeMap<String, String> {
+    BTreeMap::new()
+}
+
+/// Differences between the baseline and a scan, sorted by path
+///
+/// Directory mtimes are ignored, the added or removed entries that changed
+/// them are reported instead. Content is only compared when both sides could
+/// be read.
+pub fn compare(
+    baseline: &BTreeMap<String, FileEntry>,
+    current: &BTreeMap<String, FileEntry>,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for (path, before) in baseline {
+        match current.get(path) {
+            None => changes.push(Change {
+                path: path.clone(),
+                kind: ChangeKind::Removed,
+                details: Vec::new(),
+            }),
+            Some(after) => {
+                let details = entry_changes(before, after);
+                if !details.is_empty() {
+                    changes.push(Change {
+                        path: path.clone(),
+                        kind: ChangeKind::Modified,
+                        details,
+                    });
+                }
+            }
+        }
+    }
+    for path in current.keys().filter(|path| !baseline.contains_key(*path)) {
+        changes.push(Change {
+            path: path.clone(),
+            kind: ChangeKind::Added,
+            details: Vec::new(),
+        });
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+fn entry_changes(before: &FileEntry, after: &FileEntry) -> Vec<String> {
+    let mut details = Vec::new();
+    if before.kind != after.kind {
+        details.push(format!("type {:?} → {:?}", before.kind, after.kind).to_lowercase());
+    }
+    match (&before.sha256, &after.sha256) {
+        (Some(old), Some(new)) if old != new => details.push("content".to_string()),
+        (Some(_), Some(_)) => {}
+        _ if before.size != after.size => {
+            details.push(format!("size {} → {}", before.size, after.size))
+        }
+        _ => {}
+    }
+    if before.target != after.target {
+        details.push(format!(
+            "target {} → {}",
+            before.target.as_deref().unwrap_or("none"),
+            after.target.as_deref().unwrap_or("none")
+        ));
+    }
+    if before.mode != after.mode {
+        details.push(format!("mode {:04o} → {:04o}", before.mode, after.mode));
+    }
+    if (before.uid, before.gid) != (after.uid, after.gid) {
+        details.push(format!(
+            "owner {}:{} → {}:{}",
+            before.uid, before.gid, after.uid, after.gid
+        ));
+    }
+    if before.inode != after.inode {
+        details.push(format!("inode {} → {}", before.inode, after.inode));
+    }
+    if before.mtime != after.mtime && after.kind != FileKind::Directory {
+        details.push(format!(
+            "mtime {} → {}",
+            format_mtime(before.mtime),
+            format_mtime(after.mtime)
+        ));
+    }
+    for name in before.xattrs.keys().chain(after.xattrs.keys()) {
+        let change = match (before.xattrs.get(name), after.xattrs.get(name)) {
+            (Some(_), None) => "removed",
+            (None, Some(_)) => "added",
+            (Some(old), Some(new)) if old != new => "changed",
+            _ => continue,
+        };
+        let detail = format!("xattr {} {}", name, change);
+        if !details.contains(&detail) {
+            details.push(detail);
+        }
+    }
+    details
+}
+
+fn format_mtime(mtime: i64) -> String {
+    Local
+        .timestamp_opt(mtime, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| mtime.to_string())
+}
+
+#[derive(Default)]
+struct TreeNode<'a> {
+    change: Option<&'a Change>,
+    children: BTreeMap<String, TreeNode<'a>>,
+}
+
+/// Draw changes as a directory tree, `+` added, `-` removed and `~` modified
+pub fn render_tree(changes: &[Change]) -> Vec<String> {
+    let mut root = TreeNode::default();
+    for change in changes {
+        let mut node = &mut root;
+        for component in change.path.split('/').filter(|part| !part.is_empty()) {
+            node = node.children.entry(component.to_string()).or_default();
+        }
+        node.change = Some(change);
+    }
+
+    let mut lines = vec!["/".to_string()];
+    draw_children(&root, "", &mut lines);
+    lines
+}
+
+fn draw_children(node: &TreeNode, prefix: &str, lines: &mut Vec<String>) {
+    let count = node.children.len();
+    for (index, (name, child)) in node.children.iter().enumerate() {
+        let last = index + 1 == count;
+        // Directories that only lead to one change are shown as one `a/b/c` entry
+        let mut label = name.clone();
+        let mut child = child;
+        while child.change.is_none() && child.children.len() == 1 {
+            let (next_name, next) = child.children.iter().next().unwrap();
+            label = format!("{}/{}", label, next_name);
+            child = next;
+        }
+        let text = match child.change {
+            Some(change) if change.details.is_empty() => {
+                format!("{} {}", change.kind.marker(), label)
+            }
+            Some(change) => format!(
+                "{} {} ({})",
+                change.kind.marker(),
+                label,
+                change.details.join(", ")
+            ),
+            None => format!("{}/", label),
+        };
+        lines.push(format!(
+            "{}{}{}",
+            prefix,
+            if last { "└── " } else { "├── " },
+            text
+        ));
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        draw_children(child, &child_prefix, lines);
+    }
+}
+
+fn counts(changes: &[Change]) -> (usize, usize, usize) {
+    let count = |kind| changes.iter().filter(|change| change.kind == kind).count();
+    (
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Modified),
+    )
+}
+
+/// Summary line plus the tree of changes
+fn summary(database: &Database, changes: &[Change], unreadable: usize) -> String {
+    let since = database
+        .created_at
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M");
+    let mut out = if changes.is_empty() {
+        format!(
+            "✅ No changes since the baseline of {} ({} paths)",
+            since,
+            database.files.len()
+        )
+    } else {
+        let (added, removed, modified) = counts(changes);
+        format!(
+            "❌ {} change(s) since the baseline of {}: {} added, {} removed, {} modified\n\n{}",
+            changes.len(),
+            since,
+            added,
+            removed,
+            modified,
+            render_tree(changes).join("\n")
+        )
+    };
+    if unreadable > 0 {
+        out.push_str(&format!(
+            "\n\n⚠️  {} path(s) could not be read, run as root to check them fully",
+            unreadable
+        ));
+    }
+    out
+}
+
+fn require_database(config: &Config) -> Result<Database> {
+    load_database(config)?.ok_or_else(|| {
+        anyhow!("No integrity baseline yet, create one with `linux-toolkit integrity update`")
+    })
+}
+
+/// Compare against the baseline for the TUI details panel
+///
+/// Hashing every file takes a while, so it runs off the async runtime.
+pub async fn run_native(config: &Config) -> Result<String> {
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        let database = require_database(&config)?;
+        let scan = scan(&config)?;
+        let changes = compare(&database.files, &scan.files);
+        Ok(summary(&database, &changes, scan.unreadable))
+    })
+    .await?
+}
+
+/// Report with one finding per changed path
+fn report(changes: &[Change]) -> Report {
+    let mut report = Report::new("File Integrity");
+    report.findings = changes
+        .iter()
+        .map(|change| {
+            let (kind, status) = match change.kind {
+                ChangeKind::Added => ("added", CheckStatus::Warn),
+                ChangeKind::Removed => ("removed", CheckStatus::Fail),
+                ChangeKind::Modified => ("modified", CheckStatus::Fail),
+            };
+            CheckResult {
+                id: format!("integrity/{}", kind),
+                title: format!("{} {}", change.path, kind),
+                status,
+                evidence: change.details.clone(),
+                remediation: "Investigate the change, then run `linux-toolkit integrity update` \
+                              if it was expected"
+                    .to_string(),
+                remediation_command: None,
+            }
+        })
+        .collect();
+    report
+}
+
+fn check(config: &Config, report_path: Option<&Path>) -> Result<()> {
+    let database = require_database(config)?;
+    let scan = scan(config)?;
+    let changes = compare(&database.files, &scan.files);
+    println!("{}", summary(&database, &changes, scan.unreadable));
+
+    if let Some(path) = report_path {
+        report(&changes).write_to(path)?;
+        println!("\nReport written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Replace the baseline with the current state after showing what changes
+fn update(config: &Config, assume_yes: bool) -> Result<()> {
+    let previous = load_database(config)?;
+    let scan = scan(config)?;
+    let changes = match &previous {
+        Some(database) => {
+            let changes = compare(&database.files, &scan.files);
+            if changes.is_empty() {
+                println!("✅ The baseline is up to date");
+                return Ok(());
+            }
+            println!("{}\n", summary(database, &changes, scan.unreadable));
+            changes
+        }
+        None => {
+            println!(
+                "No baseline yet, {} paths under {} will be recorded.",
+                scan.files.len(),
+                config
+                    .integrity
+                    .paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if scan.unreadable > 0 {
+                println!(
+                    "⚠️  {} path(s) could not be read, run as root to record them fully",
+                    scan.unreadable
+                );
+            }
+            Vec::new()
+        }
+    };
+    if !assume_yes {
+        crate::utils::require_confirmation("Update the integrity baseline?")?;
+    }
+
+    let argv = vec![
+        crate::utils::toolkit_exe(),
+        "integrity".to_string(),
+        "update".to_string(),
+    ];
+    let audit = PrivilegedRun::begin("integrity/update", &argv, None, None);
+    let database = Database {
+        created_at: Utc::now(),
+        hostname: crate::utils::get_hostname(),
+        paths: config.integrity.paths.clone(),
+        files: scan.files,
+    };
+    let result = write_database(config, &database, &changes);
+    if let Some(audit) = audit {
+        audit.finish(config, Some(if result.is_ok() { 0 } else { 1 }));
+    }
+    result?;
+
+    println!(
+        "✅ Recorded {} paths in {}",
+        database.files.len(),
+        database_path(config).display()
+    );
+    Ok(())
+}
+
+/// Write the database readable only by its owner and log the update
+///
+/// The signed update record is synced before the database is renamed into
+/// place, so a crash leaves a baseline that fails its hash check rather than
+/// one without a record.
+fn write_database(config: &Config, database: &Database, changes: &[Change]) -> Result<()> {
+    let dir = integrity_dir(config);
+    fs::create_dir_all(&dir)?;
+    let content = serde_json::to_string(database)?;
+
+    let path = database_path(config);
+    let temporary = path.with_extension("json.tmp");
+    let mut options = fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temporary)
+        .with_context(|| format!("Failed to write {}", temporary.display()))?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+
+    let (added, removed, modified) = counts(changes);
+    let mut record = UpdateRecord {
+        updated_at: database.created_at,
+        user: crate::utils::get_current_user(),
+        uid: crate::utils::user_ids().map(|(uid, _)| uid),
+        files: database.files.len(),
+        added,
+        removed,
+        modified,
+        sha256: hex::encode(Sha256::digest(content.as_bytes())),
+        mac: String::new(),
+    };
+    record.mac = audit_log(config).sign(&record.signed_bytes()?)?;
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(updates_path(config))?;
+    writeln!(log, "{}", serde_json::to_string(&record)?)?;
+    log.sync_all()?;
+
+    fs::rename(&temporary, &path)?;
+    Ok(())
+}
+
+/// Print the recorded baseline updates, oldest first
+fn show_log(config: &Config) -> Result<()> {
+    let path = updates_path(config);
+    let Ok(content) = fs::read_to_string(&path) else {
+        println!("No baseline updates recorded yet");
+        return Ok(());
+    };
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let record: UpdateRecord =
+            serde_json::from_str(line).with_context(|| format!("{} is corrupt", path.display()))?;
+        println!(
+            "{}  {} (uid {})  {} paths, +{} -{} ~{}  sha256 {}",
+            record
+                .updated_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            record.user,
+            record
+                .uid
+                .map(|uid| uid.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            record.files,
+            record.added,
+            record.removed,
+            record.modified,
+            record.sha256
+        );
+    }
+    Ok(())
+}
+
+/// Handler for `linux-toolkit integrity <action>`
+pub fn integrity_command(
+    action: &str,
+    config: &Config,
+    assume_yes: bool,
+    report_path: Option<&Path>,
+) -> Result<()> {
+    match action {
+        "check" => check(config, report_path),
+        "update" => update(config, assume_yes),
+        "log" => show_log(config),
+        other => Err(anyhow!("Unknown integrity action '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn entry(sha256: &str, mode: u32) -> FileEntry {
+        FileEntry {
+            kind: FileKind::File,
+            sha256: Some(sha256.to_string()),
+            target: None,
+            mode,
+            uid: 0,
+            gid: 0,
+            size: 10,
+            mtime: 0,
+            inode: 1,
+            xattrs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn compare_reports_added_removed_and_modified() {
+        let baseline = BTreeMap::from([
+            ("/etc/hosts".to_string(), entry("a", 0o644)),
+            ("/etc/ssh/sshd_config".to_string(), entry("b", 0o600)),
+            ("/usr/bin/sudo".to_string(), entry("c", 0o4755)),
+        ]);
+        let mut current = baseline.clone();
+        current.remove("/etc/hosts");
+        current.insert("/etc/ssh/sshd_config".to_string(), entry("x", 0o666));
+        current.insert("/usr/bin/.hidden".to_string(), entry("d", 0o4755));
+
+        let changes = compare(&baseline, &current);
+        assert_eq!(
+            render_tree(&changes),
+            [
+                "/",
+                "├── etc/",
+                "│   ├── - hosts",
+                "│   └── ~ ssh/sshd_config (content, mode 0600 → 0666)",
+                "└── + usr/bin/.hidden",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_detects_changes_on_disk() {
+        let dir = TempDir::new("integrity");
+        fs::create_dir_all(dir.join("conf")).unwrap();
+        fs::write(dir.join("conf/app.conf"), "port = 80\n").unwrap();
+        fs::write(dir.join("cache"), "skip me").unwrap();
+
+        let mut config = Config::default();
+        config.integrity.paths = vec![dir.path().to_path_buf()];
+        config.integrity.exclude = vec![format!("{}/cache", dir.path().display())];
+        let before = scan(&config).unwrap();
+        assert_eq!(before.files.len(), 3);
+
+        fs::write(dir.join("conf/app.conf"), "port = 8080\n").unwrap();
+        std::os::unix::fs::symlink("/bin/sh", dir.join("conf/shell")).unwrap();
+        let after = scan(&config).unwrap();
+
+        let changes = compare(&before.files, &after.files);
+        let conf = format!("{}/conf", dir.path().display());
+        assert_eq!(changes.len(), 2, "{:?}", changes);
+        assert_eq!(changes[0].path, format!("{}/app.conf", conf));
+        assert_eq!(changes[0].details[0], "content");
+        assert_eq!(changes[1].path, format!("{}/shell", conf));
+        assert_eq!(changes[1].kind, ChangeKind::Added);
+    }
+
+    #[test]
+    fn baseline_edited_outside_update_is_rejected() {
+        let dir = TempDir::new("integrity-tamper");
+        let config = config_in(&dir);
+        let database = Database {
+            created_at: Utc::now(),
+            hostname: "host".to_string(),
+            paths: vec![PathBuf::from("/etc")],
+            files: BTreeMap::from([("/etc/hosts".to_string(), entry("a", 0o644))]),
+        };
+        write_database(&config, &database, &[]).unwrap();
+        assert_eq!(load_database(&config).unwrap().unwrap().files.len(), 1);
+
+        let path = database_path(&config);
+        let edited = fs::read_to_string(&path).unwrap().replace("\"a\"", "\"b\"");
+        fs::write(&path, edited).unwrap();
+        assert!(load_database(&config).is_err());
+
+        fs::remove_file(updates_path(&config)).unwrap();
+        assert!(load_database(&config).is_err());
+    }
+
+    fn config_in(dir: &TempDir) -> Config {
+        let mut config = Config::default();
+        config.directories.data_dir = dir.join("data");
+        config.audit.path = Some(dir.join("audit/audit.log"));
+        config.audit.key_path = Some(dir.join("audit/audit.key"));
+        config
+    }
+
+    fn database(hash: &str) -> Database {
+        Database {
+            created_at: Utc::now(),
+            hostname: "host".to_string(),
+            paths: vec![PathBuf::from("/etc")],
+            files: BTreeMap::from([("/etc/hosts".to_string(), entry(hash, 0o644))]),
+        }
+    }
+
+    #[test]
+    fn unsigned_update_records_are_rejected() {
+        let dir = TempDir::new("integrity-forged");
+        let config = config_in(&dir);
+        write_database(&config, &database("a"), &[]).unwrap();
+
+        // Swap in another baseline with a record matching it but no valid mac
+        let forged = serde_json::to_string(&database("b")).unwrap();
+        fs::write(database_path(&config), &forged).unwrap();
+        let mut record = last_update(&config).unwrap().unwrap();
+        record.sha256 = hex::encode(Sha256::digest(forged.as_bytes()));
+        let mut log = fs::OpenOptions::new()
+            .append(true)
+            .open(updates_path(&config))
+            .unwrap();
+        writeln!(log, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+
+        let error = load_database(&config).unwrap_err().to_string();
+        assert!(error.contains("not signed"), "{}", error);
+    }
+
+    #[test]
+    fn removed_baselines_are_reported() {
+        let dir = TempDir::new("integrity-removed");
+        let config = config_in(&dir);
+        assert!(load_database(&config).unwrap().is_none());
+        write_database(&config, &database("a"), &[]).unwrap();
+        // The key was created with an empty audit log, which verifies clean
+        let report = audit_log(&config).verify().unwrap();
+        assert!(report.is_intact(), "{:?}", report.problems);
+
+        fs::remove_file(database_path(&config)).unwrap();
+        assert!(load_database(&config).is_err());
+
+        // With the update log gone too, the audit log still remembers the update
+        fs::remove_file(updates_path(&config)).unwrap();
+        assert!(load_database(&config).unwrap().is_none());
+        audit_log(&config)
+            .append(|seq, prev_hash| crate::audit::AuditRecord {
+                seq,
+                user: "root".to_string(),
+                uid: Some(0),
+                euid: Some(0),
+                command_id: "integrity/update".to_string(),
+                argv: vec!["linux-toolkit".to_string()],
+                script_sha256: None,
+                backend: "root".to_string(),
+                started_at: Utc::now(),
+                finished_at: Utc::now(),
+                exit_status: Some(0),
+                prev_hash,
+            })
+            .unwrap();
+        assert!(load_database(&config).is_err());
+    }
+}
@@ -0,0 +1,500 @@
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use std::str::FromStr;
+
+use linux_toolkit::config::Keybindings;
+
+/// Something a key can be bound to in the main view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    NextPanel,
+    Execute,
+    ToggleDetails,
+    Search,
+    Favorite,
+    ViewOutput,
+    Diff,
+    ToggleCategories,
+    Zoom,
+    Export,
+    Help,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 20] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::NextPanel,
+        Action::Execute,
+        Action::ToggleDetails,
+        Action::Search,
+        Action::Favorite,
+        Action::ViewOutput,
+        Action::Diff,
+        Action::ToggleCategories,
+        Action::Zoom,
+        Action::Export,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// Name used in the `[keybindings.bindings]` config table
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::NextPanel => "next-panel",
+            Action::Execute => "execute",
+            Action::ToggleDetails => "toggle-details",
+            Action::Search => "search",
+            Action::Favorite => "favorite",
+            Action::ViewOutput => "view-output",
+            Action::Diff => "diff",
+            Action::ToggleCategories => "toggle-categories",
+            Action::Zoom => "zoom",
+            Action::Export => "export",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::Left => "Focus categories",
+            Action::Right => "Focus commands",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Top => "Jump to top",
+            Action::Bottom => "Jump to bottom",
+            Action::NextPanel => "Cycle through panels",
+            Action::Execute => "Execute selected command",
+            Action::ToggleDetails => "Toggle command details",
+            Action::Search => "Search commands",
+            Action::Favorite => "Pin/unpin command to Favorites",
+            Action::ViewOutput => "Open output in the viewer",
+            Action::Diff => "Compare stored runs of the command",
+            Action::ToggleCategories => "Collapse/expand categories panel",
+            Action::Zoom => "Show focused panel full-screen",
+            Action::Export => "Export last output as a report",
+            Action::Help => "Toggle this help",
+            Action::Quit => "Quit application",
+        }
+    }
+
+    /// Help popup section the action is listed under
+    pub fn is_navigation(self) -> bool {
+        !matches!(
+            self,
+            Action::Favorite
+                | Action::ViewOutput
+                | Action::Diff
+                | Action::ToggleCategories
+                | Action::Zoom
+                | Action::Export
+                | Action::Help
+                | Action::Quit
+        )
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| anyhow!("unknown keybinding action '{}'", s))
+    }
+}
+
+/// A key plus the modifiers held with it, written like `ctrl+d`, `G` or `PageDown`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // Terminals report shifted characters as the character plus SHIFT
+        let modifiers = match event.code {
+            KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        self.code == event.code && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // Two trailing empty parts mean the key itself is '+'
+        let key = match parts.pop() {
+            Some("") if parts.last() == Some(&"") => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => bail!("empty key binding"),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => bail!("unknown modifier '{}' in '{}'", other, s),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            lower => {
+                if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    KeyCode::F(number)
+                } else {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => bail!("unknown key '{}' in '{}'", key, s),
+                    }
+                }
+            }
+        };
+
+        // `shift+g` arrives as `G`, so store it that way
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                return Ok(Self {
+                    code: KeyCode::Char(c.to_ascii_uppercase()),
+                    modifiers: modifiers - KeyModifiers::SHIFT,
+                });
+            }
+        }
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Keys for each action, in the order they are listed in help
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Keymap {
+    /// Start from the configured preset and apply per-action overrides
+    pub fn from_config(config: &Keybindings) -> Result<Self> {
+        let preset = preset(&config.preset)?;
+        let mut bindings: Vec<(Action, Vec<KeyBinding>)> = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = preset
+                    .iter()
+                    .find(|(a, _)| *a == action)
+                    .map(|(_, keys)| parse_keys(keys))
+                    .unwrap_or_else(|| Ok(Vec::new()))?;
+                Ok((action, keys))
+            })
+            .collect::<Result<_>>()?;
+
+        for (name, keys) in &config.bindings {
+            let action: Action = name.parse()?;
+            let keys = keys
+                .iter()
+                .map(|key| key.parse())
+                .collect::<Result<Vec<KeyBinding>>>()?;
+            if let Some(entry) = bindings.iter_mut().find(|(a, _)| *a == action) {
+                entry.1 = keys;
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub fn action_for(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|key| key.matches(event)))
+            .map(|(action, _)| *action)
+    }
+
+    /// Keys bound to `action`, joined for display
+    pub fn describe(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| {
+                keys.iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn parse_keys(keys: &[&str]) -> Result<Vec<KeyBinding>> {
+    keys.iter().map(|key| key.parse()).collect()
+}
+
+type Preset = &'static [(Action, &'static [&'static str])];
+
+fn preset(name: &str) -> Result<Preset> {
+    match name {
+        "default" => Ok(DEFAULT_PRESET),
+        "vim" => Ok(VIM_PRESET),
+        "emacs" => Ok(EMACS_PRESET),
+        other => bail!(
+            "unknown keybinding preset '{}' (expected default, vim or emacs)",
+            other
+        ),
+    }
+}
+
+const DEFAULT_PRESET: Preset = &[
+    (Action::Up, &["up"]),
+    (Action::Down, &["down"]),
+    (Action::Left, &["left"]),
+    (Action::Right, &["right"]),
+    (Action::PageUp, &["pageup"]),
+    (Action::PageDown, &["pagedown"]),
+    (Action::Top, &["home"]),
+    (Action::Bottom, &["end"]),
+    (Action::NextPanel, &["tab"]),
+    (Action::Execute, &["enter"]),
+    (Action::ToggleDetails, &["space"]),
+    (Action::Search, &["/"]),
+    (Action::Favorite, &["f"]),
+    (Action::ViewOutput, &["o"]),
+    (Action::Diff, &["d"]),
+    (Action::ToggleCategories, &["c"]),
+    (Action::Zoom, &["z"]),
+    (Action::Export, &["e"]),
+    (Action::Help, &["h", "f1"]),
+    (Action::Quit, &["q", "esc"]),
+];
+
+const VIM_PRESET: Preset = &[
+    (Action::Up, &["k", "up"]),
+    (Action::Down, &["j", "down"]),
+    (Action::Left, &["h", "left"]),
+    (Action::Right, &["l", "right"]),
+    (Action::PageUp, &["ctrl+u", "ctrl+b", "pageup"]),
+    (Action::PageDown, &["ctrl+d", "ctrl+f", "pagedown"]),
+    (Action::Top, &["g", "home"]),
+    (Action::Bottom, &["G", "end"]),
+    (Action::NextPanel, &["tab"]),
+    (Action::Execute, &["enter"]),
+    (Action::ToggleDetails, &["space"]),
+    (Action::Search, &["/"]),
+    (Action::Favorite, &["f"]),
+    (Action::ViewOutput, &["o"]),
+    (Action::Diff, &["d"]),
+    (Action::ToggleCategories, &["c"]),
+    (Action::Zoom, &["z"]),
+    (Action::Export, &["e"]),
+    (Action::Help, &["?", "f1"]),
+    (Action::Quit, &["q"]),
+];
+
+const EMACS_PRESET: Preset = &[
+    (Action::Up, &["ctrl+p", "up"]),
+    (Action::Down, &["ctrl+n", "down"]),
+    (Action::Left, &["ctrl+b", "left"]),
+    (Action::Right, &["ctrl+f", "right"]),
+    (Action::PageUp, &["alt+v", "pageup"]),
+    (Action::PageDown, &["ctrl+v", "pagedown"]),
+    (Action::Top, &["alt+<", "home"]),
+    (Action::Bottom, &["alt+>", "end"]),
+    (Action::NextPanel, &["tab"]),
+    (Action::Execute, &["enter"]),
+    (Action::ToggleDetails, &["space"]),
+    // Terminals eat ctrl+s as XOFF and send ctrl+h as Backspace
+    (Action::Search, &["ctrl+r"]),
+    (Action::Favorite, &["alt+f"]),
+    (Action::ViewOutput, &["alt+o"]),
+    (Action::Diff, &["alt+d"]),
+    (Action::ToggleCategories, &["alt+c"]),
+    (Action::Zoom, &["alt+z"]),
+    (Action::Export, &["alt+e"]),
+    (Action::Help, &["alt+h", "f1"]),
+    (Action::Quit, &["ctrl+g", "ctrl+c"]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding { code, modifiers }
+    }
+
+    fn keymap(preset: &str, bindings: &[(&str, &[&str])]) -> Result<Keymap> {
+        Keymap::from_config(&Keybindings {
+            preset: preset.to_string(),
+            bindings: bindings
+                .iter()
+                .map(|(action, keys)| {
+                    let keys = keys.iter().map(|key| key.to_string()).collect();
+                    (action.to_string(), keys)
+                })
+                .collect::<BTreeMap<_, _>>(),
+        })
+    }
+
+    #[test]
+    fn parses_key_strings() {
+        let parse = |s: &str| s.parse::<KeyBinding>().unwrap();
+        assert_eq!(
+            parse("ctrl+d"),
+            key(KeyCode::Char('d'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse("Control+Alt+x"),
+            key(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(
+            parse("shift+g"),
+            key(KeyCode::Char('G'), KeyModifiers::NONE)
+        );
+        assert_eq!(parse("G"), key(KeyCode::Char('G'), KeyModifiers::NONE));
+        assert_eq!(
+            parse("ctrl++"),
+            key(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse("PageDown"),
+            key(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!(parse("f12"), key(KeyCode::F(12), KeyModifiers::NONE));
+        assert_eq!(parse("space"), key(KeyCode::Char(' '), KeyModifiers::NONE));
+
+        assert_eq!(parse("+"), key(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert!("".parse::<KeyBinding>().is_err());
+        assert!("ctrl+".parse::<KeyBinding>().is_err());
+        assert!("hyper+x".parse::<KeyBinding>().is_err());
+        assert!("ctrl+xy".parse::<KeyBinding>().is_err());
+        assert_eq!(parse("ctrl+alt+pagedown").to_string(), "Ctrl+Alt+PgDn");
+    }
+
+    #[test]
+    fn shifted_characters_match_without_shift() {
+        let bottom = "G".parse::<KeyBinding>().unwrap();
+        assert!(bottom.matches(&KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert!(!bottom.matches(&KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn presets_resolve_and_overrides_replace_their_keys() {
+        let event = |code, modifiers| KeyEvent::new(code, modifiers);
+        for name in ["default", "vim", "emacs"] {
+            let keymap = keymap(name, &[]).unwrap();
+            assert_eq!(
+                keymap.action_for(&event(KeyCode::F(1), KeyModifiers::NONE)),
+                Some(Action::Help),
+                "{}",
+                name
+            );
+        }
+
+        let vim = keymap("vim", &[]).unwrap();
+        assert_eq!(
+            vim.action_for(&event(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::Down)
+        );
+        assert_eq!(vim.describe(Action::Bottom), "G/End");
+
+        let emacs = keymap("emacs", &[]).unwrap();
+        assert_eq!(
+            emacs.action_for(&event(KeyCode::Backspace, KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            emacs.action_for(&event(KeyCode::Char('s'), KeyModifiers::CONTROL)),
+            None
+        );
+        assert_eq!(
+            emacs.action_for(&event(KeyCode::Char('r'), KeyModifiers::CONTROL)),
+            Some(Action::Search)
+        );
+
+        let custom = keymap("default", &[("execute", &["ctrl+m", "enter"])]).unwrap();
+        assert_eq!(custom.describe(Action::Execute), "Ctrl+m/Enter");
+        assert_eq!(custom.describe(Action::Quit), "q/Esc");
+
+        assert!(keymap("nano", &[]).is_err());
+        assert!(keymap("default", &[("launch", &["x"])]).is_err());
+        assert!(keymap("default", &[("quit", &["ctrl+"])]).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Command catalog, execution and hardening tools behind the `linux-toolkit` binary.
+//!
+//! The TUI and CLI are thin front-ends over this crate; other tools can load
+//! the same catalog and run commands with structured results:
+//!
+//! ```no_run
+//! # async fn demo() -> anyhow::Result<()> {
+//! let config = linux_toolkit::Config::load(None)?;
+//! let categories = linux_toolkit::load_categories();
+//! if let Some(command) = linux_toolkit::find_command(&categories, "disk usage") {
+//!     let run = linux_toolkit::run_command(command, &config).await?;
+//!     println!("{} -> {}", command.id(), run.success);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The items re-exported here and the `config` types are the supported API.
+//! [`cli`] holds the entry points of the binary's front-ends, which print and
+//! prompt, and may change between releases.
+
+mod audit;
+mod baseline;
+mod commands;
+pub mod config;
+mod environment;
+mod executor;
+mod favorites;
+mod firewall;
+mod history;
+mod integrity;
+mod packages;
+mod persistence;
+mod report;
+mod rpc;
+mod runbooks;
+mod scheduler;
+mod scripts;
+mod sshd;
+mod sshkeys;
+mod sysctl;
+mod systemd;
+mod terminal;
+mod utils;
+mod web;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+
+pub use commands::{
+    execute_command, find_command, load_categories, run_command, Command, CommandCategory,
+    CommandRun,
+};
+pub use config::Config;
+pub use executor::{Executor, Invocation, Output, Status};
+pub use report::{ExecutionRecord, Report, ReportFormat};
+pub use scripts::ScriptManager;
+
+/// Subcommand handlers and TUI helpers used by the `linux-toolkit` binary
+pub mod cli {
+    pub mod audit {
+        pub use crate::audit::verify_command;
+    }
+    pub mod baseline {
+        pub use crate::baseline::baseline_command;
+    }
+    pub mod commands {
+        pub use crate::commands::{execute_native_command, toolkit_command};
+    }
+    pub mod environment {
+        pub use crate::environment::current;
+    }
+    pub mod favorites {
+        pub use crate::favorites::{is_virtual, UsageStore, RECENT_CATEGORY};
+    }
+    pub mod firewall {
+        pub use crate::firewall::{
+            detect, firewall_command, list_rules, policies, Action, Detected, Policies, Rule,
+        };
+    }
+    pub mod history {
+        pub use crate::history::{baseline, diff, diff_command, remember, History};
+    }
+    pub mod integrity {
+        pub use crate::integrity::integrity_command;
+    }
+    pub mod packages {
+        pub use crate::packages::packages_command;
+    }
+    pub mod persistence {
+        pub use crate::persistence::persistence_command;
+    }
+    pub mod report {
+        pub use crate::report::default_export_path;
+    }
+    pub mod rpc {
+        pub use crate::rpc::serve_command;
+    }
+    pub mod runbooks {
+        pub use crate::runbooks::runbook_command;
+    }
+    pub mod scheduler {
+        pub use crate::scheduler::scheduler_command;
+    }
+    pub mod sshd {
+        pub use crate::sshd::ssh_command;
+    }
+    pub mod sshkeys {
+        pub use crate::sshkeys::ssh_keys_command;
+    }
+    pub mod sysctl {
+        pub use crate::sysctl::sysctl_command;
+    }
+    pub mod systemd {
+        pub use crate::systemd::export_command;
+    }
+    pub mod terminal {
+        pub use crate::terminal::{execute_command_in_terminal, execute_direct_command};
+    }
+    pub mod utils {
+        pub use crate::utils::{base64_encode, get_current_user, get_hostname, privilege_state};
+    }
+    pub mod web {
+        pub use crate::web::web_command;
+    }
+}
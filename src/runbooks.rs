@@ -0,0 +1,551 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::commands::{Command, CommandCategory};
+use crate::config::Config;
+use crate::report::{ExecutionRecord, Report};
+use crate::scripts::ScriptManager;
+
+/// Name of the virtual category runbooks are listed under
+pub const RUNBOOK_CATEGORY: &str = "Runbooks";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Runbook {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub requires_sudo: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub steps: Vec<RunbookStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunbookStep {
+    /// Identifier other steps use in `only_if`, defaults to the command name
+    pub id: Option<String>,
+    /// Name or ID of the command to run
+    pub command: String,
+    /// Extra arguments appended to the command's own
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub only_if: Option<Condition>,
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+impl RunbookStep {
+    pub fn id(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| crate::utils::slugify(&self.command))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Run only if the step with this ID ran and succeeded
+    StepSucceeded(String),
+    /// Run only if this path exists
+    PathExists(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    Succeeded,
+    Failed,
+    Skipped(String),
+    NotRun,
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub id: String,
+    pub command: String,
+    pub outcome: StepOutcome,
+}
+
+/// Directory holding runbook definitions, next to the extracted scripts
+pub fn runbooks_dir(script_manager: &ScriptManager) -> PathBuf {
+    script_manager.scripts_dir.join("runbooks")
+}
+
+/// Load every `*.toml` runbook in `dir`, keyed by file stem
+pub fn load_runbooks(dir: &Path) -> Vec<(String, Runbook)> {
+    let mut runbooks = Vec::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return runbooks,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("toml") {
+            continue;
+        }
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        match fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| toml::from_str::<Runbook>(&content).map_err(Into::into))
+        {
+            Ok(runbook) => runbooks.push((stem.to_string(), runbook)),
+            Err(e) => eprintln!("Warning: Failed to load runbook {}: {}", path.display(), e),
+        }
+    }
+
+    runbooks.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    runbooks
+}
+
+/// Build the "Runbooks" category shown in the TUI
+pub fn runbook_category(script_manager: &ScriptManager) -> Option<CommandCategory> {
+    let commands: Vec<Command> = load_runbooks(&runbooks_dir(script_manager))
+        .into_iter()
+        .map(|(stem, runbook)| Command {
+            name: runbook.name,
+            description: runbook.description,
+            command: "runbook".to_string(), // Special marker for runbooks
+            args: vec![stem.clone()],
+            usage: format!("linux-toolkit runbook {}", stem),
+            tags: runbook.tags,
+            requires_sudo: runbook.requires_sudo,
+            category: RUNBOOK_CATEGORY.to_string(),
+            supported_distros: Vec::new(),
+            requires_tools: Vec::new(),
+            unavailable: None,
+            missing: Vec::new(),
+        })
+        .collect();
+
+    if commands.is_empty() {
+        return None;
+    }
+
+    Some(CommandCategory {
+        name: RUNBOOK_CATEGORY.to_string(),
+        description: "Multi-step workflows composed of existing commands".to_string(),
+        commands,
+    })
+}
+
+/// Look a runbook up by file name or title
+pub fn find_runbook(name: &str) -> Result<Runbook> {
+    let script_manager = ScriptManager::new_from_exe()?;
+    load_runbooks(&runbooks_dir(&script_manager))
+        .into_iter()
+        .find(|(stem, runbook)| stem == name || runbook.name.eq_ignore_ascii_case(name))
+        .map(|(_, runbook)| runbook)
+        .ok_or_else(|| anyhow!("Runbook '{}' not found", name))
+}
+
+/// Resolve a step to the catalog command it refers to
+fn resolve_step(step: &RunbookStep, categories: &[CommandCategory]) -> Option<Command> {
+    let command = categories
+        .iter()
+        .flat_map(|category| &category.commands)
+        .filter(|command| command.command != "runbook")
+        .find(|command| {
+            command.id() == step.command || command.name.eq_ignore_ascii_case(&step.command)
+        })?;
+
+    let mut command = command.clone();
+    command.args.extend(step.args.iter().cloned());
+    Some(command)
+}
+
+/// Entry point for runbook commands selected in the TUI
+pub async fn run_in_terminal(command: &Command, config: &Config) -> Result<()> {
+    let name = command
+        .args
+        .first()
+        .ok_or_else(|| anyhow!("No runbook specified"))?;
+    let runbook = find_runbook(name)?;
+    execute(&runbook, config).await?;
+    Ok(())
+}
+
+/// Run every step in order, printing progress and a final summary
+pub async fn execute(runbook: &Runbook, config: &Config) -> Result<Vec<StepResult>> {
+    let categories = crate::commands::load_categories();
+    execute_with(
+        runbook,
+        &categories,
+        &|line: &str| println!("{}", line),
+        async |command: &Command| crate::terminal::run_in_terminal(command, config).await,
+    )
+    .await
+}
+
+/// Run every step with output captured for unattended runs
+///
+/// Also returns the progress, step output and summary as one text, none of
+/// which goes to stdout.
+pub async fn execute_captured(
+    runbook: &Runbook,
+    config: &Config,
+) -> Result<(Vec<StepResult>, String)> {
+    execute_captured_from(runbook, &crate::commands::load_categories(), config).await
+}
+
+async fn execute_captured_from(
+    runbook: &Runbook,
+    categories: &[CommandCategory],
+    config: &Config,
+) -> Result<(Vec<StepResult>, String)> {
+    let output = RefCell::new(String::new());
+    let progress = |line: &str| {
+        let mut output = output.borrow_mut();
+        output.push_str(line);
+        output.push('\n');
+    };
+    let results = execute_with(runbook, categories, &progress, async |command: &Command| {
+        let run = crate::commands::run_unattended(command, config).await?;
+        progress(run.display().trim_end());
+        Ok(run.success)
+    })
+    .await?;
+    Ok((results, output.into_inner()))
+}
+
+/// Run the steps, passing progress lines to `progress` and each resolved
+/// command to `run_step`
+async fn execute_with(
+    runbook: &Runbook,
+    categories: &[CommandCategory],
+    progress: &dyn Fn(&str),
+    mut run_step: impl AsyncFnMut(&Command) -> Result<bool>,
+) -> Result<Vec<StepResult>> {
+    let total = runbook.steps.len();
+    let mut results: Vec<StepResult> = Vec::new();
+    let mut succeeded: HashMap<String, bool> = HashMap::new();
+    let mut aborted = false;
+
+    progress(&"=".repeat(60));
+    progress(&format!("Runbook: {}", runbook.name));
+    progress(&runbook.description);
+    progress(&"=".repeat(60));
+
+    for (index, step) in runbook.steps.iter().enumerate() {
+        let id = step.id();
+        let mut result = StepResult {
+            id: id.clone(),
+            command: step.command.clone(),
+            outcome: StepOutcome::NotRun,
+        };
+
+        if aborted {
+            results.push(result);
+            continue;
+        }
+
+        progress("");
+        progress(&format!("[{}/{}] ▶ {}", index + 1, total, step.command));
+
+        result.outcome = if let Some(reason) = skip_reason(step, &succeeded) {
+            progress(&format!("⏭  Skipped: {}", reason));
+            StepOutcome::Skipped(reason)
+        } else {
+            match resolve_step(step, categories) {
+                Some(command) => {
+                    if run_step(&command).await? {
+                        StepOutcome::Succeeded
+                    } else {
+                        StepOutcome::Failed
+                    }
+                }
+                None => {
+                    progress(&format!("❌ Unknown command '{}'", step.command));
+                    StepOutcome::Failed
+                }
+            }
+        };
+
+        succeeded.insert(id, result.outcome == StepOutcome::Succeeded);
+        if result.outcome == StepOutcome::Failed && !step.continue_on_error {
+            progress("⛔ Stopping runbook, step does not allow continuing on error");
+            aborted = true;
+        }
+        results.push(result);
+    }
+
+    for line in summary(runbook, &results) {
+        progress(&line);
+    }
+    Ok(results)
+}
+
+/// Why a step's `only_if` condition keeps it from running, if it does
+fn skip_reason(step: &RunbookStep, succeeded: &HashMap<String, bool>) -> Option<String> {
+    match &step.only_if {
+        Some(Condition::StepSucceeded(other))
+            if !succeeded.get(other).copied().unwrap_or(false) =>
+        {
+            Some(format!("step '{}' did not succeed", other))
+        }
+        Some(Condition::PathExists(path)) if !path.exists() => {
+            Some(format!("{} does not exist", path.display()))
+        }
+        _ => None,
+    }
+}
+
+fn outcome_text(outcome: &StepOutcome) -> String {
+    match outcome {
+        StepOutcome::Succeeded => "✅ succeeded".to_string(),
+        StepOutcome::Failed => "❌ failed".to_string(),
+        StepOutcome::Skipped(reason) => format!("⏭  skipped ({})", reason),
+        StepOutcome::NotRun => "⛔ not run".to_string(),
+    }
+}
+
+fn summary(runbook: &Runbook, results: &[StepResult]) -> Vec<String> {
+    let mut lines = vec![
+        String::new(),
+        "=".repeat(60),
+        format!("Summary: {}", runbook.name),
+        "=".repeat(60),
+    ];
+    for result in results {
+        lines.push(format!(
+            "  {:<12} {:<30} {}",
+            result.id,
+            result.command,
+            outcome_text(&result.outcome)
+        ));
+    }
+
+    let count = |outcome: fn(&StepOutcome) -> bool| {
+        results
+            .iter()
+            .filter(|result| outcome(&result.outcome))
+            .count()
+    };
+    lines.push(String::new());
+    lines.push(format!(
+        "{} succeeded, {} failed, {} skipped, {} not run",
+        count(|o| *o == StepOutcome::Succeeded),
+        count(|o| *o == StepOutcome::Failed),
+        count(|o| matches!(o, StepOutcome::Skipped(_))),
+        count(|o| *o == StepOutcome::NotRun),
+    ));
+    lines
+}
+
+/// Handler for `linux-toolkit runbook [NAME]`
+pub async fn runbook_command(
+    name: Option<&String>,
+    config: &Config,
+    report_path: Option<&Path>,
+) -> Result<()> {
+    let Some(name) = name else {
+        let script_manager = ScriptManager::new_from_exe()?;
+        println!("Available runbooks:");
+        for (stem, runbook) in load_runbooks(&runbooks_dir(&script_manager)) {
+            println!("  {:<24} {}", stem, runbook.description);
+        }
+        return Ok(());
+    };
+
+    let runbook = find_runbook(name)?;
+    let started_at = chrono::Utc::now();
+    // Nobody can answer prompts from a timer or pipe, so run the steps unattended
+    let results = if std::io::stdin().is_terminal() {
+        execute(&runbook, config).await?
+    } else {
+        let (results, output) = execute_captured(&runbook, config).await?;
+        print!("{}", output);
+        results
+    };
+
+    if let Some(path) = report_path {
+        let mut report = Report::new(&runbook.name);
+        for result in &results {
+            report = report.with_execution(ExecutionRecord {
+                command_id: result.id.clone(),
+                command_name: result.command.clone(),
+                started_at,
+                success: match result.outcome {
+                    StepOutcome::Succeeded => Some(true),
+                    StepOutcome::Failed => Some(false),
+                    _ => None,
+                },
+                output: outcome_text(&result.outcome),
+            });
+        }
+        report.write_to(path)?;
+        println!("Report written to {}", path.display());
+    }
+
+    if results
+        .iter()
+        .any(|result| result.outcome == StepOutcome::Failed)
+    {
+        return Err(anyhow!(
+            "Runbook '{}' did not complete successfully",
+            runbook.name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{override_current, RecordingExecutor};
+    use crate::testing;
+    use std::sync::Arc;
+
+    fn step(id: &str, command: &str, only_if: Option<Condition>) -> RunbookStep {
+        RunbookStep {
+            id: Some(id.to_string()),
+            command: command.to_string(),
+            args: Vec::new(),
+            only_if,
+            continue_on_error: false,
+        }
+    }
+
+    fn runbook(steps: Vec<RunbookStep>) -> Runbook {
+        Runbook {
+            name: "Harden".to_string(),
+            description: String::new(),
+            requires_sudo: false,
+            tags: Vec::new(),
+            steps,
+        }
+    }
+
+    fn categories() -> Vec<CommandCategory> {
+        vec![CommandCategory {
+            name: "Test".to_string(),
+            description: String::new(),
+            commands: vec![
+                testing::command("Enable Firewall", "ls").build(),
+                testing::command("Secure SSH", "ls").build(),
+            ],
+        }]
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        config
+    }
+
+    #[test]
+    fn conditions_explain_why_steps_are_skipped() {
+        let succeeded = HashMap::from([("update".to_string(), false), ("ok".to_string(), true)]);
+
+        let after_failure = step(
+            "ssh",
+            "Secure SSH",
+            Some(Condition::StepSucceeded("update".into())),
+        );
+        assert_eq!(
+            skip_reason(&after_failure, &succeeded).as_deref(),
+            Some("step 'update' did not succeed")
+        );
+        let never_ran = step(
+            "ssh",
+            "Secure SSH",
+            Some(Condition::StepSucceeded("other".into())),
+        );
+        assert!(skip_reason(&never_ran, &succeeded).is_some());
+        let after_success = step(
+            "ssh",
+            "Secure SSH",
+            Some(Condition::StepSucceeded("ok".into())),
+        );
+        assert_eq!(skip_reason(&after_success, &succeeded), None);
+
+        let dir = testing::TempDir::new("runbook-path");
+        let present = step(
+            "ssh",
+            "Secure SSH",
+            Some(Condition::PathExists(dir.path().into())),
+        );
+        assert_eq!(skip_reason(&present, &succeeded), None);
+        let missing = dir.join("sshd_config");
+        let absent = step(
+            "ssh",
+            "Secure SSH",
+            Some(Condition::PathExists(missing.clone())),
+        );
+        assert_eq!(
+            skip_reason(&absent, &succeeded),
+            Some(format!("{} does not exist", missing.display()))
+        );
+    }
+
+    #[tokio::test]
+    async fn failures_propagate_unless_steps_continue_on_error() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(1, "", "E: could not lock");
+        let mut update = step("update", "Enable Firewall", None);
+        update.continue_on_error = true;
+        let runbook = runbook(vec![
+            update,
+            step(
+                "ssh",
+                "Secure SSH",
+                Some(Condition::StepSucceeded("update".into())),
+            ),
+            step("missing", "No Such Command", None),
+            step("firewall", "Enable Firewall", None),
+        ]);
+
+        let (results, output) = execute_captured_from(&runbook, &categories(), &config())
+            .await
+            .unwrap();
+
+        let outcomes: Vec<StepOutcome> = results.into_iter().map(|r| r.outcome).collect();
+        assert_eq!(
+            outcomes,
+            [
+                StepOutcome::Failed,
+                StepOutcome::Skipped("step 'update' did not succeed".to_string()),
+                StepOutcome::Failed,
+                StepOutcome::NotRun,
+            ]
+        );
+        assert_eq!(fake.calls().len(), 1);
+        assert!(output.contains("[3/4] ▶ No Such Command"), "{}", output);
+        assert!(output.contains("❌ Unknown command 'No Such Command'"));
+        assert!(output.contains("0 succeeded, 2 failed, 1 skipped, 1 not run"));
+    }
+
+    #[tokio::test]
+    async fn declined_step_fails_the_runbook() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(1, "", "Error: Aborted, nothing was changed");
+        let runbook = runbook(vec![
+            step("firewall", "Enable Firewall", None),
+            step("ssh", "Secure SSH", None),
+        ]);
+
+        let (results, output) = execute_captured_from(&runbook, &categories(), &config())
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].outcome, StepOutcome::Failed);
+        assert_eq!(results[1].outcome, StepOutcome::NotRun);
+        assert!(
+            output.contains("Aborted, nothing was changed"),
+            "{}",
+            output
+        );
+        assert_eq!(fake.calls().len(), 1);
+    }
+}
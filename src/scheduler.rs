@@ -0,0 +1,449 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc,
+};
+use std::str::FromStr;
+
+use crate::commands::{self, CommandCategory};
+use crate::config::{Config, Schedule};
+use crate::history::{self, History};
+use crate::report::ExecutionRecord;
+use crate::runbooks::{self, StepOutcome};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Longest the scheduler sleeps before checking the clock again
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// A five-field cron expression: minute, hour, day of month, month, day of week
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month and day of week were both restricted, either may match
+    either_day: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!(
+                "'{}' is not a cron expression, expected five fields",
+                expression
+            ));
+        };
+
+        // Sunday may be written as 7 as well as 0
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAYS)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[])?,
+            hours: parse_field(hour, 0, 23, &[])?,
+            days: parse_field(day, 1, 31, &[])?,
+            months: parse_field(month, 1, 12, &MONTHS)?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+}
+
+/// Parse one field into a bit set of the values it allows
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let value = |text: &str| -> Result<u32> {
+        let lowered = text.to_lowercase();
+        let value = match names.iter().position(|name| *name == lowered) {
+            // Month names count from 1, weekday names from Sunday as 0
+            Some(index) => index as u32 + min,
+            None => text
+                .parse()
+                .with_context(|| format!("'{}' is not a valid cron value", text))?,
+        };
+        if value < min || value > max {
+            return Err(anyhow!("{} is outside {}-{}", value, min, max));
+        }
+        Ok(value)
+    };
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .with_context(|| format!("'{}' is not a valid step", step))?;
+                if step == 0 {
+                    return Err(anyhow!("Step in '{}' must be positive", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` means every 15 starting at 5
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if start > end {
+            return Err(anyhow!("Range '{}' is backwards", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    /// The same schedule as a systemd `OnCalendar=` expression
+    pub fn on_calendar(&self) -> Result<String> {
+        if self.either_day {
+            return Err(anyhow!(
+                "systemd timers cannot run on a day of the month OR a day of the week"
+            ));
+        }
+        let number = |value: u32| format!("{:02}", value);
+        let weekdays = match calendar_list(self.weekdays, 0, 6, |day| {
+            let name = WEEKDAYS[day as usize];
+            name[..1].to_uppercase() + &name[1..]
+        }) {
+            all if all == "*" => String::new(),
+            days => format!("{} ", days),
+        };
+        Ok(format!(
+            "{}*-{}-{} {}:{}:00",
+            weekdays,
+            calendar_list(self.months, 1, 12, number),
+            calendar_list(self.days, 1, 31, number),
+            calendar_list(self.hours, 0, 23, number),
+            calendar_list(self.minutes, 0, 59, number),
+        ))
+    }
+
+    /// First matching minute after `after`, searching up to five years ahead
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(5 * 366);
+        let mut time = start;
+        while time < limit {
+            if self.months & (1 << time.month()) == 0 || !self.day_matches(time.date()) {
+                time = (time.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// Next local run time, skipping minutes that daylight saving jumps over
+    pub fn next_local(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut time = after.naive_local();
+        loop {
+            time = self.next_after(time)?;
+            if let Some(local) = Local.from_local_datetime(&time).earliest() {
+                return Some(local);
+            }
+        }
+    }
+}
+
+/// Allowed values as a systemd list with `..` ranges, `*` when all are allowed
+fn calendar_list(bits: u64, min: u32, max: u32, name: impl Fn(u32) -> String) -> String {
+    let allowed = |value: u32| bits & (1 << value) != 0;
+    if (min..=max).all(allowed) {
+        return "*".to_string();
+    }
+
+    let mut parts = Vec::new();
+    let mut value = min;
+    while value <= max {
+        if !allowed(value) {
+            value += 1;
+            continue;
+        }
+        let start = value;
+        while value < max && allowed(value + 1) {
+            value += 1;
+        }
+        parts.push(match value - start {
+            0 => name(start),
+            1 => format!("{},{}", name(start), name(value)),
+            _ => format!("{}..{}", name(start), name(value)),
+        });
+        value += 1;
+    }
+    parts.join(",")
+}
+
+/// Run a schedule once and record it in the command's history
+async fn run_schedule(
+    schedule: &Schedule,
+    categories: &[CommandCategory],
+    config: &Config,
+) -> Result<ExecutionRecord> {
+    let started_at = Utc::now();
+    if let Some(name) = &schedule.runbook {
+        let runbook = runbooks::find_runbook(name)?;
+        let (results, output) = runbooks::execute_captured(&runbook, config).await?;
+        return Ok(ExecutionRecord {
+            command_id: format!(
+                "{}/{}",
+                crate::utils::slugify(runbooks::RUNBOOK_CATEGORY),
+                crate::utils::slugify(&runbook.name)
+            ),
+            command_name: runbook.name.clone(),
+            started_at,
+            success: Some(
+                results
+                    .iter()
+                    .all(|result| result.outcome != StepOutcome::Failed),
+            ),
+            output,
+        });
+    }
+
+    let query = schedule.command.as_deref().ok_or_else(|| {
+        anyhow!(
+            "Schedule '{}' names no command or runbook",
+            schedule.label()
+        )
+    })?;
+    let mut command = commands::find_command(categories, query)
+        .ok_or_else(|| anyhow!("Command not found: {}", query))?
+        .clone();
+    command.args.extend(schedule.args.iter().cloned());
+    let run = commands::run_unattended(&command, config).await?;
+    Ok(run.record(&command, started_at))
+}
+
+/// Run a schedule, store the result and print what changed since its last run
+async fn run_and_compare(schedule: &Schedule, categories: &[CommandCategory], config: &Config) {
+    let now = Local::now().format("%Y-%m-%d %H:%M");
+    let record = match run_schedule(schedule, categories, config).await {
+        Ok(record) => record,
+        Err(e) => {
+            println!("[{}] ❌ {}: {}", now, schedule.label(), e);
+            return;
+        }
+    };
+
+    let key = history::key(&record.command_id, &schedule.args);
+    let history = History::new(config);
+    let previous = history.latest(&key).unwrap_or_default();
+    if let Err(e) = history.record(&key, &record) {
+        println!("⚠️  Could not store the result: {}", e);
+    }
+
+    let status = if record.success == Some(true) {
+        "✅"
+    } else {
+        "❌"
+    };
+    println!("[{}] {} {}", now, status, schedule.label());
+    match previous {
+        None => println!("    First run, nothing to compare with"),
+        Some(previous) => {
+            let diff = history::diff(&previous.output, &record.output).lines();
+            let since = previous
+                .started_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M");
+            if diff.is_empty() {
+                println!("    No changes since {}", since);
+            } else {
+                println!("    {} change(s) since {}:", diff.len(), since);
+                for line in diff {
+                    println!("    {}", history::paint(&line));
+                }
+            }
+        }
+    }
+}
+
+fn parse_schedules(config: &Config) -> Result<Vec<(&Schedule, CronSchedule)>> {
+    config
+        .schedules
+        .iter()
+        .map(|schedule| {
+            if schedule.command.is_some() == schedule.runbook.is_some() {
+                return Err(anyhow!(
+                    "Schedule '{}' must name exactly one of command or runbook",
+                    schedule.label()
+                ));
+            }
+            let cron = schedule
+                .cron
+                .parse()
+                .with_context(|| format!("Invalid schedule '{}'", schedule.label()))?;
+            Ok((schedule, cron))
+        })
+        .collect()
+}
+
+/// Handler for `linux-toolkit scheduler [run|list]`
+pub async fn scheduler_command(action: &str, config: &Config) -> Result<()> {
+    let schedules = parse_schedules(config)?;
+    if schedules.is_empty() {
+        return Err(anyhow!(
+            "No schedules configured, add [[schedules]] entries to {}",
+            config.directories.config_dir.join("config.toml").display()
+        ));
+    }
+
+    if action == "list" {
+        let now = Local::now();
+        for (schedule, cron) in &schedules {
+            let next = cron
+                .next_local(now)
+                .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "never".to_string());
+            println!(
+                "  {:<30} {:<16} next {}",
+                schedule.label(),
+                schedule.cron,
+                next
+            );
+        }
+        return Ok(());
+    }
+
+    let categories = commands::load_categories();
+    let mut due: Vec<Option<DateTime<Local>>> = schedules
+        .iter()
+        .map(|(_, cron)| cron.next_local(Local::now()))
+        .collect();
+    println!("Scheduler started with {} schedule(s)", schedules.len());
+
+    loop {
+        let now = Local::now();
+        for ((schedule, cron), due) in schedules.iter().zip(due.iter_mut()) {
+            if due.is_some_and(|time| time <= now) {
+                run_and_compare(schedule, &categories, config).await;
+                *due = cron.next_local(Local::now());
+            }
+        }
+
+        let Some(next) = due.iter().flatten().min() else {
+            return Err(anyhow!("No schedule will run again"));
+        };
+        // Wake up at least every minute so clock changes and suspends are noticed
+        let wait = (*next - Local::now())
+            .to_std()
+            .unwrap_or_default()
+            .min(MAX_SLEEP);
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> String {
+        let cron: CronSchedule = expression.parse().unwrap();
+        cron.next_after(at(after))
+            .unwrap()
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn finds_next_run_time() {
+        assert_eq!(next("30 2 * * *", "2026-10-19 02:30"), "2026-10-20 02:30");
+        assert_eq!(next("*/15 * * * *", "2026-10-19 10:07"), "2026-10-19 10:15");
+        assert_eq!(next("@monthly", "2026-12-15 08:00"), "2027-01-01 00:00");
+        assert_eq!(
+            next("0 9 * * mon-fri", "2026-10-23 09:00"),
+            "2026-10-26 09:00"
+        );
+        assert_eq!(next("0 0 29 feb *", "2026-03-01 00:00"), "2028-02-29 00:00");
+    }
+
+    #[test]
+    fn day_fields_match_either_when_both_set() {
+        // The 1st of the month or any Sunday, as in Vixie cron
+        assert_eq!(next("0 0 1 * 7", "2026-10-19 00:00"), "2026-10-25 00:00");
+        assert_eq!(next("0 0 1 * 7", "2026-10-25 00:00"), "2026-11-01 00:00");
+    }
+
+    #[test]
+    fn converts_to_systemd_calendar() {
+        let calendar = |expression: &str| {
+            expression
+                .parse::<CronSchedule>()
+                .unwrap()
+                .on_calendar()
+                .unwrap()
+        };
+        assert_eq!(calendar("@daily"), "*-*-* 00:00:00");
+        assert_eq!(calendar("30 2 * * *"), "*-*-* 02:30:00");
+        assert_eq!(
+            calendar("*/15 9-17 * * mon-fri"),
+            "Mon..Fri *-*-* 09..17:00,15,30,45:00"
+        );
+        assert_eq!(calendar("0 0 1 */3 *"), "*-01,04,07,10-01 00:00:00");
+        assert!("0 0 1 * 0"
+            .parse::<CronSchedule>()
+            .unwrap()
+            .on_calendar()
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@often",
+        ] {
+            assert!(
+                expression.parse::<CronSchedule>().is_err(),
+                "{}",
+                expression
+            );
+        }
+    }
+}
@@ -4,6 +4,10 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::audit::PrivilegedRun;
+use crate::config::Config;
+use crate::executor::{self, Invocation, Output};
+
 // Include the embedded scripts
 include!(concat!(env!("OUT_DIR"), "/embedded_scripts.rs"));
 
@@ -15,6 +19,12 @@ pub struct ScriptCommand {
     pub usage: String,
     pub requires_sudo: bool,
     pub tags: Vec<String>,
+    /// Distribution IDs (or ID_LIKE families) the script works on, empty for all
+    #[serde(default)]
+    pub supported_distros: Vec<String>,
+    /// Programs that must be on PATH for the script to work
+    #[serde(default)]
+    pub requires_tools: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,7 +66,7 @@ impl ScriptManager {
         };
 
         // Create directories for all categories in the config
-        for (_, category) in &config.scripts {
+        for category in config.scripts.values() {
             let category_dir = scripts_dir.join(&category.directory);
             fs::create_dir_all(&category_dir)?;
 
@@ -156,10 +166,8 @@ impl ScriptManager {
     /// Check if a directory is writable by trying to create a test file
     fn is_directory_writable(dir: &Path) -> bool {
         // If directory doesn't exist, try to create it first
-        if !dir.exists() {
-            if let Err(_) = fs::create_dir_all(dir) {
-                return false;
-            }
+        if !dir.exists() && fs::create_dir_all(dir).is_err() {
+            return false;
         }
 
         // Try to create a temporary file to test write permissions
@@ -194,6 +202,8 @@ impl ScriptManager {
                     "ports".to_string(),
                     "active".to_string(),
                 ],
+                supported_distros: Vec::new(),
+                requires_tools: vec!["ss".to_string()],
             }],
         };
 
@@ -254,7 +264,7 @@ echo "Script executed successfully!"
     pub fn list_available_scripts(&self) -> Vec<(String, Vec<ScriptCommand>)> {
         let mut scripts = Vec::new();
 
-        for (_, category) in &self.config.scripts {
+        for category in self.config.scripts.values() {
             let mut available_commands = Vec::new();
 
             for command in &category.commands {
@@ -281,63 +291,79 @@ echo "Script executed successfully!"
         scripts
     }
 
-    pub async fn execute_script(
-        &self,
-        script_path: &Path,
-        args: &[String],
-        use_sudo: bool,
-    ) -> Result<String> {
-        use std::process::Stdio;
-        use tokio::process::Command;
+    /// Locate a script by file name in any category directory
+    pub fn find_script(&self, script_name: &str) -> Option<PathBuf> {
+        self.config
+            .scripts
+            .values()
+            .map(|category| self.scripts_dir.join(&category.directory).join(script_name))
+            .find(|path| path.exists())
+    }
 
-        let mut cmd = if use_sudo && !crate::utils::is_root() {
-            let mut sudo_cmd = Command::new("sudo");
+    /// Interpreter invocation for a script, elevated when asked and not already root
+    pub fn invocation(script_path: &Path, args: &[String], use_sudo: bool) -> Invocation {
+        if use_sudo && !crate::utils::is_root() {
             if cfg!(target_os = "windows") {
                 // On Windows, we might use PowerShell with elevated privileges
-                sudo_cmd = Command::new("powershell");
-                sudo_cmd.arg("-Command");
-                sudo_cmd.arg(format!("& '{}' {}", script_path.display(), args.join(" ")));
+                Invocation::new(
+                    "powershell",
+                    vec![
+                        "-Command".to_string(),
+                        format!("& '{}' {}", script_path.display(), args.join(" ")),
+                    ],
+                )
             } else {
-                sudo_cmd.arg(script_path);
-                for arg in args {
-                    sudo_cmd.arg(arg);
-                }
+                let mut sudo_args = vec!["env".to_string()];
+                sudo_args.extend(Self::env_assignments());
+                sudo_args.push(script_path.display().to_string());
+                sudo_args.extend(args.iter().cloned());
+                Invocation::new("sudo", sudo_args)
             }
-            sudo_cmd
+        } else if cfg!(target_os = "windows") && which::which("bash").is_err() {
+            // Fallback to PowerShell when neither Git Bash nor WSL is available
+            let mut ps_args = vec!["-File".to_string(), script_path.display().to_string()];
+            ps_args.extend(args.iter().cloned());
+            Invocation::new("powershell", ps_args)
         } else {
-            let mut script_cmd = if cfg!(target_os = "windows") {
-                // On Windows, execute bash scripts through Git Bash or WSL if available
-                if which::which("bash").is_ok() {
-                    let mut bash_cmd = Command::new("bash");
-                    bash_cmd.arg(script_path);
-                    bash_cmd
-                } else {
-                    // Fallback to PowerShell
-                    let mut ps_cmd = Command::new("powershell");
-                    ps_cmd.arg("-File");
-                    ps_cmd.arg(script_path);
-                    ps_cmd
-                }
-            } else {
-                Command::new("bash")
-            };
-
-            if !cfg!(target_os = "windows") || which::which("bash").is_ok() {
-                script_cmd.arg(script_path);
-            }
-
-            for arg in args {
-                script_cmd.arg(arg);
-            }
-            script_cmd
-        };
+            let mut bash_args = vec![script_path.display().to_string()];
+            bash_args.extend(args.iter().cloned());
+            Invocation::new("bash", bash_args)
+        }
+        .with_env(crate::environment::current().env_vars())
+    }
 
-        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    /// Run a script with its output captured, also returning whether it succeeded
+    pub async fn execute_script(
+        &self,
+        script_path: &Path,
+        args: &[String],
+        use_sudo: bool,
+        command_id: &str,
+        config: &Config,
+    ) -> Result<(String, bool)> {
+        let invocation = Self::invocation(script_path, args, use_sudo);
+        Self::capture(invocation, script_path, command_id, config).await
+    }
 
-        let output = cmd.output().await?;
+    /// Run a prepared script invocation with its output captured
+    pub(crate) async fn capture(
+        invocation: Invocation,
+        script_path: &Path,
+        command_id: &str,
+        config: &Config,
+    ) -> Result<(String, bool)> {
+        let audit = Self::begin_audit(&invocation, command_id, script_path);
+        let output = executor::capture(invocation).await;
+        if let Some(audit) = audit {
+            audit.finish(
+                config,
+                output.as_ref().ok().and_then(|output| output.status.code),
+            );
+        }
+        let output = output?;
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = output.stdout;
+        let stderr = output.stderr;
 
         if !output.status.success() {
             let error_msg = if !stderr.is_empty() {
@@ -345,31 +371,36 @@ echo "Script executed successfully!"
             } else {
                 format!("Script failed with exit code: {}", output.status)
             };
-            return Ok(error_msg);
+            return Ok((error_msg, false));
         }
 
         if stdout.is_empty() && !stderr.is_empty() {
-            Ok(stderr.to_string())
+            Ok((stderr, true))
         } else {
-            Ok(stdout.to_string())
+            Ok((stdout, true))
         }
     }
 
+    /// `LTK_*` variables as `KEY=value` arguments for `env`, since sudo
+    /// does not pass the caller's environment through
+    fn env_assignments() -> Vec<String> {
+        crate::environment::current()
+            .env_vars()
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect()
+    }
+
+    /// Run a script on the terminal, copying its output there while capturing
+    /// it; `None` when it could not be started
     pub async fn execute_script_in_terminal(
         &self,
         script_path: &Path,
         args: &[String],
         use_sudo: bool,
-    ) -> Result<()> {
-        use std::process::Command as StdCommand;
-
-        // Clear the terminal screen
-        if cfg!(target_os = "windows") {
-            let _ = StdCommand::new("cls").status();
-        } else {
-            let _ = StdCommand::new("clear").status();
-        }
-
+        command_id: &str,
+        config: &Config,
+    ) -> Result<Option<Output>> {
         println!("Executing script: {}", script_path.display());
         if !args.is_empty() {
             println!("Arguments: {}", args.join(" "));
@@ -380,67 +411,76 @@ echo "Script executed successfully!"
         println!("{}", "=".repeat(60));
         println!();
 
-        let mut cmd = if use_sudo && !crate::utils::is_root() {
+        let invocation = if use_sudo && !crate::utils::is_root() {
             if cfg!(target_os = "windows") {
                 // On Windows, try to run with elevated privileges
-                let mut ps_cmd = StdCommand::new("powershell");
-                ps_cmd.arg("-Command");
-                ps_cmd.arg(format!(
-                    "Start-Process -FilePath 'bash' -ArgumentList '{}' -Verb RunAs -Wait",
-                    script_path.display()
-                ));
-                ps_cmd
+                Invocation::new(
+                    "powershell",
+                    vec![
+                        "-Command".to_string(),
+                        format!(
+                            "Start-Process -FilePath 'bash' -ArgumentList '{}' -Verb RunAs -Wait",
+                            script_path.display()
+                        ),
+                    ],
+                )
             } else {
-                let mut sudo_cmd = StdCommand::new("sudo");
-                sudo_cmd.arg("bash");
-                sudo_cmd.arg(script_path);
-                for arg in args {
-                    sudo_cmd.arg(arg);
-                }
-                sudo_cmd
+                let mut sudo_args = vec!["env".to_string()];
+                sudo_args.extend(Self::env_assignments());
+                sudo_args.push("bash".to_string());
+                sudo_args.push(script_path.display().to_string());
+                sudo_args.extend(args.iter().cloned());
+                Invocation::new("sudo", sudo_args)
             }
+        } else if cfg!(target_os = "windows") && which::which("bash").is_err() {
+            let mut ps_args = vec!["-File".to_string(), script_path.display().to_string()];
+            ps_args.extend(args.iter().cloned());
+            Invocation::new("powershell", ps_args)
         } else {
-            let mut script_cmd = if cfg!(target_os = "windows") {
-                if which::which("bash").is_ok() {
-                    let mut bash_cmd = StdCommand::new("bash");
-                    bash_cmd.arg(script_path);
-                    bash_cmd
-                } else {
-                    let mut ps_cmd = StdCommand::new("powershell");
-                    ps_cmd.arg("-File");
-                    ps_cmd.arg(script_path);
-                    ps_cmd
-                }
-            } else {
-                let mut bash_cmd = StdCommand::new("bash");
-                bash_cmd.arg(script_path);
-                bash_cmd
-            };
-
-            for arg in args {
-                script_cmd.arg(arg);
-            }
-            script_cmd
-        };
+            let mut bash_args = vec![script_path.display().to_string()];
+            bash_args.extend(args.iter().cloned());
+            Invocation::new("bash", bash_args)
+        }
+        .with_env(crate::environment::current().env_vars());
+
+        let audit = Self::begin_audit(&invocation, command_id, script_path);
+        let result = executor::current().tee(&invocation);
+        if let Some(audit) = audit {
+            audit.finish(
+                config,
+                result.as_ref().ok().and_then(|output| output.status.code),
+            );
+        }
 
-        match cmd.status() {
-            Ok(status) => {
+        match result {
+            Ok(output) => {
                 println!();
-                if status.success() {
+                if output.status.success() {
                     println!("✅ Script completed successfully");
                 } else {
-                    println!("❌ Script failed with exit code: {}", status);
+                    println!("❌ Script failed with exit code: {}", output.status);
                 }
+                Ok(Some(output))
             }
             Err(e) => {
                 println!("❌ Failed to execute script: {}", e);
+                Ok(None)
             }
         }
+    }
 
-        println!("\nPress Enter to continue...");
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input).ok();
-
-        Ok(())
+    /// Start an audit record for a script run, if it runs privileged
+    pub(crate) fn begin_audit(
+        invocation: &Invocation,
+        command_id: &str,
+        script_path: &Path,
+    ) -> Option<PrivilegedRun> {
+        let argv = invocation.argv();
+        let elevated_with = match invocation.program.as_str() {
+            "sudo" => Some("sudo"),
+            "powershell" if argv.iter().any(|arg| arg.contains("-Verb RunAs")) => Some("runas"),
+            _ => None,
+        };
+        PrivilegedRun::begin(command_id, &argv, elevated_with, Some(script_path))
     }
 }
@@ -0,0 +1,745 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::executor::{self, Invocation};
+
+pub const SSHD_CONFIG: &str = "/etc/ssh/sshd_config";
+
+/// sshd refuses deeper include chains, so do we
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+const BLOCK_START: &str = "# --- linux-toolkit hardening (managed) ---";
+const BLOCK_END: &str = "# --- end linux-toolkit hardening ---";
+
+#[derive(Debug, Clone)]
+pub struct Directive {
+    /// Keyword in lowercase, as sshd matches them case-insensitively
+    pub keyword: String,
+    pub value: String,
+    pub file: PathBuf,
+    pub line: usize,
+    /// Criteria of the enclosing `Match` block, `None` in the global section
+    pub match_block: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SshdConfig {
+    pub directives: Vec<Directive>,
+    /// Every file read, in the order sshd reads them
+    pub files: Vec<PathBuf>,
+}
+
+impl SshdConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Self::default();
+        let base = path.parent().unwrap_or(Path::new("/etc/ssh")).to_path_buf();
+        config.parse_file(path, &base, None, 0)?;
+        Ok(config)
+    }
+
+    fn parse_file(
+        &mut self,
+        path: &Path,
+        base: &Path,
+        inherited_match: Option<String>,
+        depth: usize,
+    ) -> Result<()> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(anyhow!("Include nesting too deep at {}", path.display()));
+        }
+        let content =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        self.files.push(path.to_path_buf());
+
+        let mut match_block = inherited_match.clone();
+        for (index, line) in content.lines().enumerate() {
+            let Some((keyword, value)) = split_directive(line) else {
+                continue;
+            };
+
+            match keyword.as_str() {
+                "match" => {
+                    // "Match all" returns to settings that apply to everyone
+                    match_block = if value.eq_ignore_ascii_case("all") {
+                        inherited_match.clone()
+                    } else {
+                        Some(value)
+                    };
+                }
+                "include" => {
+                    for pattern in value.split_whitespace() {
+                        let pattern = unquote(pattern);
+                        let pattern = if Path::new(&pattern).is_absolute() {
+                            pattern
+                        } else {
+                            base.join(&pattern).to_string_lossy().to_string()
+                        };
+                        let mut matches: Vec<PathBuf> = glob::glob(&pattern)
+                            .map(|paths| paths.flatten().collect())
+                            .unwrap_or_default();
+                        matches.sort();
+                        for included in matches {
+                            self.parse_file(&included, base, match_block.clone(), depth + 1)?;
+                        }
+                    }
+                }
+                _ => self.directives.push(Directive {
+                    keyword,
+                    value,
+                    file: path.to_path_buf(),
+                    line: index + 1,
+                    match_block: match_block.clone(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Value sshd uses for connections not matched by any `Match` block
+    ///
+    /// For most keywords the first value obtained wins.
+    pub fn effective(&self, keyword: &str) -> Option<&Directive> {
+        let keyword = keyword.to_lowercase();
+        self.directives
+            .iter()
+            .find(|d| d.match_block.is_none() && d.keyword == keyword)
+    }
+
+    /// `Match` blocks that override a keyword for some connections
+    pub fn match_overrides(&self, keyword: &str) -> Vec<&Directive> {
+        let keyword = keyword.to_lowercase();
+        self.directives
+            .iter()
+            .filter(|d| d.match_block.is_some() && d.keyword == keyword)
+            .collect()
+    }
+}
+
+/// Split a config line into a lowercase keyword and its value
+fn split_directive(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let split_at = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let keyword = line[..split_at].to_lowercase();
+    let value = line[split_at..]
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '=')
+        .trim();
+    Some((keyword, value.to_string()))
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches('"').to_string()
+}
+
+/// A hardening setting, its OpenSSH default and the value we recommend
+struct Setting {
+    keyword: &'static str,
+    /// Older spelling sshd still accepts for the same option
+    alias: Option<&'static str>,
+    default: &'static str,
+    recommended: &'static str,
+    is_hardened: fn(&str) -> bool,
+}
+
+const WEAK_KEX: &[&str] = &["sha1", "group1-"];
+const WEAK_CIPHERS: &[&str] = &["cbc", "3des", "arcfour", "rc4", "blowfish", "cast128"];
+const WEAK_MACS: &[&str] = &["md5", "sha1", "umac-64", "-96", "ripemd"];
+
+fn none_weak(value: &str, weak: &[&str]) -> bool {
+    // Leading +, - or ^ modify the default list rather than replace it
+    let value = value.trim_start_matches(['+', '-', '^']);
+    if value.is_empty() {
+        return true;
+    }
+    value
+        .split(',')
+        .all(|algorithm| !weak.iter().any(|w| algorithm.to_lowercase().contains(w)))
+}
+
+/// `LoginGraceTime` style durations such as `60`, `2m` or `1m30s`
+fn parse_seconds(value: &str) -> Option<u64> {
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: u64 = number.parse().ok()?;
+        number.clear();
+        total += n * match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+    }
+    if !number.is_empty() {
+        total += number.parse::<u64>().ok()?;
+    }
+    Some(total)
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        keyword: "PermitRootLogin",
+        alias: None,
+        default: "prohibit-password",
+        recommended: "no",
+        is_hardened: |v| v.eq_ignore_ascii_case("no"),
+    },
+    Setting {
+        keyword: "PasswordAuthentication",
+        alias: None,
+        default: "yes",
+        recommended: "no",
+        is_hardened: |v| v.eq_ignore_ascii_case("no"),
+    },
+    Setting {
+        keyword: "PermitEmptyPasswords",
+        alias: None,
+        default: "no",
+        recommended: "no",
+        is_hardened: |v| v.eq_ignore_ascii_case("no"),
+    },
+    Setting {
+        keyword: "KbdInteractiveAuthentication",
+        alias: Some("ChallengeResponseAuthentication"),
+        default: "yes",
+        recommended: "no",
+        is_hardened: |v| v.eq_ignore_ascii_case("no"),
+    },
+    Setting {
+        keyword: "X11Forwarding",
+        alias: None,
+        default: "no",
+        recommended: "no",
+        is_hardened: |v| v.eq_ignore_ascii_case("no"),
+    },
+    Setting {
+        keyword: "MaxAuthTries",
+        alias: None,
+        default: "6",
+        recommended: "4",
+        is_hardened: |v| v.parse::<u32>().map(|n| n <= 4).unwrap_or(false),
+    },
+    Setting {
+        keyword: "LoginGraceTime",
+        alias: None,
+        default: "120",
+        recommended: "60",
+        is_hardened: |v| parse_seconds(v).map(|s| s > 0 && s <= 60).unwrap_or(false),
+    },
+    Setting {
+        keyword: "ClientAliveInterval",
+        alias: None,
+        default: "0",
+        recommended: "300",
+        is_hardened: |v| parse_seconds(v).map(|s| s > 0 && s <= 300).unwrap_or(false),
+    },
+    Setting {
+        keyword: "KexAlgorithms",
+        alias: None,
+        default: "",
+        recommended: "curve25519-sha256,curve25519-sha256@libssh.org,diffie-hellman-group16-sha512,diffie-hellman-group18-sha512,diffie-hellman-group-exchange-sha256",
+        is_hardened: |v| none_weak(v, WEAK_KEX),
+    },
+    Setting {
+        keyword: "Ciphers",
+        alias: None,
+        default: "",
+        recommended: "chacha20-poly1305@openssh.com,aes256-gcm@openssh.com,aes128-gcm@openssh.com,aes256-ctr,aes192-ctr,aes128-ctr",
+        is_hardened: |v| none_weak(v, WEAK_CIPHERS),
+    },
+    Setting {
+        keyword: "MACs",
+        alias: None,
+        default: "",
+        recommended: "hmac-sha2-512-etm@openssh.com,hmac-sha2-256-etm@openssh.com,umac-128-etm@openssh.com",
+        is_hardened: |v| none_weak(v, WEAK_MACS),
+    },
+];
+
+/// Effective state of one hardening setting
+#[derive(Debug, Clone)]
+pub struct SettingStatus {
+    pub keyword: String,
+    pub value: String,
+    /// Where the value came from, `None` when sshd falls back to its default
+    pub source: Option<(PathBuf, usize)>,
+    pub recommended: String,
+    pub hardened: bool,
+    /// `Match` blocks that set a different value for some connections
+    pub overrides: Vec<String>,
+}
+
+pub fn assess(config: &SshdConfig) -> Vec<SettingStatus> {
+    SETTINGS
+        .iter()
+        .map(|setting| {
+            let directive = config
+                .effective(setting.keyword)
+                .or_else(|| setting.alias.and_then(|alias| config.effective(alias)));
+            let (value, source) = match directive {
+                Some(d) => (d.value.clone(), Some((d.file.clone(), d.line))),
+                None => (setting.default.to_string(), None),
+            };
+            let overrides = config
+                .match_overrides(setting.keyword)
+                .into_iter()
+                .map(|d| {
+                    format!(
+                        "Match {}: {} ({}:{})",
+                        d.match_block.as_deref().unwrap_or(""),
+                        d.value,
+                        d.file.display(),
+                        d.line
+                    )
+                })
+                .collect();
+
+            SettingStatus {
+                keyword: setting.keyword.to_string(),
+                hardened: (setting.is_hardened)(&value),
+                value,
+                source,
+                recommended: setting.recommended.to_string(),
+                overrides,
+            }
+        })
+        .collect()
+}
+
+pub fn status_text(statuses: &[SettingStatus], files: &[PathBuf]) -> String {
+    let mut lines = vec![
+        format!(
+            "Files read: {}",
+            files
+                .iter()
+                .map(|f| f.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        String::new(),
+    ];
+
+    for status in statuses {
+        let marker = if status.hardened { "✅" } else { "❌" };
+        let source = match &status.source {
+            Some((file, line)) => format!("{}:{}", file.display(), line),
+            None => "default".to_string(),
+        };
+        let value = if status.value.is_empty() {
+            "(OpenSSH default)"
+        } else {
+            &status.value
+        };
+        lines.push(format!("{} {} {}", marker, status.keyword, value));
+        lines.push(format!("    Source: {}", source));
+        if !status.hardened {
+            lines.push(format!("    Recommended: {}", status.recommended));
+        }
+        for o in &status.overrides {
+            lines.push(format!("⚠️   Overridden in {}", o));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Build a hardened copy of the main config file
+///
+/// Recommended values go into a managed block at the top of the file, since
+/// sshd keeps the first value it reads; conflicting global lines in the main
+/// file are commented out. Returns `None` when nothing needs to change.
+pub fn propose(content: &str, statuses: &[SettingStatus]) -> Option<String> {
+    let changes: BTreeMap<String, String> = statuses
+        .iter()
+        .filter(|s| !s.hardened)
+        .map(|s| (s.keyword.clone(), s.recommended.clone()))
+        .collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    // Keep settings from an earlier managed block and strip it out
+    let mut managed: BTreeMap<String, String> = BTreeMap::new();
+    let mut body = Vec::new();
+    let mut in_block = false;
+    for line in content.lines() {
+        if line == BLOCK_START {
+            in_block = true;
+        } else if line == BLOCK_END {
+            in_block = false;
+        } else if in_block {
+            if let Some((keyword, value)) = split_directive(line) {
+                let keyword = SETTINGS
+                    .iter()
+                    .find(|s| s.keyword.eq_ignore_ascii_case(&keyword))
+                    .map(|s| s.keyword.to_string())
+                    .unwrap_or(keyword);
+                managed.insert(keyword, value);
+            }
+        } else {
+            body.push(line.to_string());
+        }
+    }
+    managed.extend(changes);
+
+    let lowercase: Vec<String> = managed.keys().map(|k| k.to_lowercase()).collect();
+    let mut in_match = false;
+    for line in body.iter_mut() {
+        if let Some((keyword, _)) = split_directive(line) {
+            if keyword == "match" {
+                in_match = true;
+            } else if !in_match && lowercase.contains(&keyword) {
+                *line = format!("#{}", line);
+            }
+        }
+    }
+
+    let mut candidate = vec![BLOCK_START.to_string()];
+    candidate.extend(managed.iter().map(|(k, v)| format!("{} {}", k, v)));
+    candidate.push(BLOCK_END.to_string());
+    candidate.extend(body);
+    Some(candidate.join("\n") + "\n")
+}
+
+pub fn unified_diff(old: &str, new: &str, path: &Path) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &path.display().to_string(),
+            &format!("{} (proposed)", path.display()),
+        )
+        .to_string()
+}
+
+fn sshd_binary() -> Result<PathBuf> {
+    which::which("sshd")
+        .ok()
+        .or_else(|| {
+            ["/usr/sbin/sshd", "/usr/local/sbin/sshd", "/sbin/sshd"]
+                .iter()
+                .map(PathBuf::from)
+                .find(|p| p.exists())
+        })
+        .ok_or_else(|| anyhow!("sshd is not installed, install the OpenSSH server first"))
+}
+
+/// Run `sshd -t` against candidate content before it replaces the real file
+pub fn validate(main_path: &Path, content: &str) -> Result<()> {
+    let dir = main_path.parent().unwrap_or(Path::new("/etc/ssh"));
+    let candidate = dir.join(".sshd_config.linux-toolkit.tmp");
+    fs::write(&candidate, content)?;
+
+    let invocation = Invocation::new(
+        sshd_binary()?.to_string_lossy(),
+        vec![
+            "-t".to_string(),
+            "-f".to_string(),
+            candidate.to_string_lossy().to_string(),
+        ],
+    );
+    let output = executor::current().capture(&invocation);
+    let _ = fs::remove_file(&candidate);
+    let output = output?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "sshd -t rejected the configuration: {}",
+            output.stderr.trim()
+        ))
+    }
+}
+
+fn backups_dir(config: &Config) -> PathBuf {
+    config.directories.data_dir.join("sshd-backups")
+}
+
+fn reload_sshd() {
+    for service in ["ssh", "sshd"] {
+        let invocation =
+            Invocation::new("systemctl", vec!["reload".to_string(), service.to_string()]);
+        let reloaded = executor::current()
+            .capture(&invocation)
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if reloaded {
+            println!("🔄 Reloaded {}", service);
+            return;
+        }
+    }
+    println!("⚠️  Could not reload the SSH service, please restart it manually.");
+}
+
+/// Non-root accounts that can log in with a key sshd still accepts
+fn key_login_accounts() -> Vec<String> {
+    let accounts: Vec<crate::utils::Account> = crate::utils::accounts_with_homes()
+        .into_iter()
+        .filter(|account| account.uid != 0 && account.can_log_in())
+        .collect();
+    let mut names: Vec<String> = crate::sshkeys::authorized_keys(&accounts)
+        .into_iter()
+        .filter(|key| !key.key_type.starts_with("ssh-dss"))
+        .map(|key| key.account)
+        .collect();
+    names.dedup();
+    names
+}
+
+/// Why applying `statuses` could lock everyone out, if it could
+///
+/// Turning off password and root login is only safe once some other
+/// account can get in with a key.
+fn lockout_risk(statuses: &[SettingStatus], key_accounts: &[String]) -> Option<String> {
+    let disabled: Vec<&str> = statuses
+        .iter()
+        .filter(|s| !s.hardened)
+        .filter(|s| s.keyword == "PasswordAuthentication" || s.keyword == "PermitRootLogin")
+        .map(|s| s.keyword.as_str())
+        .collect();
+    if disabled.is_empty() || !key_accounts.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "No non-root account with a login shell has a usable authorized key, \
+         changing {} could lock you out of this host",
+        disabled.join(" and ")
+    ))
+}
+
+/// Show the proposed diff, validate it and write it after confirmation
+pub fn harden(config: &Config, assume_yes: bool) -> Result<()> {
+    let main_path = Path::new(SSHD_CONFIG);
+    sshd_binary()?;
+    let content = fs::read_to_string(main_path)?;
+    let parsed = SshdConfig::load(main_path)?;
+    let statuses = assess(&parsed);
+
+    let Some(candidate) = propose(&content, &statuses) else {
+        println!("✅ sshd is already hardened, nothing to change.");
+        return Ok(());
+    };
+
+    println!("{}", unified_diff(&content, &candidate, main_path));
+    for status in statuses.iter().filter(|s| !s.overrides.is_empty()) {
+        println!(
+            "⚠️  {} is also set in Match blocks, review them manually",
+            status.keyword
+        );
+    }
+
+    validate(main_path, &candidate)?;
+    println!("✅ sshd -t accepted the proposed configuration");
+
+    if let Some(risk) = lockout_risk(&statuses, &key_login_accounts()) {
+        println!("⚠️  {}", risk);
+        if assume_yes {
+            return Err(anyhow!(
+                "Refusing to harden without key-based access, add a key for a \
+                 non-root user or rerun without --yes to confirm"
+            ));
+        }
+        crate::utils::require_confirmation("Continue without key-based access?")?;
+    }
+
+    if !assume_yes {
+        crate::utils::require_confirmation("Write the new sshd_config?")?;
+    }
+
+    let dir = backups_dir(config);
+    fs::create_dir_all(&dir)?;
+    let backup = dir.join(format!(
+        "sshd_config.{}",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&backup)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .with_context(|| format!("Failed to write {}", backup.display()))?;
+    println!("📦 Backup created: {}", backup.display());
+
+    crate::utils::replace_file(main_path, &candidate)?;
+    reload_sshd();
+    println!("✅ SSH configuration hardened");
+    Ok(())
+}
+
+/// Restore the most recent backup taken by `harden`
+pub fn rollback(config: &Config, assume_yes: bool) -> Result<()> {
+    let main_path = Path::new(SSHD_CONFIG);
+    let mut backups: Vec<PathBuf> = fs::read_dir(backups_dir(config))
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    backups.sort();
+    let backup = backups
+        .pop()
+        .ok_or_else(|| anyhow!("No sshd_config backups found"))?;
+
+    let previous = fs::read_to_string(&backup)?;
+    let current = fs::read_to_string(main_path).unwrap_or_default();
+    println!("{}", unified_diff(&current, &previous, main_path));
+
+    validate(main_path, &previous)?;
+    if !assume_yes {
+        crate::utils::require_confirmation(&format!("Restore {}?", backup.display()))?;
+    }
+
+    crate::utils::replace_file(main_path, &previous)?;
+    fs::remove_file(&backup)?;
+    reload_sshd();
+    println!("✅ Restored sshd_config from {}", backup.display());
+    Ok(())
+}
+
+/// Effective hardening settings for the TUI details panel
+pub fn run_native() -> Result<String> {
+    if !Path::new(SSHD_CONFIG).exists() {
+        return Ok(format!(
+            "⚠️  {} not present, sshd not installed",
+            SSHD_CONFIG
+        ));
+    }
+    let parsed = SshdConfig::load(Path::new(SSHD_CONFIG))?;
+    Ok(status_text(&assess(&parsed), &parsed.files))
+}
+
+/// Handler for `linux-toolkit ssh <action>`
+pub fn ssh_command(action: &str, config: &Config, assume_yes: bool) -> Result<()> {
+    match action {
+        "status" => {
+            println!("{}", run_native()?);
+            Ok(())
+        }
+        "harden" => harden(config, assume_yes),
+        "rollback" => rollback(config, assume_yes),
+        other => Err(anyhow!("Unknown ssh action '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn follows_includes_and_tracks_match_blocks() {
+        let dir = TempDir::new("sshd");
+        fs::create_dir_all(dir.join("sshd_config.d")).unwrap();
+        fs::write(
+            dir.join("sshd_config"),
+            "Include sshd_config.d/*.conf\n\
+             PermitRootLogin yes\n\
+             Match User backup\n\
+             \tPasswordAuthentication yes\n\
+             Match all\n\
+             X11Forwarding yes\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("sshd_config.d/10-first.conf"),
+            "permitrootlogin=no\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("sshd_config.d/20-second.conf"),
+            "PermitRootLogin prohibit-password\nMatch Address 10.0.0.0/8\nMaxAuthTries 10\n",
+        )
+        .unwrap();
+
+        let parsed = SshdConfig::load(&dir.join("sshd_config")).unwrap();
+
+        assert_eq!(parsed.files.len(), 3);
+        assert!(parsed.files[1].ends_with("10-first.conf"));
+        // The first value read wins, even from an included file
+        let root = parsed.effective("PermitRootLogin").unwrap();
+        assert_eq!(root.value, "no");
+        assert!(root.file.ends_with("10-first.conf"));
+        // A Match block opened in an included file ends with that file
+        assert_eq!(
+            parsed.match_overrides("MaxAuthTries")[0]
+                .match_block
+                .as_deref(),
+            Some("Address 10.0.0.0/8")
+        );
+        assert!(parsed.effective("MaxAuthTries").is_none());
+        assert_eq!(
+            parsed.match_overrides("passwordauthentication")[0]
+                .match_block
+                .as_deref(),
+            Some("User backup")
+        );
+        assert_eq!(parsed.effective("X11Forwarding").unwrap().value, "yes");
+    }
+
+    fn status(keyword: &str, hardened: bool, recommended: &str) -> SettingStatus {
+        SettingStatus {
+            keyword: keyword.to_string(),
+            value: String::new(),
+            source: None,
+            recommended: recommended.to_string(),
+            hardened,
+            overrides: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn proposal_prepends_a_managed_block_and_comments_out_conflicts() {
+        let content = "# --- linux-toolkit hardening (managed) ---\n\
+                       MaxAuthTries 4\n\
+                       # --- end linux-toolkit hardening ---\n\
+                       Port 22\n\
+                       PermitRootLogin yes\n\
+                       Match User backup\n\
+                       \tPermitRootLogin yes\n";
+        let statuses = [
+            status("PermitRootLogin", false, "no"),
+            status("X11Forwarding", true, "no"),
+        ];
+
+        let proposed = propose(content, &statuses).unwrap();
+        assert_eq!(
+            proposed,
+            "# --- linux-toolkit hardening (managed) ---\n\
+             MaxAuthTries 4\n\
+             PermitRootLogin no\n\
+             # --- end linux-toolkit hardening ---\n\
+             Port 22\n\
+             #PermitRootLogin yes\n\
+             Match User backup\n\
+             \tPermitRootLogin yes\n"
+        );
+        assert!(propose(content, &[status("PermitRootLogin", true, "no")]).is_none());
+    }
+
+    #[test]
+    fn warns_before_disabling_passwords_without_key_access() {
+        let statuses = [
+            status("PasswordAuthentication", false, "no"),
+            status("PermitRootLogin", false, "no"),
+        ];
+        let risk = lockout_risk(&statuses, &[]).unwrap();
+        assert!(risk.contains("PasswordAuthentication and PermitRootLogin"));
+        assert!(lockout_risk(&statuses, &["alice".to_string()]).is_none());
+        assert!(lockout_risk(&[status("X11Forwarding", false, "no")], &[]).is_none());
+    }
+}
@@ -0,0 +1,677 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audit::PrivilegedRun;
+use crate::baseline::{CheckResult, CheckStatus};
+use crate::config::Config;
+use crate::report::{ExecutionRecord, Report};
+use crate::utils::Account;
+
+/// Key files read from each account's `~/.ssh`
+const AUTHORIZED_FILES: &[&str] = &["authorized_keys", "authorized_keys2"];
+const SYSTEM_KNOWN_HOSTS: &str = "/etc/ssh/ssh_known_hosts";
+/// RSA keys shorter than this are flagged
+const MIN_RSA_BITS: u32 = 2048;
+/// Prefix written in front of a disabled authorized_keys line
+const DISABLED_PREFIX: &str = "# disabled by linux-toolkit";
+
+/// Which file an entry was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFile {
+    AuthorizedKeys,
+    KnownHosts,
+}
+
+/// One public key line
+#[derive(Debug, Clone)]
+pub struct KeyEntry {
+    pub account: String,
+    pub file: KeyFile,
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// authorized_keys options such as `from="10.0.0.0/8"` or `command="..."`
+    pub options: Vec<String>,
+    /// known_hosts host patterns, `(hashed)` when HashKnownHosts is in use
+    pub hosts: Option<String>,
+    pub key_type: String,
+    pub fingerprint: String,
+    pub bits: Option<u32>,
+    pub comment: String,
+    pub flags: Vec<String>,
+}
+
+/// A key line split into its fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParsedLine {
+    marker: Option<String>,
+    hosts: Option<String>,
+    options: Vec<String>,
+    key_type: String,
+    blob: Vec<u8>,
+    comment: String,
+}
+
+fn is_key_type(token: &str) -> bool {
+    token.starts_with("ssh-") || token.starts_with("ecdsa-") || token.starts_with("sk-")
+}
+
+/// Fingerprint in the `SHA256:...` form printed by `ssh-keygen -l`
+pub fn fingerprint(blob: &[u8]) -> String {
+    // OpenSSH prints the hash without padding
+    let encoded = crate::utils::base64_encode(&Sha256::digest(blob));
+    format!("SHA256:{}", encoded.trim_end_matches('='))
+}
+
+/// Reader for the length-prefixed fields of an SSH public key blob
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let len = u32::from_be_bytes(self.0.get(..4)?.try_into().ok()?) as usize;
+        let field = self.0.get(4..4 + len)?;
+        self.0 = &self.0[4 + len..];
+        Some(field)
+    }
+}
+
+fn mpint_bits(value: &[u8]) -> u32 {
+    let value: Vec<u8> = value.iter().copied().skip_while(|&b| b == 0).collect();
+    match value.first() {
+        Some(first) => (value.len() as u32 - 1) * 8 + (8 - first.leading_zeros()),
+        None => 0,
+    }
+}
+
+/// Key size in bits, read from the blob
+fn key_bits(blob: &[u8]) -> Option<u32> {
+    let mut fields = Fields(blob);
+    let key_type = std::str::from_utf8(fields.next()?).ok()?;
+    let base = match key_type.strip_suffix("-cert-v01@openssh.com") {
+        Some(base) => {
+            fields.next()?; // nonce
+            base
+        }
+        None => key_type,
+    };
+    match base {
+        "ssh-rsa" => {
+            fields.next()?; // e
+            Some(mpint_bits(fields.next()?))
+        }
+        "ssh-dss" => Some(mpint_bits(fields.next()?)),
+        "ssh-ed25519" | "sk-ssh-ed25519@openssh.com" => Some(256),
+        _ if base.starts_with("ecdsa-sha2-") || base.starts_with("sk-ecdsa-sha2-") => {
+            let curve = std::str::from_utf8(fields.next()?).ok()?;
+            curve.strip_prefix("nistp")?.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Split `token rest` at the first whitespace outside double quotes
+fn split_quoted(line: &str) -> (&str, &str) {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => return (&line[..i], line[i..].trim_start()),
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+/// Comma separated authorized_keys options, keeping commas inside quotes
+fn split_options(options: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in options.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+/// Parse a line from authorized_keys or known_hosts, `None` for comments and junk
+fn parse_line(line: &str, file: KeyFile) -> Option<ParsedLine> {
+    let mut rest = line.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        return None;
+    }
+
+    let mut marker = None;
+    let mut hosts = None;
+    let mut options = Vec::new();
+    match file {
+        KeyFile::KnownHosts => {
+            if rest.starts_with('@') {
+                let (token, tail) = split_quoted(rest);
+                marker = Some(token.to_string());
+                rest = tail;
+            }
+            let (token, tail) = split_quoted(rest);
+            hosts = Some(if token.starts_with("|1|") {
+                "(hashed)".to_string()
+            } else {
+                token.to_string()
+            });
+            rest = tail;
+        }
+        KeyFile::AuthorizedKeys => {
+            let (token, tail) = split_quoted(rest);
+            if !is_key_type(token) {
+                options = split_options(token);
+                rest = tail;
+            }
+        }
+    }
+
+    let (key_type, tail) = split_quoted(rest);
+    let (encoded, comment) = split_quoted(tail);
+    if !is_key_type(key_type) {
+        return None;
+    }
+    Some(ParsedLine {
+        marker,
+        hosts,
+        options,
+        key_type: key_type.to_string(),
+        blob: crate::utils::base64_decode(encoded)?,
+        comment: comment.trim().to_string(),
+    })
+}
+
+/// Why a key type or size is too weak, if it is
+fn weakness(key_type: &str, bits: Option<u32>) -> Option<String> {
+    if key_type.starts_with("ssh-dss") {
+        return Some("DSA key, deprecated and disabled in current OpenSSH".to_string());
+    }
+    match bits {
+        Some(bits) if key_type.starts_with("ssh-rsa") && bits < MIN_RSA_BITS => Some(format!(
+            "RSA key of {} bits, use at least {}",
+            bits, MIN_RSA_BITS
+        )),
+        _ => None,
+    }
+}
+
+/// Entries of one key file, with weakness and missing comment flags
+fn read_file(account: &str, path: &Path, file: KeyFile) -> Vec<KeyEntry> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let parsed = parse_line(line, file)?;
+            let bits = key_bits(&parsed.blob);
+            let mut flags = Vec::new();
+            if let Some(reason) = weakness(&parsed.key_type, bits) {
+                flags.push(reason);
+            }
+            if let Some(marker) = &parsed.marker {
+                flags.push(format!("marked {}", marker));
+            }
+            if file == KeyFile::AuthorizedKeys && parsed.comment.is_empty() {
+                flags.push("no comment, the owner of this key is unknown".to_string());
+            }
+            Some(KeyEntry {
+                account: account.to_string(),
+                file,
+                path: path.to_path_buf(),
+                line: index + 1,
+                options: parsed.options,
+                hosts: parsed.hosts,
+                key_type: parsed.key_type,
+                fingerprint: fingerprint(&parsed.blob),
+                bits,
+                comment: parsed.comment,
+                flags,
+            })
+        })
+        .collect()
+}
+
+/// Flag authorized keys that appear more than once, in one account or several
+fn flag_duplicates(entries: &mut [KeyEntry]) {
+    let mut by_fingerprint: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.file == KeyFile::AuthorizedKeys {
+            by_fingerprint
+                .entry(entry.fingerprint.clone())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    for indexes in by_fingerprint.values().filter(|indexes| indexes.len() > 1) {
+        for &index in indexes {
+            let others: Vec<String> = indexes
+                .iter()
+                .filter(|&&other| other != index)
+                .map(|&other| &entries[other])
+                .map(|other| {
+                    if other.account == entries[index].account {
+                        format!("line {} of {}", other.line, other.path.display())
+                    } else {
+                        other.account.clone()
+                    }
+                })
+                .collect();
+            entries[index].flags.push(format!(
+                "duplicate, also authorized for {}",
+                others.join(", ")
+            ));
+        }
+    }
+}
+
+/// Keys in the authorized_keys files of `accounts`
+pub fn authorized_keys(accounts: &[Account]) -> Vec<KeyEntry> {
+    accounts
+        .iter()
+        .flat_map(|account| {
+            AUTHORIZED_FILES.iter().flat_map(|name| {
+                read_file(
+                    &account.name,
+                    &account.home.join(".ssh").join(name),
+                    KeyFile::AuthorizedKeys,
+                )
+            })
+        })
+        .collect()
+}
+
+/// Every authorized key and known host of `accounts`, plus the system known_hosts
+pub fn inventory(accounts: &[Account], system_known_hosts: &Path) -> Vec<KeyEntry> {
+    let mut entries = authorized_keys(accounts);
+    for account in accounts {
+        entries.extend(read_file(
+            &account.name,
+            &account.home.join(".ssh/known_hosts"),
+            KeyFile::KnownHosts,
+        ));
+    }
+    entries.extend(read_file(
+        "(system)",
+        system_known_hosts,
+        KeyFile::KnownHosts,
+    ));
+    flag_duplicates(&mut entries);
+    entries
+}
+
+fn disable_command(entry: &KeyEntry) -> String {
+    format!(
+        "linux-toolkit ssh-keys disable {} --user {}",
+        entry.fingerprint, entry.account
+    )
+}
+
+fn describe(entry: &KeyEntry) -> String {
+    let bits = entry
+        .bits
+        .map(|bits| format!(" {}", bits))
+        .unwrap_or_default();
+    let label = match (&entry.hosts, entry.comment.is_empty()) {
+        (Some(hosts), _) => hosts.clone(),
+        (None, true) => "(no comment)".to_string(),
+        (None, false) => entry.comment.clone(),
+    };
+    format!("{}{} {} {}", entry.key_type, bits, entry.fingerprint, label)
+}
+
+/// Inventory grouped by account, flagged keys first within each file
+pub fn render(entries: &[KeyEntry]) -> String {
+    if entries.is_empty() {
+        return "No authorized_keys or known_hosts files found".to_string();
+    }
+
+    let mut out = Vec::new();
+    let flagged = entries.iter().filter(|e| !e.flags.is_empty()).count();
+    let authorized = entries
+        .iter()
+        .filter(|e| e.file == KeyFile::AuthorizedKeys)
+        .count();
+    out.push(format!(
+        "SSH keys: {} authorized, {} known hosts, {} flagged",
+        authorized,
+        entries.len() - authorized,
+        flagged
+    ));
+
+    let mut by_path: BTreeMap<(&str, &Path), Vec<&KeyEntry>> = BTreeMap::new();
+    for entry in entries {
+        by_path
+            .entry((&entry.account, &entry.path))
+            .or_default()
+            .push(entry);
+    }
+
+    for ((account, path), mut keys) in by_path {
+        keys.sort_by_key(|key| (key.flags.is_empty(), key.line));
+        out.push(String::new());
+        out.push(format!("{} — {}", account, path.display()));
+        for key in keys {
+            let marker = if key.flags.is_empty() {
+                "✅"
+            } else {
+                "⚠️ "
+            };
+            out.push(format!("{} {}", marker, describe(key)));
+            if !key.options.is_empty() {
+                out.push(format!("     options: {}", key.options.join(",")));
+            }
+            for flag in &key.flags {
+                out.push(format!("     - {}", flag));
+            }
+            if !key.flags.is_empty() && key.file == KeyFile::AuthorizedKeys {
+                out.push(format!("     → {}", disable_command(key)));
+            }
+        }
+    }
+    out.join("\n")
+}
+
+fn scan() -> Vec<KeyEntry> {
+    inventory(
+        &crate::utils::accounts_with_homes(),
+        Path::new(SYSTEM_KNOWN_HOSTS),
+    )
+}
+
+/// Key inventory for the TUI details panel
+pub fn run_native() -> Result<String> {
+    Ok(render(&scan()))
+}
+
+fn backups_dir(config: &Config) -> PathBuf {
+    config.directories.data_dir.join("ssh-key-backups")
+}
+
+/// Comment out the lines of `content` that hold a key with `fingerprint`
+///
+/// Returns the new content and the 1-based numbers of the lines changed.
+fn disable_in(content: &str, fingerprint: &str, date: &str) -> (String, Vec<usize>) {
+    let mut changed = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let matches = parse_line(line, KeyFile::AuthorizedKeys)
+            .is_some_and(|parsed| self::fingerprint(&parsed.blob) == fingerprint);
+        if matches {
+            changed.push(index + 1);
+            lines.push(format!("{} {}: {}", DISABLED_PREFIX, date, line));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    (new_content, changed)
+}
+
+/// Comment out an authorized key after backing up the file
+pub fn disable(
+    fingerprint: &str,
+    user: Option<&str>,
+    config: &Config,
+    assume_yes: bool,
+) -> Result<()> {
+    let entries = scan();
+    let mut matches: Vec<&KeyEntry> = entries
+        .iter()
+        .filter(|e| e.file == KeyFile::AuthorizedKeys && e.fingerprint == fingerprint)
+        .filter(|e| user.is_none_or(|user| e.account == user))
+        .collect();
+    matches.dedup_by_key(|e| &e.path);
+
+    let entry = match matches.as_slice() {
+        [] => {
+            return Err(anyhow!(
+                "No authorized key with fingerprint {}{}",
+                fingerprint,
+                user.map(|user| format!(" for {}", user))
+                    .unwrap_or_default()
+            ))
+        }
+        [entry] => *entry,
+        several => {
+            let accounts: Vec<String> = several
+                .iter()
+                .map(|e| format!("{} ({})", e.account, e.path.display()))
+                .collect();
+            return Err(anyhow!(
+                "Key is authorized in several files, pick one with --user: {}",
+                accounts.join(", ")
+            ));
+        }
+    };
+
+    let content = fs::read_to_string(&entry.path)
+        .with_context(|| format!("Failed to read {}", entry.path.display()))?;
+    let (new_content, changed) = disable_in(
+        &content,
+        fingerprint,
+        &Utc::now().format("%Y-%m-%d").to_string(),
+    );
+    println!(
+        "{}",
+        crate::sshd::unified_diff(&content, &new_content, &entry.path)
+    );
+
+    if !assume_yes {
+        crate::utils::require_confirmation(&format!(
+            "Disable {} for {}?",
+            describe(entry),
+            entry.account
+        ))?;
+    }
+
+    let dir = backups_dir(config);
+    fs::create_dir_all(&dir)?;
+    let file_name = entry
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let backup = dir.join(format!(
+        "{}.{}.{}",
+        entry.account,
+        file_name,
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&backup, &content)?;
+    println!("📦 Backup created: {}", backup.display());
+
+    let argv = vec![
+        crate::utils::toolkit_exe(),
+        "ssh-keys".to_string(),
+        "disable".to_string(),
+        fingerprint.to_string(),
+        "--user".to_string(),
+        entry.account.clone(),
+    ];
+    let audit = PrivilegedRun::begin("ssh-keys/disable", &argv, None, None);
+    let result = crate::utils::replace_file(&entry.path, &new_content)
+        .with_context(|| format!("Failed to write {}", entry.path.display()));
+    if let Some(audit) = audit {
+        audit.finish(config, Some(if result.is_ok() { 0 } else { 1 }));
+    }
+    result?;
+
+    let lines: Vec<String> = changed.iter().map(|line| line.to_string()).collect();
+    println!(
+        "✅ Disabled line {} of {}",
+        lines.join(", "),
+        entry.path.display()
+    );
+    Ok(())
+}
+
+/// Handler for `linux-toolkit ssh-keys <action>`
+pub fn ssh_keys_command(
+    action: &str,
+    fingerprint: Option<&str>,
+    user: Option<&str>,
+    config: &Config,
+    assume_yes: bool,
+    report_path: Option<&Path>,
+) -> Result<()> {
+    match action {
+        "list" => {
+            let started_at = Utc::now();
+            let entries = scan();
+            let text = render(&entries);
+            println!("{}", text);
+
+            if let Some(path) = report_path {
+                let mut report = Report::new("SSH Key Inventory").with_execution(ExecutionRecord {
+                    command_id: "network/ssh-key-inventory".to_string(),
+                    command_name: "SSH Key Inventory".to_string(),
+                    started_at,
+                    success: Some(true),
+                    output: text,
+                });
+                report.findings = entries
+                    .iter()
+                    .filter(|entry| !entry.flags.is_empty())
+                    .map(|entry| CheckResult {
+                        id: format!("ssh-keys/{}", entry.fingerprint),
+                        title: format!("{}: {}", entry.account, describe(entry)),
+                        status: CheckStatus::Warn,
+                        evidence: entry.flags.clone(),
+                        remediation: "Replace weak keys, label keys with their owner and \
+                                      remove keys nobody can account for"
+                            .to_string(),
+                        remediation_command: (entry.file == KeyFile::AuthorizedKeys)
+                            .then(|| disable_command(entry)),
+                    })
+                    .collect();
+                report.write_to(path)?;
+                println!("Report written to {}", path.display());
+            }
+            Ok(())
+        }
+        "disable" => {
+            let fingerprint =
+                fingerprint.ok_or_else(|| anyhow!("Pass the fingerprint of the key to disable"))?;
+            disable(fingerprint, user, config, assume_yes)
+        }
+        other => Err(anyhow!("Unknown ssh-keys action '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const ED25519: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII4X/nNQ9t1L6s5QMYtDkanUIQ2r6vIOHqNIg5ymldxm";
+    const ED25519_FP: &str = "SHA256:T184J3OgKd2I9DyHDX128iYLRblJ2B6oG91hIojGhRA";
+    const RSA_1024: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAgQD0PtjH7ZT4gxb+05l0ESuQYEDc9IqQP0ryCA6HOIR+O6G+7s00UeKbPVJm/8sdPqxooEx6S4TxppP9g8oZpZjh44A4+sXm6tqDVU4LHU6AMmeRTNGgwyVi3ivuQHKUHqRn1cKe8stbTnF6ZI8e2uj9vAq2pD0eYrXA/D+E+G44mw==";
+    const RSA_1024_FP: &str = "SHA256:bpEumDcR02dAq4Kr7jWLChX79Xrahcy5WsNDzTbraTU";
+
+    #[test]
+    fn parses_options_fingerprints_and_sizes() {
+        let line = format!(
+            r#"from="10.0.0.0/8,192.168.1.1",command="echo \"hi, there\"" {} alice@laptop"#,
+            ED25519
+        );
+        let parsed = parse_line(&line, KeyFile::AuthorizedKeys).unwrap();
+        assert_eq!(
+            parsed.options,
+            vec![
+                r#"from="10.0.0.0/8,192.168.1.1""#.to_string(),
+                r#"command="echo \"hi, there\"""#.to_string(),
+            ]
+        );
+        assert_eq!(parsed.comment, "alice@laptop");
+        assert_eq!(fingerprint(&parsed.blob), ED25519_FP);
+        assert_eq!(key_bits(&parsed.blob), Some(256));
+
+        let rsa = parse_line(RSA_1024, KeyFile::AuthorizedKeys).unwrap();
+        assert_eq!(fingerprint(&rsa.blob), RSA_1024_FP);
+        assert_eq!(key_bits(&rsa.blob), Some(1024));
+        assert!(weakness(&rsa.key_type, Some(1024)).is_some());
+
+        let host = parse_line(
+            &format!("@cert-authority |1|abc=|def= {}", ED25519),
+            KeyFile::KnownHosts,
+        )
+        .unwrap();
+        assert_eq!(host.marker.as_deref(), Some("@cert-authority"));
+        assert_eq!(host.hosts.as_deref(), Some("(hashed)"));
+    }
+
+    #[test]
+    fn flags_weak_uncommented_and_duplicate_keys() {
+        let dir = TempDir::new("sshkeys");
+        let mut accounts = Vec::new();
+        for (name, keys) in [
+            ("alice", format!("{} alice@laptop\n{}\n", ED25519, RSA_1024)),
+            ("deploy", format!("no-pty {} shared\n", ED25519)),
+        ] {
+            let home = dir.join(name);
+            fs::create_dir_all(home.join(".ssh")).unwrap();
+            fs::write(home.join(".ssh/authorized_keys"), keys).unwrap();
+            accounts.push(Account {
+                name: name.to_string(),
+                uid: 1000,
+                home,
+                shell: "/bin/bash".to_string(),
+            });
+        }
+
+        let entries = inventory(&accounts, &dir.join("missing"));
+        assert_eq!(entries.len(), 3);
+        let rsa = entries.iter().find(|e| e.key_type == "ssh-rsa").unwrap();
+        assert_eq!(rsa.flags.len(), 2, "{:?}", rsa.flags);
+        let deploy = entries.iter().find(|e| e.account == "deploy").unwrap();
+        assert_eq!(deploy.options, vec!["no-pty".to_string()]);
+        assert_eq!(
+            deploy.flags,
+            vec!["duplicate, also authorized for alice".to_string()]
+        );
+    }
+
+    #[test]
+    fn disabling_comments_out_only_the_matching_key() {
+        let content = format!("# keys\n{} alice@laptop\n{}\n", ED25519, RSA_1024);
+        let (disabled, changed) = disable_in(&content, RSA_1024_FP, "2026-01-02");
+        assert_eq!(changed, vec![3]);
+        assert_eq!(
+            disabled,
+            format!(
+                "# keys\n{} alice@laptop\n# disabled by linux-toolkit 2026-01-02: {}\n",
+                ED25519, RSA_1024
+            )
+        );
+        assert!(disable_in(&disabled, RSA_1024_FP, "2026-01-02")
+            .1
+            .is_empty());
+    }
+}
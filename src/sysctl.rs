@@ -0,0 +1,473 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// Directories `systemd-sysctl` and `sysctl --system` read, lowest priority first
+const SYSCTL_DIRS: &[&str] = &[
+    "/usr/lib/sysctl.d",
+    "/lib/sysctl.d",
+    "/usr/local/lib/sysctl.d",
+    "/run/sysctl.d",
+    "/etc/sysctl.d",
+];
+
+/// CIS-style defaults applied unless overridden in `[sysctl]`
+const DEFAULT_PROFILE: &[(&str, &str)] = &[
+    ("kernel.randomize_va_space", "2"),
+    ("kernel.kptr_restrict", "2"),
+    ("kernel.dmesg_restrict", "1"),
+    ("kernel.yama.ptrace_scope", "1"),
+    ("kernel.unprivileged_bpf_disabled", "1"),
+    ("kernel.sysrq", "0"),
+    ("fs.protected_hardlinks", "1"),
+    ("fs.protected_symlinks", "1"),
+    ("fs.protected_fifos", "2"),
+    ("fs.protected_regular", "2"),
+    ("fs.suid_dumpable", "0"),
+    ("net.core.bpf_jit_harden", "2"),
+    ("net.ipv4.tcp_syncookies", "1"),
+    ("net.ipv4.conf.all.accept_redirects", "0"),
+    ("net.ipv4.conf.default.accept_redirects", "0"),
+    ("net.ipv4.conf.all.secure_redirects", "0"),
+    ("net.ipv4.conf.all.send_redirects", "0"),
+    ("net.ipv4.conf.all.accept_source_route", "0"),
+    ("net.ipv4.conf.all.rp_filter", "1"),
+    ("net.ipv4.conf.default.rp_filter", "1"),
+    ("net.ipv4.conf.all.log_martians", "1"),
+    ("net.ipv4.icmp_echo_ignore_broadcasts", "1"),
+    ("net.ipv4.icmp_ignore_bogus_error_responses", "1"),
+    ("net.ipv6.conf.all.accept_redirects", "0"),
+    ("net.ipv6.conf.default.accept_redirects", "0"),
+    ("net.ipv6.conf.all.accept_source_route", "0"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftStatus {
+    /// Running value matches the profile
+    Ok,
+    /// Running value differs from the profile
+    Drift,
+    /// The key does not exist on this kernel
+    Unavailable,
+}
+
+#[derive(Debug, Clone)]
+pub struct SysctlDrift {
+    pub key: String,
+    pub expected: String,
+    pub effective: Option<String>,
+    /// Value persisted in a sysctl.d file and the file it came from
+    pub configured: Option<(String, PathBuf)>,
+    pub status: DriftStatus,
+}
+
+/// Runtime values and managed file contents captured before an apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RollbackState {
+    created_at: chrono::DateTime<Utc>,
+    previous_file: Option<String>,
+    runtime_values: BTreeMap<String, String>,
+}
+
+/// Hardening profile: built-in defaults merged with user overrides
+///
+/// An override with an empty value removes the key from the profile.
+pub fn hardening_profile(config: &Config) -> BTreeMap<String, String> {
+    let mut profile: BTreeMap<String, String> = DEFAULT_PROFILE
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    if config.sysctl.disable_ip_forward {
+        profile.insert("net.ipv4.ip_forward".to_string(), "0".to_string());
+    }
+
+    for (key, value) in &config.sysctl.overrides {
+        if value.is_empty() {
+            profile.remove(key);
+        } else {
+            profile.insert(key.clone(), value.clone());
+        }
+    }
+    profile
+}
+
+/// Current value of a kernel parameter from `/proc/sys`
+pub fn read_effective(key: &str) -> Option<String> {
+    let path = Path::new("/proc/sys").join(key.replace('.', "/"));
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| normalize(value.trim()))
+}
+
+/// Collapse whitespace so multi-value keys compare equal however they are written
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Parse `key = value` lines from a sysctl configuration file
+fn parse_conf(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                return None;
+            }
+            // A leading '-' means "ignore errors" and is not part of the key
+            let line = line.trim_start_matches('-');
+            let (key, value) = line.split_once('=')?;
+            Some((key.trim().replace('/', "."), normalize(value.trim())))
+        })
+        .collect()
+}
+
+/// Persisted values from sysctl.d and /etc/sysctl.conf, in the order
+/// `sysctl --system` applies them so the last file wins
+pub fn read_configured() -> BTreeMap<String, (String, PathBuf)> {
+    // Files with the same name in a later directory shadow earlier ones
+    let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in SYSCTL_DIRS {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(".conf") {
+                    files.insert(name, entry.path());
+                }
+            }
+        }
+    }
+
+    let mut ordered: Vec<PathBuf> = files.into_values().collect();
+    ordered.push(PathBuf::from("/etc/sysctl.conf"));
+
+    let mut configured = BTreeMap::new();
+    for path in ordered {
+        if let Ok(content) = fs::read_to_string(&path) {
+            for (key, value) in parse_conf(&content) {
+                configured.insert(key, (value, path.clone()));
+            }
+        }
+    }
+    configured
+}
+
+pub fn compute_drift(config: &Config) -> Vec<SysctlDrift> {
+    let configured = read_configured();
+
+    hardening_profile(config)
+        .into_iter()
+        .map(|(key, expected)| {
+            let effective = read_effective(&key);
+            let status = match &effective {
+                None => DriftStatus::Unavailable,
+                Some(value) if *value == normalize(&expected) => DriftStatus::Ok,
+                Some(_) => DriftStatus::Drift,
+            };
+            SysctlDrift {
+                configured: configured.get(&key).cloned(),
+                key,
+                expected,
+                effective,
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Render the drift table shown in the TUI and by `sysctl drift`
+pub fn drift_table(drift: &[SysctlDrift]) -> String {
+    let width = drift
+        .iter()
+        .map(|d| d.key.len())
+        .max()
+        .unwrap_or(10)
+        .max(10);
+    let mut lines = vec![
+        format!(
+            "   {:<width$}  {:>9}  {:>9}  {}",
+            "KEY",
+            "RUNNING",
+            "PROFILE",
+            "PERSISTED IN",
+            width = width
+        ),
+        String::new(),
+    ];
+
+    for item in drift {
+        let marker = match item.status {
+            DriftStatus::Ok => "✅",
+            DriftStatus::Drift => "❌",
+            DriftStatus::Unavailable => "⚠️ ",
+        };
+        let persisted = match &item.configured {
+            Some((value, path)) => format!("{} = {}", path.display(), value),
+            None => "-".to_string(),
+        };
+        lines.push(format!(
+            "{} {:<width$}  {:>9}  {:>9}  {}",
+            marker,
+            item.key,
+            item.effective.as_deref().unwrap_or("n/a"),
+            item.expected,
+            persisted,
+            width = width
+        ));
+    }
+
+    let drifted = drift
+        .iter()
+        .filter(|d| d.status == DriftStatus::Drift)
+        .count();
+    lines.push(String::new());
+    lines.push(format!(
+        "{} of {} parameters drift from the hardening profile",
+        drifted,
+        drift.len()
+    ));
+    lines.join("\n")
+}
+
+/// Contents of the managed drop-in for the given profile
+fn managed_file_content(profile: &BTreeMap<String, String>) -> String {
+    let mut content = String::from(
+        "# Managed by linux-toolkit, changes will be overwritten.\n\
+         # Roll back with `linux-toolkit sysctl rollback`.\n\n",
+    );
+    for (key, value) in profile {
+        content.push_str(&format!("{} = {}\n", key, value));
+    }
+    content
+}
+
+fn rollback_path(config: &Config) -> PathBuf {
+    config.directories.data_dir.join("sysctl-rollback.json")
+}
+
+/// Combine a new capture with the state saved by an earlier apply
+///
+/// The earlier state wins so repeated applies still roll back to the values
+/// from before the first one; only keys it did not record are added.
+fn merge_rollback(existing: Option<RollbackState>, captured: RollbackState) -> RollbackState {
+    let Some(mut state) = existing else {
+        return captured;
+    };
+    for (key, value) in captured.runtime_values {
+        state.runtime_values.entry(key).or_insert(value);
+    }
+    state
+}
+
+/// Write the managed drop-in and load it into the running kernel
+pub fn apply(config: &Config, assume_yes: bool) -> Result<()> {
+    let managed = &config.sysctl.managed_file;
+    let profile = hardening_profile(config);
+    let drift = compute_drift(config);
+
+    let changes: Vec<&SysctlDrift> = drift
+        .iter()
+        .filter(|d| d.status == DriftStatus::Drift)
+        .collect();
+    println!("{}", drift_table(&drift));
+    println!();
+    println!("This will write {} and apply it.", managed.display());
+    if !assume_yes {
+        crate::utils::require_confirmation("Apply the hardening profile?")?;
+    }
+
+    let captured = RollbackState {
+        created_at: Utc::now(),
+        previous_file: fs::read_to_string(managed).ok(),
+        runtime_values: drift
+            .iter()
+            .filter_map(|d| Some((d.key.clone(), d.effective.clone()?)))
+            .collect(),
+    };
+    let existing = match fs::read_to_string(rollback_path(config)) {
+        Ok(content) => Some(serde_json::from_str(&content).map_err(|e| {
+            anyhow!(
+                "{} is corrupt, fix or remove it before applying: {}",
+                rollback_path(config).display(),
+                e
+            )
+        })?),
+        Err(_) => None,
+    };
+    let state = merge_rollback(existing, captured);
+    fs::create_dir_all(&config.directories.data_dir)?;
+    fs::write(rollback_path(config), serde_json::to_string_pretty(&state)?)?;
+
+    if let Some(parent) = managed.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(managed, managed_file_content(&profile))?;
+
+    let failed = write_all_effective(
+        changes
+            .iter()
+            .map(|item| (item.key.as_str(), item.expected.as_str())),
+    );
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "Could not set {}, the drop-in was written and a backup saved to {}",
+            failed.join(", "),
+            rollback_path(config).display()
+        ));
+    }
+
+    println!(
+        "✅ Applied hardening profile ({} parameter(s) changed), backup saved to {}",
+        changes.len(),
+        rollback_path(config).display()
+    );
+    Ok(())
+}
+
+/// Restore the managed drop-in and runtime values saved by the last apply
+pub fn rollback(config: &Config) -> Result<()> {
+    let path = rollback_path(config);
+    let content = fs::read_to_string(&path)
+        .map_err(|_| anyhow!("No sysctl backup found at {}", path.display()))?;
+    let state: RollbackState = serde_json::from_str(&content)?;
+    let managed = &config.sysctl.managed_file;
+
+    match &state.previous_file {
+        Some(previous) => fs::write(managed, previous)?,
+        None => {
+            if managed.exists() {
+                fs::remove_file(managed)?;
+            }
+        }
+    }
+
+    let failed = write_all_effective(
+        state
+            .runtime_values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    );
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "Could not restore {}, the backup at {} was kept",
+            failed.join(", "),
+            path.display()
+        ));
+    }
+
+    fs::remove_file(&path)?;
+    println!(
+        "✅ Rolled back to the state from {}",
+        state.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    Ok(())
+}
+
+/// Write each runtime value, reporting failures on stderr and returning the
+/// keys that could not be set
+fn write_all_effective<'a>(values: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<String> {
+    let mut failed = Vec::new();
+    for (key, value) in values {
+        if let Err(e) = write_effective(key, value) {
+            eprintln!("❌ {}: {}", key, e);
+            failed.push(key.to_string());
+        }
+    }
+    failed
+}
+
+fn write_effective(key: &str, value: &str) -> Result<()> {
+    let path = Path::new("/proc/sys").join(key.replace('.', "/"));
+    fs::write(path, value)?;
+    Ok(())
+}
+
+/// Drift table for the TUI details panel
+pub fn run_native(config: &Config) -> Result<String> {
+    Ok(drift_table(&compute_drift(config)))
+}
+
+/// Handler for `linux-toolkit sysctl <action>`
+pub fn sysctl_command(action: &str, config: &Config, assume_yes: bool) -> Result<()> {
+    match action {
+        "drift" => {
+            println!("{}", drift_table(&compute_drift(config)));
+            Ok(())
+        }
+        "apply" => apply(config, assume_yes),
+        "rollback" => rollback(config),
+        other => Err(anyhow!("Unknown sysctl action '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sysctl_conf_syntax() {
+        let content = "# comment\n; also a comment\n\n\
+                       kernel.sysrq = 0\n\
+                       -net/ipv4/ip_forward=1\n\
+                       net.ipv4.ip_local_port_range =  32768\t60999\n";
+        assert_eq!(
+            parse_conf(content),
+            vec![
+                ("kernel.sysrq".to_string(), "0".to_string()),
+                ("net.ipv4.ip_forward".to_string(), "1".to_string()),
+                (
+                    "net.ipv4.ip_local_port_range".to_string(),
+                    "32768 60999".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn profile_applies_overrides_and_keeps_forwarding_opt_in() {
+        let mut config = Config::default();
+        let profile = hardening_profile(&config);
+        assert!(!profile.contains_key("net.ipv4.ip_forward"));
+        assert_eq!(profile["kernel.sysrq"], "0");
+
+        config.sysctl.disable_ip_forward = true;
+        config
+            .sysctl
+            .overrides
+            .insert("kernel.sysrq".to_string(), "176".to_string());
+        config
+            .sysctl
+            .overrides
+            .insert("kernel.kptr_restrict".to_string(), String::new());
+        let profile = hardening_profile(&config);
+        assert_eq!(profile["net.ipv4.ip_forward"], "0");
+        assert_eq!(profile["kernel.sysrq"], "176");
+        assert!(!profile.contains_key("kernel.kptr_restrict"));
+    }
+
+    #[test]
+    fn repeated_applies_keep_the_original_values() {
+        let state = |file: Option<&str>, values: &[(&str, &str)]| RollbackState {
+            created_at: Utc::now(),
+            previous_file: file.map(String::from),
+            runtime_values: values
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        };
+        let first = state(None, &[("kernel.sysrq", "176")]);
+        let second = state(
+            Some("# managed"),
+            &[("kernel.sysrq", "0"), ("fs.suid_dumpable", "2")],
+        );
+
+        let merged = merge_rollback(Some(first), second);
+        assert_eq!(merged.previous_file, None);
+        assert_eq!(merged.runtime_values["kernel.sysrq"], "176");
+        assert_eq!(merged.runtime_values["fs.suid_dumpable"], "2");
+    }
+}
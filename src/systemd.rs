@@ -0,0 +1,320 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::audit::PrivilegedRun;
+use crate::commands::{self, Command};
+use crate::config::{Config, Schedule};
+use crate::executor::{self, Invocation};
+use crate::scheduler::CronSchedule;
+
+/// Where `--install` puts the units
+const UNIT_DIR: &str = "/etc/systemd/system";
+/// Timer schedule when neither `--on-calendar` nor a configured schedule gives one
+const DEFAULT_CALENDAR: &str = "daily";
+
+/// A generated service and timer pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Units {
+    /// Name without suffix, e.g. `linux-toolkit-security-list-users`
+    pub name: String,
+    pub service: String,
+    pub timer: String,
+}
+
+impl Units {
+    pub fn service_name(&self) -> String {
+        format!("{}.service", self.name)
+    }
+
+    pub fn timer_name(&self) -> String {
+        format!("{}.timer", self.name)
+    }
+}
+
+/// Quote an `ExecStart=` argument when systemd would otherwise split it
+fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '$' | '%' | ';'))
+    {
+        return arg.to_string();
+    }
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "$$")
+        .replace('%', "%%");
+    format!("\"{}\"", escaped)
+}
+
+/// Toolkit arguments that run `command` with `extra_args` from the CLI
+fn cli_args(command: &Command, extra_args: &[String]) -> Vec<String> {
+    match command.command.as_str() {
+        "runbook" => vec!["runbook".to_string(), command.args[0].clone()],
+        _ => {
+            let mut args = vec!["-x".to_string(), command.id()];
+            for arg in extra_args {
+                args.push(format!("--arg={}", arg));
+            }
+            args
+        }
+    }
+}
+
+/// Render the service and timer for `command`, scheduled by `on_calendar`
+///
+/// The service runs as `user` so that the toolkit finds the same config and
+/// data directory as the user who exported it.
+pub fn render(
+    command: &Command,
+    extra_args: &[String],
+    on_calendar: &str,
+    exe: &str,
+    user: &str,
+    config: &Config,
+) -> Units {
+    let name = format!("linux-toolkit-{}", crate::utils::slugify(&command.id()));
+    let mut exec = vec![quote(exe)];
+    exec.extend(cli_args(command, extra_args).iter().map(|arg| quote(arg)));
+    let sudo_note = if command.requires_sudo && user != "root" {
+        "# NoNewPrivileges blocks sudo, export as root to run this command privileged\n"
+    } else {
+        ""
+    };
+
+    let service = format!(
+        "# Generated by linux-toolkit systemd export\n\
+         [Unit]\n\
+         Description=Linux Toolkit: {name}\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         {sudo_note}\
+         User={user}\n\
+         ExecStart={exec}\n\
+         # Hardening, relax ProtectSystem for commands that change the system\n\
+         NoNewPrivileges=yes\n\
+         PrivateTmp=yes\n\
+         ProtectSystem=strict\n\
+         ProtectHome=read-only\n\
+         ProtectKernelTunables=yes\n\
+         ProtectControlGroups=yes\n\
+         RestrictSUIDSGID=yes\n\
+         ReadWritePaths=-{data_dir}\n",
+        name = command.name,
+        exec = exec.join(" "),
+        user = user,
+        data_dir = quote(&config.directories.data_dir.display().to_string()),
+    );
+    let timer = format!(
+        "# Generated by linux-toolkit systemd export\n\
+         [Unit]\n\
+         Description=Schedule for Linux Toolkit: {name}\n\
+         \n\
+         [Timer]\n\
+         OnCalendar={on_calendar}\n\
+         Persistent=true\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        name = command.name,
+    );
+
+    Units {
+        name,
+        service,
+        timer,
+    }
+}
+
+/// The `[[schedules]]` entry for the same command, if any
+fn configured_schedule<'a>(
+    command: &Command,
+    categories: &[commands::CommandCategory],
+    config: &'a Config,
+) -> Option<&'a Schedule> {
+    config.schedules.iter().find(|schedule| {
+        let by_command = schedule
+            .command
+            .as_deref()
+            .and_then(|query| commands::find_command(categories, query))
+            .is_some_and(|found| found.id() == command.id());
+        let by_runbook = schedule.runbook.as_deref().is_some_and(|name| {
+            command.command == "runbook"
+                && (command.args[0] == name || command.name.eq_ignore_ascii_case(name))
+        });
+        by_command || by_runbook
+    })
+}
+
+/// `OnCalendar=` equivalent of a schedule's cron expression
+fn schedule_calendar(schedule: &Schedule) -> Result<String> {
+    let cron: CronSchedule = schedule.cron.parse()?;
+    cron.on_calendar().with_context(|| {
+        format!(
+            "Schedule '{}' has no systemd equivalent, pass --on-calendar",
+            schedule.label()
+        )
+    })
+}
+
+/// Run systemctl with the output shown, recording it in the audit log
+fn systemctl(args: &[&str], config: &Config) -> Result<()> {
+    let invocation = Invocation::new("systemctl", args.iter().map(|a| a.to_string()).collect());
+    let audit = PrivilegedRun::begin("systemd/export", &invocation.argv(), None, None);
+    let status = executor::current().attach(&invocation);
+    if let Some(audit) = audit {
+        audit.finish(config, status.as_ref().ok().and_then(|status| status.code));
+    }
+    let status = status?;
+    if !status.success() {
+        return Err(anyhow!(
+            "systemctl {} failed with exit code {}",
+            args.join(" "),
+            status
+        ));
+    }
+    Ok(())
+}
+
+/// Write the units to the system unit directory and enable the timer
+fn install(units: &Units, config: &Config, assume_yes: bool) -> Result<()> {
+    let dir = Path::new(UNIT_DIR);
+    let service = dir.join(units.service_name());
+    let timer = dir.join(units.timer_name());
+    println!(
+        "This will write {} and {}, then enable and start {}.",
+        service.display(),
+        timer.display(),
+        units.timer_name()
+    );
+    if !assume_yes {
+        crate::utils::require_confirmation("Install the units?")?;
+    }
+
+    for (path, content) in [(&service, &units.service), (&timer, &units.timer)] {
+        fs::write(path, content).with_context(|| {
+            format!(
+                "Failed to write {}, installing units requires root",
+                path.display()
+            )
+        })?;
+    }
+    systemctl(&["daemon-reload"], config)?;
+    systemctl(&["enable", "--now", &units.timer_name()], config)?;
+    println!("✅ Installed and started {}", units.timer_name());
+    Ok(())
+}
+
+/// Handler for `linux-toolkit systemd export`
+pub fn export_command(
+    target: &str,
+    on_calendar: Option<&str>,
+    install_units: bool,
+    config: &Config,
+    assume_yes: bool,
+) -> Result<()> {
+    let categories = commands::load_categories();
+    let command = commands::find_command(&categories, target)
+        .ok_or_else(|| anyhow!("Command not found: {}", target))?;
+
+    let schedule = configured_schedule(command, &categories, config);
+    let calendar = match (on_calendar, schedule) {
+        (Some(calendar), _) => calendar.to_string(),
+        (None, Some(schedule)) => schedule_calendar(schedule)?,
+        (None, None) => DEFAULT_CALENDAR.to_string(),
+    };
+    let args = schedule
+        .map(|schedule| schedule.args.as_slice())
+        .unwrap_or_default();
+    let user = crate::utils::get_current_user();
+    if user == "unknown" {
+        return Err(anyhow!(
+            "Could not tell which user to run the service as, set $USER"
+        ));
+    }
+    let units = render(
+        command,
+        args,
+        &calendar,
+        &crate::utils::toolkit_exe(),
+        &user,
+        config,
+    );
+
+    if install_units {
+        return install(&units, config, assume_yes);
+    }
+    println!("# {}\n{}", units.service_name(), units.service);
+    println!("# {}\n{}", units.timer_name(), units.timer);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn command(command: &str, args: &[&str]) -> Command {
+        testing::command("List Users", command)
+            .args(args)
+            .category("Security")
+            .build()
+    }
+
+    #[test]
+    fn service_runs_the_command_through_the_cli() {
+        let mut config = Config::default();
+        config.directories.data_dir = "/var/lib/linux toolkit".into();
+        let units = render(
+            &command("cat", &["/etc/passwd"]),
+            &[],
+            "Mon *-*-* 02:00:00",
+            "/usr/bin/linux-toolkit",
+            "alice",
+            &config,
+        );
+
+        assert_eq!(units.name, "linux-toolkit-security-list-users");
+        assert!(units
+            .service
+            .contains("ExecStart=/usr/bin/linux-toolkit -x security/list-users\n"));
+        assert!(units.service.contains("User=alice\n"));
+        assert!(units.service.contains("NoNewPrivileges=yes\n"));
+        assert!(units
+            .service
+            .contains("ReadWritePaths=-\"/var/lib/linux toolkit\"\n"));
+        assert!(units.timer.contains("OnCalendar=Mon *-*-* 02:00:00\n"));
+    }
+
+    #[test]
+    fn runbooks_use_the_runbook_subcommand() {
+        let units = render(
+            &command("runbook", &["harden-new-server"]),
+            &[],
+            "daily",
+            "/opt/linux toolkit/bin",
+            "root",
+            &Config::default(),
+        );
+        assert!(units
+            .service
+            .contains("ExecStart=\"/opt/linux toolkit/bin\" runbook harden-new-server\n"));
+    }
+
+    #[test]
+    fn schedule_arguments_are_passed_on() {
+        let mut sudo = command("ss", &["-tlnp"]);
+        sudo.requires_sudo = true;
+        let args = vec!["--ipv4".to_string(), "two words".to_string()];
+        let units = render(&sudo, &args, "daily", "ltk", "alice", &Config::default());
+        assert!(units
+            .service
+            .contains("ExecStart=ltk -x security/list-users --arg=--ipv4 \"--arg=two words\"\n"));
+        assert!(units.service.contains("# NoNewPrivileges blocks sudo"));
+    }
+}
@@ -0,0 +1,455 @@
+//! Running catalog commands attached to the terminal, for the CLI and TUI.
+//!
+//! Unlike [`crate::commands`], these helpers talk to the user directly: they
+//! print progress, ask before installing missing programs and wait for Enter.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+use crate::audit::PrivilegedRun;
+use crate::commands::{
+    execute_native_command, find_command, load_categories, run_command, should_retry_with_sudo,
+    uses_shell, Command,
+};
+use crate::config::Config;
+use crate::executor::{self, Invocation, Output};
+use crate::report::{ExecutionRecord, Report};
+use crate::scripts::ScriptManager;
+
+pub async fn execute_command_in_terminal(command: &Command, config: &Config) -> Result<()> {
+    clear_terminal();
+
+    // Runbooks drive several commands and print their own summary
+    if command.command == "runbook" {
+        if let Err(e) = crate::runbooks::run_in_terminal(command, config).await {
+            println!("❌ Runbook error: {}", e);
+        }
+    } else {
+        run_in_terminal(command, config).await?;
+    }
+
+    wait_for_enter();
+    Ok(())
+}
+
+/// Run a single command or script attached to the terminal
+///
+/// Returns whether it completed successfully. Unlike
+/// `execute_command_in_terminal` this neither clears the screen nor waits for
+/// the user, so callers can run several commands back to back.
+pub async fn run_in_terminal(command: &Command, config: &Config) -> Result<bool> {
+    if let Some(reason) = command.unsupported_reason(crate::environment::current()) {
+        println!(
+            "❌ {} is not available on this system: {}",
+            command.name, reason
+        );
+        return Ok(false);
+    }
+
+    let missing = command.missing_binaries();
+    if !missing.is_empty() && !offer_install(&command.id(), &missing, config) {
+        return Ok(false);
+    }
+
+    // Handle script commands specially
+    if command.command == "script" {
+        return execute_script_command_in_terminal(command, config).await;
+    }
+
+    if command.command == "runbook" {
+        println!("❌ Runbooks cannot be nested inside other runbooks");
+        return Ok(false);
+    }
+
+    if command.command == "native" {
+        println!("▶ {}", command.name);
+        println!();
+        return match execute_native_command(command, config).await {
+            Ok(output) => {
+                println!("{}", output);
+                Ok(true)
+            }
+            Err(e) => {
+                println!("❌ {}", e);
+                Ok(false)
+            }
+        };
+    }
+
+    // Build the command with proper shell handling
+    let has_shell_operators = uses_shell(command);
+
+    let should_use_sudo = command.requires_sudo;
+
+    let (final_command, final_args) = if has_shell_operators {
+        let full_command = format!("{} {}", command.command, command.args.join(" "));
+        if should_use_sudo && !crate::utils::is_root() {
+            (
+                "sudo".to_string(),
+                vec!["sh".to_string(), "-c".to_string(), full_command],
+            )
+        } else {
+            ("sh".to_string(), vec!["-c".to_string(), full_command])
+        }
+    } else {
+        if should_use_sudo && !crate::utils::is_root() {
+            let mut sudo_args = vec![command.command.clone()];
+            sudo_args.extend(command.args.clone());
+            ("sudo".to_string(), sudo_args)
+        } else {
+            (command.command.clone(), command.args.clone())
+        }
+    };
+
+    // Show command info before execution
+    println!("\n{}", "=".repeat(60));
+    println!("Executing: {}", command.name);
+    println!("Description: {}", command.description);
+    if should_use_sudo {
+        println!("⚠️  This command requires elevated privileges");
+    }
+    println!("Command: {}", command.usage);
+    println!("{}", "=".repeat(60));
+    println!();
+
+    // Execute the command, keeping its output for later comparison
+    let invocation = Invocation::new(final_command, final_args);
+    let elevated_with = (invocation.program == "sudo").then_some("sudo");
+    let started_at = Utc::now();
+    let audit = PrivilegedRun::begin(&command.id(), &invocation.argv(), elevated_with, None);
+    let result = executor::current().tee(&invocation);
+    if let Some(audit) = audit {
+        audit.finish(
+            config,
+            result.as_ref().ok().and_then(|output| output.status.code),
+        );
+    }
+
+    match result {
+        Ok(output) => {
+            let status = output.status;
+            println!();
+            if status.success() {
+                remember_output(command, config, started_at, &output);
+                println!("✅ Command completed successfully");
+                return Ok(true);
+            }
+
+            let exit_code = status.code.unwrap_or(-1);
+            println!("❌ Command failed with exit code: {}", exit_code);
+
+            // 127 means a program was not found, which sudo cannot fix
+            if exit_code == 127 {
+                println!("💡 A program this command needs is not installed");
+                return Ok(false);
+            }
+
+            // Check if it might be a permission issue and suggest retry
+            if (exit_code == 1 || exit_code == 126)
+                && !should_use_sudo
+                && should_retry_with_sudo(command, config)
+            {
+                println!("💡 This might be a permission issue. Retrying with sudo...");
+                println!();
+
+                // Retry with sudo
+                return execute_command_with_sudo_retry(command, config).await;
+            }
+            remember_output(command, config, started_at, &output);
+        }
+        Err(e) => {
+            println!("❌ Failed to execute command: {}", e);
+
+            // Retry with sudo unless the program does not exist at all
+            if e.kind() != std::io::ErrorKind::NotFound
+                && !should_use_sudo
+                && should_retry_with_sudo(command, config)
+            {
+                println!("💡 Retrying with elevated privileges...");
+                println!();
+
+                return execute_command_with_sudo_retry(command, config).await;
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// Store a terminal run like a captured one, so it can be compared later
+fn remember_output(command: &Command, config: &Config, started_at: DateTime<Utc>, output: &Output) {
+    let record = ExecutionRecord {
+        command_id: command.id(),
+        command_name: command.name.clone(),
+        started_at,
+        success: Some(output.status.success()),
+        output: if output.stdout.is_empty() {
+            output.stderr.clone()
+        } else {
+            output.stdout.clone()
+        },
+    };
+    if let Err(e) = crate::history::remember(config, &command.id(), &record) {
+        println!("⚠️  Could not store the output for later comparison: {}", e);
+    }
+}
+
+/// Offer to install the packages providing `missing` programs, returning
+/// whether they are all available afterwards
+fn offer_install(command_id: &str, missing: &[String], config: &Config) -> bool {
+    println!("❌ Missing required program(s): {}", missing.join(", "));
+
+    let Some(manager) = crate::packages::detect() else {
+        println!("💡 No supported package manager found, install them manually");
+        return false;
+    };
+    let mut packages: Vec<String> = missing
+        .iter()
+        .map(|binary| manager.package_for_binary(binary))
+        .collect();
+    packages.sort();
+    packages.dedup();
+
+    if !crate::utils::confirm(&format!(
+        "Install {} with {}?",
+        packages.join(" "),
+        manager.name()
+    )) {
+        return false;
+    }
+
+    let mut install = manager.install_command(&packages);
+    if !crate::utils::is_root() {
+        install.insert(0, "sudo".to_string());
+    }
+    println!("▶ {}", install.join(" "));
+
+    let invocation = Invocation::from_argv(&install);
+    let elevated_with = (invocation.program == "sudo").then_some("sudo");
+    let audit = PrivilegedRun::begin(command_id, &invocation.argv(), elevated_with, None);
+    let result = executor::current().attach(&invocation);
+    if let Some(audit) = audit {
+        audit.finish(config, result.as_ref().ok().and_then(|status| status.code));
+    }
+
+    let still_missing: Vec<&str> = missing
+        .iter()
+        .filter(|binary| which::which(binary).is_err())
+        .map(String::as_str)
+        .collect();
+    if !still_missing.is_empty() {
+        println!(
+            "❌ Still missing after install: {}",
+            still_missing.join(", ")
+        );
+        return false;
+    }
+    println!("✅ Installed {}", packages.join(" "));
+    println!();
+    true
+}
+
+fn clear_terminal() {
+    use std::process::Command as StdCommand;
+
+    if cfg!(target_os = "windows") {
+        let _ = StdCommand::new("cls").status();
+    } else {
+        let _ = StdCommand::new("clear").status();
+    }
+}
+
+fn wait_for_enter() {
+    println!("\nPress Enter to continue...");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).ok();
+}
+
+async fn execute_script_command_in_terminal(command: &Command, config: &Config) -> Result<bool> {
+    // Get the script manager
+    let script_manager = ScriptManager::new_from_exe()?;
+
+    // The script name should be in the first argument
+    if command.args.is_empty() {
+        println!("❌ Error: No script specified");
+        return Ok(false);
+    }
+
+    let script_name = &command.args[0];
+    let script_path = match script_manager.find_script(script_name) {
+        Some(path) => path,
+        None => {
+            println!("❌ Error: Script '{}' not found", script_name);
+            return Ok(false);
+        }
+    };
+
+    // Execute the script with any additional arguments
+    let script_args: Vec<String> = command.args[1..].to_vec();
+
+    let started_at = Utc::now();
+    match script_manager
+        .execute_script_in_terminal(
+            &script_path,
+            &script_args,
+            command.requires_sudo,
+            &command.id(),
+            config,
+        )
+        .await
+    {
+        Ok(Some(output)) => {
+            remember_output(command, config, started_at, &output);
+            Ok(output.status.success())
+        }
+        Ok(None) => Ok(false),
+        Err(e) => {
+            println!("❌ Script execution error: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+async fn execute_command_with_sudo_retry(command: &Command, config: &Config) -> Result<bool> {
+    let has_shell_operators = uses_shell(command);
+
+    let (final_command, final_args) = if has_shell_operators {
+        let full_command = format!("{} {}", command.command, command.args.join(" "));
+        (
+            "sudo".to_string(),
+            vec!["sh".to_string(), "-c".to_string(), full_command],
+        )
+    } else {
+        let mut sudo_args = vec![command.command.clone()];
+        sudo_args.extend(command.args.clone());
+        ("sudo".to_string(), sudo_args)
+    };
+
+    println!("🔓 Executing with elevated privileges...");
+    println!();
+
+    let invocation = Invocation::new(final_command, final_args);
+    let started_at = Utc::now();
+    let audit = PrivilegedRun::begin(&command.id(), &invocation.argv(), Some("sudo"), None);
+    let result = executor::current().tee(&invocation);
+    if let Some(audit) = audit {
+        audit.finish(
+            config,
+            result.as_ref().ok().and_then(|output| output.status.code),
+        );
+    }
+
+    match result {
+        Ok(output) => {
+            let status = output.status;
+            remember_output(command, config, started_at, &output);
+            println!();
+            if status.success() {
+                println!("✅ Command completed successfully (elevated)");
+                return Ok(true);
+            }
+            println!(
+                "❌ Command failed even with elevated privileges (exit code: {})",
+                status.code.unwrap_or(-1)
+            );
+        }
+        Err(e) => {
+            println!("❌ Failed to execute command with sudo: {}", e);
+        }
+    }
+
+    Ok(false)
+}
+
+pub async fn execute_direct_command(
+    command_name: &str,
+    extra_args: &[String],
+    config: &Config,
+    report_path: Option<&Path>,
+) -> Result<()> {
+    let categories = load_categories();
+    let mut cmd = find_command(&categories, command_name)
+        .ok_or_else(|| anyhow::anyhow!("Command not found: {}", command_name))?
+        .clone();
+    cmd.args.extend(extra_args.iter().cloned());
+    let cmd = &cmd;
+
+    let missing = cmd.missing_binaries();
+    if !missing.is_empty() {
+        let hint = crate::packages::detect()
+            .map(|manager| {
+                let mut packages: Vec<String> = missing
+                    .iter()
+                    .map(|binary| manager.package_for_binary(binary))
+                    .collect();
+                packages.sort();
+                packages.dedup();
+                format!(
+                    " (install with: {})",
+                    manager.install_command(&packages).join(" ")
+                )
+            })
+            .unwrap_or_default();
+        return Err(anyhow::anyhow!(
+            "Missing required program(s) for '{}': {}{}",
+            cmd.name,
+            missing.join(", "),
+            hint
+        ));
+    }
+
+    println!("Executing: {}", cmd.name);
+    let started_at = Utc::now();
+    let run = run_command(cmd, config).await?;
+    println!("{}", run.display());
+
+    let record = run.record(cmd, started_at);
+    let key = crate::history::key(&cmd.id(), extra_args);
+    if let Err(e) = crate::history::remember(config, &key, &record) {
+        println!("⚠️  Could not store the output for later comparison: {}", e);
+    }
+    if let Some(path) = report_path {
+        Report::new(&cmd.name)
+            .with_execution(record)
+            .write_to(path)?;
+        println!("Report written to {}", path.display());
+    }
+    if !run.success {
+        return Err(anyhow::anyhow!("{} failed", cmd.name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{override_current, RecordingExecutor};
+    use crate::history::History;
+    use crate::testing;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn terminal_runs_are_kept_for_comparison() {
+        let fake = Arc::new(RecordingExecutor::default());
+        let _guard = override_current(fake.clone());
+        fake.respond(0, "/usr/bin/passwd\n", "");
+        let dir = testing::TempDir::new("terminal-history");
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        config.directories.data_dir = dir.path().to_path_buf();
+        let command = testing::command("Check SUID Files", "ls")
+            .args(&["/usr/bin"])
+            .build();
+
+        assert!(run_in_terminal(&command, &config).await.unwrap());
+
+        let latest = History::new(&config)
+            .latest(&command.id())
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.output, "/usr/bin/passwd\n");
+        assert_eq!(latest.success, Some(true));
+        assert_eq!(fake.calls()[0].argv(), ["ls", "/usr/bin"]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Fixtures shared by the unit tests of the library and the binary, which
+//! enables them through the `test-util` feature.
+
+use crate::commands::Command;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Catalog command with only the fields a test cares about filled in
+pub struct CommandBuilder(Command);
+
+/// Start a command named `name` that runs `program` without arguments
+pub fn command(name: &str, program: &str) -> CommandBuilder {
+    CommandBuilder(Command {
+        name: name.to_string(),
+        description: String::new(),
+        command: program.to_string(),
+        args: Vec::new(),
+        usage: program.to_string(),
+        tags: Vec::new(),
+        requires_sudo: false,
+        category: "Test".to_string(),
+        supported_distros: Vec::new(),
+        requires_tools: Vec::new(),
+        unavailable: None,
+        missing: Vec::new(),
+    })
+}
+
+impl CommandBuilder {
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.0.args = args.iter().map(|arg| arg.to_string()).collect();
+        self
+    }
+
+    pub fn category(mut self, category: &str) -> Self {
+        self.0.category = category.to_string();
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.0.description = description.to_string();
+        self
+    }
+
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.0.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        self
+    }
+
+    pub fn sudo(mut self, requires_sudo: bool) -> Self {
+        self.0.requires_sudo = requires_sudo;
+        self
+    }
+
+    pub fn build(self) -> Command {
+        self.0
+    }
+}
+
+/// Empty directory under the system temp dir, removed with its contents on drop
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(label: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "linux-toolkit-test-{}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed),
+            label
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("could not create the test directory");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
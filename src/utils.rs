@@ -7,3 +7,376 @@ pub fn get_current_user() -> String {
 pub fn is_root() -> bool {
     get_current_user() == "root" || std::env::var("EUID").unwrap_or_default() == "0"
 }
+
+/// Host name of the machine, used in reports
+#[cfg(unix)]
+pub fn get_hostname() -> String {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer is valid for its full length and gethostname
+    // NUL-terminates the name when it fits
+    let result =
+        unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+    if result != 0 {
+        return "unknown".to_string();
+    }
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..end]).to_string()
+}
+
+#[cfg(not(unix))]
+pub fn get_hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Real and effective user IDs of the current process, `None` where the
+/// platform has no numeric user IDs
+#[cfg(unix)]
+pub fn user_ids() -> Option<(u32, u32)> {
+    // SAFETY: getuid/geteuid are always successful and have no side effects
+    Some(unsafe { (libc::getuid(), libc::geteuid()) })
+}
+
+#[cfg(not(unix))]
+pub fn user_ids() -> Option<(u32, u32)> {
+    None
+}
+
+/// Turn a display name into a lowercase, dash-separated identifier
+pub fn slugify(value: &str) -> String {
+    let mut slug = String::new();
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Ask a yes/no question on the terminal, defaulting to no
+pub fn confirm(prompt: &str) -> bool {
+    use std::io::Write;
+
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush().ok();
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).ok();
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask before changing the system, failing when the answer is not yes
+///
+/// Without a terminal on stdin nobody can answer, as in runbooks, RPC and
+/// scheduled runs, so this fails without asking. Either way the caller exits
+/// non-zero instead of reporting a change that never happened.
+pub fn require_confirmation(prompt: &str) -> anyhow::Result<()> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!(
+            "Cannot ask \"{}\" without a terminal, pass --yes to run unattended",
+            prompt
+        ));
+    }
+    if !confirm(prompt) {
+        return Err(anyhow::anyhow!("Aborted, nothing was changed"));
+    }
+    Ok(())
+}
+
+/// Short description of how this session can gain privileges, for the status bar
+pub fn privilege_state() -> &'static str {
+    if is_root() {
+        return "root";
+    }
+    if which::which("sudo").is_err() {
+        return "no sudo";
+    }
+    // Probing sudo as a user without rights is logged, and may be mailed
+    // to the admin, as an incident
+    if !in_admin_group() {
+        return "no sudo group";
+    }
+    // `-n` fails instead of prompting when a password would be needed
+    let probe = crate::executor::Invocation::new("sudo", vec!["-n".into(), "true".into()]);
+    let cached = crate::executor::current()
+        .capture(&probe)
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if cached {
+        "sudo ready"
+    } else {
+        "sudo needs password"
+    }
+}
+
+/// Groups that sudoers grants admin rights to on common distributions
+const ADMIN_GROUPS: &[&str] = &["sudo", "wheel", "admin"];
+
+#[cfg(unix)]
+fn in_admin_group() -> bool {
+    // SAFETY: a zero-sized call only returns the number of groups
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    let mut gids = vec![0 as libc::gid_t; count.max(0) as usize];
+    // SAFETY: the buffer holds `count` entries
+    let count = unsafe { libc::getgroups(gids.len() as libc::c_int, gids.as_mut_ptr()) };
+    gids.truncate(count.max(0) as usize);
+    // SAFETY: getgid cannot fail
+    gids.push(unsafe { libc::getgid() });
+
+    let content = std::fs::read_to_string("/etc/group").unwrap_or_default();
+    admin_group_member(&content, &gids, &get_current_user())
+}
+
+#[cfg(not(unix))]
+fn in_admin_group() -> bool {
+    false
+}
+
+/// Whether `user`, with group ids `gids`, belongs to an admin group in `group_content`
+fn admin_group_member(group_content: &str, gids: &[u32], user: &str) -> bool {
+    group_content.lines().any(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        let [name, _, gid, members] = fields.as_slice() else {
+            return false;
+        };
+        ADMIN_GROUPS.contains(name)
+            && (gid.parse().is_ok_and(|gid: u32| gids.contains(&gid))
+                || members.split(',').any(|member| member.trim() == user))
+    })
+}
+
+/// Path of the running toolkit binary, so commands can invoke its subcommands
+pub fn toolkit_exe() -> String {
+    std::env::current_exe()
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "linux-toolkit".to_string())
+}
+
+/// A login account from `/etc/passwd`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub name: String,
+    pub uid: u32,
+    pub home: std::path::PathBuf,
+    pub shell: String,
+}
+
+impl Account {
+    /// Whether the login shell lets the account start a session
+    pub fn can_log_in(&self) -> bool {
+        !(self.shell.ends_with("/nologin") || self.shell.ends_with("/false"))
+    }
+}
+
+/// Accounts in passwd format, skipping malformed lines
+pub fn parse_passwd(content: &str) -> Vec<Account> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            Some(Account {
+                name: fields[0].to_string(),
+                uid: fields[2].parse().ok()?,
+                home: std::path::PathBuf::from(fields[5]),
+                shell: fields[6].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Accounts whose home directory exists, each home listed once
+pub fn accounts_with_homes() -> Vec<Account> {
+    let content = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let mut seen = std::collections::HashSet::new();
+    parse_passwd(&content)
+        .into_iter()
+        .filter(|account| account.home.is_dir() && account.home != std::path::Path::new("/"))
+        .filter(|account| seen.insert(account.home.clone()))
+        .collect()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard padded base64 (RFC 4648)
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode base64 with or without padding, `None` on characters outside the alphabet
+pub fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim_end_matches('=');
+    let mut decoded = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in input.bytes() {
+        let value = BASE64.iter().position(|&c| c == byte)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Write a toolkit-owned file through a synced temporary file and a rename,
+/// so readers and crashes never see it half written
+pub fn write_atomic(path: &std::path::Path, content: &str) -> anyhow::Result<()> {
+    use std::fs;
+    use std::io::Write;
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{}.tmp", file_name));
+    let write = || -> anyhow::Result<()> {
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    };
+    let result = write();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+/// Replace `path` with `content` through a synced temporary file in the same
+/// directory, so a crash leaves either the old or the new file
+///
+/// The temporary file takes the original's owner and mode first, since sshd
+/// ignores key and config files with the wrong owner or loose permissions.
+pub fn replace_file(path: &std::path::Path, content: &str) -> anyhow::Result<()> {
+    use std::fs;
+    use std::io::Write;
+
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_file() {
+        return Err(anyhow::anyhow!(
+            "{} is not a regular file, edit it by hand",
+            path.display()
+        ));
+    }
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{}.linux-toolkit", file_name));
+    let _ = fs::remove_file(&temporary);
+
+    let write = || -> anyhow::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temporary)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            std::os::unix::fs::fchown(&file, Some(metadata.uid()), Some(metadata.gid()))?;
+        }
+        file.set_permissions(metadata.permissions())?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    };
+    let result = write();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_groups_come_from_gids_or_member_lists() {
+        let group = "root:x:0:\nwheel:x:10:alice,bob\nsudo:x:27:\nusers:x:100:carol\n";
+        assert!(admin_group_member(group, &[100], "alice"));
+        assert!(admin_group_member(group, &[100, 27], "carol"));
+        assert!(!admin_group_member(group, &[100], "carol"));
+        assert!(!admin_group_member("", &[10], "alice"));
+    }
+
+    #[test]
+    fn base64_matches_rfc_4648() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(base64_encode(plain.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+            assert_eq!(
+                base64_decode(encoded.trim_end_matches('=')).unwrap(),
+                plain.as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn base64_round_trips_binary_and_rejects_garbage() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(base64_decode(&base64_encode(&data)).unwrap(), data);
+        assert_eq!(base64_decode("Zm9v!"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replacing_keeps_owner_and_mode() {
+        use crate::testing::TempDir;
+        use std::fs;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = TempDir::new("utils-replace");
+        let path = dir.join("authorized_keys");
+        fs::write(&path, "ssh-ed25519 AAAA key\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let before = fs::metadata(&path).unwrap();
+
+        replace_file(&path, "# nothing left\n").unwrap();
+
+        let after = fs::metadata(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# nothing left\n");
+        assert_eq!(after.mode() & 0o7777, 0o640);
+        assert_eq!((after.uid(), after.gid()), (before.uid(), before.gid()));
+        assert_ne!(after.ino(), before.ino());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,560 @@
+use anyhow::Result;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Full-screen view of command output with scrollback, search and copy
+pub struct OutputViewer {
+    pub title: String,
+    /// Output as produced, possibly with ANSI escapes
+    lines: Vec<String>,
+    /// The same lines with escapes removed, used for search, save and copy
+    plain: Vec<String>,
+    pub cursor: usize,
+    top: usize,
+    hscroll: u16,
+    pub wrap: bool,
+    search: Option<String>,
+    matches: Vec<usize>,
+    selection_anchor: Option<usize>,
+    pub status: Option<String>,
+    /// Rows available for text in the last frame
+    height: usize,
+    /// Wrapped row count of each line at `row_cache_width` columns, filled lazily
+    row_cache: Vec<Option<usize>>,
+    row_cache_width: usize,
+}
+
+impl OutputViewer {
+    /// Keep at most `max_lines` of scrollback, dropping the oldest lines
+    pub fn new(title: &str, output: &[String], max_lines: usize) -> Self {
+        let skip = output.len().saturating_sub(max_lines.max(1));
+        let lines: Vec<String> = output[skip..].to_vec();
+        let plain = lines.iter().map(|line| strip_ansi(line)).collect();
+        Self {
+            title: title.to_string(),
+            lines,
+            plain,
+            cursor: 0,
+            top: 0,
+            hscroll: 0,
+            wrap: true,
+            search: None,
+            matches: Vec::new(),
+            selection_anchor: None,
+            status: (skip > 0).then(|| format!("{} older line(s) dropped", skip)),
+            height: 1,
+            row_cache: Vec::new(),
+            row_cache_width: 0,
+        }
+    }
+
+    pub fn page_size(&self) -> isize {
+        self.height.max(1) as isize
+    }
+
+    pub fn move_cursor(&mut self, delta: isize) {
+        let last = self.lines.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize).saturating_add(delta).clamp(0, last) as usize;
+    }
+
+    pub fn scroll_horizontal(&mut self, delta: i32) {
+        if !self.wrap {
+            self.hscroll = (self.hscroll as i32 + delta).clamp(0, u16::MAX as i32) as u16;
+        }
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.hscroll = 0;
+    }
+
+    /// Highlight case-insensitive matches of `query` and jump to the first one
+    pub fn set_search(&mut self, query: &str) {
+        let query = query.to_ascii_lowercase();
+        if query.is_empty() {
+            self.search = None;
+            self.matches.clear();
+            return;
+        }
+
+        self.matches = self
+            .plain
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.to_ascii_lowercase().contains(&query))
+            .map(|(index, _)| index)
+            .collect();
+        self.search = Some(query);
+
+        if let Some(&first) = self
+            .matches
+            .iter()
+            .find(|&&index| index >= self.cursor)
+            .or_else(|| self.matches.first())
+        {
+            self.cursor = first;
+        }
+        self.status = Some(format!("{} matching line(s)", self.matches.len()));
+    }
+
+    /// Move to the next (or previous) matching line, wrapping around
+    pub fn next_match(&mut self, forward: bool) {
+        let next = if forward {
+            self.matches
+                .iter()
+                .find(|&&index| index > self.cursor)
+                .or_else(|| self.matches.first())
+        } else {
+            self.matches
+                .iter()
+                .rev()
+                .find(|&&index| index < self.cursor)
+                .or_else(|| self.matches.last())
+        };
+        match next {
+            Some(&index) => self.cursor = index,
+            None if self.search.is_some() => self.status = Some("No matches".to_string()),
+            None => {}
+        }
+    }
+
+    /// Start a line selection at the cursor, or clear the current one
+    pub fn toggle_selection(&mut self) {
+        self.selection_anchor = match self.selection_anchor {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+
+    fn selected_range(&self) -> (usize, usize) {
+        let anchor = self.selection_anchor.unwrap_or(self.cursor);
+        (anchor.min(self.cursor), anchor.max(self.cursor))
+    }
+
+    /// Copy the selection, or the cursor line, to the terminal's clipboard
+    pub fn copy_selection(&mut self) {
+        if self.plain.is_empty() {
+            return;
+        }
+        let (start, end) = self.selected_range();
+        let text = self.plain[start..=end].join("\n");
+        self.status = Some(match osc52_copy(&text) {
+            Ok(()) => format!("Copied {} line(s) to the clipboard", end - start + 1),
+            Err(e) => format!("Copy failed: {}", e),
+        });
+        self.selection_anchor = None;
+    }
+
+    /// Write the output without escapes to a timestamped file under `data_dir`
+    pub fn save(&mut self, data_dir: &Path) {
+        let path = linux_toolkit::cli::report::default_export_path(data_dir, &self.title)
+            .with_extension("txt");
+        self.status = Some(match write_lines(&path, &self.plain) {
+            Ok(()) => format!("Saved to {}", path.display()),
+            Err(e) => format!("Save failed: {}", e),
+        });
+    }
+
+    /// Rows `line` takes up at `width` columns
+    fn rows_for(&mut self, index: usize, width: usize) -> usize {
+        if !self.wrap || width == 0 {
+            return 1;
+        }
+        if self.row_cache_width != width {
+            self.row_cache = vec![None; self.plain.len()];
+            self.row_cache_width = width;
+        }
+        // Count rows the way the paragraph wraps them, at word boundaries
+        *self.row_cache[index].get_or_insert_with(|| {
+            Paragraph::new(self.plain[index].as_str())
+                .wrap(Wrap { trim: false })
+                .line_count(width as u16)
+                .max(1)
+        })
+    }
+
+    /// Scroll so the cursor line is fully visible
+    fn keep_cursor_visible(&mut self, width: usize) {
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        }
+        let mut rows: usize = (self.top..=self.cursor)
+            .map(|index| self.rows_for(index, width))
+            .sum();
+        while self.top < self.cursor && rows > self.height {
+            rows -= self.rows_for(self.top, width);
+            self.top += 1;
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, footer: Option<Line>) {
+        f.render_widget(Clear, area);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(area);
+
+        let inner_width = chunks[0].width.saturating_sub(2) as usize;
+        self.height = chunks[0].height.saturating_sub(2).max(1) as usize;
+        self.keep_cursor_visible(inner_width);
+
+        let (selection_start, selection_end) = self.selected_range();
+        let mut rows = 0;
+        let mut text = Vec::new();
+        for index in self.top..self.lines.len() {
+            if rows >= self.height {
+                break;
+            }
+            rows += self.rows_for(index, inner_width);
+
+            let mut line = styled_line(&self.lines[index], self.search.as_deref());
+            if self.selection_anchor.is_some() && (selection_start..=selection_end).contains(&index)
+            {
+                line = line.patch_style(Style::default().bg(Color::Blue));
+            } else if index == self.cursor {
+                line = line.patch_style(Style::default().bg(Color::DarkGray));
+            }
+            text.push(line);
+        }
+
+        let mut title = format!(
+            "{} ({}/{})",
+            self.title,
+            (self.cursor + 1).min(self.lines.len()),
+            self.lines.len()
+        );
+        if !self.wrap {
+            title.push_str(" [nowrap]");
+        }
+        if let Some(query) = &self.search {
+            title.push_str(&format!(" [/{}: {}]", query, self.matches.len()));
+        }
+
+        let mut paragraph = Paragraph::new(text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(Color::Green)),
+        );
+        paragraph = if self.wrap {
+            paragraph.wrap(Wrap { trim: false })
+        } else {
+            paragraph.scroll((0, self.hscroll))
+        };
+        f.render_widget(paragraph, chunks[0]);
+
+        let footer = footer.unwrap_or_else(|| match &self.status {
+            Some(status) => Line::from(Span::styled(
+                status.as_str(),
+                Style::default().fg(Color::Yellow),
+            )),
+            None => Line::from(Span::styled(
+                "/ search  n/N next/prev  w wrap  v select  y copy  s save  Esc close",
+                Style::default().fg(Color::Gray),
+            )),
+        });
+        f.render_widget(
+            Paragraph::new(footer).block(Block::default().borders(Borders::ALL).title("Keys")),
+            chunks[1],
+        );
+    }
+}
+
+fn write_lines(path: &PathBuf, lines: &[String]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Colour result lines by their status marker so pass/warn/fail stand out
+pub fn marker_style(line: &str) -> Style {
+    let line = line.trim_start();
+    if line.starts_with('✅') {
+        Style::default().fg(Color::Green)
+    } else if line.starts_with('⚠') {
+        Style::default().fg(Color::Yellow)
+    } else if line.starts_with('❌') {
+        Style::default().fg(Color::Red)
+    } else if line.starts_with('🔒') {
+        Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(Color::White)
+    }
+}
+
+/// Render a line of output, honouring ANSI SGR colors and highlighting `search`
+pub fn styled_line(line: &str, search: Option<&str>) -> Line<'static> {
+    let segments = if line.contains('\x1b') {
+        parse_ansi(line)
+    } else {
+        vec![(line.to_string(), marker_style(line))]
+    };
+
+    let Some(query) = search.filter(|query| !query.is_empty()) else {
+        return Line::from(
+            segments
+                .into_iter()
+                .map(|(text, style)| Span::styled(text, style))
+                .collect::<Vec<_>>(),
+        );
+    };
+
+    // Byte ranges of matches in the plain text; ASCII lowercasing keeps offsets intact
+    let plain: String = segments.iter().map(|(text, _)| text.as_str()).collect();
+    let haystack = plain.to_ascii_lowercase();
+    let ranges: Vec<(usize, usize)> = haystack
+        .match_indices(query)
+        .map(|(start, found)| (start, start + found.len()))
+        .collect();
+    let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for (text, style) in segments {
+        let end = offset + text.len();
+        let mut cut = offset;
+        for &(start, stop) in &ranges {
+            let (start, stop) = (start.max(offset), stop.min(end));
+            if start >= stop {
+                continue;
+            }
+            if start > cut {
+                spans.push(Span::styled(plain[cut..start].to_string(), style));
+            }
+            spans.push(Span::styled(
+                plain[start..stop].to_string(),
+                style.patch(highlight),
+            ));
+            cut = stop;
+        }
+        if cut < end {
+            spans.push(Span::styled(plain[cut..end].to_string(), style));
+        }
+        offset = end;
+    }
+    Line::from(spans)
+}
+
+/// Split `line` into text runs with the style set by SGR escapes before them
+fn parse_ansi(line: &str) -> Vec<(String, Style)> {
+    let mut segments = Vec::new();
+    let mut style = Style::default().fg(Color::White);
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            continue;
+        }
+        chars.next();
+
+        // CSI parameters run until the final byte, only `m` (SGR) changes style
+        let mut params = String::new();
+        let mut terminator = None;
+        for c in chars.by_ref() {
+            if ('\x40'..='\x7e').contains(&c) {
+                terminator = Some(c);
+                break;
+            }
+            params.push(c);
+        }
+        if terminator != Some('m') {
+            continue;
+        }
+
+        if !text.is_empty() {
+            segments.push((std::mem::take(&mut text), style));
+        }
+        style = apply_sgr(style, &params);
+    }
+    if !text.is_empty() {
+        segments.push((text, style));
+    }
+    segments
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params
+        .split(';')
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+    let mut codes = codes.into_iter();
+
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default().fg(Color::White),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            7 => style.add_modifier(Modifier::REVERSED),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            30..=37 => style.fg(ansi_color(code - 30)),
+            39 => style.fg(Color::White),
+            40..=47 => style.bg(ansi_color(code - 40)),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(ansi_color(code - 90 + 8)),
+            100..=107 => style.bg(ansi_color(code - 100 + 8)),
+            38 | 48 => {
+                let color = match codes.next() {
+                    Some(5) => codes.next().map(|n| Color::Indexed(n as u8)),
+                    Some(2) => match (codes.next(), codes.next(), codes.next()) {
+                        (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r as u8, g as u8, b as u8)),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match (code, color) {
+                    (38, Some(color)) => style.fg(color),
+                    (48, Some(color)) => style.bg(color),
+                    _ => style,
+                }
+            }
+            _ => style,
+        };
+    }
+    style
+}
+
+fn ansi_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::Gray,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightMagenta,
+        14 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+pub fn strip_ansi(line: &str) -> String {
+    parse_ansi(line).into_iter().map(|(text, _)| text).collect()
+}
+
+/// Ask the terminal to put `text` on the system clipboard (OSC 52), which also
+/// works through SSH sessions
+fn osc52_copy(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(
+        stdout,
+        "\x1b]52;c;{}\x07",
+        linux_toolkit::cli::utils::base64_encode(text.as_bytes())
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_follow_word_wrapping() {
+        let output = vec!["abc defgh ijk".to_string(), "short".to_string()];
+        let mut viewer = OutputViewer::new("test", &output, 100);
+        // 13 characters fit in two rows of 7, but words push `ijk` to a third
+        assert_eq!(viewer.rows_for(0, 7), 3);
+        assert_eq!(viewer.rows_for(1, 7), 1);
+        viewer.wrap = false;
+        assert_eq!(viewer.rows_for(0, 7), 1);
+    }
+
+    #[test]
+    fn cursor_stays_on_screen_with_wrapped_lines() {
+        let output = vec!["abc defgh ijk".to_string(); 4];
+        let mut viewer = OutputViewer::new("test", &output, 100);
+        viewer.height = 4;
+        viewer.cursor = 1;
+        viewer.keep_cursor_visible(7);
+        // Lines 0 and 1 need six rows, so only line 1 can be shown
+        assert_eq!(viewer.top, 1);
+    }
+
+    #[test]
+    fn row_counts_are_cached_per_width() {
+        let output = vec!["abc defgh ijk".to_string()];
+        let mut viewer = OutputViewer::new("test", &output, 100);
+        assert_eq!(viewer.rows_for(0, 7), 3);
+        assert_eq!(viewer.row_cache, [Some(3)]);
+        assert_eq!(viewer.rows_for(0, 20), 1);
+        assert_eq!(viewer.row_cache, [Some(1)]);
+    }
+
+    #[test]
+    fn sgr_reset_restores_the_default_style() {
+        let segments = parse_ansi("\x1b[1;31mred\x1b[0m plain\x1b[m end");
+        let default = Style::default().fg(Color::White);
+        assert_eq!(
+            segments,
+            [
+                (
+                    "red".to_string(),
+                    default.fg(Color::Red).add_modifier(Modifier::BOLD)
+                ),
+                (" plain".to_string(), default),
+                (" end".to_string(), default),
+            ]
+        );
+    }
+
+    #[test]
+    fn extended_colors_are_parsed() {
+        let segments = parse_ansi("\x1b[38;5;208mamber\x1b[48;2;10;20;30mboth");
+        assert_eq!(segments[0].1.fg, Some(Color::Indexed(208)));
+        assert_eq!(segments[1].1.fg, Some(Color::Indexed(208)));
+        assert_eq!(segments[1].1.bg, Some(Color::Rgb(10, 20, 30)));
+    }
+
+    #[test]
+    fn malformed_escapes_are_dropped_without_losing_text() {
+        // Truncated truecolor, a non-SGR CSI, a bare ESC and an unterminated CSI
+        let segments = parse_ansi("\x1b[38;2;1mkeep\x1b[2Kcleared\x1bxtail\x1b[31");
+        assert_eq!(
+            strip_ansi("\x1b[38;2;1mkeep\x1b[2Kcleared\x1bxtail\x1b[31"),
+            "keepclearedxtail"
+        );
+        assert!(segments
+            .iter()
+            .all(|(_, style)| style.fg == Some(Color::White)));
+        // Unknown and empty codes: the empty one parses as a reset
+        let segments = parse_ansi("\x1b[31;999;;4mtext");
+        assert_eq!(
+            segments,
+            [(
+                "text".to_string(),
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::UNDERLINED)
+            )]
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::commands;
+use crate::config::Config;
+use crate::rpc::{Server, Session};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+const APP_JS: &str = include_str!("web/app.js");
+const STYLE_CSS: &str = include_str!("web/style.css");
+
+/// Cookie holding the access token after the first visit
+const COOKIE: &str = "ltk_token";
+/// Longest request line or header accepted
+const MAX_LINE: u64 = 8 * 1024;
+/// Largest request body accepted, far more than any RPC call needs
+const MAX_BODY: usize = 64 * 1024;
+/// Interval of SSE comments that keep idle streams open and notice closed ones
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    fn cookie(&self, name: &str) -> Option<&str> {
+        self.header("cookie")?
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// The web front-end: token check, static assets and JSON-RPC over POST and SSE
+struct Web {
+    server: Arc<Server>,
+    token: String,
+    /// Accepted `Host` headers, empty when served on a Unix socket behind a proxy
+    hosts: Vec<String>,
+    sessions: Mutex<HashMap<u64, Session>>,
+}
+
+impl Web {
+    fn authorized(&self, request: &Request) -> bool {
+        let bearer = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        bearer
+            .or_else(|| request.cookie(COOKIE))
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    async fn handle<R, W>(self: Arc<Self>, reader: R, mut writer: W, peer_uid: Option<u32>)
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut reader = BufReader::new(reader);
+        let request = match read_request(&mut reader).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(e) => {
+                let _ = respond(
+                    &mut writer,
+                    "400 Bad Request",
+                    "text/plain",
+                    &[],
+                    e.to_string(),
+                )
+                .await;
+                return;
+            }
+        };
+
+        // Refuse other host names so a DNS rebinding page cannot reach us
+        let host = request.header("host").unwrap_or_default();
+        if !self.hosts.is_empty() && !self.hosts.iter().any(|allowed| allowed == host) {
+            let _ = respond(
+                &mut writer,
+                "421 Misdirected Request",
+                "text/plain",
+                &[],
+                "",
+            )
+            .await;
+            return;
+        }
+
+        // The client may already be gone, there is nobody left to tell
+        let _ = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => self.index(&request, &mut writer).await,
+            ("GET", "/app.js") => {
+                respond(&mut writer, "200 OK", "text/javascript", &[], APP_JS).await
+            }
+            ("GET", "/style.css") => {
+                respond(&mut writer, "200 OK", "text/css", &[], STYLE_CSS).await
+            }
+            _ if !self.authorized(&request) => {
+                respond(&mut writer, "401 Unauthorized", "text/plain", &[], "").await
+            }
+            ("GET", "/events") => self.events(&mut writer, peer_uid).await,
+            ("POST", "/rpc") => self.rpc(&request, &mut writer).await,
+            _ => respond(&mut writer, "404 Not Found", "text/plain", &[], "").await,
+        };
+    }
+
+    /// The page itself; a `?token=` link sets the cookie and redirects to a clean URL
+    async fn index<W: AsyncWrite + Unpin>(
+        &self,
+        request: &Request,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        if let Some(token) = request.query_param("token") {
+            if !constant_time_eq(token.as_bytes(), self.token.as_bytes()) {
+                return respond(
+                    writer,
+                    "401 Unauthorized",
+                    "text/plain",
+                    &[],
+                    "Invalid token",
+                )
+                .await;
+            }
+            let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", COOKIE, token);
+            let headers = [("Set-Cookie", cookie.as_str()), ("Location", "/")];
+            return respond(writer, "303 See Other", "text/plain", &headers, "").await;
+        }
+        if !self.authorized(request) {
+            return respond(
+                writer,
+                "401 Unauthorized",
+                "text/plain",
+                &[],
+                "Open the link printed by `linux-toolkit web`",
+            )
+            .await;
+        }
+        respond(
+            writer,
+            "200 OK",
+            "text/html; charset=utf-8",
+            &[],
+            INDEX_HTML,
+        )
+        .await
+    }
+
+    /// Server-sent events carrying every response and notification of one session
+    async fn events<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        peer_uid: Option<u32>,
+    ) -> std::io::Result<()> {
+        let (session, mut outgoing) = self.server.open_session(peer_uid);
+        let id = session.id();
+        self.sessions.lock().unwrap().insert(id, session);
+
+        let result = async {
+            writer
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                      Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+                )
+                .await?;
+            let hello = format!("event: session\ndata: {{\"session\":{}}}\n\n", id);
+            writer.write_all(hello.as_bytes()).await?;
+            writer.flush().await?;
+            loop {
+                let event = tokio::select! {
+                    message = outgoing.recv() => match message {
+                        Some(message) => format!("data: {}\n\n", message),
+                        None => break,
+                    },
+                    _ = tokio::time::sleep(KEEPALIVE) => ": keepalive\n\n".to_string(),
+                };
+                writer.write_all(event.as_bytes()).await?;
+                writer.flush().await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        // Dropping the session cancels whatever it still has running
+        self.sessions.lock().unwrap().remove(&id);
+        result
+    }
+
+    /// A JSON-RPC request whose answer arrives on the session's event stream
+    async fn rpc<W: AsyncWrite + Unpin>(
+        &self,
+        request: &Request,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        // A custom header cannot be sent cross-site without a preflight we never allow
+        let session = request
+            .header("x-session")
+            .and_then(|value| value.parse::<u64>().ok());
+        let body = String::from_utf8_lossy(&request.body);
+        let handled = session.is_some_and(|id| match self.sessions.lock().unwrap().get(&id) {
+            Some(session) => {
+                session.handle(body.trim());
+                true
+            }
+            None => false,
+        });
+        if handled {
+            respond(writer, "202 Accepted", "text/plain", &[], "").await
+        } else {
+            respond(writer, "409 Conflict", "text/plain", &[], "Unknown session").await
+        }
+    }
+}
+
+async fn read_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Result<String> {
+    let mut line = String::new();
+    (&mut *reader).take(MAX_LINE).read_line(&mut line).await?;
+    if !line.is_empty() && !line.ends_with('\n') {
+        return Err(anyhow!("Request line too long"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+async fn read_request<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Result<Option<Request>> {
+    let request_line = read_line(reader).await?;
+    if request_line.is_empty() {
+        return Ok(None);
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(anyhow!("Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = HashMap::new();
+    loop {
+        let line = read_line(reader).await?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() >= 64 {
+            return Err(anyhow!("Too many headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = match headers.get("content-length") {
+        Some(value) => value.parse::<usize>().context("Invalid Content-Length")?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(anyhow!("Request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        headers,
+        body,
+    }))
+}
+
+async fn respond<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: &str,
+    content_type: &str,
+    headers: &[(&str, &str)],
+    body: impl AsRef<[u8]>,
+) -> std::io::Result<()> {
+    let body = body.as_ref();
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\
+         Cache-Control: no-store\r\nX-Content-Type-Options: nosniff\r\n\
+         X-Frame-Options: DENY\r\nContent-Security-Policy: default-src 'self'\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 128 random bits from the kernel, hex encoded
+fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut random| std::io::Read::read_exact(&mut random, &mut bytes))
+        .context("Failed to read /dev/urandom for the access token")?;
+    Ok(hex::encode(bytes))
+}
+
+/// Entry point for `linux-toolkit web`
+pub async fn web_command(port: Option<u16>, socket: Option<&Path>, config: &Config) -> Result<()> {
+    let token = match &config.web.token {
+        Some(token) => token.clone(),
+        None => generate_token()?,
+    };
+    let server = Server::new(config.clone(), commands::load_categories());
+
+    if let Some(path) = socket {
+        return serve_socket(server, token, path.to_path_buf()).await;
+    }
+
+    let port = port.unwrap_or(config.web.port);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to listen on 127.0.0.1:{}", port))?;
+    let port = listener.local_addr()?.port();
+    let web = Arc::new(Web {
+        server,
+        token,
+        hosts: vec![format!("127.0.0.1:{}", port), format!("localhost:{}", port)],
+        sessions: Mutex::new(HashMap::new()),
+    });
+
+    println!("Web UI: http://127.0.0.1:{}/?token={}", port, web.token);
+    println!("Press Ctrl+C to stop");
+    let accept = async {
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => return Err::<(), _>(anyhow::Error::from(e)),
+            };
+            let (reader, writer) = stream.into_split();
+            tokio::spawn(web.clone().handle(reader, writer, None));
+        }
+    };
+    tokio::select! {
+        result = accept => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    }
+}
+
+/// Serve on a Unix socket for a reverse proxy, admitting peers like `serve socket`
+#[cfg(unix)]
+async fn serve_socket(server: Arc<Server>, token: String, path: PathBuf) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    crate::rpc::remove_stale_socket(&path).await?;
+    let listener = tokio::net::UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind {}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    let web = Arc::new(Web {
+        server,
+        token,
+        hosts: Vec::new(),
+        sessions: Mutex::new(HashMap::new()),
+    });
+
+    println!("Web UI on {}, access token {}", path.display(), web.token);
+    let accept = async {
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => return Err::<(), _>(anyhow::Error::from(e)),
+            };
+            let Ok(uid) = stream.peer_cred().map(|credentials| credentials.uid()) else {
+                continue;
+            };
+            if !web.server.peer_allowed(uid) {
+                eprintln!("Rejected connection from uid {}", uid);
+                continue;
+            }
+            let (reader, writer) = stream.into_split();
+            tokio::spawn(web.clone().handle(reader, writer, Some(uid)));
+        }
+    };
+    let result = tokio::select! {
+        result = accept => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+#[cfg(not(unix))]
+async fn serve_socket(_server: Arc<Server>, _token: String, _path: PathBuf) -> Result<()> {
+    Err(anyhow!(
+        "Unix domain sockets are not available on this platform"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn web() -> Arc<Web> {
+        let mut config = Config::default();
+        config.audit.enabled = false;
+        Arc::new(Web {
+            server: Server::new(config, Vec::new()),
+            token: "secret".to_string(),
+            hosts: vec!["127.0.0.1:8765".to_string()],
+            sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn exchange(web: Arc<Web>, request: &str) -> String {
+        let (client, remote) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(remote);
+        let (mut client_reader, mut client_writer) = tokio::io::split(client);
+        client_writer.write_all(request.as_bytes()).await.unwrap();
+        web.handle(reader, writer, None).await;
+        let mut response = String::new();
+        client_reader.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn token_link_sets_cookie_and_redirects() {
+        let response = exchange(
+            web(),
+            "GET /?token=secret HTTP/1.1\r\nHost: 127.0.0.1:8765\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 303"), "{}", response);
+        assert!(
+            response.contains("Set-Cookie: ltk_token=secret; Path=/; HttpOnly; SameSite=Strict")
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_token() {
+        let request =
+            |auth: &str| format!("GET / HTTP/1.1\r\nHost: 127.0.0.1:8765\r\n{}\r\n", auth);
+        let denied = exchange(web(), &request("Cookie: ltk_token=wrong\r\n")).await;
+        assert!(denied.starts_with("HTTP/1.1 401"), "{}", denied);
+        let cookie = exchange(web(), &request("Cookie: theme=dark; ltk_token=secret\r\n")).await;
+        assert!(cookie.starts_with("HTTP/1.1 200"), "{}", cookie);
+        let bearer = exchange(web(), &request("Authorization: Bearer secret\r\n")).await;
+        assert!(bearer.contains("<title>Linux Toolkit</title>"));
+    }
+
+    #[tokio::test]
+    async fn foreign_host_is_refused() {
+        let response = exchange(
+            web(),
+            "GET / HTTP/1.1\r\nHost: attacker.example:8765\r\nCookie: ltk_token=secret\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 421"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn rpc_needs_a_live_session() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"commands.list"}"#;
+        let response = exchange(
+            web(),
+            &format!(
+                "POST /rpc HTTP/1.1\r\nHost: 127.0.0.1:8765\r\nAuthorization: Bearer secret\r\n\
+                 X-Session: 42\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 409"), "{}", response);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn socket_path_of_another_file_is_kept() {
+        let dir = crate::testing::TempDir::new("web-socket");
+        let path = dir.join("web.sock");
+        std::fs::write(&path, "keep me").unwrap();
+        let server = web().server.clone();
+        let result = serve_socket(server, "secret".to_string(), path.clone()).await;
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+    }
+}
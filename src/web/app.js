@@ -0,0 +1,233 @@
+'use strict';
+
+// Requests go out as JSON-RPC over POST /rpc; their answers and all
+// execution notifications come back on the /events stream.
+
+const pending = new Map();
+let nextId = 1;
+let session = null;
+let selected = null;
+let running = null;
+
+const $ = (id) => document.getElementById(id);
+
+function element(tag, text, className) {
+  const node = document.createElement(tag);
+  if (text !== undefined) node.textContent = text;
+  if (className) node.className = className;
+  return node;
+}
+
+function call(method, params) {
+  const id = nextId++;
+  return new Promise((resolve, reject) => {
+    pending.set(id, { resolve, reject });
+    fetch('/rpc', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json', 'X-Session': String(session) },
+      body: JSON.stringify({ jsonrpc: '2.0', id, method, params }),
+    }).then(
+      (response) => {
+        if (!response.ok) {
+          pending.delete(id);
+          reject(new Error(`${response.status} ${response.statusText}`));
+        }
+      },
+      (error) => {
+        pending.delete(id);
+        reject(error);
+      },
+    );
+  });
+}
+
+function receive(message) {
+  if (message.id !== undefined && pending.has(message.id)) {
+    const { resolve, reject } = pending.get(message.id);
+    pending.delete(message.id);
+    if (message.error) reject(new Error(message.error.message));
+    else resolve(message.result);
+    return;
+  }
+  const params = message.params || {};
+  if (params.execution_id !== running) return;
+  if (message.method === 'execution.output') {
+    appendOutput(params.line, params.stream);
+  } else if (message.method === 'execution.finished') {
+    finished(params);
+  }
+}
+
+function connect() {
+  const events = new EventSource('/events');
+  events.addEventListener('session', (event) => {
+    session = JSON.parse(event.data).session;
+    $('connection').textContent = 'Connected';
+    $('connection').classList.remove('offline');
+    loadCommands().catch(showError);
+  });
+  events.onmessage = (event) => receive(JSON.parse(event.data));
+  events.onerror = () => {
+    // The server cancels a session's executions when its stream closes
+    $('connection').textContent = 'Disconnected, retrying…';
+    $('connection').classList.add('offline');
+    if (running !== null) finished({ cancelled: true });
+    for (const { reject } of pending.values()) reject(new Error('Disconnected'));
+    pending.clear();
+  };
+}
+
+async function loadCommands() {
+  const commands = await call('commands.list', {});
+  const groups = new Map();
+  for (const command of commands) {
+    if (!groups.has(command.category)) groups.set(command.category, []);
+    groups.get(command.category).push(command);
+  }
+
+  const container = $('commands');
+  container.replaceChildren();
+  for (const [category, list] of groups) {
+    const group = element('details');
+    group.open = true;
+    group.append(element('summary', category));
+    const items = element('ul');
+    for (const command of list) {
+      const button = element('button', command.name);
+      button.title = command.description;
+      button.dataset.id = command.id;
+      if (!command.available) button.classList.add('unavailable');
+      if (command.requires_sudo) button.append(element('span', 'sudo', 'badge'));
+      button.addEventListener('click', () => select(command.id));
+      const item = element('li');
+      item.append(button);
+      items.append(item);
+    }
+    group.append(items);
+    container.append(group);
+  }
+  filterCommands();
+}
+
+function filterCommands() {
+  const query = $('filter').value.trim().toLowerCase();
+  for (const group of $('commands').children) {
+    let visible = 0;
+    for (const button of group.querySelectorAll('button')) {
+      const match = !query || button.textContent.toLowerCase().includes(query)
+        || button.title.toLowerCase().includes(query);
+      button.parentElement.hidden = !match;
+      if (match) visible++;
+    }
+    group.hidden = visible === 0;
+  }
+}
+
+async function select(id) {
+  const command = await call('commands.describe', { id });
+  selected = command;
+  for (const button of $('commands').querySelectorAll('button')) {
+    button.classList.toggle('selected', button.dataset.id === id);
+  }
+
+  const details = $('details');
+  details.replaceChildren(element('h2', command.name), element('p', command.description));
+  const facts = element('dl');
+  const fact = (label, value) => {
+    if (!value) return;
+    facts.append(element('dt', label), element('dd', value));
+  };
+  fact('Command', command.usage);
+  fact('Privileges', command.requires_sudo ? 'Runs with sudo' : 'None');
+  fact('Requires', command.required_binaries.join(', '));
+  fact('Distributions', command.supported_distros.join(', '));
+  fact('Tags', command.tags.join(', '));
+  details.append(facts);
+
+  let blocked = null;
+  if (command.unavailable) blocked = command.unavailable;
+  else if (command.missing.length) blocked = `Missing ${command.missing.join(', ')}`;
+  if (blocked) details.append(element('p', blocked, 'warning'));
+
+  $('run').disabled = running !== null || blocked !== null;
+}
+
+async function run(event) {
+  event.preventDefault();
+  if (!selected || running !== null) return;
+  $('output').replaceChildren();
+  setStatus(`Running ${selected.name}…`);
+  $('run').disabled = true;
+  try {
+    const result = await call('execution.start', { id: selected.id });
+    running = result.execution_id;
+    $('cancel').disabled = false;
+  } catch (error) {
+    $('run').disabled = false;
+    showError(error);
+  }
+}
+
+function appendOutput(line, stream) {
+  const output = $('output');
+  const atBottom = output.scrollTop + output.clientHeight >= output.scrollHeight - 4;
+  output.append(element('span', `${line}\n`, stream === 'stderr' ? 'stderr' : undefined));
+  if (atBottom) output.scrollTop = output.scrollHeight;
+}
+
+function finished(result) {
+  running = null;
+  $('cancel').disabled = true;
+  $('run').disabled = !selected;
+  if (result.cancelled) setStatus('Cancelled', 'failed');
+  else if (result.success) setStatus(`Finished (exit code ${result.exit_code})`, 'succeeded');
+  else setStatus(`Failed (exit code ${result.exit_code ?? 'none'})`, 'failed');
+  if (!$('history-view').hidden) loadHistory().catch(showError);
+}
+
+function setStatus(text, className) {
+  $('status').textContent = text;
+  $('status').className = className || '';
+}
+
+function showError(error) {
+  setStatus(error.message, 'failed');
+}
+
+async function loadHistory() {
+  const entries = await call('history.list', { limit: 100 });
+  const rows = entries.map((entry) => {
+    const row = element('tr');
+    row.append(
+      element('td', new Date(entry.finished_at).toLocaleString()),
+      element('td', entry.command_name),
+      element('td', entry.exit_code ?? '–'),
+      element('td', entry.cancelled ? 'Cancelled' : entry.success ? 'Succeeded' : 'Failed'),
+    );
+    row.addEventListener('click', () => {
+      for (const other of $('history').children) other.classList.remove('selected');
+      row.classList.add('selected');
+      $('history-output').textContent = entry.output;
+    });
+    return row;
+  });
+  $('history').replaceChildren(...rows);
+  if (!rows.length) $('history-output').textContent = 'Nothing has run yet.';
+}
+
+function showTab(name) {
+  $('tab-commands').classList.toggle('active', name === 'commands');
+  $('tab-history').classList.toggle('active', name === 'history');
+  $('commands-view').hidden = name !== 'commands';
+  $('history-view').hidden = name !== 'history';
+  if (name === 'history') loadHistory().catch(showError);
+}
+
+$('filter').addEventListener('input', filterCommands);
+$('run-form').addEventListener('submit', run);
+$('cancel').addEventListener('click', () => {
+  if (running !== null) call('execution.cancel', { execution_id: running }).catch(showError);
+});
+$('tab-commands').addEventListener('click', () => showTab('commands'));
+$('tab-history').addEventListener('click', () => showTab('history'));
+connect();